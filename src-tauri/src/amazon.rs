@@ -0,0 +1,749 @@
+use std::fs;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager, State};
+use crate::listing::ListingCondition;
+use crate::{keychain, network};
+
+const TOKEN_URL: &str = "https://api.amazon.com/auth/o2/token";
+
+const SECRET_ACCOUNT: &str = "amazon-lwa-secret";
+const TOKEN_ACCOUNT: &str = "amazon-refresh";
+
+const FEED_STATUS_EVENT: &str = "amazon-feed://status";
+
+// Tokens are refreshed this long before they expire
+const EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+// Feeds usually finish within a few minutes; Amazon asks not to poll more
+// often than this
+const FEED_POLL_INTERVAL: Duration = Duration::from_secs(30);
+const FEED_TIMEOUT: Duration = Duration::from_secs(20 * 60);
+
+// Amazon.com
+const DEFAULT_MARKETPLACE_ID: &str = "ATVPDKIKX0DER";
+
+const FEED_CONTENT_TYPE: &str = "application/json; charset=UTF-8";
+
+// Feeds whose SKUs are kept; reports are only available for a few weeks
+const MAX_SAVED_FEEDS: usize = 200;
+
+// The most items a catalog search returns per page
+const CATALOG_PAGE_SIZE: usize = 20;
+
+// SP-API region, which sets the endpoint; it must be the one the seller's
+// marketplace belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AmazonRegion {
+    // US, Canada, Mexico and Brazil
+    NorthAmerica,
+    // Europe, the Middle East and India
+    Europe,
+    // Japan, Australia and Singapore
+    FarEast,
+}
+
+impl AmazonRegion {
+    fn endpoint(self) -> &'static str {
+        match self {
+            AmazonRegion::NorthAmerica => "https://sellingpartnerapi-na.amazon.com",
+            AmazonRegion::Europe => "https://sellingpartnerapi-eu.amazon.com",
+            AmazonRegion::FarEast => "https://sellingpartnerapi-fe.amazon.com",
+        }
+    }
+}
+
+fn default_region() -> AmazonRegion {
+    AmazonRegion::NorthAmerica
+}
+
+fn default_marketplace_id() -> String {
+    DEFAULT_MARKETPLACE_ID.to_string()
+}
+
+fn default_language_tag() -> String {
+    "en_US".to_string()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AmazonSettings {
+    // LWA client ID of the seller's private SP-API app
+    pub client_id: String,
+    // Merchant token, shown under Account Info in Seller Central
+    pub seller_id: String,
+    #[serde(default = "default_region")]
+    pub region: AmazonRegion,
+    #[serde(default = "default_marketplace_id")]
+    pub marketplace_id: String,
+    // Language of condition notes, e.g. "en_US" or "de_DE"
+    #[serde(default = "default_language_tag")]
+    pub language_tag: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AmazonSettingsInfo {
+    #[serde(flatten)]
+    pub settings: AmazonSettings,
+    pub has_client_secret: bool,
+    pub connected: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum IdentifierType {
+    Asin,
+    Upc,
+    Ean,
+    Isbn,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CatalogItem {
+    pub asin: String,
+    pub title: Option<String>,
+    pub brand: Option<String>,
+    // Main image
+    pub image: Option<String>,
+    // Needed to create a listing with full product details; offers on an
+    // existing ASIN don't need it
+    pub product_type: Option<String>,
+    // UPC, EAN and ISBN codes on the catalog page, as "TYPE:code"
+    pub identifiers: Vec<String>,
+}
+
+// An offer on a product already in Amazon's catalog. Books, media and
+// electronics nearly always are, so the title, photos and details come from
+// the catalog page and only the condition and price are the seller's.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AmazonOffer {
+    pub sku: String,
+    // From `lookup_amazon_catalog`
+    pub asin: String,
+    pub price: f64,
+    pub currency: String,
+    #[serde(default = "default_quantity")]
+    pub quantity: u32,
+    pub condition: ListingCondition,
+    // Wear, missing parts and the like; shown next to the offer
+    #[serde(default)]
+    pub condition_note: Option<String>,
+    // Product type from the catalog; "PRODUCT" works for offers
+    #[serde(default)]
+    pub product_type: Option<String>,
+}
+
+fn default_quantity() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FeedIssue {
+    // Position of the offer in the feed, from 1
+    pub message_id: u64,
+    pub sku: Option<String>,
+    pub severity: String,
+    pub code: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FeedStatus {
+    pub feed_id: String,
+    // IN_QUEUE, IN_PROGRESS, DONE, CANCELLED or FATAL
+    pub processing_status: String,
+    pub done: bool,
+    pub accepted: Option<u64>,
+    pub invalid: Option<u64>,
+    pub issues: Vec<FeedIssue>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedFeed {
+    feed_id: String,
+    skus: Vec<String>,
+}
+
+struct CachedToken {
+    token: String,
+    expires_at: Instant,
+}
+
+// The current access token, and a lock held while the saved feeds are
+// rewritten
+#[derive(Default)]
+pub struct AmazonAuth {
+    token: Mutex<Option<CachedToken>>,
+    feeds: Mutex<()>,
+}
+
+impl AmazonAuth {
+    fn clear(&self) {
+        if let Ok(mut token) = self.token.lock() {
+            *token = None;
+        }
+    }
+}
+
+fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app.path_resolver()
+        .app_data_dir()
+        .ok_or("Failed to resolve app data directory")?;
+    Ok(data_dir.join("amazon.json"))
+}
+
+// Submitted feeds with the SKUs of their messages in order, for naming the
+// offers in processing reports after a restart
+fn feeds_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app.path_resolver()
+        .app_data_dir()
+        .ok_or("Failed to resolve app data directory")?;
+    Ok(data_dir.join("amazon_feeds.json"))
+}
+
+fn load_feeds(app: &AppHandle) -> Result<Vec<SavedFeed>, String> {
+    let path = feeds_path(app)?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let json = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read saved Amazon feeds: {}", e))?;
+    serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse saved Amazon feeds: {}", e))
+}
+
+fn save_feed(app: &AppHandle, auth: &AmazonAuth, feed_id: &str, skus: Vec<String>) -> Result<(), String> {
+    let _guard = auth.feeds.lock().map_err(|_| "Amazon feed lock poisoned".to_string())?;
+    let mut feeds = load_feeds(app)?;
+    feeds.retain(|feed| feed.feed_id != feed_id);
+    feeds.push(SavedFeed { feed_id: feed_id.to_string(), skus });
+    let excess = feeds.len().saturating_sub(MAX_SAVED_FEEDS);
+    feeds.drain(..excess);
+
+    let path = feeds_path(app)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create settings directory: {}", e))?;
+    }
+    let json = serde_json::to_string(&feeds)
+        .map_err(|e| format!("Failed to serialize Amazon feeds: {}", e))?;
+    fs::write(&path, json)
+        .map_err(|e| format!("Failed to save Amazon feeds: {}", e))
+}
+
+fn feed_skus(app: &AppHandle, feed_id: &str) -> Result<Vec<String>, String> {
+    Ok(load_feeds(app)?
+        .into_iter()
+        .find(|feed| feed.feed_id == feed_id)
+        .map(|feed| feed.skus)
+        .unwrap_or_default())
+}
+
+fn load_settings(app: &AppHandle) -> Result<AmazonSettings, String> {
+    let path = settings_path(app)?;
+    if !path.exists() {
+        return Err("Amazon isn't set up; add the SP-API app's credentials in settings".to_string());
+    }
+    let json = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read Amazon settings: {}", e))?;
+    serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse Amazon settings: {}", e))
+}
+
+// Message from an SP-API or LWA error body, or the body itself
+fn error_message(body: &str) -> String {
+    serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|error| {
+            error["errors"][0]["message"]
+                .as_str()
+                .or(error["error_description"].as_str())
+                .or(error["error"].as_str())
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| body.trim().to_string())
+}
+
+// Send a request and parse its JSON response
+async fn send(request: reqwest::RequestBuilder, action: &str) -> Result<Value, String> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Failed to {}: {}", action, e))?;
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return Err(format!("Failed to {}: Amazon's rate limit was reached; try again shortly", action));
+    }
+    if !status.is_success() {
+        return Err(format!("Failed to {} ({}): {}", action, status, error_message(&body)));
+    }
+    serde_json::from_str(&body).map_err(|e| format!("Failed to parse Amazon response: {}", e))
+}
+
+// An LWA access token, exchanged for the stored refresh token when the cached
+// one has expired. LWA access tokens last an hour.
+async fn access_token(app: &AppHandle, settings: &AmazonSettings, auth: &AmazonAuth) -> Result<String, String> {
+    let cached = auth.token.lock().ok().and_then(|token| {
+        token.as_ref().filter(|token| Instant::now() < token.expires_at).map(|token| token.token.clone())
+    });
+    if let Some(token) = cached {
+        return Ok(token);
+    }
+
+    let secret = keychain::get_secret(SECRET_ACCOUNT)?
+        .ok_or("No Amazon LWA client secret is stored")?;
+    let refresh_token = keychain::get_secret(TOKEN_ACCOUNT)?
+        .ok_or("No Amazon seller account is connected")?;
+    let request = network::client(app).post(TOKEN_URL).form(&[
+        ("grant_type", "refresh_token"),
+        ("refresh_token", refresh_token.as_str()),
+        ("client_id", settings.client_id.trim()),
+        ("client_secret", secret.as_str()),
+    ]);
+    let token: TokenResponse = serde_json::from_value(send(request, "get Amazon token").await?)
+        .map_err(|e| format!("Failed to parse Amazon token: {}", e))?;
+    if let Ok(mut cached) = auth.token.lock() {
+        *cached = Some(CachedToken {
+            token: token.access_token.clone(),
+            expires_at: Instant::now() + Duration::from_secs(token.expires_in).saturating_sub(EXPIRY_MARGIN),
+        });
+    }
+    Ok(token.access_token)
+}
+
+// Identifier type from the code's shape: ASINs are ten letters and digits
+// starting with a letter, ISBN-10s ten digits (the last may be X), UPCs 12
+// digits and EANs 13, which covers ISBN-13s
+fn detect_identifier_type(code: &str) -> Result<IdentifierType, String> {
+    if !code.is_ascii() {
+        return Err(format!("\"{}\" isn't an ASIN, UPC, EAN or ISBN", code));
+    }
+    let digits = code.chars().all(|c| c.is_ascii_digit());
+    match code.len() {
+        10 if code.starts_with(|c: char| c.is_ascii_alphabetic()) && code.chars().all(|c| c.is_ascii_alphanumeric()) => {
+            Ok(IdentifierType::Asin)
+        }
+        10 if code[..9].chars().all(|c| c.is_ascii_digit()) && (digits || code.ends_with(['X', 'x'])) => {
+            Ok(IdentifierType::Isbn)
+        }
+        12 if digits => Ok(IdentifierType::Upc),
+        13 if digits => Ok(IdentifierType::Ean),
+        _ => Err(format!("\"{}\" isn't an ASIN, UPC, EAN or ISBN", code)),
+    }
+}
+
+// Entry of a per-marketplace catalog array for the settings' marketplace
+fn for_marketplace<'a>(entries: &'a Value, marketplace_id: &str) -> Option<&'a Value> {
+    let entries = entries.as_array()?;
+    entries
+        .iter()
+        .find(|entry| entry["marketplaceId"].as_str() == Some(marketplace_id))
+        .or(entries.first())
+}
+
+fn catalog_item(item: &Value, marketplace_id: &str) -> Option<CatalogItem> {
+    let asin = item["asin"].as_str()?.to_string();
+    let summary = for_marketplace(&item["summaries"], marketplace_id);
+    let image = for_marketplace(&item["images"], marketplace_id).and_then(|images| {
+        let images = images["images"].as_array()?;
+        images
+            .iter()
+            .find(|image| image["variant"].as_str() == Some("MAIN"))
+            .or(images.first())
+            .and_then(|image| image["link"].as_str())
+            .map(str::to_string)
+    });
+    let identifiers = for_marketplace(&item["identifiers"], marketplace_id)
+        .and_then(|identifiers| identifiers["identifiers"].as_array())
+        .map(|identifiers| {
+            identifiers
+                .iter()
+                .filter_map(|id| Some(format!("{}:{}", id["identifierType"].as_str()?, id["identifier"].as_str()?)))
+                .collect()
+        })
+        .unwrap_or_default();
+    Some(CatalogItem {
+        asin,
+        title: summary.and_then(|summary| summary["itemName"].as_str()).map(str::to_string),
+        brand: summary.and_then(|summary| summary["brand"].as_str()).map(str::to_string),
+        image,
+        product_type: for_marketplace(&item["productTypes"], marketplace_id)
+            .and_then(|product_type| product_type["productType"].as_str())
+            .map(str::to_string),
+        identifiers,
+    })
+}
+
+// Amazon's condition types; new items without tags count as like new since
+// Amazon only sells unused, unopened items as new
+fn amazon_condition(condition: ListingCondition) -> &'static str {
+    match condition {
+        ListingCondition::NewWithTags => "new_new",
+        ListingCondition::NewWithoutTags => "used_like_new",
+        ListingCondition::VeryGood => "used_very_good",
+        ListingCondition::Good => "used_good",
+        ListingCondition::Satisfactory => "used_acceptable",
+    }
+}
+
+// What stops an offer being submitted
+fn offer_problems(offer: &AmazonOffer) -> Vec<String> {
+    let mut problems = Vec::new();
+    if offer.sku.trim().is_empty() {
+        problems.push("SKU is required".to_string());
+    }
+    // Book ASINs are their ISBN-10s, so any ten letters and digits will do
+    let asin = offer.asin.trim();
+    if asin.len() != 10 || !asin.chars().all(|c| c.is_ascii_alphanumeric()) {
+        problems.push(format!("\"{}\" isn't an ASIN; look the item up in the catalog first", offer.asin));
+    }
+    if offer.price <= 0.0 {
+        problems.push("Price must be greater than zero".to_string());
+    }
+    if offer.currency.trim().is_empty() {
+        problems.push("Currency is required".to_string());
+    }
+    // Amazon caps condition notes at 1000 characters
+    if offer.condition_note.as_deref().is_some_and(|note| note.chars().count() > 1000) {
+        problems.push("Amazon condition notes are limited to 1000 characters".to_string());
+    }
+    problems
+}
+
+// JSON_LISTINGS_FEED message creating or replacing an offer on an ASIN
+fn feed_message(message_id: usize, offer: &AmazonOffer, settings: &AmazonSettings) -> Value {
+    let marketplace_id = settings.marketplace_id.as_str();
+    let mut attributes = json!({
+        "merchant_suggested_asin": [{ "value": offer.asin.trim(), "marketplace_id": marketplace_id }],
+        "condition_type": [{ "value": amazon_condition(offer.condition), "marketplace_id": marketplace_id }],
+        "fulfillment_availability": [{ "fulfillment_channel_code": "DEFAULT", "quantity": offer.quantity }],
+        "purchasable_offer": [{
+            "marketplace_id": marketplace_id,
+            "currency": offer.currency.trim().to_uppercase(),
+            "our_price": [{ "schedule": [{ "value_with_tax": offer.price }] }],
+        }],
+    });
+    if let Some(note) = offer.condition_note.as_deref().map(str::trim).filter(|note| !note.is_empty()) {
+        attributes["condition_note"] = json!([{
+            "value": note,
+            "language_tag": settings.language_tag,
+            "marketplace_id": marketplace_id,
+        }]);
+    }
+    json!({
+        "messageId": message_id,
+        "sku": offer.sku.trim(),
+        "operationType": "UPDATE",
+        "productType": offer.product_type.as_deref().unwrap_or("PRODUCT"),
+        "requirements": "LISTING_OFFER_ONLY",
+        "attributes": attributes,
+    })
+}
+
+// Upload a feed document and submit it, returning the feed ID
+async fn submit_feed(app: &AppHandle, settings: &AmazonSettings, token: &str, feed: &Value) -> Result<String, String> {
+    let client = network::client(app);
+    let endpoint = settings.region.endpoint();
+
+    let document = send(
+        client
+            .post(format!("{}/feeds/2021-06-30/documents", endpoint))
+            .header("x-amz-access-token", token)
+            .json(&json!({ "contentType": FEED_CONTENT_TYPE })),
+        "create Amazon feed document",
+    )
+    .await?;
+    let document_id = document["feedDocumentId"].as_str().ok_or("Amazon returned no feed document ID")?;
+    let upload_url = document["url"].as_str().ok_or("Amazon returned no feed upload URL")?;
+
+    // The upload URL is presigned, so it takes no access token
+    client
+        .put(upload_url)
+        .header(reqwest::header::CONTENT_TYPE, FEED_CONTENT_TYPE)
+        .body(feed.to_string())
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to upload Amazon feed: {}", e))?;
+
+    let created = send(
+        client
+            .post(format!("{}/feeds/2021-06-30/feeds", endpoint))
+            .header("x-amz-access-token", token)
+            .json(&json!({
+                "feedType": "JSON_LISTINGS_FEED",
+                "marketplaceIds": [settings.marketplace_id],
+                "inputFeedDocumentId": document_id,
+            })),
+        "submit Amazon feed",
+    )
+    .await?;
+    created["feedId"].as_str().map(str::to_string).ok_or_else(|| "Amazon returned no feed ID".to_string())
+}
+
+// Issues from a finished feed's processing report
+async fn feed_report(
+    app: &AppHandle,
+    settings: &AmazonSettings,
+    token: &str,
+    document_id: &str,
+    skus: &[String],
+) -> Result<(Option<u64>, Option<u64>, Vec<FeedIssue>), String> {
+    let client = network::client(app);
+    let document = send(
+        client
+            .get(format!("{}/feeds/2021-06-30/documents/{}", settings.region.endpoint(), document_id))
+            .header("x-amz-access-token", token),
+        "get Amazon feed report",
+    )
+    .await?;
+    let url = document["url"].as_str().ok_or("Amazon returned no report URL")?;
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to download Amazon feed report: {}", e))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("Failed to download Amazon feed report ({})", status));
+    }
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to download Amazon feed report: {}", e))?;
+    let json = if document["compressionAlgorithm"].as_str() == Some("GZIP") {
+        let mut json = String::new();
+        GzDecoder::new(&bytes[..])
+            .read_to_string(&mut json)
+            .map_err(|e| format!("Failed to decompress Amazon feed report: {}", e))?;
+        json
+    } else {
+        String::from_utf8(bytes.to_vec()).map_err(|e| format!("Amazon's feed report isn't text: {}", e))?
+    };
+    let report: Value = serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse Amazon feed report: {}", e))?;
+
+    let issues = report["issues"]
+        .as_array()
+        .map(|issues| {
+            issues
+                .iter()
+                .map(|issue| {
+                    let message_id = issue["messageId"].as_u64().unwrap_or_default();
+                    FeedIssue {
+                        message_id,
+                        sku: (message_id as usize).checked_sub(1).and_then(|i| skus.get(i)).cloned(),
+                        severity: issue["severity"].as_str().unwrap_or_default().to_string(),
+                        code: issue["code"].as_str().unwrap_or_default().to_string(),
+                        message: issue["message"].as_str().unwrap_or_default().to_string(),
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let summary = &report["summary"];
+    Ok((summary["messagesAccepted"].as_u64(), summary["messagesInvalid"].as_u64(), issues))
+}
+
+async fn feed_status(app: &AppHandle, settings: &AmazonSettings, auth: &AmazonAuth, feed_id: &str) -> Result<FeedStatus, String> {
+    let token = access_token(app, settings, auth).await?;
+    let feed = send(
+        network::client(app)
+            .get(format!("{}/feeds/2021-06-30/feeds/{}", settings.region.endpoint(), urlencoding::encode(feed_id)))
+            .header("x-amz-access-token", &token),
+        "get Amazon feed status",
+    )
+    .await?;
+    let processing_status = feed["processingStatus"].as_str().unwrap_or("IN_QUEUE").to_string();
+    let done = matches!(processing_status.as_str(), "DONE" | "CANCELLED" | "FATAL");
+    let mut status = FeedStatus {
+        feed_id: feed_id.to_string(),
+        processing_status,
+        done,
+        accepted: None,
+        invalid: None,
+        issues: Vec::new(),
+    };
+    // Fatal feeds have a report too, saying why they failed
+    if let Some(document_id) = feed["resultFeedDocumentId"].as_str() {
+        let (accepted, invalid, issues) = feed_report(app, settings, &token, document_id, &feed_skus(app, feed_id)?).await?;
+        status.accepted = accepted;
+        status.invalid = invalid;
+        status.issues = issues;
+    }
+    Ok(status)
+}
+
+// Command to configure the SP-API app credentials. The LWA client secret and
+// the refresh token from authorizing the app in Seller Central are stored in
+// the OS keychain: pass one to set it, an empty string to remove it, or leave
+// it out to keep the current one.
+#[tauri::command]
+pub fn set_amazon_settings(
+    app: AppHandle,
+    auth: State<AmazonAuth>,
+    settings: AmazonSettings,
+    client_secret: Option<String>,
+    refresh_token: Option<String>,
+) -> Result<(), String> {
+    if settings.client_id.trim().is_empty() {
+        return Err("LWA client ID is required".to_string());
+    }
+    if settings.seller_id.trim().is_empty() {
+        return Err("Seller ID is required".to_string());
+    }
+    if settings.marketplace_id.trim().is_empty() {
+        return Err("Marketplace ID is required".to_string());
+    }
+
+    let path = settings_path(&app)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create settings directory: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(&settings)
+        .map_err(|e| format!("Failed to serialize Amazon settings: {}", e))?;
+    fs::write(&path, json)
+        .map_err(|e| format!("Failed to save Amazon settings: {}", e))?;
+    auth.clear();
+
+    for (account, secret) in [(SECRET_ACCOUNT, client_secret), (TOKEN_ACCOUNT, refresh_token)] {
+        match secret.as_deref().map(str::trim) {
+            Some("") => keychain::delete_secret(account)?,
+            Some(secret) => keychain::set_secret(account, secret)?,
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+// Command to get the Amazon settings and whether the client secret and a
+// refresh token are stored
+#[tauri::command]
+pub fn get_amazon_settings(app: AppHandle) -> Result<Option<AmazonSettingsInfo>, String> {
+    if !settings_path(&app)?.exists() {
+        return Ok(None);
+    }
+    let settings = load_settings(&app)?;
+    let has_client_secret = keychain::get_secret(SECRET_ACCOUNT)?.is_some();
+    let connected = keychain::get_secret(TOKEN_ACCOUNT)?.is_some();
+    Ok(Some(AmazonSettingsInfo { settings, has_client_secret, connected }))
+}
+
+// Command to find catalog items by ASIN, UPC, EAN or ISBN, e.g. a scanned
+// barcode, so an offer can be made on the existing product page. The type
+// is worked out from the code when not given.
+#[tauri::command]
+pub async fn lookup_amazon_catalog(
+    app: AppHandle,
+    auth: State<'_, AmazonAuth>,
+    identifier: String,
+    identifier_type: Option<IdentifierType>,
+) -> Result<Vec<CatalogItem>, String> {
+    let settings = load_settings(&app)?;
+    let code: String = identifier.chars().filter(|c| !c.is_whitespace() && *c != '-').collect();
+    let identifier_type = match identifier_type {
+        Some(identifier_type) => identifier_type,
+        None => detect_identifier_type(&code)?,
+    };
+    let token = access_token(&app, &settings, &auth).await?;
+    let type_name = serde_json::to_value(identifier_type)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default();
+    let page_size = CATALOG_PAGE_SIZE.to_string();
+    let result = send(
+        network::client(&app)
+            .get(format!("{}/catalog/2022-04-01/items", settings.region.endpoint()))
+            .header("x-amz-access-token", &token)
+            .query(&[
+                ("identifiers", code.as_str()),
+                ("identifiersType", type_name.as_str()),
+                ("marketplaceIds", settings.marketplace_id.as_str()),
+                ("includedData", "summaries,images,productTypes,identifiers"),
+                ("pageSize", page_size.as_str()),
+            ]),
+        "search Amazon catalog",
+    )
+    .await?;
+    Ok(result["items"]
+        .as_array()
+        .map(|items| items.iter().filter_map(|item| catalog_item(item, &settings.marketplace_id)).collect())
+        .unwrap_or_default())
+}
+
+// Command to list offers on catalog items through a JSON listings feed, all
+// offers in one feed. Returns the feed ID once Amazon has queued it; the
+// listings go live after it's processed, which `get_amazon_feed_status`
+// reports.
+#[tauri::command]
+pub async fn create_amazon_listings(
+    app: AppHandle,
+    auth: State<'_, AmazonAuth>,
+    offers: Vec<AmazonOffer>,
+) -> Result<String, String> {
+    if offers.is_empty() {
+        return Err("No offers given".to_string());
+    }
+    for offer in &offers {
+        if let Some(problem) = offer_problems(offer).into_iter().next() {
+            return Err(format!("{}: {}", offer.sku, problem));
+        }
+    }
+    let settings = load_settings(&app)?;
+    let token = access_token(&app, &settings, &auth).await?;
+
+    let feed = json!({
+        "header": {
+            "sellerId": settings.seller_id.trim(),
+            "version": "2.0",
+            "issueLocale": settings.language_tag,
+        },
+        "messages": offers
+            .iter()
+            .enumerate()
+            .map(|(i, offer)| feed_message(i + 1, offer, &settings))
+            .collect::<Vec<_>>(),
+    });
+    let feed_id = submit_feed(&app, &settings, &token, &feed).await?;
+    // The feed is in; without its SKUs the report only numbers the offers
+    let _ = save_feed(&app, &auth, &feed_id, offers.iter().map(|offer| offer.sku.trim().to_string()).collect());
+    Ok(feed_id)
+}
+
+// Command to get a listings feed's processing status and, once it's done,
+// the issues Amazon found with each offer. With `wait`, polls until the feed
+// is done, emitting each status as an event.
+#[tauri::command]
+pub async fn get_amazon_feed_status(
+    app: AppHandle,
+    auth: State<'_, AmazonAuth>,
+    feed_id: String,
+    wait: Option<bool>,
+) -> Result<FeedStatus, String> {
+    let settings = load_settings(&app)?;
+    let started = Instant::now();
+    loop {
+        let status = feed_status(&app, &settings, &auth, &feed_id).await?;
+        if status.done || !wait.unwrap_or(false) {
+            return Ok(status);
+        }
+        let _ = app.emit_all(FEED_STATUS_EVENT, &status);
+        if started.elapsed() >= FEED_TIMEOUT {
+            return Err(format!("Amazon is still processing feed {}; check again later", feed_id));
+        }
+        tokio::time::sleep(FEED_POLL_INTERVAL).await;
+    }
+}
@@ -0,0 +1,207 @@
+use std::sync::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use crate::storage::{self, PresignedRequest, StorageProvider};
+
+const AUTHORIZE_URL: &str = "https://api.backblazeb2.com/b2api/v2/b2_authorize_account";
+
+// Backblaze B2 native API credentials (an application key)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct B2Config {
+    pub key_id: String,
+    #[serde(default)]
+    pub application_key: String,
+    pub bucket: String,
+    // Custom domain or CDN the bucket is served from, if any
+    #[serde(default)]
+    pub public_url: Option<String>,
+}
+
+impl B2Config {
+    pub fn redacted(&self) -> B2Config {
+        B2Config { application_key: String::new(), ..self.clone() }
+    }
+
+    // Settings come back from the UI redacted; a blank key for the same key
+    // ID means "unchanged"
+    pub fn keep_secret(self, stored: &B2Config) -> B2Config {
+        if self.application_key.is_empty() && self.key_id == stored.key_id {
+            B2Config { application_key: stored.application_key.clone(), ..self }
+        } else {
+            self
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Authorization {
+    account_id: String,
+    authorization_token: String,
+    api_url: String,
+    download_url: String,
+    // Set when the key is restricted to one bucket
+    allowed: Option<AllowedBucket>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AllowedBucket {
+    bucket_id: Option<String>,
+    bucket_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BucketList {
+    buckets: Vec<Bucket>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Bucket {
+    bucket_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UploadUrl {
+    upload_url: String,
+    authorization_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DownloadAuthorization {
+    authorization_token: String,
+}
+
+// B2 native API as a storage provider. B2 has no presigned PUT; instead each
+// upload gets an upload URL plus token, and downloads of private files a
+// download authorization token.
+pub struct B2Provider {
+    config: B2Config,
+    client: reqwest::Client,
+    // Account download host, known after the first authorization
+    download_url: Mutex<Option<String>>,
+}
+
+// Send a B2 API call and decode its JSON response
+async fn call<T: serde::de::DeserializeOwned>(request: reqwest::RequestBuilder, name: &str) -> Result<T, String> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("B2 {} failed: {}", name, e))?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("B2 {} failed ({}): {}", name, status, body.trim()));
+    }
+    response.json()
+        .await
+        .map_err(|e| format!("Unexpected B2 {} response: {}", name, e))
+}
+
+impl B2Provider {
+    pub fn new(config: B2Config, client: reqwest::Client) -> Result<B2Provider, String> {
+        if config.key_id.is_empty() || config.application_key.is_empty() || config.bucket.is_empty() {
+            return Err("B2 settings need a key ID, application key and bucket".to_string());
+        }
+        Ok(B2Provider { config, client, download_url: Mutex::new(None) })
+    }
+
+    async fn authorize(&self) -> Result<Authorization, String> {
+        let request = self.client
+            .get(AUTHORIZE_URL)
+            .basic_auth(&self.config.key_id, Some(&self.config.application_key));
+        let authorization: Authorization = call(request, "authorization").await?;
+        if let Ok(mut download_url) = self.download_url.lock() {
+            *download_url = Some(authorization.download_url.clone());
+        }
+        Ok(authorization)
+    }
+
+    async fn bucket_id(&self, authorization: &Authorization) -> Result<String, String> {
+        // Keys restricted to our bucket already name its ID
+        if let Some(AllowedBucket { bucket_id: Some(id), bucket_name: Some(name) }) = &authorization.allowed {
+            if *name == self.config.bucket {
+                return Ok(id.clone());
+            }
+        }
+
+        let request = self.client
+            .post(format!("{}/b2api/v2/b2_list_buckets", authorization.api_url))
+            .header(reqwest::header::AUTHORIZATION, &authorization.authorization_token)
+            .json(&json!({ "accountId": authorization.account_id, "bucketName": self.config.bucket }));
+        let list: BucketList = call(request, "bucket lookup").await?;
+        list.buckets
+            .into_iter()
+            .next()
+            .map(|bucket| bucket.bucket_id)
+            .ok_or_else(|| format!("B2 bucket not found: {}", self.config.bucket))
+    }
+
+    // Upload URL and token for one upload (B2 tokens are valid for 24 hours)
+    async fn upload_request(&self, object: &str, content_type: Option<&str>) -> Result<PresignedRequest, String> {
+        let authorization = self.authorize().await?;
+        let bucket_id = self.bucket_id(&authorization).await?;
+
+        let request = self.client
+            .post(format!("{}/b2api/v2/b2_get_upload_url", authorization.api_url))
+            .header(reqwest::header::AUTHORIZATION, &authorization.authorization_token)
+            .json(&json!({ "bucketId": bucket_id }));
+        let upload: UploadUrl = call(request, "upload URL").await?;
+
+        let file_name = storage::encode_object_name(object);
+        let headers = [
+            ("Authorization", upload.authorization_token.as_str()),
+            ("X-Bz-File-Name", file_name.as_str()),
+            ("Content-Type", content_type.unwrap_or("b2/x-auto")),
+            ("X-Bz-Content-Sha1", "do_not_verify"),
+        ];
+        Ok(PresignedRequest::new("POST", upload.upload_url, &headers))
+    }
+
+    // Time-limited download URL, which also works for private buckets
+    async fn download_request(&self, object: &str, expires_in_seconds: i64) -> Result<PresignedRequest, String> {
+        let authorization = self.authorize().await?;
+        let bucket_id = self.bucket_id(&authorization).await?;
+
+        let request = self.client
+            .post(format!("{}/b2api/v2/b2_get_download_authorization", authorization.api_url))
+            .header(reqwest::header::AUTHORIZATION, &authorization.authorization_token)
+            .json(&json!({
+                "bucketId": bucket_id,
+                "fileNamePrefix": object,
+                "validDurationInSeconds": expires_in_seconds,
+            }));
+        let download: DownloadAuthorization = call(request, "download authorization").await?;
+
+        let url = format!(
+            "{}/file/{}/{}?Authorization={}",
+            authorization.download_url,
+            self.config.bucket,
+            storage::encode_object_name(object),
+            urlencoding::encode(&download.authorization_token)
+        );
+        Ok(PresignedRequest::new("GET", url, &[]))
+    }
+}
+
+impl StorageProvider for B2Provider {
+    async fn presign(&self, method: &str, object: &str, content_type: Option<&str>, expires_in_seconds: i64) -> Result<PresignedRequest, String> {
+        match method {
+            "PUT" | "POST" => self.upload_request(object, content_type).await,
+            "GET" => self.download_request(object, expires_in_seconds).await,
+            other => Err(format!("B2 does not support presigned {} requests", other)),
+        }
+    }
+
+    fn object_url(&self, object: &str) -> String {
+        let base = self.config.public_url.clone().unwrap_or_else(|| {
+            let download_url = self.download_url.lock().ok().and_then(|url| url.clone()).unwrap_or_default();
+            format!("{}/file/{}", download_url, self.config.bucket)
+        });
+        format!("{}/{}", base.trim_end_matches('/'), storage::encode_object_name(object))
+    }
+}
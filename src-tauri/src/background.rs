@@ -0,0 +1,240 @@
+use std::fs;
+use image::{DynamicImage, GrayImage, Luma, Rgba, RgbImage, RgbaImage, imageops::FilterType};
+use serde::Serialize;
+use tauri::{AppHandle, State};
+use tract_onnx::prelude::*;
+use crate::{image_io, photo_editing};
+use crate::models::ModelStore;
+
+// U2-Net (rembg) salient object segmentation model
+const U2NET_MODEL: &str = "u2netp.onnx";
+const U2NET_SIZE: usize = 320;
+
+// ImageNet normalization used when U2-Net was trained
+const MEAN: [f32; 3] = [0.485, 0.456, 0.406];
+const STD: [f32; 3] = [0.229, 0.224, 0.225];
+
+// Predict a foreground mask (255 = item) at the image's original size
+pub fn foreground_mask(models: &ModelStore, img: &DynamicImage) -> Result<GrayImage, String> {
+    let model = models.get(U2NET_MODEL, [1, 3, U2NET_SIZE, U2NET_SIZE])?;
+
+    let resized = img
+        .resize_exact(U2NET_SIZE as u32, U2NET_SIZE as u32, FilterType::Lanczos3)
+        .to_rgb8();
+
+    // Scale by the brightest channel value, then normalize per channel
+    let max_value = resized.pixels().flat_map(|p| p.0).max().unwrap_or(255).max(1) as f32;
+    let input: Tensor = tract_ndarray::Array4::from_shape_fn(
+        (1, 3, U2NET_SIZE, U2NET_SIZE),
+        |(_, c, y, x)| {
+            let value = resized.get_pixel(x as u32, y as u32)[c] as f32 / max_value;
+            (value - MEAN[c]) / STD[c]
+        },
+    )
+    .into();
+
+    let outputs = model.run(tvec!(input.into()))
+        .map_err(|e| format!("Background removal failed: {}", e))?;
+    let prediction = outputs[0]
+        .to_array_view::<f32>()
+        .map_err(|e| format!("Unexpected model output: {}", e))?;
+
+    // First output is the fused saliency map; stretch it to 0-255
+    let values: Vec<f32> = prediction.iter().take(U2NET_SIZE * U2NET_SIZE).copied().collect();
+    let min = values.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let range = (max - min).max(f32::EPSILON);
+
+    let mut mask = GrayImage::new(U2NET_SIZE as u32, U2NET_SIZE as u32);
+    for (i, value) in values.iter().enumerate() {
+        let x = (i % U2NET_SIZE) as u32;
+        let y = (i / U2NET_SIZE) as u32;
+        mask.put_pixel(x, y, Luma([(((value - min) / range) * 255.0) as u8]));
+    }
+
+    Ok(image::imageops::resize(&mask, img.width(), img.height(), FilterType::Triangle))
+}
+
+// Composite an image over a solid colour (or transparency) using a mask
+pub fn apply_mask(img: &DynamicImage, mask: &GrayImage, background: Option<[u8; 3]>) -> DynamicImage {
+    let rgb = img.to_rgb8();
+    let mut output = RgbaImage::new(rgb.width(), rgb.height());
+
+    for (x, y, pixel) in rgb.enumerate_pixels() {
+        let alpha = mask.get_pixel(x, y)[0];
+        let composited = match background {
+            Some(bg) => {
+                let a = alpha as f32 / 255.0;
+                let blend = |fg: u8, bg: u8| (fg as f32 * a + bg as f32 * (1.0 - a)).round() as u8;
+                Rgba([blend(pixel[0], bg[0]), blend(pixel[1], bg[1]), blend(pixel[2], bg[2]), 255])
+            }
+            None => Rgba([pixel[0], pixel[1], pixel[2], alpha]),
+        };
+        output.put_pixel(x, y, composited);
+    }
+
+    match background {
+        Some(_) => DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(output).to_rgb8()),
+        None => DynamicImage::ImageRgba8(output),
+    }
+}
+
+// Command to remove the background from a photo with the local U2-Net model
+// `bg_color` is a hex colour like "#ffffff"; leave it out for a transparent PNG
+#[tauri::command(async)]
+pub fn remove_background(
+    models: State<ModelStore>,
+    path: String,
+    output_path: String,
+    bg_color: Option<String>,
+) -> Result<String, String> {
+    let background = bg_color.as_deref().map(image_io::parse_hex_color).transpose()?;
+    if background.is_none() && !output_path.to_lowercase().ends_with(".png") {
+        return Err("Transparent output requires a .png output path".to_string());
+    }
+
+    let img = image_io::open_image(&path)?;
+    let mask = foreground_mask(&models, &img)?;
+    let result = apply_mask(&img, &mask, background);
+
+    result.save(&output_path)
+        .map_err(|e| format!("Failed to save image: {}", e))?;
+
+    Ok(output_path)
+}
+
+// Image rules for a marketplace's primary (first) photo
+struct PrimaryImagePolicy {
+    // Shortest allowed longest edge, in pixels
+    min_edge: u32,
+    // Size photos are scaled to when the source is large enough
+    target_edge: u32,
+    max_bytes: u64,
+}
+
+fn primary_image_policy(marketplace: &str) -> Result<PrimaryImagePolicy, String> {
+    match marketplace {
+        // eBay: at least 500px on the longest side, 1600px recommended for zoom
+        "ebay" => Ok(PrimaryImagePolicy { min_edge: 500, target_edge: 1600, max_bytes: 12 * 1024 * 1024 }),
+        // Amazon: pure white background, 1000px or more enables zoom
+        "amazon" => Ok(PrimaryImagePolicy { min_edge: 1000, target_edge: 2000, max_bytes: 10 * 1024 * 1024 }),
+        other => Err(format!("Unknown marketplace: {}", other)),
+    }
+}
+
+// Share of the square canvas the item fills, within Amazon's 85% guideline
+const PRIMARY_FILL: f64 = 0.85;
+
+// Mask value above which a pixel counts as part of the item
+pub const FOREGROUND_THRESHOLD: u8 = 128;
+
+// Border pixels may drift this far from 255 after JPEG encoding and still
+// count as pure white
+const WHITE_TOLERANCE: u8 = 3;
+
+#[derive(Debug, Serialize)]
+pub struct PolicyCheck {
+    pub rule: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PrimaryImage {
+    pub output_path: String,
+    pub width: u32,
+    pub height: u32,
+    // Every check passed
+    pub compliant: bool,
+    pub checks: Vec<PolicyCheck>,
+}
+
+// Bounding box (left, top, width, height) of the mask's foreground
+fn foreground_bounds(mask: &GrayImage) -> Option<(u32, u32, u32, u32)> {
+    let (mut left, mut top, mut right, mut bottom) = (u32::MAX, u32::MAX, 0, 0);
+    for (x, y, value) in mask.enumerate_pixels() {
+        if value[0] > FOREGROUND_THRESHOLD {
+            left = left.min(x);
+            top = top.min(y);
+            right = right.max(x);
+            bottom = bottom.max(y);
+        }
+    }
+    (left <= right && top <= bottom).then(|| (left, top, right - left + 1, bottom - top + 1))
+}
+
+// Whether the outermost pixels of an image are all pure white
+fn border_is_white(img: &RgbImage) -> bool {
+    let (width, height) = img.dimensions();
+    let white = |x: u32, y: u32| img.get_pixel(x, y).0.iter().all(|&c| c >= 255 - WHITE_TOLERANCE);
+    (0..width).all(|x| white(x, 0) && white(x, height - 1)) && (0..height).all(|y| white(0, y) && white(width - 1, y))
+}
+
+// Command to turn a photo into a marketplace-ready primary image in one step:
+// remove the background, composite onto pure white, centre the item on a
+// square canvas and scale it to the marketplace's size rules. The JPEG
+// written is then checked against the policy (`marketplace` is "ebay", the
+// default, or "amazon").
+#[tauri::command(async)]
+pub fn make_marketplace_primary(
+    app: AppHandle,
+    models: State<ModelStore>,
+    path: String,
+    marketplace: Option<String>,
+    output_path: Option<String>,
+) -> Result<PrimaryImage, String> {
+    let policy = primary_image_policy(marketplace.as_deref().unwrap_or("ebay"))?;
+
+    let img = image_io::open_image(&path)?;
+    let mask = foreground_mask(&models, &img)?;
+    let (left, top, width, height) = foreground_bounds(&mask).ok_or("No item detected in photo")?;
+    let item = apply_mask(&img, &mask, Some([255, 255, 255])).crop_imm(left, top, width, height).to_rgb8();
+
+    // Square canvas sized so the item fills PRIMARY_FILL of it, then scaled
+    // down to the target size or up to the minimum
+    let side = (width.max(height) as f64 / PRIMARY_FILL).ceil() as u32;
+    let mut canvas = RgbImage::from_pixel(side, side, image::Rgb([255, 255, 255]));
+    image::imageops::overlay(&mut canvas, &item, ((side - width) / 2) as i64, ((side - height) / 2) as i64);
+    let final_side = side.clamp(policy.min_edge, policy.target_edge);
+    let canvas = if final_side == side {
+        canvas
+    } else {
+        image::imageops::resize(&canvas, final_side, final_side, FilterType::Lanczos3)
+    };
+
+    let jpeg = image_io::encode_jpeg(&DynamicImage::ImageRgb8(canvas), photo_editing::EDIT_JPEG_QUALITY)?;
+    let output_path = photo_editing::edited_output_path(&app, &path, output_path, "primary", "jpg")?;
+    fs::write(&output_path, &jpeg)
+        .map_err(|e| format!("Failed to write primary image: {}", e))?;
+
+    // Validate what was actually written, after JPEG encoding
+    let written = image::load_from_memory(&jpeg)
+        .map_err(|e| format!("Failed to read back primary image: {}", e))?
+        .to_rgb8();
+    let (out_width, out_height) = written.dimensions();
+    let checks = vec![
+        PolicyCheck {
+            rule: "min_resolution".to_string(),
+            passed: out_width.max(out_height) >= policy.min_edge,
+            detail: format!("{}x{}, minimum {}px on the longest side", out_width, out_height, policy.min_edge),
+        },
+        PolicyCheck {
+            rule: "white_background".to_string(),
+            passed: border_is_white(&written),
+            detail: "Edges of the image are pure white".to_string(),
+        },
+        PolicyCheck {
+            rule: "file_size".to_string(),
+            passed: jpeg.len() as u64 <= policy.max_bytes,
+            detail: format!("{} bytes, maximum {}", jpeg.len(), policy.max_bytes),
+        },
+    ];
+
+    Ok(PrimaryImage {
+        output_path: image_io::path_to_string(&output_path)?,
+        width: out_width,
+        height: out_height,
+        compliant: checks.iter().all(|check| check.passed),
+        checks,
+    })
+}
@@ -0,0 +1,269 @@
+use std::collections::HashSet;
+use image::GrayImage;
+use rxing::{BarcodeFormat, DecodeHints};
+use serde::Serialize;
+use tauri::AppHandle;
+use crate::{image_io, network};
+
+// Working size for barcode scanning; bars need a few pixels each
+const BARCODE_EDGE: u32 = 1600;
+
+// Formats looked for; others (PDF417, Aztec, ...) rarely appear on
+// resale items and only slow the scan down
+const FORMATS: [BarcodeFormat; 7] = [
+    BarcodeFormat::UPC_A,
+    BarcodeFormat::UPC_E,
+    BarcodeFormat::EAN_13,
+    BarcodeFormat::EAN_8,
+    BarcodeFormat::CODE_128,
+    BarcodeFormat::QR_CODE,
+    BarcodeFormat::DATA_MATRIX,
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Symbology {
+    UpcA,
+    UpcE,
+    Ean13,
+    Ean8,
+    // EAN-13 in the 978/979 "Bookland" range
+    Isbn13,
+    Code128,
+    QrCode,
+    DataMatrix,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Barcode {
+    pub symbology: Symbology,
+    pub value: String,
+    // The older 10-digit ISBN of a 978 ISBN-13; 979 ISBNs have none
+    pub isbn10: Option<String>,
+    // Response from the catalog lookup, when one was requested and succeeded
+    pub catalog: Option<serde_json::Value>,
+}
+
+// Whether the last digit of a UPC/EAN is the right check digit for the rest
+fn checksum_ok(digits: &str) -> bool {
+    let Some(digits) = digits.chars().map(|c| c.to_digit(10)).collect::<Option<Vec<u32>>>() else {
+        return false;
+    };
+    let Some((check, body)) = digits.split_last() else {
+        return false;
+    };
+    let sum: u32 = body
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| d * if i % 2 == 0 { 3 } else { 1 })
+        .sum();
+    (10 - sum % 10) % 10 == *check
+}
+
+// The ISBN-10 of a 978 ISBN-13: the nine digits after the prefix and a
+// mod-11 check digit, X standing for 10
+fn isbn10_of(isbn13: &str) -> Option<String> {
+    let body = isbn13.strip_prefix("978")?.get(..9)?;
+    let sum: u32 = body
+        .chars()
+        .zip((2..=10).rev())
+        .map(|(c, weight)| c.to_digit(10).map(|d| d * weight))
+        .sum::<Option<u32>>()?;
+    let check = match (11 - sum % 11) % 11 {
+        10 => 'X',
+        check => char::from(b'0' + check as u8),
+    };
+    Some(format!("{}{}", body, check))
+}
+
+// Symbology of a decoded code, or None for a UPC/EAN failing its checksum
+fn classify(format: &BarcodeFormat, value: &str) -> Option<Symbology> {
+    let symbology = match format {
+        BarcodeFormat::UPC_A => Symbology::UpcA,
+        BarcodeFormat::UPC_E => Symbology::UpcE,
+        BarcodeFormat::EAN_8 => Symbology::Ean8,
+        BarcodeFormat::EAN_13 if value.starts_with("978") || value.starts_with("979") => Symbology::Isbn13,
+        BarcodeFormat::EAN_13 => Symbology::Ean13,
+        BarcodeFormat::CODE_128 => return Some(Symbology::Code128),
+        BarcodeFormat::QR_CODE => return Some(Symbology::QrCode),
+        BarcodeFormat::DATA_MATRIX => return Some(Symbology::DataMatrix),
+        _ => return None,
+    };
+    // UPC-E carries its check digit for the expanded UPC-A, so it's left
+    // to the decoder
+    (symbology == Symbology::UpcE || checksum_ok(value)).then_some(symbology)
+}
+
+// Every supported code in the image, in the order found, each once
+fn scan(gray: &GrayImage) -> Vec<(Symbology, String)> {
+    let mut hints = DecodeHints {
+        PossibleFormats: Some(HashSet::from(FORMATS)),
+        TryHarder: Some(true),
+        ..DecodeHints::default()
+    };
+    let (width, height) = gray.dimensions();
+    // No code in the image is an error to rxing
+    let results = rxing::helpers::detect_multiple_in_luma_with_hints(gray.as_raw().clone(), width, height, &mut hints)
+        .unwrap_or_default();
+
+    let mut codes: Vec<(Symbology, String)> = Vec::new();
+    for result in results {
+        let value = result.getText().to_string();
+        if let Some(symbology) = classify(result.getBarcodeFormat(), &value) {
+            if !codes.iter().any(|(_, found)| *found == value) {
+                codes.push((symbology, value));
+            }
+        }
+    }
+    codes
+}
+
+// GET a catalog lookup URL with `{code}` filled in, expecting JSON back
+async fn lookup(app: &AppHandle, url_template: &str, code: &str) -> Result<serde_json::Value, String> {
+    let url = url_template.replace("{code}", &urlencoding::encode(code));
+    let response = network::client(app)
+        .get(&url)
+        .send()
+        .await
+        .map_err(|e| format!("Catalog lookup failed: {}", e))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(format!("Catalog lookup failed with status {}", status));
+    }
+    response.json().await.map_err(|e| format!("Catalog lookup returned invalid JSON: {}", e))
+}
+
+// Command to find barcodes in a photo: UPC-A/E, EAN-13 (including ISBN, with
+// its ISBN-10) and EAN-8 product codes, and Code 128, QR and Data Matrix
+// labels. `lookup_url`, if given, is a catalog URL template containing
+// `{code}`; each detected code is looked up there and the JSON response
+// returned with it. A failed lookup leaves `catalog` empty rather than
+// failing the scan.
+#[tauri::command]
+pub async fn detect_barcodes(app: AppHandle, path: String, lookup_url: Option<String>) -> Result<Vec<Barcode>, String> {
+    if let Some(template) = &lookup_url {
+        if !template.contains("{code}") {
+            return Err("lookup_url must contain {code}".to_string());
+        }
+    }
+
+    let codes = tauri::async_runtime::spawn_blocking(move || {
+        let gray = image_io::open_image_scaled(&path, BARCODE_EDGE)?.to_luma8();
+        Ok::<_, String>(scan(&gray))
+    })
+    .await
+    .map_err(|e| format!("Barcode scan failed: {}", e))??;
+
+    let mut barcodes = Vec::with_capacity(codes.len());
+    for (symbology, value) in codes {
+        let catalog = match &lookup_url {
+            Some(template) => lookup(&app, template, &value).await.ok(),
+            None => None,
+        };
+        let isbn10 = if symbology == Symbology::Isbn13 { isbn10_of(&value) } else { None };
+        barcodes.push(Barcode { symbology, value, isbn10, catalog });
+    }
+    Ok(barcodes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+    use rxing::{MultiFormatWriter, Writer};
+
+    // A code as a photo would show it: rendered by rxing's encoder, scaled
+    // up and set on a white page
+    fn render(format: BarcodeFormat, value: &str, width: i32, height: i32) -> GrayImage {
+        let matrix = MultiFormatWriter.encode(value, &format, width, height).expect("encode");
+        let border = 40;
+        let mut image = GrayImage::from_pixel(matrix.width() + border * 2, matrix.height() + border * 2, Luma([255]));
+        for y in 0..matrix.height() {
+            for x in 0..matrix.width() {
+                if matrix.get(x, y) {
+                    image.put_pixel(x + border, y + border, Luma([0]));
+                }
+            }
+        }
+        image
+    }
+
+    fn scanned(format: BarcodeFormat, value: &str, width: i32, height: i32) -> Vec<(Symbology, String)> {
+        scan(&render(format, value, width, height))
+    }
+
+    #[test]
+    fn check_digits() {
+        assert!(checksum_ok("4006381333931"));
+        assert!(checksum_ok("036000291452"));
+        assert!(checksum_ok("96385074"));
+        assert!(checksum_ok("9780306406157"));
+        assert!(!checksum_ok("4006381333932"));
+        assert!(!checksum_ok("036000291453"));
+        assert!(!checksum_ok("40063813339a1"));
+        assert!(!checksum_ok(""));
+    }
+
+    #[test]
+    fn isbn10_from_isbn13() {
+        assert_eq!(isbn10_of("9780306406157").as_deref(), Some("0306406152"));
+        // Check digit 10 is written X
+        assert_eq!(isbn10_of("9780804429573").as_deref(), Some("080442957X"));
+        assert_eq!(isbn10_of("9791034304899"), None);
+    }
+
+    #[test]
+    fn classify_rejects_bad_check_digits() {
+        assert_eq!(classify(&BarcodeFormat::EAN_13, "4006381333931"), Some(Symbology::Ean13));
+        assert_eq!(classify(&BarcodeFormat::EAN_13, "4006381333932"), None);
+        assert_eq!(classify(&BarcodeFormat::EAN_13, "9791034304899"), Some(Symbology::Isbn13));
+        assert_eq!(classify(&BarcodeFormat::CODE_128, "anything"), Some(Symbology::Code128));
+    }
+
+    #[test]
+    fn reads_ean13() {
+        assert_eq!(scanned(BarcodeFormat::EAN_13, "4006381333931", 400, 150), vec![(Symbology::Ean13, "4006381333931".to_string())]);
+    }
+
+    #[test]
+    fn reads_upc_a() {
+        assert_eq!(scanned(BarcodeFormat::UPC_A, "036000291452", 400, 150), vec![(Symbology::UpcA, "036000291452".to_string())]);
+    }
+
+    #[test]
+    fn reads_upc_e() {
+        assert_eq!(scanned(BarcodeFormat::UPC_E, "01234565", 300, 150), vec![(Symbology::UpcE, "01234565".to_string())]);
+    }
+
+    #[test]
+    fn reads_ean8() {
+        assert_eq!(scanned(BarcodeFormat::EAN_8, "96385074", 300, 150), vec![(Symbology::Ean8, "96385074".to_string())]);
+    }
+
+    #[test]
+    fn reads_isbn() {
+        assert_eq!(scanned(BarcodeFormat::EAN_13, "9780306406157", 400, 150), vec![(Symbology::Isbn13, "9780306406157".to_string())]);
+    }
+
+    #[test]
+    fn reads_code128() {
+        assert_eq!(scanned(BarcodeFormat::CODE_128, "SKU-0042-B12", 500, 150), vec![(Symbology::Code128, "SKU-0042-B12".to_string())]);
+    }
+
+    #[test]
+    fn reads_qr_code() {
+        let url = "https://example.com/item/42";
+        assert_eq!(scanned(BarcodeFormat::QR_CODE, url, 300, 300), vec![(Symbology::QrCode, url.to_string())]);
+    }
+
+    #[test]
+    fn reads_data_matrix() {
+        assert_eq!(scanned(BarcodeFormat::DATA_MATRIX, "LOT 7731", 200, 200), vec![(Symbology::DataMatrix, "LOT 7731".to_string())]);
+    }
+
+    #[test]
+    fn blank_image_has_no_codes() {
+        assert!(scan(&GrayImage::from_pixel(400, 300, Luma([255]))).is_empty());
+    }
+}
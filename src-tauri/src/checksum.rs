@@ -0,0 +1,63 @@
+use tokio::io::AsyncReadExt;
+
+// Castagnoli polynomial, bit-reversed
+const CRC32C_POLY: u32 = 0x82F6_3B78;
+
+// Read size when checksumming a file
+const READ_BYTES: usize = 256 * 1024;
+
+const fn crc32c_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ CRC32C_POLY } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+static CRC32C_TABLE: [u32; 256] = crc32c_table();
+
+// Running CRC32C, the checksum GCS keeps for every object
+pub struct Crc32c(u32);
+
+impl Crc32c {
+    pub fn new() -> Crc32c {
+        Crc32c(!0)
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = CRC32C_TABLE[((self.0 ^ byte as u32) & 0xFF) as usize] ^ (self.0 >> 8);
+        }
+    }
+
+    pub fn finish(&self) -> u32 {
+        !self.0
+    }
+}
+
+// CRC32C of a file's contents
+pub async fn file_crc32c(path: &str) -> Result<u32, String> {
+    let mut file = tokio::fs::File::open(path)
+        .await
+        .map_err(|e| format!("Failed to open file: {}", e))?;
+
+    let mut crc = Crc32c::new();
+    let mut buffer = vec![0u8; READ_BYTES];
+    loop {
+        let read = file.read(&mut buffer)
+            .await
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+        if read == 0 {
+            return Ok(crc.finish());
+        }
+        crc.update(&buffer[..read]);
+    }
+}
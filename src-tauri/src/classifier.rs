@@ -0,0 +1,107 @@
+use image::{DynamicImage, imageops::FilterType};
+use serde::Serialize;
+use tauri::State;
+use tract_onnx::prelude::*;
+use crate::image_io;
+use crate::models::ModelStore;
+
+// Small image classifier fine-tuned on coarse resale categories
+pub const CLASSIFIER_MODEL: &str = "category-classifier.onnx";
+const CLASSIFIER_SIZE: usize = 224;
+
+// ImageNet normalization used by the classifier's backbone
+const MEAN: [f32; 3] = [0.485, 0.456, 0.406];
+const STD: [f32; 3] = [0.229, 0.224, 0.225];
+
+// Categories in the order of the model's outputs
+pub const CATEGORIES: [&str; 12] = [
+    "clothing",
+    "shoes",
+    "bags",
+    "accessories",
+    "jewelry",
+    "electronics",
+    "toys",
+    "media",
+    "home",
+    "sports",
+    "beauty",
+    "other",
+];
+
+// Scores returned alongside the best category
+const TOP_SCORES: usize = 3;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CategoryScore {
+    pub category: String,
+    pub confidence: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Classification {
+    pub category: String,
+    pub confidence: f32,
+    // Most likely categories, best first, including the one above
+    pub scores: Vec<CategoryScore>,
+}
+
+// Predict the coarse category of the item in a photo
+pub fn classify(models: &ModelStore, img: &DynamicImage) -> Result<Classification, String> {
+    let model = models.get(CLASSIFIER_MODEL, [1, 3, CLASSIFIER_SIZE, CLASSIFIER_SIZE])?;
+
+    let resized = img
+        .resize_to_fill(CLASSIFIER_SIZE as u32, CLASSIFIER_SIZE as u32, FilterType::CatmullRom)
+        .to_rgb8();
+    let input: Tensor = tract_ndarray::Array4::from_shape_fn(
+        (1, 3, CLASSIFIER_SIZE, CLASSIFIER_SIZE),
+        |(_, c, y, x)| {
+            let value = resized.get_pixel(x as u32, y as u32)[c] as f32 / 255.0;
+            (value - MEAN[c]) / STD[c]
+        },
+    )
+    .into();
+
+    let outputs = model.run(tvec!(input.into()))
+        .map_err(|e| format!("Classification failed: {}", e))?;
+    let logits: Vec<f32> = outputs[0]
+        .to_array_view::<f32>()
+        .map_err(|e| format!("Unexpected model output: {}", e))?
+        .iter()
+        .copied()
+        .collect();
+    if logits.len() != CATEGORIES.len() {
+        return Err(format!(
+            "{} has {} outputs; expected {} categories",
+            CLASSIFIER_MODEL,
+            logits.len(),
+            CATEGORIES.len()
+        ));
+    }
+
+    // Softmax, shifted by the largest logit to avoid overflow
+    let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let exps: Vec<f32> = logits.iter().map(|v| (v - max).exp()).collect();
+    let total: f32 = exps.iter().sum();
+
+    let mut scores: Vec<CategoryScore> = CATEGORIES
+        .iter()
+        .zip(exps)
+        .map(|(category, exp)| CategoryScore { category: category.to_string(), confidence: exp / total })
+        .collect();
+    scores.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    scores.truncate(TOP_SCORES);
+
+    Ok(Classification {
+        category: scores[0].category.clone(),
+        confidence: scores[0].confidence,
+        scores,
+    })
+}
+
+// Command to predict a photo's coarse category with the local classifier model
+#[tauri::command(async)]
+pub fn classify_item(models: State<ModelStore>, path: String) -> Result<Classification, String> {
+    let img = image_io::open_image(&path)?;
+    classify(&models, &img)
+}
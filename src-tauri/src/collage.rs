@@ -0,0 +1,82 @@
+use std::fs;
+use image::{DynamicImage, Rgb, RgbImage, imageops};
+use tauri::AppHandle;
+use crate::image_io;
+use crate::photo_editing::{self, EDIT_JPEG_QUALITY};
+
+// Most photos a collage can hold
+const MAX_PHOTOS: usize = 6;
+
+// Width of the finished collage in pixels
+const COLLAGE_WIDTH: u32 = 1600;
+
+const DEFAULT_SPACING: u32 = 12;
+
+// Parse a "<columns>x<rows>" layout such as "2x2" or "3x2"
+fn parse_layout(layout: &str) -> Result<(u32, u32), String> {
+    let (columns, rows) = layout.trim().to_lowercase()
+        .split_once('x')
+        .and_then(|(c, r)| Some((c.parse::<u32>().ok()?, r.parse::<u32>().ok()?)))
+        .ok_or_else(|| format!("Invalid layout: {} (expected e.g. \"2x2\")", layout))?;
+
+    if columns == 0 || rows == 0 || columns.saturating_mul(rows) as usize > MAX_PHOTOS {
+        return Err(format!("Layout must have between 1 and {} cells", MAX_PHOTOS));
+    }
+    Ok((columns, rows))
+}
+
+// Command to combine several photos (e.g. front/back/tag/flaw) into one JPEG
+// `layout` is "<columns>x<rows>"; photos fill cells left to right, top to bottom,
+// each cropped to fill its cell. `spacing` is the gap in pixels (default 12) and
+// `background_color` a hex colour for the gaps (default white).
+#[tauri::command(async)]
+pub fn create_collage(
+    app: AppHandle,
+    paths: Vec<String>,
+    layout: String,
+    output_path: Option<String>,
+    spacing: Option<u32>,
+    background_color: Option<String>,
+) -> Result<String, String> {
+    let (columns, rows) = parse_layout(&layout)?;
+    if paths.is_empty() || paths.len() > (columns * rows) as usize {
+        return Err(format!("Layout {} holds 1 to {} photos", layout, columns * rows));
+    }
+
+    let spacing = spacing.unwrap_or(DEFAULT_SPACING);
+    let background = background_color
+        .as_deref()
+        .map(image_io::parse_hex_color)
+        .transpose()?
+        .unwrap_or([255, 255, 255]);
+
+    // Square cells sized so the collage is COLLAGE_WIDTH wide. Capping the
+    // spacing first keeps the sizes below from overflowing, as layouts are
+    // at most MAX_PHOTOS cells across or down.
+    if spacing >= COLLAGE_WIDTH {
+        return Err("spacing is too large for this layout".to_string());
+    }
+    let cell = COLLAGE_WIDTH.saturating_sub(spacing * (columns + 1)) / columns;
+    if cell == 0 {
+        return Err("spacing is too large for this layout".to_string());
+    }
+    let width = cell * columns + spacing * (columns + 1);
+    let height = cell * rows + spacing * (rows + 1);
+
+    let mut canvas = RgbImage::from_pixel(width, height, Rgb(background));
+    for (index, path) in paths.iter().enumerate() {
+        let photo = image_io::open_image(path)?
+            .resize_to_fill(cell, cell, imageops::FilterType::Lanczos3)
+            .to_rgb8();
+        let column = index as u32 % columns;
+        let row = index as u32 / columns;
+        let x = spacing + column * (cell + spacing);
+        let y = spacing + row * (cell + spacing);
+        imageops::overlay(&mut canvas, &photo, x as i64, y as i64);
+    }
+
+    let output_path = photo_editing::edited_output_path(&app, &paths[0], output_path, "collage", "jpg")?;
+    fs::write(&output_path, image_io::encode_jpeg(&DynamicImage::ImageRgb8(canvas), EDIT_JPEG_QUALITY)?)
+        .map_err(|e| format!("Failed to write collage: {}", e))?;
+    image_io::path_to_string(&output_path)
+}
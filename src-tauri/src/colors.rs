@@ -0,0 +1,203 @@
+use serde::Serialize;
+use crate::image_io;
+
+// Longest edge photos are scaled to before clustering
+const SAMPLE_EDGE: u32 = 100;
+
+const MAX_ITERATIONS: usize = 20;
+
+// Largest number of colors that can be requested
+const MAX_COLORS: usize = 12;
+
+// Names offered for marketplace "Color" item specifics
+const NAMED_COLORS: [(&str, [u8; 3]); 30] = [
+    ("black", [0, 0, 0]),
+    ("charcoal", [54, 69, 79]),
+    ("gray", [128, 128, 128]),
+    ("silver", [192, 192, 192]),
+    ("white", [255, 255, 255]),
+    ("ivory", [255, 255, 240]),
+    ("beige", [225, 198, 153]),
+    ("tan", [210, 180, 140]),
+    ("khaki", [195, 176, 145]),
+    ("brown", [139, 69, 19]),
+    ("chocolate", [90, 50, 30]),
+    ("burgundy", [128, 0, 32]),
+    ("maroon", [110, 20, 30]),
+    ("red", [220, 20, 30]),
+    ("coral", [255, 127, 80]),
+    ("orange", [255, 140, 0]),
+    ("gold", [212, 175, 55]),
+    ("yellow", [255, 220, 0]),
+    ("olive", [128, 128, 0]),
+    ("green", [34, 139, 34]),
+    ("mint", [152, 255, 152]),
+    ("teal", [0, 128, 128]),
+    ("turquoise", [64, 224, 208]),
+    ("light blue", [150, 200, 240]),
+    ("blue", [30, 80, 200]),
+    ("navy", [20, 30, 80]),
+    ("purple", [110, 40, 150]),
+    ("lavender", [190, 170, 230]),
+    ("pink", [255, 170, 200]),
+    ("magenta", [200, 30, 140]),
+];
+
+#[derive(Debug, Serialize)]
+pub struct DominantColor {
+    hex: String,
+    name: String,
+    // Share of the photo's pixels closest to this color
+    fraction: f64,
+}
+
+// Convert sRGB to CIE Lab so color distances match perceived differences
+fn to_lab(rgb: [f64; 3]) -> [f64; 3] {
+    let linear = rgb.map(|c| {
+        let c = c / 255.0;
+        if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+    });
+
+    // D65 white point
+    let x = (0.4124 * linear[0] + 0.3576 * linear[1] + 0.1805 * linear[2]) / 0.95047;
+    let y = 0.2126 * linear[0] + 0.7152 * linear[1] + 0.0722 * linear[2];
+    let z = (0.0193 * linear[0] + 0.1192 * linear[1] + 0.9505 * linear[2]) / 1.08883;
+
+    let f = |t: f64| if t > 0.008856 { t.cbrt() } else { 7.787 * t + 16.0 / 116.0 };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+fn distance_sq(a: &[f64; 3], b: &[f64; 3]) -> f64 {
+    (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)
+}
+
+fn nearest(point: &[f64; 3], centroids: &[[f64; 3]]) -> usize {
+    centroids
+        .iter()
+        .enumerate()
+        .min_by(|a, b| distance_sq(point, a.1).total_cmp(&distance_sq(point, b.1)))
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+// Nearest human-readable name for a color
+fn color_name(lab: &[f64; 3]) -> &'static str {
+    NAMED_COLORS
+        .iter()
+        .min_by(|a, b| {
+            let da = distance_sq(lab, &to_lab(a.1.map(f64::from)));
+            let db = distance_sq(lab, &to_lab(b.1.map(f64::from)));
+            da.total_cmp(&db)
+        })
+        .map(|(name, _)| *name)
+        .unwrap_or("unknown")
+}
+
+// Cluster pixels (in Lab) into `count` colors with k-means.
+// Centroids start from farthest-point seeding so results are deterministic.
+fn kmeans(points: &[[f64; 3]], count: usize) -> (Vec<[f64; 3]>, Vec<usize>) {
+    let mut centroids = vec![points[0]];
+    while centroids.len() < count {
+        let farthest = points
+            .iter()
+            .max_by(|a, b| {
+                let da = distance_sq(a, &centroids[nearest(a, &centroids)]);
+                let db = distance_sq(b, &centroids[nearest(b, &centroids)]);
+                da.total_cmp(&db)
+            })
+            .copied()
+            .unwrap_or(points[0]);
+        centroids.push(farthest);
+    }
+
+    let mut assignments = vec![0; points.len()];
+    for _ in 0..MAX_ITERATIONS {
+        let mut changed = false;
+        for (point, assignment) in points.iter().zip(assignments.iter_mut()) {
+            let closest = nearest(point, &centroids);
+            if closest != *assignment {
+                *assignment = closest;
+                changed = true;
+            }
+        }
+
+        let mut sums = vec![[0.0; 3]; count];
+        let mut sizes = vec![0usize; count];
+        for (point, &assignment) in points.iter().zip(&assignments) {
+            for c in 0..3 {
+                sums[assignment][c] += point[c];
+            }
+            sizes[assignment] += 1;
+        }
+        for ((centroid, sum), size) in centroids.iter_mut().zip(sums).zip(sizes) {
+            if size > 0 {
+                *centroid = sum.map(|c| c / size as f64);
+            }
+        }
+
+        if !changed {
+            break;
+        }
+    }
+
+    (centroids, assignments)
+}
+
+// Command to find a photo's main colors, most common first
+// Each color comes with a hex value and the nearest named color (e.g. "navy")
+#[tauri::command(async)]
+pub fn extract_dominant_colors(path: String, count: usize) -> Result<Vec<DominantColor>, String> {
+    if !(1..=MAX_COLORS).contains(&count) {
+        return Err(format!("count must be between 1 and {}", MAX_COLORS));
+    }
+
+    let img = image_io::open_image_scaled(&path, SAMPLE_EDGE)?.to_rgba8();
+
+    // Ignore transparent pixels, e.g. from background removal
+    let pixels: Vec<[f64; 3]> = img
+        .pixels()
+        .filter(|p| p[3] >= 128)
+        .map(|p| [p[0] as f64, p[1] as f64, p[2] as f64])
+        .collect();
+    if pixels.is_empty() {
+        return Err("Photo has no opaque pixels".to_string());
+    }
+
+    let points: Vec<[f64; 3]> = pixels.iter().map(|p| to_lab(*p)).collect();
+    let (centroids, assignments) = kmeans(&points, count.min(points.len()));
+
+    let mut colors: Vec<DominantColor> = centroids
+        .iter()
+        .enumerate()
+        .filter_map(|(index, centroid)| {
+            let members: Vec<&[f64; 3]> = pixels
+                .iter()
+                .zip(&assignments)
+                .filter(|(_, &assignment)| assignment == index)
+                .map(|(pixel, _)| pixel)
+                .collect();
+            if members.is_empty() {
+                return None;
+            }
+
+            // Report the mean RGB of the cluster rather than converting back from Lab
+            let mut rgb = [0.0; 3];
+            for pixel in &members {
+                for c in 0..3 {
+                    rgb[c] += pixel[c];
+                }
+            }
+            let rgb = rgb.map(|c| (c / members.len() as f64).round() as u8);
+
+            Some(DominantColor {
+                hex: format!("#{:02x}{:02x}{:02x}", rgb[0], rgb[1], rgb[2]),
+                name: color_name(centroid).to_string(),
+                fraction: members.len() as f64 / pixels.len() as f64,
+            })
+        })
+        .collect();
+
+    colors.sort_by(|a, b| b.fraction.total_cmp(&a.fraction));
+    Ok(colors)
+}
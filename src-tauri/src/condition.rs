@@ -0,0 +1,241 @@
+use futures_util::StreamExt;
+use image::{DynamicImage, imageops::FilterType};
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+use tract_onnx::prelude::*;
+use crate::models::ModelStore;
+use crate::ocr::{self, OcrRegion};
+
+// Multi-label flaw classifier run over tiles of each photo
+pub const DEFECT_MODEL: &str = "defect-detector.onnx";
+const DEFECT_SIZE: usize = 224;
+
+// ImageNet normalization used by the model's backbone
+const MEAN: [f32; 3] = [0.485, 0.456, 0.406];
+const STD: [f32; 3] = [0.229, 0.224, 0.225];
+
+// Flaws in the order of the model's outputs
+pub const DEFECTS: [&str; 6] = ["stain", "pilling", "scuff", "hole", "tear", "fading"];
+
+// Flaws are small, so photos are checked as a grid of tiles
+const TILE_GRID: u32 = 3;
+
+// Sigmoid score above which a flaw is reported
+const DEFECT_THRESHOLD: f32 = 0.5;
+
+// Photos checked at once; OCR may be a Vision request
+const CONDITION_CONCURRENCY: usize = 4;
+
+// Tag and packaging wording, compared on upper-cased words. Retail price
+// tags carry "RRP" or "MSRP".
+const WITH_TAGS: [&str; 6] = ["NWT", "BNWT", "NEW WITH TAGS", "BRAND NEW WITH TAGS", "RRP", "MSRP"];
+const NEW_UNTAGGED: [&str; 6] = ["NWOT", "BNWOT", "NIB", "BNIB", "NEW IN BOX", "NEW WITHOUT TAGS"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConditionGrade {
+    Fair,
+    Good,
+    Excellent,
+    New,
+    #[serde(rename = "nwt")]
+    NewWithTags,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Flaw {
+    // One of `DEFECTS`
+    pub kind: String,
+    pub confidence: f32,
+    // Tile the flaw is most visible in, as fractions of the photo
+    pub bounds: OcrRegion,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PhotoEvidence {
+    pub path: String,
+    pub flaws: Vec<Flaw>,
+    // Tag wording read in the photo, e.g. "NWT"
+    pub tag_text: Vec<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConditionSuggestion {
+    pub grade: ConditionGrade,
+    // Why this grade was chosen, for the user to check
+    pub reasons: Vec<String>,
+    pub photos: Vec<PhotoEvidence>,
+}
+
+// Flaws that make an item fair at best; others allow good
+fn is_major(kind: &str) -> bool {
+    matches!(kind, "hole" | "tear" | "stain")
+}
+
+fn sigmoid(value: f32) -> f32 {
+    1.0 / (1.0 + (-value).exp())
+}
+
+// Highest-scoring tile for each flaw the model finds in the photo
+fn detect_flaws(models: &ModelStore, img: &DynamicImage) -> Result<Vec<Flaw>, String> {
+    let model = models.get(DEFECT_MODEL, [1, 3, DEFECT_SIZE, DEFECT_SIZE])?;
+    let (width, height) = (img.width() / TILE_GRID, img.height() / TILE_GRID);
+    if width == 0 || height == 0 {
+        return Err("Photo is too small to check for flaws".to_string());
+    }
+
+    let mut best: Vec<Option<Flaw>> = DEFECTS.iter().map(|_| None).collect();
+    for row in 0..TILE_GRID {
+        for column in 0..TILE_GRID {
+            let tile = img
+                .crop_imm(column * width, row * height, width, height)
+                .resize_exact(DEFECT_SIZE as u32, DEFECT_SIZE as u32, FilterType::CatmullRom)
+                .to_rgb8();
+            let input: Tensor = tract_ndarray::Array4::from_shape_fn(
+                (1, 3, DEFECT_SIZE, DEFECT_SIZE),
+                |(_, c, y, x)| {
+                    let value = tile.get_pixel(x as u32, y as u32)[c] as f32 / 255.0;
+                    (value - MEAN[c]) / STD[c]
+                },
+            )
+            .into();
+
+            let outputs = model.run(tvec!(input.into()))
+                .map_err(|e| format!("Flaw detection failed: {}", e))?;
+            let logits: Vec<f32> = outputs[0]
+                .to_array_view::<f32>()
+                .map_err(|e| format!("Unexpected model output: {}", e))?
+                .iter()
+                .copied()
+                .collect();
+            if logits.len() != DEFECTS.len() {
+                return Err(format!(
+                    "{} has {} outputs; expected {} flaw types",
+                    DEFECT_MODEL,
+                    logits.len(),
+                    DEFECTS.len()
+                ));
+            }
+
+            let fraction = 1.0 / TILE_GRID as f64;
+            for (i, confidence) in logits.into_iter().map(sigmoid).enumerate() {
+                if confidence >= DEFECT_THRESHOLD && best[i].as_ref().is_none_or(|flaw| confidence > flaw.confidence) {
+                    best[i] = Some(Flaw {
+                        kind: DEFECTS[i].to_string(),
+                        confidence,
+                        bounds: OcrRegion {
+                            x: column as f64 * fraction,
+                            y: row as f64 * fraction,
+                            width: fraction,
+                            height: fraction,
+                        },
+                    });
+                }
+            }
+        }
+    }
+    Ok(best.into_iter().flatten().collect())
+}
+
+// Tag phrases in OCR text, matched on whole words
+fn tag_phrases(text: &str) -> Vec<String> {
+    let words: String = text
+        .to_uppercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+    let words = format!(" {} ", words.split_whitespace().collect::<Vec<_>>().join(" "));
+    WITH_TAGS
+        .iter()
+        .chain(NEW_UNTAGGED.iter())
+        .filter(|phrase| words.contains(&format!(" {} ", phrase)))
+        .map(|phrase| phrase.to_string())
+        .collect()
+}
+
+async fn check_photo(app: &AppHandle, path: &str) -> Result<PhotoEvidence, String> {
+    let image_path = path.to_string();
+    let handle = app.clone();
+    let (img, flaws) = tauri::async_runtime::spawn_blocking(move || {
+        let img = ocr::crop(&image_path, OcrRegion::WHOLE)?;
+        let flaws = detect_flaws(&handle.state::<ModelStore>(), &img)?;
+        Ok::<_, String>((img, flaws))
+    })
+    .await
+    .map_err(|e| format!("Flaw detection failed: {}", e))??;
+
+    // Most photos have no tag in shot, so failing to read text isn't an error
+    let tag_text = match ocr::read_text(app, img, OcrRegion::WHOLE).await {
+        Ok(text) => tag_phrases(&text.text),
+        Err(_) => Vec::new(),
+    };
+    Ok(PhotoEvidence { path: path.to_string(), flaws, tag_text, error: None })
+}
+
+// Grade from the flaws and tags across all photos
+fn grade(photos: &[PhotoEvidence]) -> (ConditionGrade, Vec<String>) {
+    let mut kinds: Vec<&str> = photos.iter().flat_map(|photo| photo.flaws.iter().map(|flaw| flaw.kind.as_str())).collect();
+    kinds.sort();
+    kinds.dedup();
+    let tags: Vec<&str> = photos.iter().flat_map(|photo| photo.tag_text.iter().map(String::as_str)).collect();
+    let with_tags = tags.iter().any(|tag| WITH_TAGS.contains(tag));
+
+    let mut reasons = Vec::new();
+    if !kinds.is_empty() {
+        reasons.push(format!("Possible flaws: {}", kinds.join(", ")));
+    }
+    let grade = if kinds.iter().any(|kind| is_major(kind)) || kinds.len() >= 3 {
+        ConditionGrade::Fair
+    } else if !kinds.is_empty() {
+        ConditionGrade::Good
+    } else if with_tags {
+        reasons.push("Tags attached and no flaws found".to_string());
+        ConditionGrade::NewWithTags
+    } else if !tags.is_empty() {
+        reasons.push(format!("\"{}\" read in a photo and no flaws found", tags[0]));
+        ConditionGrade::New
+    } else {
+        reasons.push("No flaws found".to_string());
+        ConditionGrade::Excellent
+    };
+
+    if !tags.is_empty() && !kinds.is_empty() {
+        reasons.push("Tag wording was read, but flaws were found; check the item before listing as new".to_string());
+    }
+    if photos.iter().any(|photo| photo.error.is_some()) {
+        reasons.push("Some photos couldn't be checked".to_string());
+    }
+    (grade, reasons)
+}
+
+// Command to suggest a condition grade (new, nwt, excellent, good or fair)
+// from photos of an item: flaws such as stains, pilling and scuffs found by
+// the local defect model, plus "NWT"-style wording read on tags. Evidence is
+// returned per photo so the user can see what the grade is based on. A photo
+// that can't be checked gets an `error` rather than failing the rest.
+#[tauri::command]
+pub async fn suggest_condition(app: AppHandle, photos: Vec<String>) -> Result<ConditionSuggestion, String> {
+    if photos.is_empty() {
+        return Err("No photos given".to_string());
+    }
+    let evidence: Vec<PhotoEvidence> = futures_util::stream::iter(photos)
+        .map(|path| {
+            let app = app.clone();
+            async move {
+                match check_photo(&app, &path).await {
+                    Ok(evidence) => evidence,
+                    Err(e) => PhotoEvidence { path, flaws: Vec::new(), tag_text: Vec::new(), error: Some(e) },
+                }
+            }
+        })
+        .buffered(CONDITION_CONCURRENCY)
+        .collect()
+        .await;
+
+    if let Some(error) = evidence.iter().map(|photo| photo.error.as_ref()).collect::<Option<Vec<_>>>() {
+        return Err(error[0].clone());
+    }
+    let (grade, reasons) = grade(&evidence);
+    Ok(ConditionSuggestion { grade, reasons, photos: evidence })
+}
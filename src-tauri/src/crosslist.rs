@@ -0,0 +1,269 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, State};
+use crate::depop::{self, DepopListing};
+use crate::ebay::{self, EbayListing, EbayTaxonomy};
+use crate::etsy::{self, EtsyAuth, EtsyListing};
+use crate::facebook::{self, FacebookListing};
+use crate::listing::{Listing, MappedListing};
+use crate::mercari::{self, MercariListing};
+use crate::poshmark::{self, PoshmarkListing};
+use crate::{history, sync};
+use crate::vinted::{self, VintedListing};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Marketplace {
+    Ebay,
+    Etsy,
+    Poshmark,
+    Mercari,
+    Vinted,
+    Depop,
+    Facebook,
+}
+
+impl Marketplace {
+    pub fn name(self) -> &'static str {
+        match self {
+            Marketplace::Ebay => "ebay",
+            Marketplace::Etsy => "etsy",
+            Marketplace::Poshmark => "poshmark",
+            Marketplace::Mercari => "mercari",
+            Marketplace::Vinted => "vinted",
+            Marketplace::Depop => "depop",
+            Marketplace::Facebook => "facebook",
+        }
+    }
+}
+
+// One marketplace to list an item on, with that marketplace's fields
+// alongside the shared listing ones, e.g. {"marketplace": "etsy", "title":
+// ..., "taxonomy_id": ...}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "marketplace", rename_all = "snake_case")]
+pub enum CrossListTarget {
+    Ebay(EbayListing),
+    Etsy(EtsyListing),
+    // Poshmark automation is left to `export_to_poshmark`
+    Poshmark(PoshmarkListing),
+    Mercari(MercariListing),
+    Vinted(VintedListing),
+    Depop(DepopListing),
+    Facebook(FacebookListing),
+}
+
+impl CrossListTarget {
+    pub fn marketplace(&self) -> Marketplace {
+        match self {
+            CrossListTarget::Ebay(_) => Marketplace::Ebay,
+            CrossListTarget::Etsy(_) => Marketplace::Etsy,
+            CrossListTarget::Poshmark(_) => Marketplace::Poshmark,
+            CrossListTarget::Mercari(_) => Marketplace::Mercari,
+            CrossListTarget::Vinted(_) => Marketplace::Vinted,
+            CrossListTarget::Depop(_) => Marketplace::Depop,
+            CrossListTarget::Facebook(_) => Marketplace::Facebook,
+        }
+    }
+
+    pub fn item(&self) -> &Listing {
+        match self {
+            CrossListTarget::Ebay(listing) => &listing.item,
+            CrossListTarget::Etsy(listing) => &listing.item,
+            CrossListTarget::Poshmark(listing) => &listing.item,
+            CrossListTarget::Mercari(listing) => &listing.item,
+            CrossListTarget::Vinted(listing) => &listing.item,
+            CrossListTarget::Depop(listing) => &listing.item,
+            CrossListTarget::Facebook(listing) => &listing.item,
+        }
+    }
+
+    pub fn title(&self) -> &str {
+        self.item().title.trim()
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CrossListResult {
+    pub marketplace: Marketplace,
+    // Live listing or draft, for marketplaces with an API
+    pub url: Option<String>,
+    // Export package, for the others
+    pub folder: Option<String>,
+    pub error: Option<String>,
+}
+
+impl CrossListResult {
+    fn listed(marketplace: Marketplace, url: String) -> CrossListResult {
+        CrossListResult { marketplace, url: Some(url), folder: None, error: None }
+    }
+
+    fn exported(marketplace: Marketplace, folder: String) -> CrossListResult {
+        CrossListResult { marketplace, url: None, folder: Some(folder), error: None }
+    }
+}
+
+// A marketplace's view of a listing from the checks before publishing
+#[derive(Debug, Serialize)]
+pub struct MarketplaceCheck {
+    pub marketplace: Marketplace,
+    // No problems, so publishing or exporting should go through
+    pub ready: bool,
+    #[serde(flatten)]
+    pub mapped: MappedListing,
+}
+
+// The listing as `marketplace`'s adapter takes it: the shared fields with
+// the marketplace's own from `marketplace_fields` merged in. Fails when a
+// field the marketplace needs is missing or of the wrong type.
+pub fn target(listing: &Listing, marketplace: Marketplace) -> Result<CrossListTarget, String> {
+    let mut item = listing.clone();
+    let fields = item.marketplace_fields.remove(&marketplace).unwrap_or_default();
+    item.marketplace_fields.clear();
+    let mut value = serde_json::to_value(&item)
+        .map_err(|e| format!("Failed to serialize listing: {}", e))?;
+    let object = value.as_object_mut().ok_or("Listing isn't an object")?;
+    object.extend(fields);
+    object.insert("marketplace".to_string(), Value::from(marketplace.name()));
+    serde_json::from_value(value).map_err(|e| format!("Listing details for {} are incomplete: {}", marketplace.name(), e))
+}
+
+// Each marketplace's adapter, mapping the canonical listing's category,
+// condition, size and photos to its own. Missing marketplace fields are
+// reported as a problem.
+pub fn map_target(listing: &Listing, marketplace: Marketplace) -> MappedListing {
+    let target = match target(listing, marketplace) {
+        Ok(target) => target,
+        Err(e) => return MappedListing { size: listing.size.clone(), problems: vec![e], ..MappedListing::default() },
+    };
+    match &target {
+        CrossListTarget::Ebay(listing) => ebay::map_listing(listing),
+        CrossListTarget::Etsy(listing) => etsy::map_listing(listing),
+        CrossListTarget::Poshmark(listing) => poshmark::map_listing(listing),
+        CrossListTarget::Mercari(listing) => mercari::map_listing(listing),
+        CrossListTarget::Vinted(listing) => vinted::map_listing(listing),
+        CrossListTarget::Depop(listing) => depop::map_listing(listing),
+        CrossListTarget::Facebook(listing) => facebook::map_listing(listing),
+    }
+}
+
+// Check the listing for one marketplace before publishing. eBay item
+// specifics are checked against the category's aspects too, which needs the
+// eBay app credentials.
+pub async fn validate_for(app: &AppHandle, taxonomy: &EbayTaxonomy, listing: &Listing, marketplace: Marketplace) -> MarketplaceCheck {
+    let mut mapped = map_target(listing, marketplace);
+    if let Ok(CrossListTarget::Ebay(listing)) = target(listing, marketplace) {
+        if !listing.category_id.trim().is_empty() {
+            match ebay::item_specific_problems(app, taxonomy, &listing).await {
+                Ok(problems) => mapped.problems.extend(problems),
+                Err(e) => mapped.problems.push(format!("Couldn't check item specifics: {}", e)),
+            }
+        }
+    }
+    MarketplaceCheck { marketplace, ready: mapped.problems.is_empty(), mapped }
+}
+
+// List on the target's marketplace; used by `cross_list` and by scheduled
+// listings
+pub async fn list_on(
+    app: &AppHandle,
+    taxonomy: &EbayTaxonomy,
+    etsy_auth: &EtsyAuth,
+    target: &CrossListTarget,
+    output_dir: Option<String>,
+) -> Result<CrossListResult, String> {
+    let marketplace = target.marketplace();
+    let result = match target {
+        CrossListTarget::Ebay(listing) => CrossListResult::listed(marketplace, ebay::publish(app, taxonomy, listing).await?.url),
+        CrossListTarget::Etsy(listing) => {
+            let draft = etsy::create_draft(app, etsy_auth, listing).await?;
+            // The draft exists whether or not it's indexed
+            let _ = history::index_published(app, marketplace, &draft.listing_id.to_string(), &listing.item).await;
+            let mut result = CrossListResult::listed(marketplace, draft.edit_url);
+            if !draft.photo_errors.is_empty() {
+                result.error = Some(draft.photo_errors.join("; "));
+            }
+            result
+        }
+        CrossListTarget::Poshmark(listing) => {
+            CrossListResult::exported(marketplace, poshmark::export(app, listing, output_dir, false).await?.folder)
+        }
+        CrossListTarget::Mercari(listing) => {
+            CrossListResult::exported(marketplace, mercari::export(app, listing, output_dir).await?.folder)
+        }
+        CrossListTarget::Vinted(listing) => {
+            CrossListResult::exported(marketplace, vinted::export(app, listing, output_dir).await?.folder)
+        }
+        CrossListTarget::Depop(listing) => {
+            CrossListResult::exported(marketplace, depop::export(app, listing, output_dir).await?.folder)
+        }
+        CrossListTarget::Facebook(listing) => {
+            CrossListResult::exported(marketplace, facebook::export(app, listing, output_dir).await?.folder)
+        }
+    };
+    // Exports are recorded for sync so a sale elsewhere asks for them to be
+    // ended, and in the listing history; eBay and Etsy record their own
+    // listings, with the listing ID
+    let item = target.item();
+    if !matches!(marketplace, Marketplace::Ebay | Marketplace::Etsy) {
+        if let Some(sku) = item.sku.as_deref().map(str::trim).filter(|sku| !sku.is_empty()) {
+            let _ = sync::record_listing(app, marketplace, sku, None, item.quantity);
+            let _ = history::index_published(app, marketplace, sku, item).await;
+        }
+    }
+    Ok(result)
+}
+
+// Command to list one item on several marketplaces in turn: published
+// through the API where the marketplace has one (eBay, and Etsy as a draft)
+// and written as an export package otherwise. Each marketplace's own fields
+// come from the listing's `marketplace_fields`. A marketplace that fails
+// gets an `error` rather than stopping the rest. A listing without a SKU
+// is given one first, so every copy of the item is synced as one.
+#[tauri::command]
+pub async fn cross_list(
+    app: AppHandle,
+    taxonomy: State<'_, EbayTaxonomy>,
+    etsy_auth: State<'_, EtsyAuth>,
+    mut listing: Listing,
+    marketplaces: Vec<Marketplace>,
+    output_dir: Option<String>,
+) -> Result<Vec<CrossListResult>, String> {
+    if marketplaces.is_empty() {
+        return Err("Pick at least one marketplace".to_string());
+    }
+    if listing.sku.as_deref().is_none_or(|sku| sku.trim().is_empty()) {
+        listing.sku = Some(ebay::new_sku());
+    }
+    let mut results = Vec::new();
+    for &marketplace in &marketplaces {
+        let listed = match target(&listing, marketplace) {
+            Ok(target) => list_on(&app, &taxonomy, &etsy_auth, &target, output_dir.clone()).await,
+            Err(e) => Err(e),
+        };
+        let result = match listed {
+            Ok(result) => result,
+            Err(e) => CrossListResult { marketplace, url: None, folder: None, error: Some(e) },
+        };
+        results.push(result);
+    }
+    Ok(results)
+}
+
+// Command to check a listing for each marketplace before publishing: how
+// its category, condition and size map to the marketplace's, how many
+// photos it takes and everything that has to be fixed first (see
+// `validate_for`)
+#[tauri::command]
+pub async fn validate_listing(
+    app: AppHandle,
+    taxonomy: State<'_, EbayTaxonomy>,
+    listing: Listing,
+    marketplaces: Vec<Marketplace>,
+) -> Result<Vec<MarketplaceCheck>, String> {
+    let mut checks = Vec::new();
+    for &marketplace in &marketplaces {
+        checks.push(validate_for(&app, &taxonomy, &listing, marketplace).await);
+    }
+    Ok(checks)
+}
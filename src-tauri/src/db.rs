@@ -0,0 +1,606 @@
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension, Row, Transaction};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+use crate::hash_cache;
+use crate::listing::Listing;
+
+// Schema changes, applied in order. The database's user_version is the
+// number applied so far, so a migration is never edited once released;
+// change the schema by adding one.
+const MIGRATIONS: &[&str] = &[
+    // 1: listings and app settings
+    "CREATE TABLE listings (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        sku TEXT UNIQUE,
+        title TEXT NOT NULL,
+        status TEXT NOT NULL,
+        listing TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    );
+    CREATE INDEX listings_status ON listings (status, updated_at);
+    CREATE TABLE settings (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    );",
+    // 2: inventory, with each item's photos
+    "CREATE TABLE inventory_items (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        sku TEXT NOT NULL UNIQUE,
+        title TEXT NOT NULL,
+        purchase_cost REAL,
+        purchase_date TEXT,
+        location TEXT,
+        quantity INTEGER NOT NULL,
+        status TEXT NOT NULL,
+        notes TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    );
+    CREATE INDEX inventory_items_status ON inventory_items (status, updated_at);
+    CREATE TABLE inventory_photos (
+        path TEXT PRIMARY KEY,
+        item_id INTEGER NOT NULL REFERENCES inventory_items (id) ON DELETE CASCADE,
+        position INTEGER NOT NULL
+    );
+    CREATE INDEX inventory_photos_item ON inventory_photos (item_id, position);",
+    // 3: hash cache
+    "CREATE TABLE image_hashes (
+        path TEXT NOT NULL,
+        algorithm TEXT NOT NULL,
+        mtime_nanos INTEGER NOT NULL,
+        size INTEGER NOT NULL,
+        hash TEXT NOT NULL,
+        PRIMARY KEY (path, algorithm)
+    );
+    CREATE TABLE photo_quality (
+        path TEXT PRIMARY KEY,
+        mtime_nanos INTEGER NOT NULL,
+        size INTEGER NOT NULL,
+        sharpness REAL NOT NULL,
+        exposure REAL NOT NULL,
+        overall REAL NOT NULL
+    );
+    CREATE TABLE photo_colors (
+        path TEXT PRIMARY KEY,
+        mtime_nanos INTEGER NOT NULL,
+        size INTEGER NOT NULL,
+        signature TEXT NOT NULL
+    );
+    CREATE TABLE photo_embeddings (
+        path TEXT NOT NULL,
+        model TEXT NOT NULL,
+        mtime_nanos INTEGER NOT NULL,
+        size INTEGER NOT NULL,
+        embedding BLOB NOT NULL,
+        PRIMARY KEY (path, model)
+    );",
+    // 4: grouping sessions and corrections
+    "CREATE TABLE grouping_sessions (
+        name TEXT PRIMARY KEY,
+        groups TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    );
+    CREATE TABLE grouping_corrections (
+        photo_a TEXT NOT NULL,
+        photo_b TEXT NOT NULL,
+        together INTEGER NOT NULL,
+        PRIMARY KEY (photo_a, photo_b)
+    );",
+    // 5: GCS resumable upload sessions
+    "CREATE TABLE resumable_uploads (
+        bucket TEXT NOT NULL,
+        object TEXT NOT NULL,
+        file_path TEXT NOT NULL,
+        mtime_nanos INTEGER NOT NULL,
+        size INTEGER NOT NULL,
+        session_uri TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        PRIMARY KEY (bucket, object, file_path)
+    );",
+    // 6: upload queue
+    "CREATE TABLE upload_queue (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        file_path TEXT NOT NULL,
+        object TEXT NOT NULL,
+        status TEXT NOT NULL,
+        attempts INTEGER NOT NULL DEFAULT 0,
+        error TEXT,
+        url TEXT,
+        next_attempt_at INTEGER NOT NULL DEFAULT 0,
+        created_at TEXT NOT NULL
+    );",
+    // 7: listing history
+    "CREATE TABLE listing_history (
+        listing_id TEXT PRIMARY KEY,
+        title TEXT NOT NULL,
+        price REAL,
+        description TEXT NOT NULL,
+        photo_path TEXT NOT NULL,
+        model TEXT NOT NULL,
+        embedding BLOB NOT NULL,
+        listed_at INTEGER NOT NULL
+    );",
+    // 8: listings imported from eBay
+    "CREATE TABLE ebay_listings (
+        item_id TEXT PRIMARY KEY,
+        status TEXT NOT NULL,
+        sku TEXT,
+        title TEXT NOT NULL,
+        description TEXT NOT NULL,
+        price REAL NOT NULL,
+        currency TEXT NOT NULL,
+        category_id TEXT NOT NULL,
+        category_name TEXT NOT NULL,
+        condition TEXT,
+        quantity INTEGER NOT NULL,
+        quantity_sold INTEGER NOT NULL,
+        item_specifics TEXT NOT NULL,
+        photo_urls TEXT NOT NULL,
+        photos TEXT NOT NULL,
+        url TEXT,
+        listed_at TEXT,
+        sold_at TEXT,
+        imported_at TEXT NOT NULL
+    );",
+    // 9: batch publish log
+    "CREATE TABLE publish_log (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        batch_id TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        sku TEXT NOT NULL,
+        title TEXT NOT NULL,
+        listing TEXT NOT NULL,
+        status TEXT NOT NULL,
+        attempts INTEGER NOT NULL DEFAULT 0,
+        error TEXT,
+        listing_id TEXT,
+        url TEXT,
+        created_at TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    );
+    CREATE INDEX publish_log_batch ON publish_log (batch_id, position);",
+    // 10: listing templates
+    "CREATE TABLE listing_templates (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL,
+        category TEXT,
+        description TEXT NOT NULL,
+        shipping TEXT NOT NULL,
+        returns TEXT NOT NULL,
+        item_specifics TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    );",
+    // 11: drafts and their snapshots
+    "CREATE TABLE drafts (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        listing TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    );
+    CREATE TABLE draft_snapshots (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        draft_id INTEGER NOT NULL,
+        listing TEXT NOT NULL,
+        saved_at TEXT NOT NULL
+    );
+    CREATE INDEX draft_snapshots_draft ON draft_snapshots (draft_id, id);",
+    // 12: scheduled listings
+    "CREATE TABLE scheduled_listings (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        listing_id TEXT NOT NULL,
+        marketplace TEXT NOT NULL,
+        title TEXT NOT NULL,
+        target TEXT NOT NULL,
+        publish_at TEXT NOT NULL,
+        status TEXT NOT NULL,
+        url TEXT,
+        folder TEXT,
+        error TEXT,
+        finished_at TEXT
+    );
+    CREATE INDEX scheduled_listings_due ON scheduled_listings (status, publish_at);",
+    // 13: quantity sync
+    "CREATE TABLE synced_listings (
+        marketplace TEXT NOT NULL,
+        sku TEXT NOT NULL,
+        listing_id TEXT,
+        quantity INTEGER NOT NULL,
+        status TEXT NOT NULL,
+        updated_at TEXT NOT NULL,
+        PRIMARY KEY (marketplace, sku)
+    );
+    CREATE TABLE handled_sales (
+        marketplace TEXT NOT NULL,
+        order_id TEXT NOT NULL,
+        sku TEXT NOT NULL,
+        handled_at TEXT NOT NULL,
+        PRIMARY KEY (marketplace, order_id, sku)
+    );
+    CREATE TABLE pending_sales (
+        marketplace TEXT NOT NULL,
+        order_id TEXT NOT NULL,
+        sku TEXT NOT NULL,
+        quantity INTEGER NOT NULL,
+        first_seen_at TEXT NOT NULL,
+        PRIMARY KEY (marketplace, order_id, sku)
+    );
+    CREATE TABLE sync_log (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        created_at TEXT NOT NULL,
+        sku TEXT,
+        marketplace TEXT NOT NULL,
+        action TEXT NOT NULL,
+        listing_id TEXT,
+        detail TEXT NOT NULL
+    );
+    CREATE TABLE sync_state (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );",
+    // 14: price drop rules
+    "CREATE TABLE price_drop_rules (
+        sku TEXT PRIMARY KEY,
+        drop_percent REAL NOT NULL,
+        every_days INTEGER NOT NULL,
+        floor_price REAL NOT NULL,
+        offer_percent REAL,
+        enabled INTEGER NOT NULL,
+        current_price REAL NOT NULL,
+        currency TEXT NOT NULL,
+        next_drop_at TEXT NOT NULL,
+        last_dropped_at TEXT,
+        status TEXT NOT NULL,
+        last_error TEXT
+    );",
+];
+
+// Brings over data from an attached "legacy" database that can't be copied
+// as it is, e.g. rows in an older table's format
+type LegacyConversion = fn(&Transaction) -> Result<(), String>;
+
+// Databases the feature stores kept before their tables moved here, beside
+// app.sqlite, with the tables to bring over. Each is copied in once and
+// renamed to *.imported.
+const LEGACY_DATABASES: &[(&str, &[&str], Option<LegacyConversion>)] = &[
+    (
+        "hash_cache.sqlite",
+        &["image_hashes", "photo_quality", "photo_colors", "photo_embeddings"],
+        Some(hash_cache::import_integer_hashes),
+    ),
+    ("sessions.sqlite", &["grouping_sessions", "grouping_corrections"], None),
+    ("uploads.sqlite", &["resumable_uploads"], None),
+    ("upload_queue.sqlite", &["upload_queue"], None),
+    ("listing_history.sqlite", &["listing_history"], None),
+    ("ebay_imports.sqlite", &["ebay_listings"], None),
+    ("publish_log.sqlite", &["publish_log"], None),
+    ("templates.sqlite", &["listing_templates"], None),
+    ("drafts.sqlite", &["drafts", "draft_snapshots"], None),
+    ("schedule.sqlite", &["scheduled_listings"], None),
+    ("sync.sqlite", &["synced_listings", "handled_sales", "pending_sales", "sync_log", "sync_state"], None),
+    ("price_rules.sqlite", &["price_drop_rules"], None),
+];
+
+// Bring a database up to date with `migrations`, each in its own
+// transaction so a failed one leaves the schema as it was
+pub fn migrate(conn: &mut Connection, migrations: &[&str]) -> Result<(), String> {
+    let applied: usize = conn
+        .query_row("PRAGMA user_version", [], |row| row.get::<_, i64>(0))
+        .map_err(|e| format!("Failed to read schema version: {}", e))? as usize;
+    if applied > migrations.len() {
+        return Err(format!(
+            "The database is at schema version {}, newer than this version of the app supports ({})",
+            applied,
+            migrations.len()
+        ));
+    }
+    for (i, migration) in migrations.iter().enumerate().skip(applied) {
+        let version = i + 1;
+        let tx = conn
+            .transaction()
+            .map_err(|e| format!("Failed to start migration {}: {}", version, e))?;
+        tx.execute_batch(migration)
+            .map_err(|e| format!("Migration {} failed: {}", version, e))?;
+        // PRAGMA takes no parameters
+        tx.execute_batch(&format!("PRAGMA user_version = {}", version))
+            .map_err(|e| format!("Migration {} failed: {}", version, e))?;
+        tx.commit()
+            .map_err(|e| format!("Migration {} failed: {}", version, e))?;
+    }
+    Ok(())
+}
+
+fn column_names(conn: &Connection, schema: &str, table: &str) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare(&format!("PRAGMA {}.table_info({})", schema, table))
+        .map_err(|e| format!("Failed to read table {}: {}", table, e))?;
+    let names = stmt
+        .query_map([], |row| row.get(1))
+        .map_err(|e| format!("Failed to read table {}: {}", table, e))?;
+    names.collect::<Result<_, _>>()
+        .map_err(|e| format!("Failed to read table {}: {}", table, e))
+}
+
+// Copy the rows of `tables` from the attached "legacy" database, in the
+// columns both have; a table the old file never had is skipped
+fn copy_legacy_tables(conn: &mut Connection, tables: &[&str], convert: Option<LegacyConversion>) -> Result<(), String> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start import: {}", e))?;
+    for table in tables {
+        let old_columns = column_names(&tx, "legacy", table)?;
+        let columns: Vec<String> = column_names(&tx, "main", table)?
+            .into_iter()
+            .filter(|column| old_columns.contains(column))
+            .collect();
+        if columns.is_empty() {
+            continue;
+        }
+        let columns = columns.join(", ");
+        tx.execute_batch(&format!(
+            "INSERT OR IGNORE INTO main.{table} ({columns}) SELECT {columns} FROM legacy.{table}"
+        ))
+        .map_err(|e| format!("Failed to import {}: {}", table, e))?;
+    }
+    if let Some(convert) = convert {
+        convert(&tx)?;
+    }
+    tx.commit().map_err(|e| format!("Failed to import: {}", e))
+}
+
+// Bring in the data of feature databases from before they shared this one,
+// so upgrading keeps drafts, templates, sync state and the rest
+fn import_legacy_databases(conn: &mut Connection, data_dir: &Path) -> Result<(), String> {
+    for (file, tables, convert) in LEGACY_DATABASES {
+        let path = data_dir.join(file);
+        if !path.exists() {
+            continue;
+        }
+        conn.execute("ATTACH DATABASE ?1 AS legacy", params![path.to_string_lossy()])
+            .map_err(|e| format!("Failed to open {}: {}", file, e))?;
+        let copied = copy_legacy_tables(conn, tables, *convert);
+        conn.execute_batch("DETACH DATABASE legacy")
+            .map_err(|e| format!("Failed to close {}: {}", file, e))?;
+        copied.map_err(|e| format!("Failed to import {}: {}", file, e))?;
+        let mut imported = path.clone().into_os_string();
+        imported.push(".imported");
+        fs::rename(&path, &imported)
+            .map_err(|e| format!("Failed to set aside {}: {}", file, e))?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ListingStatus {
+    // Written up but not on any marketplace yet
+    Ready,
+    Listed,
+    Sold,
+    Ended,
+}
+
+impl ListingStatus {
+    fn name(self) -> &'static str {
+        match self {
+            ListingStatus::Ready => "ready",
+            ListingStatus::Listed => "listed",
+            ListingStatus::Sold => "sold",
+            ListingStatus::Ended => "ended",
+        }
+    }
+
+    fn parse(status: &str) -> ListingStatus {
+        match status {
+            "listed" => ListingStatus::Listed,
+            "sold" => ListingStatus::Sold,
+            "ended" => ListingStatus::Ended,
+            _ => ListingStatus::Ready,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StoredListing {
+    pub id: i64,
+    pub status: ListingStatus,
+    pub listing: Listing,
+    // RFC 3339
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+fn listing_from_row(row: &Row) -> rusqlite::Result<StoredListing> {
+    let status: String = row.get(1)?;
+    let listing: String = row.get(2)?;
+    Ok(StoredListing {
+        id: row.get(0)?,
+        status: ListingStatus::parse(&status),
+        listing: serde_json::from_str(&listing)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, Box::new(e)))?,
+        created_at: row.get(3)?,
+        updated_at: row.get(4)?,
+    })
+}
+
+const LISTING_COLUMNS: &str = "id, status, listing, created_at, updated_at";
+
+// The app's database. Its schema is versioned with `MIGRATIONS`; feature
+// stores share its connection (see `Database::connection`) and keep their
+// tables here too.
+pub struct Database {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl Database {
+    pub fn open(db_path: &Path) -> Result<Database, String> {
+        if let Some(parent) = db_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create data directory: {}", e))?;
+        }
+        let mut conn = Connection::open(db_path)
+            .map_err(|e| format!("Failed to open app database: {}", e))?;
+        // WAL keeps reads from waiting on a write; foreign keys are off in
+        // SQLite unless asked for
+        conn.execute_batch("PRAGMA journal_mode = WAL; PRAGMA foreign_keys = ON;")
+            .map_err(|e| format!("Failed to configure app database: {}", e))?;
+        migrate(&mut conn, MIGRATIONS)?;
+        if let Some(data_dir) = db_path.parent() {
+            import_legacy_databases(&mut conn, data_dir)?;
+        }
+        Ok(Database { conn: Arc::new(Mutex::new(conn)) })
+    }
+
+    pub fn lock(&self) -> Result<MutexGuard<'_, Connection>, String> {
+        self.conn.lock().map_err(|_| "Database lock poisoned".to_string())
+    }
+
+    // The connection, for a feature store to keep. Stores lock it only for
+    // a statement or transaction at a time, and never while holding another
+    // store's lock.
+    pub fn connection(&self) -> Arc<Mutex<Connection>> {
+        self.conn.clone()
+    }
+
+    // Insert a listing, or replace the one with the ID. SKUs are unique, so
+    // saving a second listing with one already stored fails.
+    fn save_listing(&self, id: Option<i64>, status: ListingStatus, listing: &Listing) -> Result<i64, String> {
+        let json = serde_json::to_string(listing)
+            .map_err(|e| format!("Failed to serialize listing: {}", e))?;
+        let sku = listing.sku.as_deref().map(str::trim).filter(|sku| !sku.is_empty());
+        let now = Utc::now().to_rfc3339();
+        let conn = self.lock()?;
+        let saved = match id {
+            Some(id) => conn
+                .execute(
+                    "UPDATE listings SET sku = ?2, title = ?3, status = ?4, listing = ?5, updated_at = ?6 WHERE id = ?1",
+                    params![id, sku, listing.title.trim(), status.name(), json, now],
+                )
+                .map(|updated| (updated > 0).then_some(id)),
+            None => conn
+                .execute(
+                    "INSERT INTO listings (sku, title, status, listing, created_at, updated_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?5)",
+                    params![sku, listing.title.trim(), status.name(), json, now],
+                )
+                .map(|_| Some(conn.last_insert_rowid())),
+        };
+        match saved {
+            Ok(Some(id)) => Ok(id),
+            Ok(None) => Err(format!("No listing with ID {}", id.unwrap_or_default())),
+            Err(rusqlite::Error::SqliteFailure(error, _)) if error.code == rusqlite::ErrorCode::ConstraintViolation => {
+                Err(format!("Another listing already has SKU {}", sku.unwrap_or_default()))
+            }
+            Err(e) => Err(format!("Failed to save listing: {}", e)),
+        }
+    }
+
+    fn listing(&self, id: i64) -> Result<Option<StoredListing>, String> {
+        let conn = self.lock()?;
+        conn.query_row(
+            &format!("SELECT {} FROM listings WHERE id = ?1", LISTING_COLUMNS),
+            params![id],
+            listing_from_row,
+        )
+        .optional()
+        .map_err(|e| format!("Failed to read listing: {}", e))
+    }
+
+    fn listings(&self, status: Option<ListingStatus>) -> Result<Vec<StoredListing>, String> {
+        let conn = self.lock()?;
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {} FROM listings WHERE ?1 IS NULL OR status = ?1 ORDER BY updated_at DESC",
+                LISTING_COLUMNS
+            ))
+            .map_err(|e| format!("Failed to read listings: {}", e))?;
+        let rows = stmt
+            .query_map(params![status.map(ListingStatus::name)], listing_from_row)
+            .map_err(|e| format!("Failed to read listings: {}", e))?;
+        rows.collect::<Result<_, _>>()
+            .map_err(|e| format!("Failed to read listings: {}", e))
+    }
+
+    fn delete_listing(&self, id: i64) -> Result<bool, String> {
+        let conn = self.lock()?;
+        let removed = conn
+            .execute("DELETE FROM listings WHERE id = ?1", params![id])
+            .map_err(|e| format!("Failed to delete listing: {}", e))?;
+        Ok(removed > 0)
+    }
+
+    fn setting(&self, key: &str) -> Result<Option<Value>, String> {
+        let conn = self.lock()?;
+        let value: Option<String> = conn
+            .query_row("SELECT value FROM settings WHERE key = ?1", params![key], |row| row.get(0))
+            .optional()
+            .map_err(|e| format!("Failed to read setting {}: {}", key, e))?;
+        value
+            .map(|value| serde_json::from_str(&value).map_err(|e| format!("Failed to parse setting {}: {}", key, e)))
+            .transpose()
+    }
+
+    fn set_setting(&self, key: &str, value: &Value) -> Result<(), String> {
+        let conn = self.lock()?;
+        conn.execute(
+            "INSERT OR REPLACE INTO settings (key, value, updated_at) VALUES (?1, ?2, ?3)",
+            params![key, value.to_string(), Utc::now().to_rfc3339()],
+        )
+        .map_err(|e| format!("Failed to save setting {}: {}", key, e))?;
+        Ok(())
+    }
+}
+
+// Command to store a listing in the app database, replacing the one with
+// `listing_id` when given; `status` defaults to ready. Returns the stored
+// listing.
+#[tauri::command]
+pub fn save_listing(
+    db: State<Database>,
+    listing: Listing,
+    listing_id: Option<i64>,
+    status: Option<ListingStatus>,
+) -> Result<StoredListing, String> {
+    if listing.title.trim().is_empty() {
+        return Err("Title is required".to_string());
+    }
+    let id = db.save_listing(listing_id, status.unwrap_or(ListingStatus::Ready), &listing)?;
+    db.listing(id)?.ok_or_else(|| format!("No listing with ID {}", id))
+}
+
+#[tauri::command]
+pub fn get_listing(db: State<Database>, listing_id: i64) -> Result<Option<StoredListing>, String> {
+    db.listing(listing_id)
+}
+
+// Command to list stored listings, most recently updated first
+#[tauri::command]
+pub fn list_listings(db: State<Database>, status: Option<ListingStatus>) -> Result<Vec<StoredListing>, String> {
+    db.listings(status)
+}
+
+#[tauri::command]
+pub fn delete_listing(db: State<Database>, listing_id: i64) -> Result<bool, String> {
+    db.delete_listing(listing_id)
+}
+
+// Command to read a frontend setting, e.g. a layout preference; `None` when
+// it was never set. Settings with secrets belong in the keychain instead.
+#[tauri::command]
+pub fn get_app_setting(db: State<Database>, key: String) -> Result<Option<Value>, String> {
+    db.setting(&key)
+}
+
+#[tauri::command]
+pub fn set_app_setting(db: State<Database>, key: String, value: Value) -> Result<(), String> {
+    if key.trim().is_empty() {
+        return Err("Setting key is required".to_string());
+    }
+    db.set_setting(key.trim(), &value)
+}
@@ -0,0 +1,251 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::AppHandle;
+use crate::crosslist::Marketplace;
+use crate::image_io;
+use crate::listing::{self, Listing, ListingCondition, MappedListing, PackageContents};
+use crate::photo_editing;
+use crate::sizes::{self, SizeCategory};
+
+// Depop takes up to 8 square photos and recommends 1280px
+const MAX_PHOTOS: usize = 8;
+const PHOTO_SIZE: u32 = 1280;
+const MAX_DESCRIPTION_CHARS: usize = 1000;
+
+// Depop only counts the first five hashtags
+const MAX_HASHTAGS: usize = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DepopListing {
+    #[serde(flatten)]
+    pub item: Listing,
+    // The app's category, one of `classifier::CATEGORIES`, mapped to
+    // Depop's with `size_category` telling menswear, womenswear and kids
+    // apart
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub size_category: Option<SizeCategory>,
+    // Depop category path, e.g. ["Womenswear", "Coats and jackets"], used in
+    // place of the mapped one
+    #[serde(default)]
+    pub depop_category: Vec<String>,
+    // Put first, before ones made from the brand, colour and category
+    #[serde(default)]
+    pub hashtags: Vec<String>,
+    // Colour photos are padded with; defaults to white
+    #[serde(default)]
+    pub background_color: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DepopExport {
+    pub folder: String,
+    // Square photos in listing order
+    pub photos: Vec<String>,
+    pub data_file: String,
+    pub text_file: String,
+    // Ready-to-paste description with the hashtags at the end
+    pub description: String,
+    pub category: Vec<String>,
+    pub size: Option<String>,
+}
+
+fn depop_condition(condition: ListingCondition) -> &'static str {
+    match condition {
+        ListingCondition::NewWithTags => "Brand new",
+        ListingCondition::NewWithoutTags => "Like new",
+        ListingCondition::VeryGood => "Used - Excellent",
+        ListingCondition::Good => "Used - Good",
+        ListingCondition::Satisfactory => "Used - Fair",
+    }
+}
+
+// Depop category path for one of the app's categories, womenswear when the
+// size category doesn't say
+fn depop_category(category: &str, size_category: Option<SizeCategory>) -> Vec<String> {
+    let department = match size_category {
+        Some(SizeCategory::MensClothing | SizeCategory::MensShoes) => "Menswear",
+        Some(SizeCategory::KidsClothing) => "Kids",
+        _ => "Womenswear",
+    };
+    let path: &[&str] = match category {
+        "clothing" => &[department],
+        "shoes" => &[department, "Footwear"],
+        "bags" => &[department, "Bags and purses"],
+        "accessories" => &[department, "Accessories"],
+        "jewelry" => &[department, "Jewellery"],
+        "electronics" => &["Everything else", "Tech"],
+        "toys" => &["Everything else", "Toys"],
+        "media" => &["Everything else", "Books and magazines"],
+        "home" => &["Everything else", "Home"],
+        "sports" => &["Everything else", "Sports equipment"],
+        "beauty" => &["Everything else", "Beauty"],
+        _ => &["Everything else"],
+    };
+    path.iter().map(|part| part.to_string()).collect()
+}
+
+// Size in the seller's region, going by the currency: letter sizes where
+// the tag has one, otherwise the UK, US or EU number
+fn depop_size(size: &str, category: Option<SizeCategory>, currency: &str) -> Option<String> {
+    let size = size.trim();
+    if size.is_empty() {
+        return None;
+    }
+    let Some(category) = category else {
+        return Some(size.to_string());
+    };
+    let parsed = sizes::parse_size(size, category);
+    let (label, regional) = match currency.trim().to_uppercase().as_str() {
+        "GBP" => ("UK", parsed.uk),
+        "USD" => ("US", parsed.us),
+        _ => ("EU", parsed.eu),
+    };
+    let regional = regional.map(|value| format!("{} {}", label, value));
+    let shoes = matches!(category, SizeCategory::WomensShoes | SizeCategory::MensShoes | SizeCategory::Shoes);
+    let mapped = if shoes {
+        regional
+    } else {
+        parsed.letter.or(regional).or_else(|| parsed.waist.map(|waist| format!("W{}", waist)))
+    };
+    Some(mapped.or(parsed.display).unwrap_or_else(|| size.to_string()))
+}
+
+// Hashtags without spaces or punctuation, e.g. "Levi's" as "#levis"
+fn hashtag(text: &str) -> Option<String> {
+    let tag: String = text.trim_start_matches('#').chars().filter(|c| c.is_alphanumeric()).collect::<String>().to_lowercase();
+    (!tag.is_empty()).then(|| format!("#{}", tag))
+}
+
+fn hashtags(listing: &DepopListing, category: &[String]) -> Vec<String> {
+    let item = &listing.item;
+    let mut tags: Vec<String> = Vec::new();
+    let candidates = listing
+        .hashtags
+        .iter()
+        .map(String::as_str)
+        .chain(item.brand.as_deref())
+        .chain(item.colors.first().map(String::as_str))
+        .chain(category.last().map(String::as_str).filter(|_| category.len() > 1));
+    for tag in candidates.filter_map(hashtag) {
+        if !tags.contains(&tag) && tags.len() < MAX_HASHTAGS {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+// Depop listings have no title, so the description opens with it, then the
+// details, and ends with the hashtags as Depop sellers do
+fn description(listing: &DepopListing, size: Option<&str>, tags: &[String]) -> String {
+    let item = &listing.item;
+    let mut parts = vec![item.title.trim().to_string(), item.description_with_condition().trim().to_string()];
+    let mut details = Vec::new();
+    if let Some(brand) = item.brand.as_deref().filter(|brand| !brand.trim().is_empty()) {
+        details.push(format!("Brand: {}", brand.trim()));
+    }
+    if let Some(size) = size {
+        details.push(format!("Size: {}", size));
+    }
+    if !item.materials.is_empty() {
+        details.push(format!("Material: {}", item.materials.join(", ")));
+    }
+    if !details.is_empty() {
+        parts.push(details.join("\n"));
+    }
+    if !tags.is_empty() {
+        parts.push(tags.join(" "));
+    }
+    parts.retain(|part| !part.is_empty());
+    parts.join("\n\n")
+}
+
+// How Depop will see the listing, with what stops it being exported
+pub fn map_listing(listing: &DepopListing) -> MappedListing {
+    let item = &listing.item;
+    let mut problems = item.problems("Depop", MAX_PHOTOS);
+    let category = if listing.depop_category.is_empty() {
+        depop_category(listing.category.as_deref().unwrap_or("other"), listing.size_category)
+    } else {
+        listing.depop_category.clone()
+    };
+    let size = item.size.as_deref().and_then(|size| depop_size(size, listing.size_category, &item.currency));
+    let description = description(listing, size.as_deref(), &hashtags(listing, &category));
+    if description.chars().count() > MAX_DESCRIPTION_CHARS {
+        problems.push(format!(
+            "Depop descriptions, with the title and hashtags, are limited to {} characters",
+            MAX_DESCRIPTION_CHARS
+        ));
+    }
+    MappedListing {
+        category,
+        condition: Some(depop_condition(item.condition).to_string()),
+        size,
+        max_photos: MAX_PHOTOS,
+        problems,
+    }
+}
+
+// Write a listing's Depop export package; used by the command and by
+// cross-listing
+pub async fn export(app: &AppHandle, listing: &DepopListing, output_dir: Option<String>) -> Result<DepopExport, String> {
+    let mapped = map_listing(listing);
+    let item = &listing.item;
+    let description = description(listing, mapped.size.as_deref(), &hashtags(listing, &mapped.category));
+    let background = listing
+        .background_color
+        .as_deref()
+        .map(image_io::parse_hex_color)
+        .transpose()?
+        .unwrap_or([255, 255, 255]);
+
+    let data = json!({
+        "description": description,
+        "category": mapped.category,
+        "brand": item.brand,
+        "size": mapped.size,
+        "condition": depop_condition(item.condition),
+        "colours": item.colors.iter().take(2).collect::<Vec<_>>(),
+        "price": item.price,
+        "currency": item.currency.trim().to_uppercase(),
+    });
+    let text = format!(
+        "{}\n\nCategory: {}\nCondition: {}\nPrice: {:.2} {}\n",
+        description,
+        mapped.category.join(" > "),
+        depop_condition(item.condition),
+        item.price,
+        item.currency.trim().to_uppercase()
+    );
+    // Padded rather than cropped, so nothing at the edges of a photo is lost
+    let contents = PackageContents {
+        photo_edge: PHOTO_SIZE,
+        prepare: Box::new(move |img| photo_editing::pad_square(&img, background)),
+        data: Some(data),
+        text,
+    };
+    let package = listing::write_package(app, Marketplace::Depop, item, &mapped, output_dir, contents).await?;
+
+    Ok(DepopExport {
+        folder: package.folder,
+        photos: package.photos,
+        data_file: package.data_file.unwrap_or_default(),
+        text_file: package.text_file,
+        description,
+        category: mapped.category,
+        size: mapped.size,
+    })
+}
+
+// Command to export a listing for Depop: photos padded to squares (at most
+// 8), a description in Depop's style with the title first and up to five
+// hashtags last, and the category, size and condition mapped to Depop's
+#[tauri::command]
+pub async fn export_to_depop(
+    app: AppHandle,
+    listing: DepopListing,
+    output_dir: Option<String>,
+) -> Result<DepopExport, String> {
+    export(&app, &listing, output_dir).await
+}
@@ -0,0 +1,263 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+use serde_json::Value;
+use tauri::State;
+use crate::db::Database;
+
+// Saves come in as often as the frontend likes; a snapshot of the draft is
+// kept at most this often, so an edit that goes wrong can be rolled back
+const SNAPSHOT_SECONDS: i64 = 120;
+
+// Snapshots kept per draft, newest first
+const MAX_SNAPSHOTS: i64 = 20;
+
+// A draft as listed, without its content
+#[derive(Debug, Clone, Serialize)]
+pub struct DraftSummary {
+    pub id: i64,
+    pub title: String,
+    pub photo_count: usize,
+    // The first photo, for a thumbnail
+    pub primary_photo: Option<String>,
+    // RFC 3339
+    pub created_at: String,
+    pub updated_at: String,
+    pub snapshot_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DraftSnapshot {
+    pub id: i64,
+    pub saved_at: String,
+    pub title: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RestoredDraft {
+    pub id: i64,
+    // The listing as the frontend saved it
+    pub listing: Value,
+    pub updated_at: String,
+    // Local photos that are no longer on disk
+    pub missing_photos: Vec<String>,
+}
+
+// Title and photos of an unfinished listing; drafts are saved mid-edit, so
+// neither has to be there
+fn title_of(listing: &Value) -> String {
+    listing["title"].as_str().unwrap_or_default().trim().to_string()
+}
+
+fn photos_of(listing: &Value) -> Vec<String> {
+    listing["photos"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|photo| photo.as_str().map(str::to_string))
+        .collect()
+}
+
+fn summary_from_row(row: &Row) -> rusqlite::Result<DraftSummary> {
+    let listing: String = row.get(1)?;
+    let listing: Value = serde_json::from_str(&listing).unwrap_or_default();
+    let photos = photos_of(&listing);
+    Ok(DraftSummary {
+        id: row.get(0)?,
+        title: title_of(&listing),
+        photo_count: photos.len(),
+        primary_photo: photos.into_iter().next(),
+        created_at: row.get(2)?,
+        updated_at: row.get(3)?,
+        snapshot_count: row.get::<_, i64>(4)? as usize,
+    })
+}
+
+// Listings in progress, stored in SQLite so a crash or accidental close
+// doesn't lose them. Each save replaces the draft; snapshots keep a few
+// earlier versions.
+pub struct DraftStore {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl DraftStore {
+    pub fn new(db: &Database) -> DraftStore {
+        DraftStore { conn: db.connection() }
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Connection>, String> {
+        self.conn.lock().map_err(|_| "Draft store lock poisoned".to_string())
+    }
+
+    // Insert a draft, or replace the one with the ID, snapshotting it when
+    // the last snapshot is old enough
+    fn save(&self, id: Option<i64>, listing: &Value) -> Result<i64, String> {
+        let json = serde_json::to_string(listing)
+            .map_err(|e| format!("Failed to serialize draft: {}", e))?;
+        let now = Utc::now();
+        let mut conn = self.lock()?;
+        let tx = conn.transaction().map_err(|e| format!("Failed to save draft: {}", e))?;
+        let id = match id {
+            Some(id) => {
+                let updated = tx
+                    .execute("UPDATE drafts SET listing = ?2, updated_at = ?3 WHERE id = ?1", params![id, json, now.to_rfc3339()])
+                    .map_err(|e| format!("Failed to save draft: {}", e))?;
+                if updated == 0 {
+                    return Err(format!("No draft with ID {}", id));
+                }
+                id
+            }
+            None => {
+                tx.execute(
+                    "INSERT INTO drafts (listing, created_at, updated_at) VALUES (?1, ?2, ?2)",
+                    params![json, now.to_rfc3339()],
+                )
+                .map_err(|e| format!("Failed to save draft: {}", e))?;
+                tx.last_insert_rowid()
+            }
+        };
+
+        let last_snapshot: Option<String> = tx
+            .query_row("SELECT MAX(saved_at) FROM draft_snapshots WHERE draft_id = ?1", params![id], |row| row.get(0))
+            .map_err(|e| format!("Failed to read draft snapshots: {}", e))?;
+        let due = last_snapshot
+            .and_then(|saved_at| DateTime::parse_from_rfc3339(&saved_at).ok())
+            .is_none_or(|saved_at| now - saved_at.with_timezone(&Utc) >= chrono::Duration::seconds(SNAPSHOT_SECONDS));
+        if due {
+            tx.execute(
+                "INSERT INTO draft_snapshots (draft_id, listing, saved_at) VALUES (?1, ?2, ?3)",
+                params![id, json, now.to_rfc3339()],
+            )
+            .map_err(|e| format!("Failed to snapshot draft: {}", e))?;
+            tx.execute(
+                "DELETE FROM draft_snapshots WHERE draft_id = ?1 AND id NOT IN
+                    (SELECT id FROM draft_snapshots WHERE draft_id = ?1 ORDER BY id DESC LIMIT ?2)",
+                params![id, MAX_SNAPSHOTS],
+            )
+            .map_err(|e| format!("Failed to prune draft snapshots: {}", e))?;
+        }
+        tx.commit().map_err(|e| format!("Failed to save draft: {}", e))?;
+        Ok(id)
+    }
+
+    fn summaries(&self, id: Option<i64>) -> Result<Vec<DraftSummary>, String> {
+        let conn = self.lock()?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT d.id, d.listing, d.created_at, d.updated_at,
+                    (SELECT COUNT(*) FROM draft_snapshots s WHERE s.draft_id = d.id)
+                 FROM drafts d WHERE ?1 IS NULL OR d.id = ?1 ORDER BY d.updated_at DESC",
+            )
+            .map_err(|e| format!("Failed to read drafts: {}", e))?;
+        let rows = stmt
+            .query_map(params![id], summary_from_row)
+            .map_err(|e| format!("Failed to read drafts: {}", e))?;
+        rows.collect::<Result<_, _>>()
+            .map_err(|e| format!("Failed to read drafts: {}", e))
+    }
+
+    // The draft, or one of its snapshots; (listing, saved at)
+    fn content(&self, id: i64, snapshot_id: Option<i64>) -> Result<Option<(String, String)>, String> {
+        let conn = self.lock()?;
+        let row = |row: &Row| Ok((row.get(0)?, row.get(1)?));
+        match snapshot_id {
+            Some(snapshot_id) => conn
+                .query_row(
+                    "SELECT listing, saved_at FROM draft_snapshots WHERE draft_id = ?1 AND id = ?2",
+                    params![id, snapshot_id],
+                    row,
+                )
+                .optional(),
+            None => conn
+                .query_row("SELECT listing, updated_at FROM drafts WHERE id = ?1", params![id], row)
+                .optional(),
+        }
+        .map_err(|e| format!("Failed to read draft: {}", e))
+    }
+
+    fn snapshots(&self, id: i64) -> Result<Vec<DraftSnapshot>, String> {
+        let conn = self.lock()?;
+        let mut stmt = conn
+            .prepare("SELECT id, listing, saved_at FROM draft_snapshots WHERE draft_id = ?1 ORDER BY id DESC")
+            .map_err(|e| format!("Failed to read draft snapshots: {}", e))?;
+        let rows = stmt
+            .query_map(params![id], |row| {
+                let listing: String = row.get(1)?;
+                Ok(DraftSnapshot {
+                    id: row.get(0)?,
+                    saved_at: row.get(2)?,
+                    title: title_of(&serde_json::from_str(&listing).unwrap_or_default()),
+                })
+            })
+            .map_err(|e| format!("Failed to read draft snapshots: {}", e))?;
+        rows.collect::<Result<_, _>>()
+            .map_err(|e| format!("Failed to read draft snapshots: {}", e))
+    }
+
+    fn delete(&self, id: i64) -> Result<bool, String> {
+        let conn = self.lock()?;
+        conn.execute("DELETE FROM draft_snapshots WHERE draft_id = ?1", params![id])
+            .map_err(|e| format!("Failed to delete draft: {}", e))?;
+        let removed = conn
+            .execute("DELETE FROM drafts WHERE id = ?1", params![id])
+            .map_err(|e| format!("Failed to delete draft: {}", e))?;
+        Ok(removed > 0)
+    }
+}
+
+// Command to autosave the listing being edited. Call it whenever the form
+// changes, without `draft_id` the first time and with the returned ID after;
+// every call replaces the draft and earlier versions are snapshotted every
+// couple of minutes. The listing is stored as sent, so it needn't be
+// complete.
+#[tauri::command]
+pub fn save_draft(store: State<DraftStore>, listing: Value, draft_id: Option<i64>) -> Result<DraftSummary, String> {
+    if !listing.is_object() {
+        return Err("Draft must be a listing object".to_string());
+    }
+    let id = store.save(draft_id, &listing)?;
+    store
+        .summaries(Some(id))?
+        .into_iter()
+        .next()
+        .ok_or_else(|| format!("No draft with ID {}", id))
+}
+
+// Command to list saved drafts, most recently edited first; on startup,
+// any left here were open when the app closed
+#[tauri::command]
+pub fn list_drafts(store: State<DraftStore>) -> Result<Vec<DraftSummary>, String> {
+    store.summaries(None)
+}
+
+// Command to list a draft's snapshots, newest first
+#[tauri::command]
+pub fn list_draft_snapshots(store: State<DraftStore>, draft_id: i64) -> Result<Vec<DraftSnapshot>, String> {
+    store.snapshots(draft_id)
+}
+
+// Command to get a draft's listing back to keep editing, or one of its
+// snapshots with `snapshot_id`. Local photos that have since been moved or
+// deleted are reported in `missing_photos`.
+#[tauri::command]
+pub fn restore_draft(store: State<DraftStore>, draft_id: i64, snapshot_id: Option<i64>) -> Result<RestoredDraft, String> {
+    let (json, updated_at) = store
+        .content(draft_id, snapshot_id)?
+        .ok_or_else(|| format!("No draft with ID {}", draft_id))?;
+    let listing: Value = serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse draft: {}", e))?;
+    let missing_photos = photos_of(&listing)
+        .into_iter()
+        .filter(|photo| !photo.starts_with("https://") && !photo.starts_with("http://") && !Path::new(photo).exists())
+        .collect();
+    Ok(RestoredDraft { id: draft_id, listing, updated_at, missing_photos })
+}
+
+// Command to remove a draft and its snapshots, once it's published or
+// discarded
+#[tauri::command]
+pub fn delete_draft(store: State<DraftStore>, draft_id: i64) -> Result<bool, String> {
+    store.delete(draft_id)
+}
@@ -0,0 +1,850 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::Duration;
+use chrono::{DateTime, SecondsFormat, Utc};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, State};
+use crate::crosslist::Marketplace;
+use crate::ebay_auth::{self, ApplicationScope, EbayEnvironment};
+use crate::listing::{Listing, ListingCondition, MappedListing};
+use crate::storage::{self, PhotoHost};
+use crate::{ebay_policies, history, image_io, network, sync};
+
+// eBay also hosts photos uploaded through the Media API; it wants at least
+// 500px on the long edge and recommends 1600
+const EBAY_IMAGE_EDGE: u32 = 1600;
+const EBAY_JPEG_QUALITY: u8 = 90;
+
+// Most photos eBay allows on a listing
+const MAX_EBAY_PHOTOS: usize = 24;
+
+pub const DEFAULT_MARKETPLACE: &str = "EBAY_US";
+
+// Category suggestions returned; eBay sends up to ten
+const MAX_CATEGORY_SUGGESTIONS: usize = 10;
+
+// Inventory API condition enum
+fn ebay_condition(condition: ListingCondition) -> &'static str {
+    match condition {
+        ListingCondition::NewWithTags => "NEW",
+        ListingCondition::NewWithoutTags => "NEW_OTHER",
+        ListingCondition::VeryGood => "USED_VERY_GOOD",
+        ListingCondition::Good => "USED_GOOD",
+        ListingCondition::Satisfactory => "USED_ACCEPTABLE",
+    }
+}
+
+// Business policy IDs from the seller's eBay account; any left out use the
+// account's default for the marketplace (see `get_ebay_policies`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EbayPolicies {
+    #[serde(default)]
+    pub fulfillment_policy_id: Option<String>,
+    #[serde(default)]
+    pub payment_policy_id: Option<String>,
+    #[serde(default)]
+    pub return_policy_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EbayListing {
+    // Local photos are uploaded to eBay
+    #[serde(flatten)]
+    pub item: Listing,
+    pub category_id: String,
+    // Other item specifics by aspect name, e.g. {"Style": ["Bomber"]}
+    #[serde(default)]
+    pub item_specifics: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    pub policies: EbayPolicies,
+    // Inventory location the item ships from, set up in the seller's account
+    pub merchant_location_key: String,
+    // e.g. "EBAY_US", "EBAY_GB", "EBAY_DE"
+    #[serde(default)]
+    pub marketplace_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CategorySuggestion {
+    pub category_id: String,
+    pub name: String,
+    // Names from the top-level category down to this one
+    pub path: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AspectUsage {
+    Required,
+    Recommended,
+    Optional,
+}
+
+// Item specific a category takes
+#[derive(Debug, Clone, Serialize)]
+pub struct EbayAspect {
+    pub name: String,
+    pub usage: AspectUsage,
+    // Only `values` are accepted, rather than free text
+    pub selection_only: bool,
+    // Whether more than one value may be given
+    pub multiple: bool,
+    pub max_length: Option<usize>,
+    // Allowed values for selection-only aspects, suggestions otherwise
+    pub values: Vec<String>,
+}
+
+// Category tree IDs by environment and marketplace, and aspects by category
+// too; both rarely change
+#[derive(Default)]
+pub struct EbayTaxonomy {
+    trees: Mutex<HashMap<(EbayEnvironment, String), String>>,
+    aspects: Mutex<HashMap<(EbayEnvironment, String, String), Vec<EbayAspect>>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PublishedListing {
+    pub listing_id: String,
+    pub url: String,
+    pub sku: String,
+    pub offer_id: String,
+}
+
+// (locale for Content-Language, site domain) of a marketplace
+fn marketplace_site(marketplace_id: &str) -> Result<(&'static str, &'static str), String> {
+    Ok(match marketplace_id {
+        "EBAY_US" => ("en-US", "ebay.com"),
+        "EBAY_GB" => ("en-GB", "ebay.co.uk"),
+        "EBAY_AU" => ("en-AU", "ebay.com.au"),
+        "EBAY_CA" => ("en-CA", "ebay.ca"),
+        "EBAY_DE" => ("de-DE", "ebay.de"),
+        "EBAY_FR" => ("fr-FR", "ebay.fr"),
+        "EBAY_IT" => ("it-IT", "ebay.it"),
+        "EBAY_ES" => ("es-ES", "ebay.es"),
+        "EBAY_IE" => ("en-IE", "ebay.ie"),
+        "EBAY_NL" => ("nl-NL", "ebay.nl"),
+        other => return Err(format!("Unsupported eBay marketplace: {}", other)),
+    })
+}
+
+fn media_url(environment: EbayEnvironment) -> &'static str {
+    match environment {
+        EbayEnvironment::Production => "https://apim.ebay.com/commerce/media/v1_beta",
+        EbayEnvironment::Sandbox => "https://apim.sandbox.ebay.com/commerce/media/v1_beta",
+    }
+}
+
+// Messages from an eBay API error body, or the body itself
+fn error_message(body: &str) -> String {
+    let messages: Vec<String> = serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|error| error["errors"].as_array().cloned())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|error| error["longMessage"].as_str().or(error["message"].as_str()).map(str::to_string))
+        .collect();
+    if messages.is_empty() {
+        body.trim().to_string()
+    } else {
+        messages.join("; ")
+    }
+}
+
+// A failed eBay call, with the HTTP status when eBay answered with an error
+#[derive(Debug)]
+pub struct EbayError {
+    pub message: String,
+    pub status: Option<StatusCode>,
+    // How long eBay asked to be left alone, from a 429's Retry-After
+    pub retry_after: Option<Duration>,
+}
+
+impl EbayError {
+    pub fn is_status(&self, status: StatusCode) -> bool {
+        self.status == Some(status)
+    }
+
+    // Whether eBay refused the call for making too many
+    pub fn is_rate_limited(&self) -> bool {
+        self.is_status(StatusCode::TOO_MANY_REQUESTS)
+    }
+}
+
+impl std::fmt::Display for EbayError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<String> for EbayError {
+    fn from(message: String) -> EbayError {
+        EbayError { message, status: None, retry_after: None }
+    }
+}
+
+impl From<&str> for EbayError {
+    fn from(message: &str) -> EbayError {
+        EbayError::from(message.to_string())
+    }
+}
+
+impl From<EbayError> for String {
+    fn from(error: EbayError) -> String {
+        error.message
+    }
+}
+
+// Send a request and parse its JSON response; empty responses are Null
+pub async fn send(request: reqwest::RequestBuilder, action: &str) -> Result<Value, EbayError> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Failed to {}: {}", action, e))?;
+    let status = response.status();
+    // Seconds; eBay doesn't send the HTTP-date form
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(Duration::from_secs);
+    let body = response.text().await.unwrap_or_default();
+    if !status.is_success() {
+        return Err(EbayError {
+            message: format!("Failed to {} ({}): {}", action, status, error_message(&body)),
+            status: Some(status),
+            retry_after,
+        });
+    }
+    if body.trim().is_empty() {
+        return Ok(Value::Null);
+    }
+    Ok(serde_json::from_str(&body).map_err(|e| format!("Failed to parse eBay response: {}", e))?)
+}
+
+// Upload a local photo to eBay Picture Services and return its URL
+pub async fn upload_photo(app: &AppHandle, environment: EbayEnvironment, token: &str, path: &str) -> Result<String, String> {
+    let image_path = path.to_string();
+    let jpeg = tauri::async_runtime::spawn_blocking(move || {
+        let img = image_io::open_image_scaled(&image_path, EBAY_IMAGE_EDGE)?;
+        image_io::encode_jpeg(&img, EBAY_JPEG_QUALITY)
+    })
+    .await
+    .map_err(|e| format!("Failed to read image: {}", e))??;
+
+    let (body, content_type) = network::multipart(&[("image", Some("image.jpg"), "image/jpeg", &jpeg)]);
+    let request = network::client(app)
+        .post(format!("{}/image/create_image_from_file", media_url(environment)))
+        .bearer_auth(token)
+        .header(reqwest::header::CONTENT_TYPE, content_type)
+        .body(body);
+    let image = send(request, "upload photo to eBay").await?;
+    image["imageUrl"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or_else(|| format!("eBay returned no URL for {}", path))
+}
+
+// Item specifics for the listing. Brand, size, colours and materials use
+// English aspect names; other marketplaces need theirs in `item_specifics`,
+// which take precedence.
+fn aspects(listing: &EbayListing, locale: &str) -> BTreeMap<String, Vec<String>> {
+    let mut aspects = listing.item_specifics.clone();
+    let mut set = |name: &str, values: Vec<String>| {
+        let values: Vec<String> = values.into_iter().map(|v| v.trim().to_string()).filter(|v| !v.is_empty()).collect();
+        if !values.is_empty() {
+            aspects.entry(name.to_string()).or_insert(values);
+        }
+    };
+    set("Brand", listing.item.brand.iter().cloned().collect());
+    set("Size", listing.item.size.iter().cloned().collect());
+    let colour = if matches!(locale, "en-US" | "en-CA") { "Color" } else { "Colour" };
+    set(colour, listing.item.colors.clone());
+    set("Material", listing.item.materials.clone());
+    aspects
+}
+
+// How eBay will see the listing, with what stops it being published.
+// Item specifics are checked separately, as that needs the category's
+// aspects from eBay.
+pub fn map_listing(listing: &EbayListing) -> MappedListing {
+    let mut problems = listing.item.problems("eBay", MAX_EBAY_PHOTOS);
+    // eBay counts characters, not bytes
+    if listing.item.title.chars().count() > 80 {
+        problems.push("eBay titles are limited to 80 characters".to_string());
+    }
+    if listing.category_id.trim().is_empty() {
+        problems.push("Category is required".to_string());
+    }
+    let marketplace_id = listing.marketplace_id.as_deref().unwrap_or(DEFAULT_MARKETPLACE);
+    if let Err(e) = marketplace_site(marketplace_id) {
+        problems.push(e);
+    }
+    MappedListing {
+        category: vec![listing.category_id.trim().to_string()],
+        condition: Some(ebay_condition(listing.item.condition).to_string()),
+        size: listing.item.size.clone(),
+        max_photos: MAX_EBAY_PHOTOS,
+        problems,
+    }
+}
+
+// Problems with the listing's item specifics against its category's
+// aspects, e.g. a required one missing
+pub async fn item_specific_problems(app: &AppHandle, taxonomy: &EbayTaxonomy, listing: &EbayListing) -> Result<Vec<String>, String> {
+    let environment = ebay_auth::load_settings(app)?.environment;
+    let marketplace_id = listing.marketplace_id.clone().unwrap_or_else(|| DEFAULT_MARKETPLACE.to_string());
+    let (locale, _) = marketplace_site(&marketplace_id)?;
+    let category_aspects = category_aspects(app, taxonomy, environment, &marketplace_id, listing.category_id.trim()).await?;
+    Ok(check_aspects(&category_aspects, &aspects(listing, locale)))
+}
+
+// Command to list an item on eBay through the Sell Inventory API: creates or
+// replaces the inventory item for the SKU, creates or updates its offer, and
+// publishes it. Item specifics are checked against the category's aspects
+// and local photos are uploaded first, to eBay unless the storage settings
+// host eBay photos in storage. Needs a connected account (see
+// `ebay_connect_account`).
+#[tauri::command]
+pub async fn publish_to_ebay(
+    app: AppHandle,
+    taxonomy: State<'_, EbayTaxonomy>,
+    listing: EbayListing,
+) -> Result<PublishedListing, String> {
+    Ok(publish(&app, &taxonomy, &listing).await?)
+}
+
+// Command to upload a photo to eBay Picture Services (EPS) through the Media
+// API, for sellers who'd rather eBay host their photos than set up a storage
+// bucket. It's resized for eBay first. Returns the photo's eBay URL; eBay
+// removes photos that aren't used in a listing after a while.
+#[tauri::command]
+pub async fn upload_to_eps(app: AppHandle, image_path: String) -> Result<String, String> {
+    let environment = ebay_auth::load_settings(&app)?.environment;
+    let token = ebay_auth::access_token(&app).await?;
+    upload_photo(&app, environment, &token, &image_path).await
+}
+
+// SKU for a listing that doesn't have one: a timestamp plus a random suffix,
+// so listings published together don't collide
+pub fn new_sku() -> String {
+    let suffix: String = rand::thread_rng().sample_iter(&Alphanumeric).take(4).map(char::from).collect();
+    format!("LA-{}-{}", Utc::now().format("%Y%m%d%H%M%S"), suffix.to_uppercase())
+}
+
+// Publish a listing, keeping eBay's HTTP status on failure so callers can
+// tell a rate limit from a rejected listing
+pub async fn publish(app: &AppHandle, taxonomy: &EbayTaxonomy, listing: &EbayListing) -> Result<PublishedListing, EbayError> {
+    map_listing(listing).check()?;
+    let settings = ebay_auth::load_settings(app)?;
+    let environment = settings.environment;
+    let token = ebay_auth::access_token(app).await?;
+    let marketplace_id = listing.marketplace_id.clone().unwrap_or_else(|| DEFAULT_MARKETPLACE.to_string());
+    let (locale, domain) = marketplace_site(&marketplace_id)?;
+
+    // Checked before photos are uploaded, so a listing eBay would reject
+    // fails early
+    let problems = item_specific_problems(app, taxonomy, listing).await?;
+    if !problems.is_empty() {
+        return Err(format!("Item specifics need fixing: {}", problems.join("; ")).into());
+    }
+    let item_aspects = aspects(listing, locale);
+    let listing_policies = ebay_policies::listing_policies(app, environment, &token, &marketplace_id, &listing.policies).await?;
+    let api = format!("{}/sell/inventory/v1", environment.api_url());
+    let client = network::client(app);
+
+    let sku = listing
+        .item
+        .sku
+        .clone()
+        .map(|sku| sku.trim().to_string())
+        .filter(|sku| !sku.is_empty())
+        .unwrap_or_else(new_sku);
+
+    let photo_host = storage::photo_host(app, Marketplace::Ebay)?;
+    let mut image_urls = Vec::new();
+    for photo in &listing.item.photos {
+        if photo.starts_with("https://") {
+            image_urls.push(photo.clone());
+        } else if photo_host == PhotoHost::Storage {
+            image_urls.push(storage::upload_photo(app, photo).await?);
+        } else {
+            image_urls.push(upload_photo(app, environment, &token, photo).await?);
+        }
+    }
+
+    let mut item = json!({
+        "availability": { "shipToLocationAvailability": { "quantity": listing.item.quantity } },
+        "condition": ebay_condition(listing.item.condition),
+        "product": {
+            "title": listing.item.title.trim(),
+            "description": listing.item.description,
+            "aspects": item_aspects,
+            "imageUrls": image_urls,
+        },
+    });
+    if let Some(description) = listing.item.condition_description.as_deref().filter(|d| !d.trim().is_empty()) {
+        item["conditionDescription"] = json!(description.trim());
+    }
+    let request = client
+        .put(format!("{}/inventory_item/{}", api, urlencoding::encode(&sku)))
+        .bearer_auth(&token)
+        .header(reqwest::header::CONTENT_LANGUAGE, locale)
+        .json(&item);
+    send(request, "save eBay inventory item").await?;
+
+    let offer = json!({
+        "sku": sku,
+        "marketplaceId": marketplace_id,
+        "format": "FIXED_PRICE",
+        "availableQuantity": listing.item.quantity,
+        "categoryId": listing.category_id.trim(),
+        "listingDescription": listing.item.description,
+        "listingPolicies": listing_policies,
+        "pricingSummary": {
+            "price": { "value": format!("{:.2}", listing.item.price), "currency": listing.item.currency.trim().to_uppercase() },
+        },
+        "merchantLocationKey": listing.merchant_location_key,
+    });
+
+    // An SKU published before already has an offer on the marketplace;
+    // eBay returns 404 when it has none. Any other failure stops here, as
+    // creating a second offer for the SKU would fail anyway.
+    let offers = match send(
+        client
+            .get(format!("{}/offer", api))
+            .bearer_auth(&token)
+            .query(&[("sku", sku.as_str()), ("marketplace_id", marketplace_id.as_str())]),
+        "look up eBay offers",
+    )
+    .await
+    {
+        Err(e) if e.is_status(StatusCode::NOT_FOUND) => Value::Null,
+        offers => offers?,
+    };
+    let existing = offers["offers"][0]["offerId"].as_str().map(str::to_string);
+    let offer_id = match existing {
+        Some(offer_id) => {
+            let request = client
+                .put(format!("{}/offer/{}", api, offer_id))
+                .bearer_auth(&token)
+                .header(reqwest::header::CONTENT_LANGUAGE, locale)
+                .json(&offer);
+            send(request, "update eBay offer").await?;
+            offer_id
+        }
+        None => {
+            let request = client
+                .post(format!("{}/offer", api))
+                .bearer_auth(&token)
+                .header(reqwest::header::CONTENT_LANGUAGE, locale)
+                .json(&offer);
+            send(request, "create eBay offer").await?["offerId"]
+                .as_str()
+                .map(str::to_string)
+                .ok_or("eBay returned no offer ID")?
+        }
+    };
+
+    let published = send(
+        client.post(format!("{}/offer/{}/publish", api, offer_id)).bearer_auth(&token),
+        "publish eBay offer",
+    )
+    .await?;
+    let listing_id = published["listingId"].as_str().ok_or("eBay returned no listing ID")?.to_string();
+    let url = match environment {
+        EbayEnvironment::Production => format!("https://www.{}/itm/{}", domain, listing_id),
+        EbayEnvironment::Sandbox => format!("https://sandbox.ebay.com/itm/{}", listing_id),
+    };
+    // Publishing already succeeded, so a failure here is left for the next
+    // publish, or `register_synced_listing` and `add_listing_to_history`, to
+    // fix
+    let _ = sync::record_listing(app, Marketplace::Ebay, &sku, Some(&listing_id), listing.item.quantity);
+    let _ = history::index_published(app, Marketplace::Ebay, &listing_id, &listing.item).await;
+    Ok(PublishedListing { listing_id, url, sku, offer_id })
+}
+
+// A line item of an eBay order, for quantity sync
+#[derive(Debug, Clone)]
+pub struct EbaySale {
+    pub order_id: String,
+    pub sku: String,
+    pub quantity: u32,
+}
+
+// Line items with a SKU from orders created since a time, leaving out
+// cancelled orders. Needs the sell.fulfillment.readonly scope.
+pub async fn sales_since(app: &AppHandle, since: DateTime<Utc>) -> Result<Vec<EbaySale>, String> {
+    const PAGE_SIZE: usize = 200;
+    ebay_auth::require_scope(app, ebay_auth::FULFILLMENT_SCOPE, "sync")?;
+    let environment = ebay_auth::load_settings(app)?.environment;
+    let token = ebay_auth::access_token(app).await?;
+    let filter = format!("creationdate:[{}..]", since.to_rfc3339_opts(SecondsFormat::Millis, true));
+    let client = network::client(app);
+
+    let mut sales = Vec::new();
+    let mut offset = 0;
+    loop {
+        let page_size = PAGE_SIZE.to_string();
+        let offset_param = offset.to_string();
+        let result = send(
+            client
+                .get(format!("{}/sell/fulfillment/v1/order", environment.api_url()))
+                .bearer_auth(&token)
+                .query(&[("filter", filter.as_str()), ("limit", page_size.as_str()), ("offset", offset_param.as_str())]),
+            "get eBay orders",
+        )
+        .await?;
+        let orders = result["orders"].as_array().cloned().unwrap_or_default();
+        for order in &orders {
+            if order["cancelStatus"]["cancelState"].as_str() == Some("CANCELED") {
+                continue;
+            }
+            let Some(order_id) = order["orderId"].as_str() else {
+                continue;
+            };
+            for item in order["lineItems"].as_array().into_iter().flatten() {
+                if let Some(sku) = item["sku"].as_str().filter(|sku| !sku.is_empty()) {
+                    sales.push(EbaySale {
+                        order_id: order_id.to_string(),
+                        sku: sku.to_string(),
+                        quantity: item["quantity"].as_u64().unwrap_or(1) as u32,
+                    });
+                }
+            }
+        }
+        if orders.len() < PAGE_SIZE {
+            return Ok(sales);
+        }
+        offset += PAGE_SIZE;
+    }
+}
+
+// An SKU's published offers, one per marketplace it's listed on
+pub async fn published_offers(app: &AppHandle, sku: &str) -> Result<Vec<Value>, String> {
+    let environment = ebay_auth::load_settings(app)?.environment;
+    let token = ebay_auth::access_token(app).await?;
+    let offers = send(
+        network::client(app)
+            .get(format!("{}/sell/inventory/v1/offer", environment.api_url()))
+            .bearer_auth(&token)
+            .query(&[("sku", sku)]),
+        "look up eBay offers",
+    )
+    .await;
+    published(offers)
+}
+
+// The published offers in an offer lookup. eBay answers 404 for an SKU
+// without offers, e.g. one ended or deleted by hand, which is no offers
+// rather than a failure.
+fn published(offers: Result<Value, EbayError>) -> Result<Vec<Value>, String> {
+    let offers = match offers {
+        Err(e) if e.is_status(StatusCode::NOT_FOUND) => return Ok(Vec::new()),
+        offers => offers?,
+    };
+    Ok(offers["offers"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|offer| offer["status"].as_str() == Some("PUBLISHED"))
+        .cloned()
+        .collect())
+}
+
+// End an SKU's live listings by withdrawing its published offers; returns
+// how many were withdrawn
+pub async fn end_listing(app: &AppHandle, sku: &str) -> Result<usize, String> {
+    let environment = ebay_auth::load_settings(app)?.environment;
+    let token = ebay_auth::access_token(app).await?;
+    let client = network::client(app);
+
+    let mut withdrawn = 0;
+    for offer in published_offers(app, sku).await? {
+        let Some(offer_id) = offer["offerId"].as_str() else {
+            continue;
+        };
+        send(
+            client
+                .post(format!("{}/sell/inventory/v1/offer/{}/withdraw", environment.api_url(), offer_id))
+                .bearer_auth(&token),
+            "end eBay listing",
+        )
+        .await?;
+        withdrawn += 1;
+    }
+    Ok(withdrawn)
+}
+
+// Set an SKU's available quantity, keeping its listings up; at zero they
+// show as out of stock when the account has out-of-stock control on
+pub async fn set_quantity(app: &AppHandle, sku: &str, quantity: u32) -> Result<(), String> {
+    let environment = ebay_auth::load_settings(app)?.environment;
+    let token = ebay_auth::access_token(app).await?;
+    let request = network::client(app)
+        .post(format!("{}/sell/inventory/v1/bulk_update_price_quantity", environment.api_url()))
+        .bearer_auth(&token)
+        .json(&json!({
+            "requests": [{ "sku": sku, "shipToLocationAvailability": { "quantity": quantity } }],
+        }));
+    bulk_update(request, "update eBay quantity").await
+}
+
+// Set the price of an SKU's live listings; returns how many were updated
+pub async fn set_price(app: &AppHandle, sku: &str, price: f64) -> Result<usize, String> {
+    // Each offer keeps its own currency, as offers on other eBay sites may
+    // be priced in another
+    let offers: Vec<Value> = published_offers(app, sku)
+        .await?
+        .iter()
+        .filter_map(|offer| Some((offer["offerId"].as_str()?, offer["pricingSummary"]["price"]["currency"].as_str()?)))
+        .map(|(offer_id, currency)| json!({
+            "offerId": offer_id,
+            "price": { "value": format!("{:.2}", price), "currency": currency },
+        }))
+        .collect();
+    if offers.is_empty() {
+        return Ok(0);
+    }
+    let count = offers.len();
+    let environment = ebay_auth::load_settings(app)?.environment;
+    let token = ebay_auth::access_token(app).await?;
+    let request = network::client(app)
+        .post(format!("{}/sell/inventory/v1/bulk_update_price_quantity", environment.api_url()))
+        .bearer_auth(&token)
+        .json(&json!({ "requests": [{ "sku": sku, "offers": offers }] }));
+    bulk_update(request, "update eBay price").await?;
+    Ok(count)
+}
+
+// bulk_update_price_quantity answers with a status per SKU and offer; any
+// that failed is an error
+async fn bulk_update(request: reqwest::RequestBuilder, action: &str) -> Result<(), String> {
+    let result = send(request, action).await?;
+    let failed = result["responses"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|response| response["statusCode"].as_u64().is_some_and(|status| status >= 300));
+    match failed {
+        Some(response) => Err(format!("Failed to {}: {}", action, error_message(&response.to_string()))),
+        None => Ok(()),
+    }
+}
+
+// Category tree for a marketplace, from the cache when possible
+async fn category_tree_id(
+    app: &AppHandle,
+    taxonomy: &EbayTaxonomy,
+    environment: EbayEnvironment,
+    token: &str,
+    marketplace_id: &str,
+) -> Result<String, String> {
+    let key = (environment, marketplace_id.to_string());
+    if let Some(tree_id) = taxonomy.trees.lock().ok().and_then(|trees| trees.get(&key).cloned()) {
+        return Ok(tree_id);
+    }
+    let request = network::client(app)
+        .get(format!("{}/commerce/taxonomy/v1/get_default_category_tree_id", environment.api_url()))
+        .bearer_auth(token)
+        .query(&[("marketplace_id", marketplace_id)]);
+    let tree_id = send(request, "get eBay category tree").await?["categoryTreeId"]
+        .as_str()
+        .map(str::to_string)
+        .ok_or("eBay returned no category tree")?;
+    if let Ok(mut trees) = taxonomy.trees.lock() {
+        trees.insert(key, tree_id.clone());
+    }
+    Ok(tree_id)
+}
+
+// Command to suggest eBay leaf categories for a title or keywords with the
+// Taxonomy API, best match first, so the right one can be pre-selected.
+// Only needs the app credentials, not a connected account.
+#[tauri::command]
+pub async fn suggest_ebay_category(
+    app: AppHandle,
+    taxonomy: State<'_, EbayTaxonomy>,
+    title_or_keywords: String,
+    marketplace_id: Option<String>,
+) -> Result<Vec<CategorySuggestion>, String> {
+    let query = title_or_keywords.trim();
+    if query.is_empty() {
+        return Err("Enter a title or keywords".to_string());
+    }
+    let environment = ebay_auth::load_settings(&app)?.environment;
+    let marketplace_id = marketplace_id.unwrap_or_else(|| DEFAULT_MARKETPLACE.to_string());
+    marketplace_site(&marketplace_id)?;
+    let token = ebay_auth::application_token(&app, ApplicationScope::Public).await?;
+    let tree_id = category_tree_id(&app, &taxonomy, environment, &token, &marketplace_id).await?;
+
+    let request = network::client(&app)
+        .get(format!(
+            "{}/commerce/taxonomy/v1/category_tree/{}/get_category_suggestions",
+            environment.api_url(),
+            tree_id
+        ))
+        .bearer_auth(&token)
+        .query(&[("q", query)]);
+    let result = send(request, "get eBay category suggestions").await?;
+
+    // Ancestors are listed from the parent upwards
+    let suggestions = result["categorySuggestions"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|suggestion| {
+            let category = &suggestion["category"];
+            let name = category["categoryName"].as_str()?.to_string();
+            let mut path: Vec<String> = suggestion["categoryTreeNodeAncestors"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .rev()
+                .filter_map(|ancestor| ancestor["categoryName"].as_str().map(str::to_string))
+                .collect();
+            path.push(name.clone());
+            Some(CategorySuggestion { category_id: category["categoryId"].as_str()?.to_string(), name, path })
+        })
+        .take(MAX_CATEGORY_SUGGESTIONS)
+        .collect();
+    Ok(suggestions)
+}
+
+fn parse_aspect(aspect: &Value) -> Option<EbayAspect> {
+    let constraint = &aspect["aspectConstraint"];
+    let usage = if constraint["aspectRequired"].as_bool().unwrap_or(false) {
+        AspectUsage::Required
+    } else if constraint["aspectUsage"].as_str() == Some("RECOMMENDED") {
+        AspectUsage::Recommended
+    } else {
+        AspectUsage::Optional
+    };
+    Some(EbayAspect {
+        name: aspect["localizedAspectName"].as_str()?.to_string(),
+        usage,
+        selection_only: constraint["aspectMode"].as_str() == Some("SELECTION_ONLY"),
+        multiple: constraint["itemToAspectCardinality"].as_str() == Some("MULTI"),
+        max_length: constraint["aspectMaxLength"].as_u64().map(|length| length as usize),
+        values: aspect["aspectValues"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|value| value["localizedValue"].as_str().map(str::to_string))
+            .collect(),
+    })
+}
+
+// Aspects of a category, from the cache when possible
+async fn category_aspects(
+    app: &AppHandle,
+    taxonomy: &EbayTaxonomy,
+    environment: EbayEnvironment,
+    marketplace_id: &str,
+    category_id: &str,
+) -> Result<Vec<EbayAspect>, String> {
+    let key = (environment, marketplace_id.to_string(), category_id.to_string());
+    if let Some(aspects) = taxonomy.aspects.lock().ok().and_then(|aspects| aspects.get(&key).cloned()) {
+        return Ok(aspects);
+    }
+    let token = ebay_auth::application_token(app, ApplicationScope::Public).await?;
+    let tree_id = category_tree_id(app, taxonomy, environment, &token, marketplace_id).await?;
+    let request = network::client(app)
+        .get(format!(
+            "{}/commerce/taxonomy/v1/category_tree/{}/get_item_aspects_for_category",
+            environment.api_url(),
+            tree_id
+        ))
+        .bearer_auth(&token)
+        .query(&[("category_id", category_id)]);
+    let result = send(request, "get eBay item specifics").await?;
+    let mut aspects: Vec<EbayAspect> = result["aspects"].as_array().into_iter().flatten().filter_map(parse_aspect).collect();
+    // Required first, then recommended, keeping eBay's order within each
+    aspects.sort_by_key(|aspect| aspect.usage as u8);
+    if let Ok(mut cached) = taxonomy.aspects.lock() {
+        cached.insert(key, aspects.clone());
+    }
+    Ok(aspects)
+}
+
+// Problems with item specifics against a category's aspects: missing
+// required ones, values outside a selection-only list, too many values or
+// values that are too long. Names and values are compared ignoring case.
+fn check_aspects(aspects: &[EbayAspect], given: &BTreeMap<String, Vec<String>>) -> Vec<String> {
+    let mut problems = Vec::new();
+    for aspect in aspects {
+        let values = given
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(&aspect.name))
+            .map(|(_, values)| values.as_slice())
+            .unwrap_or_default();
+        if values.is_empty() {
+            if aspect.usage == AspectUsage::Required {
+                problems.push(format!("{} is required", aspect.name));
+            }
+            continue;
+        }
+        if !aspect.multiple && values.len() > 1 {
+            problems.push(format!("{} takes a single value", aspect.name));
+        }
+        for value in values {
+            if aspect.selection_only && !aspect.values.iter().any(|allowed| allowed.eq_ignore_ascii_case(value)) {
+                problems.push(format!("\"{}\" isn't an allowed {}", value, aspect.name));
+            }
+            if let Some(max) = aspect.max_length.filter(|max| value.chars().count() > *max) {
+                problems.push(format!("{} values are limited to {} characters", aspect.name, max));
+            }
+        }
+    }
+    problems
+}
+
+// Command to get the item specifics (aspects) an eBay category takes:
+// required ones first, then recommended and optional, each with its allowed
+// or suggested values, so they can be filled in and checked before publishing
+#[tauri::command]
+pub async fn get_ebay_aspects(
+    app: AppHandle,
+    taxonomy: State<'_, EbayTaxonomy>,
+    category_id: String,
+    marketplace_id: Option<String>,
+) -> Result<Vec<EbayAspect>, String> {
+    let category_id = category_id.trim();
+    if category_id.is_empty() {
+        return Err("Category is required".to_string());
+    }
+    let environment = ebay_auth::load_settings(&app)?.environment;
+    let marketplace_id = marketplace_id.unwrap_or_else(|| DEFAULT_MARKETPLACE.to_string());
+    marketplace_site(&marketplace_id)?;
+    category_aspects(&app, &taxonomy, environment, &marketplace_id, category_id).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status_error(status: StatusCode) -> EbayError {
+        EbayError { message: format!("Failed with status {}", status), status: Some(status), retry_after: None }
+    }
+
+    #[test]
+    fn missing_offers_are_none() {
+        assert!(published(Err(status_error(StatusCode::NOT_FOUND))).unwrap().is_empty());
+        assert!(published(Err(status_error(StatusCode::INTERNAL_SERVER_ERROR))).is_err());
+    }
+
+    #[test]
+    fn only_published_offers_are_kept() {
+        let offers = json!({ "offers": [
+            { "offerId": "1", "status": "PUBLISHED" },
+            { "offerId": "2", "status": "UNPUBLISHED" },
+        ] });
+        let published = published(Ok(offers)).unwrap();
+        assert_eq!(published.len(), 1);
+        assert_eq!(published[0]["offerId"], "1");
+    }
+}
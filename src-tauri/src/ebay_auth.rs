@@ -0,0 +1,511 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use base64::{Engine as _, engine::general_purpose};
+use chrono::Utc;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use crate::{ebay_policies, keychain, network};
+
+// Scopes asked for when connecting: listing through the Inventory API,
+// reading the account's business policies and reading orders for sync.
+// They're saved with the token, which is refreshed with the same ones, so an
+// account connected before a scope was added keeps working without it.
+const USER_SCOPES: [&str; 4] = [
+    "https://api.ebay.com/oauth/api_scope",
+    "https://api.ebay.com/oauth/api_scope/sell.inventory",
+    "https://api.ebay.com/oauth/api_scope/sell.account",
+    FULFILLMENT_SCOPE,
+];
+
+// Reading orders, which only sales sync needs
+pub const FULFILLMENT_SCOPE: &str = "https://api.ebay.com/oauth/api_scope/sell.fulfillment.readonly";
+
+// Scopes of tokens saved before the scopes were, all of them connected
+// without order access
+const LEGACY_SCOPES: [&str; 3] = [
+    "https://api.ebay.com/oauth/api_scope",
+    "https://api.ebay.com/oauth/api_scope/sell.inventory",
+    "https://api.ebay.com/oauth/api_scope/sell.account",
+];
+
+// How long the user has to finish signing in on eBay
+const CONSENT_TIMEOUT: Duration = Duration::from_secs(300);
+
+// Tokens are refreshed this long before they expire
+const EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+const DEFAULT_REDIRECT_PORT: u16 = 8976;
+
+const REDIRECT_PAGE: &str = "<!DOCTYPE html><html><body style=\"font-family: sans-serif\">\
+<p>Your eBay account is connected. You can close this window.</p></body></html>";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EbayEnvironment {
+    Production,
+    Sandbox,
+}
+
+impl EbayEnvironment {
+    pub fn api_url(self) -> &'static str {
+        match self {
+            EbayEnvironment::Production => "https://api.ebay.com",
+            EbayEnvironment::Sandbox => "https://api.sandbox.ebay.com",
+        }
+    }
+
+    fn auth_url(self) -> &'static str {
+        match self {
+            EbayEnvironment::Production => "https://auth.ebay.com/oauth2/authorize",
+            EbayEnvironment::Sandbox => "https://auth.sandbox.ebay.com/oauth2/authorize",
+        }
+    }
+
+    // Keychain account holding the user's refresh token
+    fn token_account(self) -> &'static str {
+        match self {
+            EbayEnvironment::Production => "ebay-refresh-production",
+            EbayEnvironment::Sandbox => "ebay-refresh-sandbox",
+        }
+    }
+}
+
+// Scopes of application tokens, each cached separately
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApplicationScope {
+    // Public APIs such as Taxonomy and Browse
+    Public,
+    // Marketplace Insights, which eBay grants to approved apps only
+    MarketplaceInsights,
+}
+
+impl ApplicationScope {
+    fn scope(self) -> &'static str {
+        match self {
+            ApplicationScope::Public => "https://api.ebay.com/oauth/api_scope",
+            ApplicationScope::MarketplaceInsights => "https://api.ebay.com/oauth/api_scope/buy.marketplace.insights",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EbaySettings {
+    pub environment: EbayEnvironment,
+    // App ID from the eBay developer portal
+    pub client_id: String,
+    // RuName, which eBay uses in place of a redirect URL. Its accept URL
+    // should point at `http://localhost:<redirect_port>/` or at a page that
+    // forwards the query string to the app.
+    pub ru_name: String,
+    // Port of the local listener catching the redirect; `None` when the
+    // redirect reaches the app another way, via `ebay_handle_redirect`
+    #[serde(default = "default_redirect_port")]
+    pub redirect_port: Option<u16>,
+}
+
+fn default_redirect_port() -> Option<u16> {
+    Some(DEFAULT_REDIRECT_PORT)
+}
+
+impl EbaySettings {
+    fn validate(&self) -> Result<(), String> {
+        if self.client_id.trim().is_empty() {
+            return Err("Client ID is required".to_string());
+        }
+        if self.ru_name.trim().is_empty() {
+            return Err("RuName is required".to_string());
+        }
+        Ok(())
+    }
+
+    fn secret_account(&self) -> &'static str {
+        match self.environment {
+            EbayEnvironment::Production => "ebay-client-secret-production",
+            EbayEnvironment::Sandbox => "ebay-client-secret-sandbox",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct EbaySettingsInfo {
+    #[serde(flatten)]
+    pub settings: EbaySettings,
+    pub has_client_secret: bool,
+    // Whether a refresh token is stored for the environment
+    pub connected: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EbayAccount {
+    pub environment: EbayEnvironment,
+    // RFC 3339; the user has to connect again after this
+    pub refresh_token_expires_at: Option<String>,
+}
+
+// Refresh token as kept in the keychain. Access tokens are too long for some
+// keychains and only last two hours, so they are kept in memory.
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredToken {
+    refresh_token: String,
+    expires_at: Option<String>,
+    // Scopes the user consented to
+    #[serde(default)]
+    scopes: Vec<String>,
+}
+
+impl StoredToken {
+    fn scopes(&self) -> Vec<String> {
+        if self.scopes.is_empty() {
+            LEGACY_SCOPES.map(str::to_string).to_vec()
+        } else {
+            self.scopes.clone()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+    refresh_token: Option<String>,
+    refresh_token_expires_in: Option<i64>,
+}
+
+struct CachedToken {
+    environment: EbayEnvironment,
+    token: String,
+    expires_at: Instant,
+}
+
+struct PendingConsent {
+    state: String,
+    code: oneshot::Sender<String>,
+}
+
+// Sign-in in progress and the current access tokens
+#[derive(Default)]
+pub struct EbayAuth {
+    pending: Mutex<Option<PendingConsent>>,
+    // For the connected account
+    user: Mutex<Option<CachedToken>>,
+    // For the app itself, by scope
+    application: Mutex<Option<CachedToken>>,
+    insights: Mutex<Option<CachedToken>>,
+}
+
+impl EbayAuth {
+    fn begin(&self, state: String) -> Result<oneshot::Receiver<String>, String> {
+        let (sender, receiver) = oneshot::channel();
+        let mut pending = self.pending.lock().map_err(|_| "eBay auth lock poisoned".to_string())?;
+        *pending = Some(PendingConsent { state, code: sender });
+        Ok(receiver)
+    }
+
+    // Hand the code from a redirect URL's query to the waiting sign-in
+    fn deliver(&self, query: &str) -> Result<(), String> {
+        let params = network::query_params(query);
+        let param = |name: &str| params.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str());
+
+        let mut pending = self.pending.lock().map_err(|_| "eBay auth lock poisoned".to_string())?;
+        let Some(consent) = pending.take_if(|consent| param("state") == Some(consent.state.as_str())) else {
+            return Err("No eBay sign-in is waiting for this redirect".to_string());
+        };
+        match param("code") {
+            Some(code) => {
+                let _ = consent.code.send(code.to_string());
+                Ok(())
+            }
+            // Dropping the sender tells the sign-in the user declined
+            None => Err("eBay sign-in was declined".to_string()),
+        }
+    }
+
+    fn clear(&self) {
+        for slot in [&self.user, &self.application, &self.insights] {
+            if let Ok(mut cached) = slot.lock() {
+                *cached = None;
+            }
+        }
+    }
+}
+
+fn cached(slot: &Mutex<Option<CachedToken>>, environment: EbayEnvironment) -> Option<String> {
+    let cached = slot.lock().ok()?;
+    cached
+        .as_ref()
+        .filter(|cached| cached.environment == environment && Instant::now() < cached.expires_at)
+        .map(|cached| cached.token.clone())
+}
+
+fn cache(slot: &Mutex<Option<CachedToken>>, environment: EbayEnvironment, token: &TokenResponse) {
+    if let Ok(mut cached) = slot.lock() {
+        *cached = Some(CachedToken {
+            environment,
+            token: token.access_token.clone(),
+            expires_at: Instant::now() + Duration::from_secs(token.expires_in).saturating_sub(EXPIRY_MARGIN),
+        });
+    }
+}
+
+fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app.path_resolver()
+        .app_data_dir()
+        .ok_or("Failed to resolve app data directory")?;
+    Ok(data_dir.join("ebay.json"))
+}
+
+pub fn load_settings(app: &AppHandle) -> Result<EbaySettings, String> {
+    let path = settings_path(app)?;
+    if !path.exists() {
+        return Err("eBay isn't set up; add the app's client ID and RuName in settings".to_string());
+    }
+    let json = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read eBay settings: {}", e))?;
+    serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse eBay settings: {}", e))
+}
+
+// Basic credentials for the token endpoint
+fn client_credentials(settings: &EbaySettings) -> Result<String, String> {
+    let secret = keychain::get_secret(settings.secret_account())?
+        .ok_or("No eBay client secret is stored")?;
+    Ok(general_purpose::STANDARD.encode(format!("{}:{}", settings.client_id.trim(), secret)))
+}
+
+async fn request_token(app: &AppHandle, settings: &EbaySettings, form: &[(&str, &str)]) -> Result<TokenResponse, String> {
+    let response = network::client(app)
+        .post(format!("{}/identity/v1/oauth2/token", settings.environment.api_url()))
+        .header(reqwest::header::AUTHORIZATION, format!("Basic {}", client_credentials(settings)?))
+        .form(form)
+        .send()
+        .await
+        .map_err(|e| format!("eBay token request failed: {}", e))?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("eBay token request failed ({}): {}", status, body.trim()));
+    }
+    response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse eBay token: {}", e))
+}
+
+fn save_refresh_token(environment: EbayEnvironment, token: &TokenResponse) -> Result<Option<String>, String> {
+    let refresh_token = token.refresh_token.clone().ok_or("eBay returned no refresh token")?;
+    let expires_at = token
+        .refresh_token_expires_in
+        .map(|seconds| (Utc::now() + chrono::Duration::seconds(seconds)).to_rfc3339());
+    let scopes = USER_SCOPES.map(str::to_string).to_vec();
+    let stored = StoredToken { refresh_token, expires_at: expires_at.clone(), scopes };
+    let json = serde_json::to_string(&stored)
+        .map_err(|e| format!("Failed to serialize eBay token: {}", e))?;
+    keychain::set_secret(environment.token_account(), &json)?;
+    Ok(expires_at)
+}
+
+fn stored_token(environment: EbayEnvironment) -> Result<StoredToken, String> {
+    let json = keychain::get_secret(environment.token_account())?
+        .ok_or("No eBay account is connected")?;
+    serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse stored eBay token: {}", e))
+}
+
+// Fail unless the connected account granted `scope`, e.g. an account
+// connected before sales sync asked for order access
+pub fn require_scope(app: &AppHandle, scope: &str, feature: &str) -> Result<(), String> {
+    let settings = load_settings(app)?;
+    if stored_token(settings.environment)?.scopes().iter().any(|granted| granted == scope) {
+        Ok(())
+    } else {
+        Err(format!("Reconnect the eBay account to enable {}; it was connected without that access", feature))
+    }
+}
+
+// A user access token for the configured environment, refreshed when the
+// cached one has expired
+pub async fn access_token(app: &AppHandle) -> Result<String, String> {
+    let settings = load_settings(app)?;
+    let auth = app.state::<EbayAuth>();
+    if let Some(token) = cached(&auth.user, settings.environment) {
+        return Ok(token);
+    }
+
+    let stored = stored_token(settings.environment)?;
+    let scopes = stored.scopes().join(" ");
+    let token = request_token(app, &settings, &[
+        ("grant_type", "refresh_token"),
+        ("refresh_token", &stored.refresh_token),
+        ("scope", &scopes),
+    ])
+    .await
+    .map_err(|e| format!("{}; connect the eBay account again if this persists", e))?;
+    cache(&auth.user, settings.environment, &token);
+    Ok(token.access_token)
+}
+
+// An application access token from the client credentials, for APIs that
+// don't act for a seller; no connected account is needed
+pub async fn application_token(app: &AppHandle, scope: ApplicationScope) -> Result<String, String> {
+    let settings = load_settings(app)?;
+    let auth = app.state::<EbayAuth>();
+    let slot = match scope {
+        ApplicationScope::Public => &auth.application,
+        ApplicationScope::MarketplaceInsights => &auth.insights,
+    };
+    if let Some(token) = cached(slot, settings.environment) {
+        return Ok(token);
+    }
+    let token = request_token(app, &settings, &[
+        ("grant_type", "client_credentials"),
+        ("scope", scope.scope()),
+    ])
+    .await?;
+    cache(slot, settings.environment, &token);
+    Ok(token.access_token)
+}
+
+// Command to configure the eBay app credentials
+// `client_secret` is stored in the OS keychain: pass a secret to set it, an
+// empty string to remove it, or leave it out to keep the current one.
+#[tauri::command]
+pub fn set_ebay_settings(
+    app: AppHandle,
+    auth: State<EbayAuth>,
+    settings: EbaySettings,
+    client_secret: Option<String>,
+) -> Result<(), String> {
+    settings.validate()?;
+
+    let path = settings_path(&app)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create settings directory: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(&settings)
+        .map_err(|e| format!("Failed to serialize eBay settings: {}", e))?;
+    fs::write(&path, json)
+        .map_err(|e| format!("Failed to save eBay settings: {}", e))?;
+    auth.clear();
+
+    match client_secret.as_deref().map(str::trim) {
+        Some("") => keychain::delete_secret(settings.secret_account()),
+        Some(secret) => keychain::set_secret(settings.secret_account(), secret),
+        None => Ok(()),
+    }
+}
+
+// Whether an eBay account is connected in the configured environment
+pub fn is_connected(app: &AppHandle) -> bool {
+    load_settings(app)
+        .ok()
+        .and_then(|settings| keychain::get_secret(settings.environment.token_account()).ok().flatten())
+        .is_some()
+}
+
+// Command to get the eBay settings, whether a client secret is stored and
+// whether an account is connected
+#[tauri::command]
+pub fn get_ebay_settings(app: AppHandle) -> Result<Option<EbaySettingsInfo>, String> {
+    if !settings_path(&app)?.exists() {
+        return Ok(None);
+    }
+    let settings = load_settings(&app)?;
+    let has_client_secret = keychain::get_secret(settings.secret_account())?.is_some();
+    let connected = keychain::get_secret(settings.environment.token_account())?.is_some();
+    Ok(Some(EbaySettingsInfo { settings, has_client_secret, connected }))
+}
+
+// Command to connect the user's eBay account: opens eBay's consent page in
+// the browser, waits for the redirect (on the localhost listener, or passed
+// in with `ebay_handle_redirect`), exchanges the code and stores the refresh
+// token in the OS keychain
+#[tauri::command]
+pub async fn ebay_connect_account(app: AppHandle, auth: State<'_, EbayAuth>) -> Result<EbayAccount, String> {
+    let settings = load_settings(&app)?;
+    // Fails early, before the user signs in, when the secret is missing
+    client_credentials(&settings)?;
+
+    let state: String = rand::thread_rng().sample_iter(&Alphanumeric).take(32).map(char::from).collect();
+    let consent_url = format!(
+        "{}?client_id={}&redirect_uri={}&response_type=code&scope={}&state={}",
+        settings.environment.auth_url(),
+        urlencoding::encode(settings.client_id.trim()),
+        urlencoding::encode(settings.ru_name.trim()),
+        urlencoding::encode(&USER_SCOPES.join(" ")),
+        state
+    );
+
+    let listener = match settings.redirect_port {
+        Some(port) => {
+            let listener = TcpListener::bind(("127.0.0.1", port))
+                .await
+                .map_err(|e| format!("Failed to listen on port {} for the eBay redirect: {}", port, e))?;
+            let handle = app.clone();
+            Some(tauri::async_runtime::spawn(network::serve_redirect(listener, REDIRECT_PAGE, move |query| {
+                handle.state::<EbayAuth>().deliver(query).is_ok()
+            })))
+        }
+        None => None,
+    };
+    let code = auth.begin(state)?;
+
+    let result = async {
+        tauri::api::shell::open(&app.shell_scope(), &consent_url, None)
+            .map_err(|e| format!("Failed to open the browser: {}", e))?;
+        match tokio::time::timeout(CONSENT_TIMEOUT, code).await {
+            Ok(Ok(code)) => Ok(code),
+            Ok(Err(_)) => Err("eBay sign-in was declined".to_string()),
+            Err(_) => Err("Timed out waiting for eBay sign-in".to_string()),
+        }
+    }
+    .await;
+    if let Some(listener) = listener {
+        listener.abort();
+    }
+    if let Ok(mut pending) = auth.pending.lock() {
+        *pending = None;
+    }
+    let code = result?;
+
+    let token = request_token(&app, &settings, &[
+        ("grant_type", "authorization_code"),
+        ("code", &code),
+        ("redirect_uri", settings.ru_name.trim()),
+    ])
+    .await?;
+    let refresh_token_expires_at = save_refresh_token(settings.environment, &token)?;
+    cache(&auth.user, settings.environment, &token);
+    ebay_policies::clear_cache(&app);
+    Ok(EbayAccount { environment: settings.environment, refresh_token_expires_at })
+}
+
+// Command to finish a sign-in from the redirect URL when it reaches the app
+// some other way than the localhost listener, e.g. a deep link
+#[tauri::command]
+pub fn ebay_handle_redirect(auth: State<EbayAuth>, url: String) -> Result<(), String> {
+    let query = url.split_once('?').map(|(_, query)| query).unwrap_or(&url);
+    let query = query.split('#').next().unwrap_or(query);
+    auth.deliver(query)
+}
+
+// Command to get a user access token for eBay's APIs
+#[tauri::command]
+pub async fn ebay_get_token(app: AppHandle) -> Result<String, String> {
+    access_token(&app).await
+}
+
+// Command to forget the connected account's tokens
+#[tauri::command]
+pub fn ebay_disconnect_account(app: AppHandle, auth: State<EbayAuth>) -> Result<(), String> {
+    let settings = load_settings(&app)?;
+    auth.clear();
+    ebay_policies::clear_cache(&app);
+    keychain::delete_secret(settings.environment.token_account())
+}
@@ -0,0 +1,654 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use chrono::Utc;
+use rusqlite::{params, Connection, Row};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+use crate::crosslist::Marketplace;
+use crate::db::Database;
+use crate::ebay_auth::{self, EbayEnvironment};
+use crate::history::{self, ListingHistory};
+use crate::models::ModelStore;
+use crate::{image_io, network, sync};
+
+// Emitted with an `ImportProgress` after each listing
+const PROGRESS_EVENT: &str = "ebay-import://progress";
+
+// Trading API version the requests are written against
+const COMPATIBILITY_LEVEL: &str = "1193";
+
+// Largest page GetMyeBaySelling returns
+const PAGE_SIZE: u32 = 200;
+
+// eBay keeps sold listings in My eBay for 60 days
+const MAX_SOLD_DAYS: u32 = 60;
+
+// A listing from the seller's eBay account, stored locally
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportedListing {
+    pub item_id: String,
+    // active or sold
+    pub status: String,
+    pub sku: Option<String>,
+    pub title: String,
+    // As on eBay, usually HTML
+    pub description: String,
+    pub price: f64,
+    pub currency: String,
+    pub category_id: String,
+    pub category_name: String,
+    pub condition: Option<String>,
+    pub quantity: u32,
+    pub quantity_sold: u32,
+    pub item_specifics: BTreeMap<String, Vec<String>>,
+    // eBay's photo URLs and the local copies, in the same order
+    pub photo_urls: Vec<String>,
+    pub photos: Vec<String>,
+    pub url: Option<String>,
+    // RFC 3339
+    pub listed_at: Option<String>,
+    pub sold_at: Option<String>,
+    pub imported_at: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportProgress {
+    pub item_id: String,
+    pub done: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportFailure {
+    pub item_id: String,
+    pub error: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ImportReport {
+    pub active: usize,
+    pub sold: usize,
+    pub photos_downloaded: usize,
+    // Listings added to the history index for `match_against_history`
+    pub indexed: usize,
+    // Listings imported but not indexed, e.g. because the CLIP model isn't
+    // downloaded or a photo couldn't be read
+    pub index_failed: Vec<ImportFailure>,
+    pub failed: Vec<ImportFailure>,
+}
+
+// Trading API responses, as far as the import reads them
+
+#[derive(Debug, Deserialize)]
+struct ApiError {
+    #[serde(rename = "LongMessage", default)]
+    long_message: String,
+    #[serde(rename = "SeverityCode", default)]
+    severity: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Pagination {
+    #[serde(rename = "TotalNumberOfPages", default)]
+    total_pages: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct ItemRef {
+    #[serde(rename = "ItemID")]
+    item_id: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ItemArray {
+    #[serde(rename = "Item", default)]
+    items: Vec<ItemRef>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ActiveList {
+    #[serde(rename = "ItemArray", default)]
+    item_array: ItemArray,
+    #[serde(rename = "PaginationResult", default)]
+    pagination: Pagination,
+}
+
+#[derive(Debug, Deserialize)]
+struct Transaction {
+    #[serde(rename = "Item")]
+    item: ItemRef,
+    #[serde(rename = "CreatedDate")]
+    created_date: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct TransactionArray {
+    #[serde(rename = "Transaction", default)]
+    transactions: Vec<Transaction>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Order {
+    #[serde(rename = "TransactionArray", default)]
+    transaction_array: TransactionArray,
+}
+
+// A single-item sale comes as a Transaction, a multi-item order as an Order
+#[derive(Debug, Deserialize)]
+struct OrderTransaction {
+    #[serde(rename = "Transaction")]
+    transaction: Option<Transaction>,
+    #[serde(rename = "Order")]
+    order: Option<Order>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct OrderTransactionArray {
+    #[serde(rename = "OrderTransaction", default)]
+    order_transactions: Vec<OrderTransaction>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SoldList {
+    #[serde(rename = "OrderTransactionArray", default)]
+    order_transaction_array: OrderTransactionArray,
+    #[serde(rename = "PaginationResult", default)]
+    pagination: Pagination,
+}
+
+#[derive(Debug, Deserialize)]
+struct MyEbaySellingResponse {
+    #[serde(rename = "Ack")]
+    ack: String,
+    #[serde(rename = "Errors", default)]
+    errors: Vec<ApiError>,
+    #[serde(rename = "ActiveList")]
+    active_list: Option<ActiveList>,
+    #[serde(rename = "SoldList")]
+    sold_list: Option<SoldList>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Amount {
+    #[serde(rename = "@currencyID", default)]
+    currency: String,
+    #[serde(rename = "$text", default)]
+    value: f64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SellingStatus {
+    #[serde(rename = "CurrentPrice", default)]
+    current_price: Amount,
+    #[serde(rename = "QuantitySold", default)]
+    quantity_sold: u32,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Category {
+    #[serde(rename = "CategoryID", default)]
+    id: String,
+    #[serde(rename = "CategoryName", default)]
+    name: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PictureDetails {
+    #[serde(rename = "PictureURL", default)]
+    urls: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NameValueList {
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(rename = "Value", default)]
+    values: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ItemSpecifics {
+    #[serde(rename = "NameValueList", default)]
+    lists: Vec<NameValueList>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ListingDetails {
+    #[serde(rename = "StartTime")]
+    start_time: Option<String>,
+    #[serde(rename = "ViewItemURL")]
+    view_item_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Item {
+    #[serde(rename = "ItemID")]
+    item_id: String,
+    #[serde(rename = "Title", default)]
+    title: String,
+    #[serde(rename = "Description", default)]
+    description: String,
+    #[serde(rename = "SKU")]
+    sku: Option<String>,
+    #[serde(rename = "Quantity", default)]
+    quantity: u32,
+    #[serde(rename = "ConditionDisplayName")]
+    condition: Option<String>,
+    #[serde(rename = "PrimaryCategory", default)]
+    category: Category,
+    #[serde(rename = "SellingStatus", default)]
+    selling_status: SellingStatus,
+    #[serde(rename = "PictureDetails", default)]
+    picture_details: PictureDetails,
+    #[serde(rename = "ItemSpecifics", default)]
+    item_specifics: ItemSpecifics,
+    #[serde(rename = "ListingDetails", default)]
+    listing_details: ListingDetails,
+}
+
+#[derive(Debug, Deserialize)]
+struct GetItemResponse {
+    #[serde(rename = "Ack")]
+    ack: String,
+    #[serde(rename = "Errors", default)]
+    errors: Vec<ApiError>,
+    #[serde(rename = "Item")]
+    item: Option<Item>,
+}
+
+// An error for a response eBay didn't accept; warnings are let through
+fn check_ack(ack: &str, errors: &[ApiError], action: &str) -> Result<(), String> {
+    if ack == "Success" || ack == "Warning" {
+        return Ok(());
+    }
+    let messages: Vec<&str> = errors
+        .iter()
+        .filter(|error| error.severity != "Warning")
+        .map(|error| error.long_message.as_str())
+        .collect();
+    Err(format!("Failed to {}: {}", action, messages.join("; ")))
+}
+
+// Strings and lists of them, which always serialize
+fn to_json<T: Serialize>(value: &T) -> String {
+    serde_json::to_string(value).unwrap_or_default()
+}
+
+fn json_column<T: serde::de::DeserializeOwned + Default>(row: &Row, index: usize) -> rusqlite::Result<T> {
+    let json: String = row.get(index)?;
+    Ok(serde_json::from_str(&json).unwrap_or_default())
+}
+
+fn listing_from_row(row: &Row) -> rusqlite::Result<ImportedListing> {
+    Ok(ImportedListing {
+        item_id: row.get(0)?,
+        status: row.get(1)?,
+        sku: row.get(2)?,
+        title: row.get(3)?,
+        description: row.get(4)?,
+        price: row.get(5)?,
+        currency: row.get(6)?,
+        category_id: row.get(7)?,
+        category_name: row.get(8)?,
+        condition: row.get(9)?,
+        quantity: row.get(10)?,
+        quantity_sold: row.get(11)?,
+        item_specifics: json_column(row, 12)?,
+        photo_urls: json_column(row, 13)?,
+        photos: json_column(row, 14)?,
+        url: row.get(15)?,
+        listed_at: row.get(16)?,
+        sold_at: row.get(17)?,
+        imported_at: row.get(18)?,
+    })
+}
+
+const LISTING_COLUMNS: &str = "item_id, status, sku, title, description, price, currency, category_id, category_name,
+    condition, quantity, quantity_sold, item_specifics, photo_urls, photos, url, listed_at, sold_at, imported_at";
+
+// Listings imported from eBay, stored in SQLite
+pub struct EbayImports {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl EbayImports {
+    pub fn new(db: &Database) -> EbayImports {
+        EbayImports { conn: db.connection() }
+    }
+
+    fn put(&self, listing: &ImportedListing) -> Result<(), String> {
+        let conn = self.conn.lock().map_err(|_| "Import store lock poisoned".to_string())?;
+        conn.execute(
+            &format!(
+                "INSERT OR REPLACE INTO ebay_listings ({})
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)",
+                LISTING_COLUMNS
+            ),
+            params![
+                listing.item_id,
+                listing.status,
+                listing.sku,
+                listing.title,
+                listing.description,
+                listing.price,
+                listing.currency,
+                listing.category_id,
+                listing.category_name,
+                listing.condition,
+                listing.quantity,
+                listing.quantity_sold,
+                to_json(&listing.item_specifics),
+                to_json(&listing.photo_urls),
+                to_json(&listing.photos),
+                listing.url,
+                listing.listed_at,
+                listing.sold_at,
+                listing.imported_at,
+            ],
+        )
+        .map_err(|e| format!("Failed to save imported listing {}: {}", listing.item_id, e))?;
+        Ok(())
+    }
+
+    fn list(&self, status: Option<&str>) -> Result<Vec<ImportedListing>, String> {
+        let conn = self.conn.lock().map_err(|_| "Import store lock poisoned".to_string())?;
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {} FROM ebay_listings WHERE ?1 IS NULL OR status = ?1
+                 ORDER BY COALESCE(sold_at, listed_at) DESC",
+                LISTING_COLUMNS
+            ))
+            .map_err(|e| format!("Failed to read imported listings: {}", e))?;
+        let rows = stmt
+            .query_map(params![status], listing_from_row)
+            .map_err(|e| format!("Failed to read imported listings: {}", e))?;
+        rows.collect::<Result<_, _>>()
+            .map_err(|e| format!("Failed to read imported listings: {}", e))
+    }
+}
+
+fn trading_url(environment: EbayEnvironment) -> String {
+    format!("{}/ws/api.dll", environment.api_url())
+}
+
+// Send a Trading API call with the user's OAuth token and return the body
+async fn call(app: &AppHandle, environment: EbayEnvironment, token: &str, call_name: &str, body: String) -> Result<String, String> {
+    let response = network::client(app)
+        .post(trading_url(environment))
+        .header("X-EBAY-API-CALL-NAME", call_name)
+        .header("X-EBAY-API-SITEID", "0")
+        .header("X-EBAY-API-COMPATIBILITY-LEVEL", COMPATIBILITY_LEVEL)
+        .header("X-EBAY-API-IAF-TOKEN", token)
+        .header(reqwest::header::CONTENT_TYPE, "text/xml")
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to call eBay {}: {}", call_name, e))?;
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    if !status.is_success() {
+        return Err(format!("Failed to call eBay {} ({}): {}", call_name, status, body.trim()));
+    }
+    Ok(body)
+}
+
+// One page of My eBay's active or sold list
+async fn selling_page(
+    app: &AppHandle,
+    environment: EbayEnvironment,
+    token: &str,
+    list: &str,
+    extra: &str,
+    page: u32,
+) -> Result<MyEbaySellingResponse, String> {
+    let body = format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<GetMyeBaySellingRequest xmlns="urn:ebay:apis:eBLBaseComponents">
+  <{list}>
+    <Include>true</Include>{extra}
+    <Pagination><EntriesPerPage>{size}</EntriesPerPage><PageNumber>{page}</PageNumber></Pagination>
+  </{list}>
+</GetMyeBaySellingRequest>"#,
+        list = list,
+        extra = extra,
+        size = PAGE_SIZE,
+        page = page,
+    );
+    let xml = call(app, environment, token, "GetMyeBaySelling", body).await?;
+    let response: MyEbaySellingResponse = quick_xml::de::from_str(&xml)
+        .map_err(|e| format!("Failed to parse eBay listings: {}", e))?;
+    check_ack(&response.ack, &response.errors, "list eBay listings")?;
+    Ok(response)
+}
+
+// Item IDs of the active listings
+async fn active_item_ids(app: &AppHandle, environment: EbayEnvironment, token: &str) -> Result<Vec<String>, String> {
+    let mut ids = Vec::new();
+    let mut page = 1;
+    loop {
+        let list = selling_page(app, environment, token, "ActiveList", "", page).await?.active_list.unwrap_or_default();
+        ids.extend(list.item_array.items.into_iter().map(|item| item.item_id));
+        if page >= list.pagination.total_pages {
+            return Ok(ids);
+        }
+        page += 1;
+    }
+}
+
+// Item IDs sold in the last `days`, with when each last sold
+async fn sold_item_ids(
+    app: &AppHandle,
+    environment: EbayEnvironment,
+    token: &str,
+    days: u32,
+) -> Result<BTreeMap<String, Option<String>>, String> {
+    let extra = format!("\n    <DurationInDays>{}</DurationInDays>", days);
+    let mut sold: BTreeMap<String, Option<String>> = BTreeMap::new();
+    let mut page = 1;
+    loop {
+        let list = selling_page(app, environment, token, "SoldList", &extra, page).await?.sold_list.unwrap_or_default();
+        for order_transaction in list.order_transaction_array.order_transactions {
+            let transactions = order_transaction
+                .transaction
+                .into_iter()
+                .chain(order_transaction.order.into_iter().flat_map(|order| order.transaction_array.transactions));
+            for transaction in transactions {
+                let sold_at = sold.entry(transaction.item.item_id).or_default();
+                // RFC 3339 times in UTC compare as strings
+                if transaction.created_date > *sold_at {
+                    *sold_at = transaction.created_date;
+                }
+            }
+        }
+        if page >= list.pagination.total_pages {
+            return Ok(sold);
+        }
+        page += 1;
+    }
+}
+
+async fn get_item(app: &AppHandle, environment: EbayEnvironment, token: &str, item_id: &str) -> Result<Item, String> {
+    let body = format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<GetItemRequest xmlns="urn:ebay:apis:eBLBaseComponents">
+  <ItemID>{}</ItemID>
+  <DetailLevel>ReturnAll</DetailLevel>
+  <IncludeItemSpecifics>true</IncludeItemSpecifics>
+</GetItemRequest>"#,
+        item_id
+    );
+    let xml = call(app, environment, token, "GetItem", body).await?;
+    let response: GetItemResponse = quick_xml::de::from_str(&xml)
+        .map_err(|e| format!("Failed to parse eBay listing {}: {}", item_id, e))?;
+    check_ack(&response.ack, &response.errors, "get eBay listing")?;
+    response.item.ok_or_else(|| format!("eBay returned no listing for {}", item_id))
+}
+
+fn photo_dir(app: &AppHandle, item_id: &str) -> Result<PathBuf, String> {
+    let data_dir = app.path_resolver()
+        .app_data_dir()
+        .ok_or("Failed to resolve app data directory")?;
+    Ok(data_dir.join("ebay_imports").join(item_id))
+}
+
+// Local copies of a listing's photos, downloading those not already there;
+// returns the paths and how many were downloaded
+async fn download_photos(app: &AppHandle, item_id: &str, urls: &[String]) -> Result<(Vec<String>, usize), String> {
+    let dir = photo_dir(app, item_id)?;
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create photo directory: {}", e))?;
+    let client = network::client(app);
+    let mut paths = Vec::new();
+    let mut downloaded = 0;
+    for (i, url) in urls.iter().enumerate() {
+        let path = dir.join(format!("{}.jpg", i + 1));
+        if !path.exists() {
+            let response = client
+                .get(url)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(|e| format!("Failed to download photo {}: {}", url, e))?;
+            let bytes = response
+                .bytes()
+                .await
+                .map_err(|e| format!("Failed to download photo {}: {}", url, e))?;
+            fs::write(&path, &bytes)
+                .map_err(|e| format!("Failed to save photo: {}", e))?;
+            downloaded += 1;
+        }
+        paths.push(image_io::path_to_string(&path)?);
+    }
+    Ok((paths, downloaded))
+}
+
+// Fetch one listing in full, download its photos and store it
+async fn import_item(
+    app: &AppHandle,
+    environment: EbayEnvironment,
+    token: &str,
+    item_id: &str,
+    sold_at: Option<Option<String>>,
+    active: bool,
+    report: &mut ImportReport,
+) -> Result<ImportedListing, String> {
+    let item = get_item(app, environment, token, item_id).await?;
+    let (photos, downloaded) = download_photos(app, item_id, &item.picture_details.urls).await?;
+    report.photos_downloaded += downloaded;
+
+    let mut item_specifics = BTreeMap::new();
+    for list in item.item_specifics.lists {
+        item_specifics.insert(list.name, list.values);
+    }
+    let listing = ImportedListing {
+        item_id: item.item_id,
+        // A multi-quantity listing that has sold some is still active
+        status: if active { "active" } else { "sold" }.to_string(),
+        sku: item.sku.filter(|sku| !sku.trim().is_empty()),
+        title: item.title,
+        description: item.description,
+        price: item.selling_status.current_price.value,
+        currency: item.selling_status.current_price.currency,
+        category_id: item.category.id,
+        category_name: item.category.name,
+        condition: item.condition,
+        quantity: item.quantity,
+        quantity_sold: item.selling_status.quantity_sold,
+        item_specifics,
+        photo_urls: item.picture_details.urls,
+        photos,
+        url: item.listing_details.view_item_url,
+        listed_at: item.listing_details.start_time,
+        sold_at: sold_at.flatten(),
+        imported_at: Utc::now().to_rfc3339(),
+    };
+    app.state::<EbayImports>().put(&listing)?;
+    Ok(listing)
+}
+
+// Add an imported listing to the history index from its first photo
+async fn index(app: &AppHandle, listing: &ImportedListing) -> Result<bool, String> {
+    let Some(photo) = listing.photos.first().cloned() else {
+        return Ok(false);
+    };
+    let app = app.clone();
+    let listing_id = format!("ebay:{}", listing.item_id);
+    let title = listing.title.clone();
+    let price = listing.price;
+    let description = listing.description.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let history = app.state::<ListingHistory>();
+        let models = app.state::<ModelStore>();
+        history::index_listing(&history, &models, &listing_id, &photo, &title, Some(price), &description)
+    })
+    .await
+    .map_err(|e| format!("Failed to index listing: {}", e))??;
+    Ok(true)
+}
+
+// Command to import the connected eBay account's active listings, and those
+// sold in the last `sold_days` (at most and by default 60), with their
+// titles, descriptions, prices, item specifics and photos. Listings are
+// stored locally (see `list_imported_listings`), photos are downloaded
+// unless already there, and each listing is added to the history index so
+// `match_against_history` finds it. Active listings with a SKU are also
+// registered for quantity sync. Importing again refreshes them. Progress is
+// emitted as `ebay-import://progress` events; a listing that fails is
+// reported and the rest carry on.
+#[tauri::command]
+pub async fn import_ebay_listings(
+    app: AppHandle,
+    include_sold: Option<bool>,
+    sold_days: Option<u32>,
+) -> Result<ImportReport, String> {
+    let sold_days = sold_days.unwrap_or(MAX_SOLD_DAYS);
+    if !(1..=MAX_SOLD_DAYS).contains(&sold_days) {
+        return Err(format!("sold_days must be between 1 and {}", MAX_SOLD_DAYS));
+    }
+    let environment = ebay_auth::load_settings(&app)?.environment;
+    let token = ebay_auth::access_token(&app).await?;
+
+    let active: BTreeSet<String> = active_item_ids(&app, environment, &token).await?.into_iter().collect();
+    let sold = if include_sold.unwrap_or(true) {
+        sold_item_ids(&app, environment, &token, sold_days).await?
+    } else {
+        BTreeMap::new()
+    };
+    let item_ids: BTreeSet<&String> = active.iter().chain(sold.keys()).collect();
+
+    let mut report = ImportReport::default();
+    let total = item_ids.len();
+    for (done, item_id) in item_ids.into_iter().enumerate() {
+        let is_active = active.contains(item_id);
+        match import_item(&app, environment, &token, item_id, sold.get(item_id).cloned(), is_active, &mut report).await {
+            Ok(listing) => {
+                if is_active {
+                    report.active += 1;
+                    if let Some(sku) = &listing.sku {
+                        let available = listing.quantity.saturating_sub(listing.quantity_sold);
+                        let _ = sync::record_listing(&app, Marketplace::Ebay, sku, Some(&listing.item_id), available);
+                    }
+                } else {
+                    report.sold += 1;
+                }
+                match index(&app, &listing).await {
+                    Ok(true) => report.indexed += 1,
+                    Ok(false) => {}
+                    Err(error) => report.index_failed.push(ImportFailure { item_id: item_id.clone(), error }),
+                }
+            }
+            Err(error) => report.failed.push(ImportFailure { item_id: item_id.clone(), error }),
+        }
+        let _ = app.emit_all(PROGRESS_EVENT, ImportProgress { item_id: item_id.clone(), done: done + 1, total });
+    }
+    Ok(report)
+}
+
+// Command to list listings imported from eBay, optionally only `active` or
+// `sold` ones, most recent first
+#[tauri::command]
+pub fn list_imported_listings(imports: State<EbayImports>, status: Option<String>) -> Result<Vec<ImportedListing>, String> {
+    imports.list(status.as_deref())
+}
@@ -0,0 +1,257 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::AppHandle;
+use crate::ebay::{self, EbayPolicies};
+use crate::ebay_auth::{self, EbayEnvironment};
+use crate::network;
+
+// Policies are fetched again after this long, or when asked to
+const CACHE_HOURS: i64 = 24;
+
+// A business policy set up in the seller's eBay account
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EbayPolicy {
+    pub policy_id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    // The account's default for the marketplace, used when a listing
+    // doesn't name one
+    #[serde(default)]
+    pub default: bool,
+    // Short description of the terms, e.g. "30 day returns, buyer pays
+    // return shipping"
+    #[serde(default)]
+    pub summary: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountPolicies {
+    pub marketplace_id: String,
+    pub payment: Vec<EbayPolicy>,
+    pub fulfillment: Vec<EbayPolicy>,
+    pub returns: Vec<EbayPolicy>,
+    // RFC 3339
+    pub fetched_at: String,
+}
+
+fn cache_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app.path_resolver()
+        .app_data_dir()
+        .ok_or("Failed to resolve app data directory")?;
+    Ok(data_dir.join("ebay_policies.json"))
+}
+
+fn cache_key(environment: EbayEnvironment, marketplace_id: &str) -> String {
+    let environment = match environment {
+        EbayEnvironment::Production => "production",
+        EbayEnvironment::Sandbox => "sandbox",
+    };
+    format!("{}/{}", environment, marketplace_id)
+}
+
+// Cached policies by environment and marketplace; a missing or unreadable
+// cache is empty, as it's only ever refetched
+fn load_cache(app: &AppHandle) -> BTreeMap<String, AccountPolicies> {
+    cache_path(app)
+        .ok()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save_cache(app: &AppHandle, cache: &BTreeMap<String, AccountPolicies>) -> Result<(), String> {
+    let path = cache_path(app)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create data directory: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(cache)
+        .map_err(|e| format!("Failed to serialize eBay policies: {}", e))?;
+    fs::write(&path, json)
+        .map_err(|e| format!("Failed to save eBay policies: {}", e))
+}
+
+// Forget cached policies, e.g. when another account is connected
+pub fn clear_cache(app: &AppHandle) {
+    if let Ok(path) = cache_path(app) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+// A TimeDuration's count and unit, e.g. (30, "day")
+fn period(value: &Value) -> Option<(u64, String)> {
+    let count = value["value"].as_u64()?;
+    Some((count, value["unit"].as_str().unwrap_or("DAY").to_lowercase()))
+}
+
+fn fulfillment_summary(policy: &Value) -> Option<String> {
+    let mut parts = Vec::new();
+    if let Some((count, unit)) = period(&policy["handlingTime"]) {
+        parts.push(format!("Ships within {} {}{}", count, unit, if count == 1 { "" } else { "s" }));
+    }
+    let domestic = policy["shippingOptions"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|option| option["optionType"].as_str() == Some("DOMESTIC"));
+    if let Some(service) = domestic.and_then(|option| option["shippingServices"][0].as_object()) {
+        let code = service.get("shippingServiceCode").and_then(Value::as_str).unwrap_or("shipping");
+        if service.get("freeShipping").and_then(Value::as_bool).unwrap_or(false) {
+            parts.push(format!("{}, free", code));
+        } else if let Some(cost) = service.get("shippingCost") {
+            parts.push(format!(
+                "{}, {} {}",
+                code,
+                cost["value"].as_str().unwrap_or("0"),
+                cost["currency"].as_str().unwrap_or_default()
+            ));
+        } else {
+            parts.push(code.to_string());
+        }
+    }
+    (!parts.is_empty()).then(|| parts.join("; "))
+}
+
+fn return_summary(policy: &Value) -> Option<String> {
+    if !policy["returnsAccepted"].as_bool().unwrap_or(false) {
+        return Some("No returns".to_string());
+    }
+    let payer = match policy["returnShippingCostPayer"].as_str() {
+        Some("SELLER") => "seller pays return shipping",
+        _ => "buyer pays return shipping",
+    };
+    Some(match period(&policy["returnPeriod"]) {
+        Some((count, unit)) => format!("{} {} returns, {}", count, unit, payer),
+        None => format!("Returns accepted, {}", payer),
+    })
+}
+
+fn payment_summary(policy: &Value) -> Option<String> {
+    policy["immediatePay"].as_bool().filter(|immediate| *immediate).map(|_| "Immediate payment required".to_string())
+}
+
+fn parse_policies(result: &Value, list: &str, id_field: &str, summary: fn(&Value) -> Option<String>) -> Vec<EbayPolicy> {
+    result[list]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|policy| {
+            Some(EbayPolicy {
+                policy_id: policy[id_field].as_str()?.to_string(),
+                name: policy["name"].as_str().unwrap_or_default().to_string(),
+                description: policy["description"].as_str().map(str::to_string),
+                default: policy["categoryTypes"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .any(|category_type| category_type["default"].as_bool().unwrap_or(false)),
+                summary: summary(policy),
+            })
+        })
+        .collect()
+}
+
+async fn fetch(app: &AppHandle, environment: EbayEnvironment, token: &str, marketplace_id: &str) -> Result<AccountPolicies, String> {
+    let api = format!("{}/sell/account/v1", environment.api_url());
+    let client = network::client(app);
+    let get = |path: &str| {
+        client
+            .get(format!("{}/{}", api, path))
+            .bearer_auth(token)
+            .query(&[("marketplace_id", marketplace_id)])
+    };
+    let payment = ebay::send(get("payment_policy"), "get eBay payment policies").await?;
+    let fulfillment = ebay::send(get("fulfillment_policy"), "get eBay shipping policies").await?;
+    let returns = ebay::send(get("return_policy"), "get eBay return policies").await?;
+    Ok(AccountPolicies {
+        marketplace_id: marketplace_id.to_string(),
+        payment: parse_policies(&payment, "paymentPolicies", "paymentPolicyId", payment_summary),
+        fulfillment: parse_policies(&fulfillment, "fulfillmentPolicies", "fulfillmentPolicyId", fulfillment_summary),
+        returns: parse_policies(&returns, "returnPolicies", "returnPolicyId", return_summary),
+        fetched_at: Utc::now().to_rfc3339(),
+    })
+}
+
+// The account's policies for a marketplace, from the cache unless it's
+// stale or `refresh` is set
+async fn account_policies(
+    app: &AppHandle,
+    environment: EbayEnvironment,
+    token: &str,
+    marketplace_id: &str,
+    refresh: bool,
+) -> Result<AccountPolicies, String> {
+    let key = cache_key(environment, marketplace_id);
+    let mut cache = load_cache(app);
+    let fresh = cache.get(&key).filter(|policies| {
+        DateTime::parse_from_rfc3339(&policies.fetched_at)
+            .is_ok_and(|fetched| Utc::now() - fetched.with_timezone(&Utc) < chrono::Duration::hours(CACHE_HOURS))
+    });
+    if let Some(policies) = fresh.filter(|_| !refresh) {
+        return Ok(policies.clone());
+    }
+    let policies = fetch(app, environment, token, marketplace_id).await?;
+    cache.insert(key, policies.clone());
+    save_cache(app, &cache)?;
+    Ok(policies)
+}
+
+// The given policy ID, or else the account's default (or only) policy
+fn choose(given: Option<&str>, policies: &[EbayPolicy], kind: &str) -> Result<String, String> {
+    if let Some(id) = given.map(str::trim).filter(|id| !id.is_empty()) {
+        return Ok(id.to_string());
+    }
+    let chosen = match policies {
+        [only] => Some(only),
+        _ => policies.iter().find(|policy| policy.default),
+    };
+    chosen
+        .map(|policy| policy.policy_id.clone())
+        .ok_or_else(|| format!("Choose a {} policy; the eBay account has no default one", kind))
+}
+
+// The offer's listingPolicies, filling in any policy the listing leaves out
+// with the account's default. Policies are only fetched when one is missing.
+pub async fn listing_policies(
+    app: &AppHandle,
+    environment: EbayEnvironment,
+    token: &str,
+    marketplace_id: &str,
+    given: &EbayPolicies,
+) -> Result<Value, String> {
+    let given_ids = [&given.fulfillment_policy_id, &given.payment_policy_id, &given.return_policy_id];
+    let account = if given_ids.iter().all(|id| id.as_deref().is_some_and(|id| !id.trim().is_empty())) {
+        None
+    } else {
+        Some(account_policies(app, environment, token, marketplace_id, false).await?)
+    };
+    let (fulfillment, payment, returns) = match &account {
+        Some(account) => (account.fulfillment.as_slice(), account.payment.as_slice(), account.returns.as_slice()),
+        None => (&[][..], &[][..], &[][..]),
+    };
+    Ok(json!({
+        "fulfillmentPolicyId": choose(given.fulfillment_policy_id.as_deref(), fulfillment, "shipping")?,
+        "paymentPolicyId": choose(given.payment_policy_id.as_deref(), payment, "payment")?,
+        "returnPolicyId": choose(given.return_policy_id.as_deref(), returns, "return")?,
+    }))
+}
+
+// Command to get the payment, shipping and return policies set up in the
+// connected eBay account, through the Account API. They're cached for a
+// day; pass `refresh` after changing them on eBay.
+#[tauri::command]
+pub async fn get_ebay_policies(
+    app: AppHandle,
+    marketplace_id: Option<String>,
+    refresh: Option<bool>,
+) -> Result<AccountPolicies, String> {
+    let environment = ebay_auth::load_settings(&app)?.environment;
+    let marketplace_id = marketplace_id.unwrap_or_else(|| ebay::DEFAULT_MARKETPLACE.to_string());
+    let token = ebay_auth::access_token(&app).await?;
+    account_policies(&app, environment, &token, &marketplace_id, refresh.unwrap_or(false)).await
+}
@@ -0,0 +1,65 @@
+use image::{DynamicImage, imageops::FilterType};
+use tract_onnx::prelude::*;
+use crate::models::ModelStore;
+
+// CLIP ViT-B/32 image encoder exported to ONNX
+pub const CLIP_MODEL: &str = "clip-vit-b32-image.onnx";
+const CLIP_SIZE: usize = 224;
+
+// Normalization CLIP was trained with
+const MEAN: [f32; 3] = [0.481_454_66, 0.457_827_5, 0.408_210_73];
+const STD: [f32; 3] = [0.268_629_54, 0.261_302_6, 0.275_777_1];
+
+// Encode a photo as a unit-length CLIP embedding. Embeddings capture what the
+// item is rather than how the pixels are laid out, so they cope with different
+// backgrounds and angles far better than perceptual hashes.
+pub fn image_embedding(models: &ModelStore, img: &DynamicImage) -> Result<Vec<f32>, String> {
+    let model = models.get(CLIP_MODEL, [1, 3, CLIP_SIZE, CLIP_SIZE])?;
+
+    // Resize the short side and center crop, as CLIP's own preprocessing does
+    let resized = img
+        .resize_to_fill(CLIP_SIZE as u32, CLIP_SIZE as u32, FilterType::CatmullRom)
+        .to_rgb8();
+    let input: Tensor = tract_ndarray::Array4::from_shape_fn(
+        (1, 3, CLIP_SIZE, CLIP_SIZE),
+        |(_, c, y, x)| {
+            let value = resized.get_pixel(x as u32, y as u32)[c] as f32 / 255.0;
+            (value - MEAN[c]) / STD[c]
+        },
+    )
+    .into();
+
+    let outputs = model.run(tvec!(input.into()))
+        .map_err(|e| format!("Image embedding failed: {}", e))?;
+    let embedding: Vec<f32> = outputs[0]
+        .to_array_view::<f32>()
+        .map_err(|e| format!("Unexpected model output: {}", e))?
+        .iter()
+        .copied()
+        .collect();
+
+    let norm = embedding.iter().map(|v| v * v).sum::<f32>().sqrt().max(f32::EPSILON);
+    Ok(embedding.into_iter().map(|v| v / norm).collect())
+}
+
+// Cosine similarity of two unit embeddings, clamped to 0.0-1.0 so it can be
+// used with the same thresholds as hash similarity
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f64 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    (dot as f64).clamp(0.0, 1.0)
+}
+
+// Pack an embedding for storage in the cache (little-endian f32s)
+pub fn to_bytes(embedding: &[f32]) -> Vec<u8> {
+    embedding.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+pub fn from_bytes(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+        .collect()
+}
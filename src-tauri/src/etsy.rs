@@ -0,0 +1,716 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use base64::{Engine as _, engine::general_purpose};
+use chrono::Utc;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use rsa::sha2::{Digest, Sha256};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager, State};
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
+use crate::crosslist::Marketplace;
+use crate::listing::{Listing, MappedListing};
+use crate::{image_io, keychain, network, sync};
+
+const CONNECT_URL: &str = "https://www.etsy.com/oauth/connect";
+const TOKEN_URL: &str = "https://api.etsy.com/v3/public/oauth/token";
+const API_URL: &str = "https://api.etsy.com/v3/application";
+
+// Reading the shop, creating and ending listings, and reading orders for
+// sync; shops connected before a scope was added need connecting again
+const SCOPES: [&str; 4] = ["shops_r", "listings_r", "listings_w", "transactions_r"];
+
+const SECRET_ACCOUNT: &str = "etsy-shared-secret";
+const TOKEN_ACCOUNT: &str = "etsy-refresh";
+
+// How long the user has to finish signing in on Etsy
+const CONSENT_TIMEOUT: Duration = Duration::from_secs(300);
+
+// Tokens are refreshed this long before they expire
+const EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+// Etsy refresh tokens last 90 days and are replaced on every refresh
+const REFRESH_TOKEN_DAYS: i64 = 90;
+
+const DEFAULT_REDIRECT_PORT: u16 = 8977;
+
+const REDIRECT_PAGE: &str = "<!DOCTYPE html><html><body style=\"font-family: sans-serif\">\
+<p>Your Etsy shop is connected. You can close this window.</p></body></html>";
+
+// Etsy's listing limits; it recommends photos 2000px wide
+const MAX_ETSY_PHOTOS: usize = 10;
+const MAX_TITLE_CHARS: usize = 140;
+const MAX_TAGS: usize = 13;
+const MAX_TAG_CHARS: usize = 20;
+const ETSY_IMAGE_EDGE: u32 = 2000;
+const ETSY_JPEG_QUALITY: u8 = 90;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EtsySettings {
+    // Keystring from the Etsy developer portal
+    pub client_id: String,
+    // Must match a callback URL registered for the app
+    #[serde(default = "default_redirect_uri")]
+    pub redirect_uri: String,
+    // Port of the local listener catching the redirect; `None` when the
+    // redirect reaches the app another way, via `etsy_handle_redirect`
+    #[serde(default = "default_redirect_port")]
+    pub redirect_port: Option<u16>,
+}
+
+fn default_redirect_uri() -> String {
+    format!("http://localhost:{}/", DEFAULT_REDIRECT_PORT)
+}
+
+fn default_redirect_port() -> Option<u16> {
+    Some(DEFAULT_REDIRECT_PORT)
+}
+
+#[derive(Debug, Serialize)]
+pub struct EtsySettingsInfo {
+    #[serde(flatten)]
+    pub settings: EtsySettings,
+    pub has_shared_secret: bool,
+    pub connected: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WhoMade {
+    // The seller
+    IDid,
+    // A member of the seller's shop
+    Collective,
+    // E.g. vintage or supplies
+    SomeoneElse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EtsyListing {
+    // Local photos are uploaded after the draft is created
+    #[serde(flatten)]
+    pub item: Listing,
+    // Seller taxonomy node, e.g. 1429 for women's dresses
+    pub taxonomy_id: u64,
+    pub who_made: WhoMade,
+    // Etsy's period, e.g. "made_to_order", "2020_2025" or "1990s"; vintage
+    // items must be at least 20 years old
+    pub when_made: String,
+    #[serde(default)]
+    pub is_supply: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    // Profiles set up in the shop; Etsy needs shipping and processing ones
+    // before a physical listing can go live
+    #[serde(default)]
+    pub shipping_profile_id: Option<u64>,
+    #[serde(default)]
+    pub return_policy_id: Option<u64>,
+    #[serde(default)]
+    pub readiness_state_id: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EtsyShop {
+    pub shop_id: u64,
+    pub name: String,
+    // Listings are priced in this currency
+    pub currency: String,
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EtsyDraft {
+    pub listing_id: u64,
+    // Listing editor, where the draft can be checked and published
+    pub edit_url: String,
+    pub photos_uploaded: usize,
+    // Photos that failed to upload; the draft is kept either way
+    pub photo_errors: Vec<String>,
+}
+
+// Refresh token as kept in the keychain
+#[derive(Debug, Serialize, Deserialize)]
+struct StoredToken {
+    refresh_token: String,
+    expires_at: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64,
+    refresh_token: String,
+}
+
+struct CachedToken {
+    token: String,
+    expires_at: Instant,
+}
+
+struct PendingConsent {
+    state: String,
+    code: oneshot::Sender<String>,
+}
+
+// Sign-in in progress, the current access token and the connected shop
+#[derive(Default)]
+pub struct EtsyAuth {
+    pending: Mutex<Option<PendingConsent>>,
+    user: Mutex<Option<CachedToken>>,
+    shop: Mutex<Option<EtsyShop>>,
+}
+
+impl EtsyAuth {
+    fn begin(&self, state: String) -> Result<oneshot::Receiver<String>, String> {
+        let (sender, receiver) = oneshot::channel();
+        let mut pending = self.pending.lock().map_err(|_| "Etsy auth lock poisoned".to_string())?;
+        *pending = Some(PendingConsent { state, code: sender });
+        Ok(receiver)
+    }
+
+    // Hand the code from a redirect URL's query to the waiting sign-in
+    fn deliver(&self, query: &str) -> Result<(), String> {
+        let params = network::query_params(query);
+        let param = |name: &str| params.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str());
+
+        let mut pending = self.pending.lock().map_err(|_| "Etsy auth lock poisoned".to_string())?;
+        let Some(consent) = pending.take_if(|consent| param("state") == Some(consent.state.as_str())) else {
+            return Err("No Etsy sign-in is waiting for this redirect".to_string());
+        };
+        match param("code") {
+            Some(code) => {
+                let _ = consent.code.send(code.to_string());
+                Ok(())
+            }
+            None => Err("Etsy sign-in was declined".to_string()),
+        }
+    }
+
+    fn clear(&self) {
+        if let Ok(mut user) = self.user.lock() {
+            *user = None;
+        }
+        if let Ok(mut shop) = self.shop.lock() {
+            *shop = None;
+        }
+    }
+}
+
+fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app.path_resolver()
+        .app_data_dir()
+        .ok_or("Failed to resolve app data directory")?;
+    Ok(data_dir.join("etsy.json"))
+}
+
+fn load_settings(app: &AppHandle) -> Result<EtsySettings, String> {
+    let path = settings_path(app)?;
+    if !path.exists() {
+        return Err("Etsy isn't set up; add the app's keystring in settings".to_string());
+    }
+    let json = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read Etsy settings: {}", e))?;
+    serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse Etsy settings: {}", e))
+}
+
+// Etsy wants the keystring and shared secret on every request
+fn api_key(settings: &EtsySettings) -> Result<String, String> {
+    let secret = keychain::get_secret(SECRET_ACCOUNT)?
+        .ok_or("No Etsy shared secret is stored")?;
+    Ok(format!("{}:{}", settings.client_id.trim(), secret))
+}
+
+// Code verifier and its S256 challenge
+fn pkce_pair() -> (String, String) {
+    let verifier: String = rand::thread_rng().sample_iter(&Alphanumeric).take(64).map(char::from).collect();
+    let challenge = general_purpose::URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()));
+    (verifier, challenge)
+}
+
+// Message from an Etsy API error body, or the body itself
+fn error_message(body: &str) -> String {
+    serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|error| {
+            error["error_description"].as_str().or(error["error"].as_str()).map(str::to_string)
+        })
+        .unwrap_or_else(|| body.trim().to_string())
+}
+
+// Send a request and parse its JSON response
+async fn send(request: reqwest::RequestBuilder, action: &str) -> Result<Value, String> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Failed to {}: {}", action, e))?;
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    if !status.is_success() {
+        return Err(format!("Failed to {} ({}): {}", action, status, error_message(&body)));
+    }
+    serde_json::from_str(&body).map_err(|e| format!("Failed to parse Etsy response: {}", e))
+}
+
+async fn request_token(app: &AppHandle, form: &[(&str, &str)]) -> Result<TokenResponse, String> {
+    let request = network::client(app).post(TOKEN_URL).form(form);
+    let token = send(request, "get Etsy token").await?;
+    serde_json::from_value(token).map_err(|e| format!("Failed to parse Etsy token: {}", e))
+}
+
+fn save_token(auth: &EtsyAuth, token: &TokenResponse) -> Result<(), String> {
+    let stored = StoredToken {
+        refresh_token: token.refresh_token.clone(),
+        expires_at: Some((Utc::now() + chrono::Duration::days(REFRESH_TOKEN_DAYS)).to_rfc3339()),
+    };
+    let json = serde_json::to_string(&stored)
+        .map_err(|e| format!("Failed to serialize Etsy token: {}", e))?;
+    keychain::set_secret(TOKEN_ACCOUNT, &json)?;
+    if let Ok(mut user) = auth.user.lock() {
+        *user = Some(CachedToken {
+            token: token.access_token.clone(),
+            expires_at: Instant::now() + Duration::from_secs(token.expires_in).saturating_sub(EXPIRY_MARGIN),
+        });
+    }
+    Ok(())
+}
+
+// An access token for the connected shop, refreshed when the cached one has
+// expired. Etsy access tokens only last an hour.
+async fn access_token(app: &AppHandle, settings: &EtsySettings, auth: &EtsyAuth) -> Result<String, String> {
+    let cached = auth.user.lock().ok().and_then(|user| {
+        user.as_ref().filter(|user| Instant::now() < user.expires_at).map(|user| user.token.clone())
+    });
+    if let Some(token) = cached {
+        return Ok(token);
+    }
+
+    let json = keychain::get_secret(TOKEN_ACCOUNT)?
+        .ok_or("No Etsy shop is connected")?;
+    let stored: StoredToken = serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse stored Etsy token: {}", e))?;
+    let token = request_token(app, &[
+        ("grant_type", "refresh_token"),
+        ("client_id", settings.client_id.trim()),
+        ("refresh_token", &stored.refresh_token),
+    ])
+    .await
+    .map_err(|e| format!("{}; connect the Etsy shop again if this persists", e))?;
+    save_token(auth, &token)?;
+    Ok(token.access_token)
+}
+
+// The connected user's shop, from the cache when possible
+async fn shop(app: &AppHandle, settings: &EtsySettings, auth: &EtsyAuth) -> Result<EtsyShop, String> {
+    if let Some(shop) = auth.shop.lock().ok().and_then(|shop| shop.clone()) {
+        return Ok(shop);
+    }
+    let token = access_token(app, settings, auth).await?;
+    let key = api_key(settings)?;
+    let client = network::client(app);
+
+    let me = send(
+        client.get(format!("{}/users/me", API_URL)).bearer_auth(&token).header("x-api-key", &key),
+        "look up Etsy user",
+    )
+    .await?;
+    let shop_id = me["shop_id"].as_u64().ok_or("This Etsy account has no shop")?;
+    let result = send(
+        client.get(format!("{}/shops/{}", API_URL, shop_id)).bearer_auth(&token).header("x-api-key", &key),
+        "look up Etsy shop",
+    )
+    .await?;
+    let shop = EtsyShop {
+        shop_id,
+        name: result["shop_name"].as_str().unwrap_or_default().to_string(),
+        currency: result["currency_code"].as_str().unwrap_or_default().to_string(),
+        url: result["url"].as_str().map(str::to_string),
+    };
+    if let Ok(mut cached) = auth.shop.lock() {
+        *cached = Some(shop.clone());
+    }
+    Ok(shop)
+}
+
+// How Etsy will see the listing, with what stops the draft being created.
+// Etsy has no condition field; the shop's currency is checked when the
+// draft is created.
+pub fn map_listing(listing: &EtsyListing) -> MappedListing {
+    let mut problems = listing.item.problems("Etsy", MAX_ETSY_PHOTOS);
+    if listing.item.title.chars().count() > MAX_TITLE_CHARS {
+        problems.push(format!("Etsy titles are limited to {} characters", MAX_TITLE_CHARS));
+    }
+    if listing.tags.len() > MAX_TAGS {
+        problems.push(format!("Etsy allows at most {} tags", MAX_TAGS));
+    }
+    for tag in listing.tags.iter().filter(|tag| tag.trim().chars().count() > MAX_TAG_CHARS) {
+        problems.push(format!("\"{}\" is longer than Etsy's {} character tag limit", tag, MAX_TAG_CHARS));
+    }
+    if listing.when_made.trim().is_empty() {
+        problems.push("When the item was made is required".to_string());
+    }
+    MappedListing {
+        category: vec![listing.taxonomy_id.to_string()],
+        condition: None,
+        size: listing.item.size.clone(),
+        max_photos: MAX_ETSY_PHOTOS,
+        problems,
+    }
+}
+
+// Upload a photo to a listing at a position, 1 being the primary photo
+async fn upload_photo(
+    app: &AppHandle,
+    token: &str,
+    key: &str,
+    shop_id: u64,
+    listing_id: u64,
+    path: &str,
+    rank: usize,
+) -> Result<(), String> {
+    let jpeg = if path.starts_with("https://") {
+        let response = network::client(app)
+            .get(path)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| format!("Failed to download {}: {}", path, e))?;
+        response.bytes().await.map_err(|e| format!("Failed to download {}: {}", path, e))?.to_vec()
+    } else {
+        let image_path = path.to_string();
+        tauri::async_runtime::spawn_blocking(move || {
+            let img = image_io::open_image_scaled(&image_path, ETSY_IMAGE_EDGE)?;
+            image_io::encode_jpeg(&img, ETSY_JPEG_QUALITY)
+        })
+        .await
+        .map_err(|e| format!("Failed to read image: {}", e))??
+    };
+
+    let rank = rank.to_string();
+    let (body, content_type) = network::multipart(&[
+        ("image", Some("image.jpg"), "image/jpeg", &jpeg),
+        ("rank", None, "text/plain", rank.as_bytes()),
+    ]);
+    let request = network::client(app)
+        .post(format!("{}/shops/{}/listings/{}/images", API_URL, shop_id, listing_id))
+        .bearer_auth(token)
+        .header("x-api-key", key)
+        .header(reqwest::header::CONTENT_TYPE, content_type)
+        .body(body);
+    send(request, "upload photo to Etsy").await.map(|_| ())
+}
+
+// Command to configure the Etsy app credentials
+// `shared_secret` is stored in the OS keychain: pass a secret to set it, an
+// empty string to remove it, or leave it out to keep the current one.
+#[tauri::command]
+pub fn set_etsy_settings(
+    app: AppHandle,
+    auth: State<EtsyAuth>,
+    settings: EtsySettings,
+    shared_secret: Option<String>,
+) -> Result<(), String> {
+    if settings.client_id.trim().is_empty() {
+        return Err("Keystring is required".to_string());
+    }
+    if settings.redirect_uri.trim().is_empty() {
+        return Err("Callback URL is required".to_string());
+    }
+
+    let path = settings_path(&app)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create settings directory: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(&settings)
+        .map_err(|e| format!("Failed to serialize Etsy settings: {}", e))?;
+    fs::write(&path, json)
+        .map_err(|e| format!("Failed to save Etsy settings: {}", e))?;
+    auth.clear();
+
+    match shared_secret.as_deref().map(str::trim) {
+        Some("") => keychain::delete_secret(SECRET_ACCOUNT),
+        Some(secret) => keychain::set_secret(SECRET_ACCOUNT, secret),
+        None => Ok(()),
+    }
+}
+
+// Command to get the Etsy settings, whether a shared secret is stored and
+// whether a shop is connected
+#[tauri::command]
+pub fn get_etsy_settings(app: AppHandle) -> Result<Option<EtsySettingsInfo>, String> {
+    if !settings_path(&app)?.exists() {
+        return Ok(None);
+    }
+    let settings = load_settings(&app)?;
+    let has_shared_secret = keychain::get_secret(SECRET_ACCOUNT)?.is_some();
+    let connected = keychain::get_secret(TOKEN_ACCOUNT)?.is_some();
+    Ok(Some(EtsySettingsInfo { settings, has_shared_secret, connected }))
+}
+
+// Command to connect the seller's Etsy shop: opens Etsy's consent page in
+// the browser, waits for the redirect (on the localhost listener, or passed
+// in with `etsy_handle_redirect`), exchanges the code with PKCE, stores the
+// refresh token in the OS keychain and returns the shop
+#[tauri::command]
+pub async fn etsy_connect_account(app: AppHandle, auth: State<'_, EtsyAuth>) -> Result<EtsyShop, String> {
+    let settings = load_settings(&app)?;
+    // Fails early, before the user signs in, when the secret is missing
+    api_key(&settings)?;
+
+    let state: String = rand::thread_rng().sample_iter(&Alphanumeric).take(32).map(char::from).collect();
+    let (verifier, challenge) = pkce_pair();
+    let consent_url = format!(
+        "{}?response_type=code&client_id={}&redirect_uri={}&scope={}&state={}&code_challenge={}&code_challenge_method=S256",
+        CONNECT_URL,
+        urlencoding::encode(settings.client_id.trim()),
+        urlencoding::encode(settings.redirect_uri.trim()),
+        urlencoding::encode(&SCOPES.join(" ")),
+        state,
+        challenge
+    );
+
+    let listener = match settings.redirect_port {
+        Some(port) => {
+            let listener = TcpListener::bind(("127.0.0.1", port))
+                .await
+                .map_err(|e| format!("Failed to listen on port {} for the Etsy redirect: {}", port, e))?;
+            let handle = app.clone();
+            Some(tauri::async_runtime::spawn(network::serve_redirect(listener, REDIRECT_PAGE, move |query| {
+                handle.state::<EtsyAuth>().deliver(query).is_ok()
+            })))
+        }
+        None => None,
+    };
+    let code = auth.begin(state)?;
+
+    let result = async {
+        tauri::api::shell::open(&app.shell_scope(), &consent_url, None)
+            .map_err(|e| format!("Failed to open the browser: {}", e))?;
+        match tokio::time::timeout(CONSENT_TIMEOUT, code).await {
+            Ok(Ok(code)) => Ok(code),
+            Ok(Err(_)) => Err("Etsy sign-in was declined".to_string()),
+            Err(_) => Err("Timed out waiting for Etsy sign-in".to_string()),
+        }
+    }
+    .await;
+    if let Some(listener) = listener {
+        listener.abort();
+    }
+    if let Ok(mut pending) = auth.pending.lock() {
+        *pending = None;
+    }
+    let code = result?;
+
+    let token = request_token(&app, &[
+        ("grant_type", "authorization_code"),
+        ("client_id", settings.client_id.trim()),
+        ("redirect_uri", settings.redirect_uri.trim()),
+        ("code", &code),
+        ("code_verifier", &verifier),
+    ])
+    .await?;
+    auth.clear();
+    save_token(&auth, &token)?;
+    shop(&app, &settings, &auth).await
+}
+
+// Command to finish a sign-in from the redirect URL when it reaches the app
+// some other way than the localhost listener, e.g. a deep link
+#[tauri::command]
+pub fn etsy_handle_redirect(auth: State<EtsyAuth>, url: String) -> Result<(), String> {
+    let query = url.split_once('?').map(|(_, query)| query).unwrap_or(&url);
+    let query = query.split('#').next().unwrap_or(query);
+    auth.deliver(query)
+}
+
+// Command to forget the connected shop's tokens
+#[tauri::command]
+pub fn etsy_disconnect_account(auth: State<EtsyAuth>) -> Result<(), String> {
+    auth.clear();
+    keychain::delete_secret(TOKEN_ACCOUNT)
+}
+
+// Command to get the connected Etsy shop
+#[tauri::command]
+pub async fn get_etsy_shop(app: AppHandle, auth: State<'_, EtsyAuth>) -> Result<EtsyShop, String> {
+    let settings = load_settings(&app)?;
+    shop(&app, &settings, &auth).await
+}
+
+// Command to create a draft listing in the connected Etsy shop from the same
+// listing data used for eBay, then add its photos in order. Drafts aren't
+// visible to buyers until published from Etsy's listing editor. Etsy has no
+// condition field, so condition notes are added to the description.
+#[tauri::command]
+pub async fn create_etsy_draft_listing(
+    app: AppHandle,
+    auth: State<'_, EtsyAuth>,
+    listing: EtsyListing,
+) -> Result<EtsyDraft, String> {
+    create_draft(&app, &auth, &listing).await
+}
+
+pub async fn create_draft(app: &AppHandle, auth: &EtsyAuth, listing: &EtsyListing) -> Result<EtsyDraft, String> {
+    let settings = load_settings(app)?;
+    let shop = shop(app, &settings, auth).await?;
+    map_listing(listing).check()?;
+    if !shop.currency.is_empty() && !listing.item.currency.trim().eq_ignore_ascii_case(&shop.currency) {
+        return Err(format!("Etsy lists in the shop's currency, {}", shop.currency));
+    }
+    let token = access_token(app, &settings, auth).await?;
+    let key = api_key(&settings)?;
+
+    let tags: Vec<&str> = listing.tags.iter().map(|tag| tag.trim()).filter(|tag| !tag.is_empty()).collect();
+    let mut draft = json!({
+        "quantity": listing.item.quantity,
+        "title": listing.item.title.trim(),
+        "description": listing.item.description_with_condition(),
+        "price": listing.item.price,
+        "who_made": listing.who_made,
+        "when_made": listing.when_made.trim(),
+        "taxonomy_id": listing.taxonomy_id,
+        "is_supply": listing.is_supply,
+        "type": "physical",
+        "tags": tags,
+        "materials": listing.item.materials,
+    });
+    for (field, value) in [
+        ("shipping_profile_id", listing.shipping_profile_id),
+        ("return_policy_id", listing.return_policy_id),
+        ("readiness_state_id", listing.readiness_state_id),
+    ] {
+        if let Some(value) = value {
+            draft[field] = json!(value);
+        }
+    }
+    let request = network::client(app)
+        .post(format!("{}/shops/{}/listings", API_URL, shop.shop_id))
+        .bearer_auth(&token)
+        .header("x-api-key", &key)
+        .json(&draft);
+    let created = send(request, "create Etsy draft listing").await?;
+    let listing_id = created["listing_id"].as_u64().ok_or("Etsy returned no listing ID")?;
+
+    let mut photos_uploaded = 0;
+    let mut photo_errors = Vec::new();
+    for (i, photo) in listing.item.photos.iter().enumerate() {
+        match upload_photo(app, &token, &key, shop.shop_id, listing_id, photo, i + 1).await {
+            Ok(()) => photos_uploaded += 1,
+            Err(e) => photo_errors.push(e),
+        }
+    }
+    // Sync goes by SKU, so listings without one aren't tracked; a failure
+    // to record doesn't undo the draft
+    if let Some(sku) = listing.item.sku.as_deref().map(str::trim).filter(|sku| !sku.is_empty()) {
+        let _ = sync::record_listing(app, Marketplace::Etsy, sku, Some(&listing_id.to_string()), listing.item.quantity);
+    }
+    Ok(EtsyDraft {
+        listing_id,
+        edit_url: format!("https://www.etsy.com/your/shops/me/listing-editor/edit/{}", listing_id),
+        photos_uploaded,
+        photo_errors,
+    })
+}
+
+// Whether an Etsy shop is connected
+pub fn is_connected() -> bool {
+    keychain::get_secret(TOKEN_ACCOUNT).ok().flatten().is_some()
+}
+
+// A line item of a paid Etsy order, for quantity sync
+#[derive(Debug, Clone)]
+pub struct EtsySale {
+    pub receipt_id: u64,
+    pub listing_id: u64,
+    pub sku: Option<String>,
+    pub quantity: u32,
+}
+
+// Line items of paid, uncancelled orders created since a Unix time
+pub async fn sales_since(app: &AppHandle, auth: &EtsyAuth, since: i64) -> Result<Vec<EtsySale>, String> {
+    const PAGE_SIZE: usize = 100;
+    let settings = load_settings(app)?;
+    let shop = shop(app, &settings, auth).await?;
+    let token = access_token(app, &settings, auth).await?;
+    let key = api_key(&settings)?;
+    let client = network::client(app);
+
+    let mut sales = Vec::new();
+    let mut offset = 0;
+    loop {
+        let query = [
+            ("min_created", since.to_string()),
+            ("was_paid", "true".to_string()),
+            ("was_canceled", "false".to_string()),
+            ("limit", PAGE_SIZE.to_string()),
+            ("offset", offset.to_string()),
+        ];
+        let result = send(
+            client
+                .get(format!("{}/shops/{}/receipts", API_URL, shop.shop_id))
+                .bearer_auth(&token)
+                .header("x-api-key", &key)
+                .query(&query),
+            "get Etsy orders",
+        )
+        .await?;
+        let receipts = result["results"].as_array().cloned().unwrap_or_default();
+        for receipt in &receipts {
+            let Some(receipt_id) = receipt["receipt_id"].as_u64() else {
+                continue;
+            };
+            for transaction in receipt["transactions"].as_array().into_iter().flatten() {
+                if let Some(listing_id) = transaction["listing_id"].as_u64() {
+                    sales.push(EtsySale {
+                        receipt_id,
+                        listing_id,
+                        sku: transaction["sku"].as_str().filter(|sku| !sku.is_empty()).map(str::to_string),
+                        quantity: transaction["quantity"].as_u64().unwrap_or(1) as u32,
+                    });
+                }
+            }
+        }
+        if receipts.len() < PAGE_SIZE {
+            return Ok(sales);
+        }
+        offset += PAGE_SIZE;
+    }
+}
+
+// Deactivate an active listing so it can't be bought; returns false when it
+// wasn't active, e.g. a draft or already sold out
+pub async fn end_listing(app: &AppHandle, auth: &EtsyAuth, listing_id: &str) -> Result<bool, String> {
+    let settings = load_settings(app)?;
+    let shop = shop(app, &settings, auth).await?;
+    let token = access_token(app, &settings, auth).await?;
+    let key = api_key(&settings)?;
+    let client = network::client(app);
+
+    let listing = send(
+        client
+            .get(format!("{}/listings/{}", API_URL, urlencoding::encode(listing_id)))
+            .bearer_auth(&token)
+            .header("x-api-key", &key),
+        "look up Etsy listing",
+    )
+    .await?;
+    if listing["state"].as_str() != Some("active") {
+        return Ok(false);
+    }
+    send(
+        client
+            .patch(format!("{}/shops/{}/listings/{}", API_URL, shop.shop_id, urlencoding::encode(listing_id)))
+            .bearer_auth(&token)
+            .header("x-api-key", &key)
+            .form(&[("state", "inactive")]),
+        "end Etsy listing",
+    )
+    .await?;
+    Ok(true)
+}
@@ -0,0 +1,209 @@
+use std::fs;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use crate::image_io;
+use crate::crosslist::Marketplace;
+use crate::listing::{self, Listing, ListingCondition, MappedListing, PackageContents};
+use crate::photo_editing;
+
+// Marketplace takes up to 10 photos and serves them at 2048px at most
+const MAX_PHOTOS: usize = 10;
+const PHOTO_EDGE: u32 = 2048;
+
+// Commerce Manager's catalog columns, in the order of its template
+const CATALOG_COLUMNS: [&str; 14] = [
+    "id",
+    "title",
+    "description",
+    "availability",
+    "condition",
+    "price",
+    "link",
+    "image_link",
+    "additional_image_link",
+    "brand",
+    "size",
+    "color",
+    "material",
+    "quantity_to_sell_on_facebook",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FacebookListing {
+    #[serde(flatten)]
+    pub item: Listing,
+    // Item page the catalog links to, e.g. the same item on the seller's
+    // shop; catalogs need one, Marketplace posts don't
+    #[serde(default)]
+    pub link: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FacebookExport {
+    pub folder: String,
+    pub photos: Vec<String>,
+    // Title, price and description, ready to copy into the Marketplace form
+    pub clipboard_text: String,
+    pub text_file: String,
+}
+
+// Marketplace's condition names
+fn marketplace_condition(condition: ListingCondition) -> &'static str {
+    match condition {
+        ListingCondition::NewWithTags => "New",
+        ListingCondition::NewWithoutTags => "Used - Like New",
+        ListingCondition::VeryGood | ListingCondition::Good => "Used - Good",
+        ListingCondition::Satisfactory => "Used - Fair",
+    }
+}
+
+// Catalogs only tell new and used apart
+fn catalog_condition(condition: ListingCondition) -> &'static str {
+    match condition {
+        ListingCondition::NewWithTags | ListingCondition::NewWithoutTags => "new",
+        _ => "used",
+    }
+}
+
+fn clipboard_text(listing: &FacebookListing) -> String {
+    let item = &listing.item;
+    let mut lines = vec![
+        item.title.trim().to_string(),
+        format!("{:.2} {}", item.price, item.currency.trim().to_uppercase()),
+        format!("Condition: {}", marketplace_condition(item.condition)),
+        String::new(),
+        item.description_with_condition().trim().to_string(),
+    ];
+    let mut details = Vec::new();
+    if let Some(brand) = item.brand.as_deref().filter(|brand| !brand.trim().is_empty()) {
+        details.push(format!("Brand: {}", brand.trim()));
+    }
+    if let Some(size) = item.size.as_deref().filter(|size| !size.trim().is_empty()) {
+        details.push(format!("Size: {}", size.trim()));
+    }
+    if !item.colors.is_empty() {
+        details.push(format!("Colour: {}", item.colors.join(", ")));
+    }
+    if !details.is_empty() {
+        lines.push(String::new());
+        lines.extend(details);
+    }
+    lines.join("\n") + "\n"
+}
+
+// Quote a CSV field when it holds a comma, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+// Catalog row for a listing; catalogs fetch photos themselves, so they must
+// be public URLs
+fn catalog_row(listing: &FacebookListing) -> Result<Vec<String>, String> {
+    map_listing(listing).check()?;
+    let item = &listing.item;
+    let title = item.title.trim();
+    let link = listing
+        .link
+        .as_deref()
+        .map(str::trim)
+        .filter(|link| link.starts_with("http"))
+        .ok_or_else(|| format!("\"{}\" needs a link for the catalog", title))?;
+    if let Some(photo) = item.photos.iter().find(|photo| !photo.starts_with("https://")) {
+        return Err(format!("\"{}\" has a local photo ({}); upload photos before adding them to a catalog", title, photo));
+    }
+    let id = item
+        .sku
+        .clone()
+        .filter(|sku| !sku.trim().is_empty())
+        .ok_or_else(|| format!("\"{}\" needs a SKU to use as its catalog ID", title))?;
+
+    Ok(vec![
+        id,
+        title.to_string(),
+        item.description_with_condition(),
+        "in stock".to_string(),
+        catalog_condition(item.condition).to_string(),
+        format!("{:.2} {}", item.price, item.currency.trim().to_uppercase()),
+        link.to_string(),
+        item.photos[0].clone(),
+        item.photos[1..].join(","),
+        item.brand.clone().unwrap_or_default(),
+        item.size.clone().unwrap_or_default(),
+        item.colors.join("/"),
+        item.materials.join("/"),
+        item.quantity.to_string(),
+    ])
+}
+
+// How Marketplace will see the listing, with what stops it being exported.
+// Marketplace picks the category itself from the title.
+pub fn map_listing(listing: &FacebookListing) -> MappedListing {
+    MappedListing {
+        category: Vec::new(),
+        condition: Some(marketplace_condition(listing.item.condition).to_string()),
+        size: listing.item.size.clone(),
+        max_photos: MAX_PHOTOS,
+        problems: listing.item.problems("Facebook Marketplace", MAX_PHOTOS),
+    }
+}
+
+// Write a listing's Facebook Marketplace package; used by the command and
+// by cross-listing
+pub async fn export(app: &AppHandle, listing: &FacebookListing, output_dir: Option<String>) -> Result<FacebookExport, String> {
+    let clipboard_text = clipboard_text(listing);
+    let contents = PackageContents::resized(PHOTO_EDGE, None, clipboard_text.clone());
+    let package = listing::write_package(app, Marketplace::Facebook, &listing.item, &map_listing(listing), output_dir, contents).await?;
+    Ok(FacebookExport {
+        folder: package.folder,
+        photos: package.photos,
+        clipboard_text,
+        text_file: package.text_file,
+    })
+}
+
+// Command to prepare a listing for Facebook Marketplace, which has no
+// listing API for individual sellers: writes the photos resized for upload
+// and returns a title, price and description block to copy into the form
+#[tauri::command]
+pub async fn export_for_facebook(
+    app: AppHandle,
+    listing: FacebookListing,
+    output_dir: Option<String>,
+) -> Result<FacebookExport, String> {
+    export(&app, &listing, output_dir).await
+}
+
+// Command to write listings as a CSV in Commerce Manager's catalog format,
+// for sellers with a catalog to upload it to. Every listing needs a SKU, a
+// link and photos at public URLs; the first listing missing one fails the
+// export, so the file is never partial. Returns the CSV's path.
+#[tauri::command]
+pub fn export_facebook_catalog(
+    app: AppHandle,
+    listings: Vec<FacebookListing>,
+    output_path: Option<String>,
+) -> Result<String, String> {
+    if listings.is_empty() {
+        return Err("No listings given".to_string());
+    }
+    let mut csv = CATALOG_COLUMNS.join(",") + "\n";
+    for listing in &listings {
+        let row: Vec<String> = catalog_row(listing)?.iter().map(|field| csv_field(field)).collect();
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+
+    let path = match output_path {
+        Some(path) => PathBuf::from(path),
+        None => photo_editing::output_dir(&app, "exports")?
+            .join(format!("facebook-catalog-{}.csv", chrono::Utc::now().format("%Y%m%d-%H%M%S"))),
+    };
+    fs::write(&path, csv)
+        .map_err(|e| format!("Failed to write catalog: {}", e))?;
+    image_io::path_to_string(&path)
+}
@@ -0,0 +1,182 @@
+use std::collections::{HashMap, HashSet};
+use image::GrayImage;
+use imageproc::corners::corners_fast9;
+use imageproc::filter::gaussian_blur_f32;
+use rayon::prelude::*;
+use crate::image_io;
+
+// Pairs whose hash similarity is within this distance of the threshold are
+// re-checked with keypoint matching
+const BORDERLINE_BAND: f64 = 0.08;
+
+// Share of keypoints that must match for a borderline pair to be merged
+const MIN_MATCH_RATIO: f64 = 0.12;
+
+// Working size for keypoint detection
+const FEATURE_EDGE: u32 = 512;
+
+const FAST_THRESHOLD: u8 = 20;
+const MAX_KEYPOINTS: usize = 500;
+
+// BRIEF patch radius; keypoints closer than this (plus rotation slack) to the
+// edge are dropped
+const PATCH_RADIUS: i32 = 15;
+const BORDER: u32 = 22;
+
+// Descriptor bits that may differ for two keypoints to count as a match
+const MAX_MATCH_DISTANCE: u32 = 64;
+
+// Lowe's ratio test: best match must be clearly better than the runner-up
+const RATIO_TEST: f64 = 0.8;
+
+// 256-bit rotated BRIEF descriptor
+type Descriptor = [u64; 4];
+
+// Fixed pseudo-random point pairs inside the patch, the same for every image
+fn sampling_pattern() -> Vec<[(f64, f64); 2]> {
+    let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+    let mut next = || {
+        state = state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+        // Roughly Gaussian offsets (sum of uniforms), clamped to the patch
+        let sum: f64 = (0..3).map(|shift| ((state >> (16 * shift + 11)) & 0xFFFF) as f64 / 65535.0).sum();
+        ((sum / 3.0 - 0.5) * 2.0 * PATCH_RADIUS as f64).clamp(-(PATCH_RADIUS as f64), PATCH_RADIUS as f64)
+    };
+
+    (0..256).map(|_| [(next(), next()), (next(), next())]).collect()
+}
+
+// ORB-style keypoint descriptors: FAST corners, intensity-centroid
+// orientation and rotated BRIEF tests on a blurred image
+fn describe(gray: &GrayImage, pattern: &[[(f64, f64); 2]]) -> Vec<Descriptor> {
+    let (width, height) = gray.dimensions();
+    if width <= BORDER * 2 || height <= BORDER * 2 {
+        return Vec::new();
+    }
+
+    let mut corners: Vec<_> = corners_fast9(gray, FAST_THRESHOLD)
+        .into_iter()
+        .filter(|c| c.x >= BORDER && c.y >= BORDER && c.x < width - BORDER && c.y < height - BORDER)
+        .collect();
+    corners.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    // Keep the strongest corner per 4x4 cell so keypoints don't bunch up
+    let mut taken = HashSet::new();
+    corners.retain(|c| taken.insert((c.x / 4, c.y / 4)));
+    corners.truncate(MAX_KEYPOINTS);
+
+    let smoothed = gaussian_blur_f32(gray, 2.0);
+    let pixel = |x: f64, y: f64| smoothed.get_pixel(x.round() as u32, y.round() as u32)[0];
+
+    corners
+        .iter()
+        .map(|corner| {
+            // Orientation from the intensity centroid of the patch
+            let (mut m10, mut m01) = (0.0, 0.0);
+            for dy in -PATCH_RADIUS..=PATCH_RADIUS {
+                for dx in -PATCH_RADIUS..=PATCH_RADIUS {
+                    if dx * dx + dy * dy <= PATCH_RADIUS * PATCH_RADIUS {
+                        let value = pixel(corner.x as f64 + dx as f64, corner.y as f64 + dy as f64) as f64;
+                        m10 += dx as f64 * value;
+                        m01 += dy as f64 * value;
+                    }
+                }
+            }
+            let (sin, cos) = m01.atan2(m10).sin_cos();
+            let rotate = |(dx, dy): (f64, f64)| {
+                (corner.x as f64 + dx * cos - dy * sin, corner.y as f64 + dx * sin + dy * cos)
+            };
+
+            let mut descriptor = [0u64; 4];
+            for (bit, [a, b]) in pattern.iter().enumerate() {
+                let (ax, ay) = rotate(*a);
+                let (bx, by) = rotate(*b);
+                if pixel(ax, ay) < pixel(bx, by) {
+                    descriptor[bit / 64] |= 1 << (bit % 64);
+                }
+            }
+            descriptor
+        })
+        .collect()
+}
+
+fn distance(a: &Descriptor, b: &Descriptor) -> u32 {
+    a.iter().zip(b).map(|(x, y)| (x ^ y).count_ones()).sum()
+}
+
+// Share of keypoints with a confident match in the other photo
+fn match_ratio(a: &[Descriptor], b: &[Descriptor]) -> f64 {
+    if a.is_empty() || b.len() < 2 {
+        return 0.0;
+    }
+
+    let matches = a
+        .iter()
+        .filter(|descriptor| {
+            let mut best = u32::MAX;
+            let mut second = u32::MAX;
+            for candidate in b {
+                let d = distance(descriptor, candidate);
+                if d < best {
+                    second = best;
+                    best = d;
+                } else if d < second {
+                    second = d;
+                }
+            }
+            best <= MAX_MATCH_DISTANCE && (best as f64) < RATIO_TEST * second as f64
+        })
+        .count();
+
+    matches as f64 / a.len().min(b.len()) as f64
+}
+
+// Re-check pairs whose similarity is within `BORDERLINE_BAND` of the threshold
+// using keypoint matching, which survives changes in angle and distance that
+// throw off perceptual hashes. Returns corrected similarities for those pairs:
+// confirmed pairs are lifted to at least the threshold, rejected ones pushed below it.
+pub fn verify_borderline<F>(paths: &[String], threshold: f64, similarity: F) -> Result<HashMap<(usize, usize), f64>, String>
+where
+    F: Fn(usize, usize) -> f64,
+{
+    let mut borderline = Vec::new();
+    for i in 0..paths.len() {
+        for j in (i + 1)..paths.len() {
+            let value = similarity(i, j);
+            if value > 0.0 && (value - threshold).abs() <= BORDERLINE_BAND {
+                borderline.push((i, j, value));
+            }
+        }
+    }
+    if borderline.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let involved: Vec<usize> = borderline
+        .iter()
+        .flat_map(|&(i, j, _)| [i, j])
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+
+    let pattern = sampling_pattern();
+    let descriptors: HashMap<usize, Vec<Descriptor>> = involved
+        .par_iter()
+        .map(|&index| {
+            let gray = image_io::open_image_scaled(&paths[index], FEATURE_EDGE)?.to_luma8();
+            Ok((index, describe(&gray, &pattern)))
+        })
+        .collect::<Result<_, String>>()?;
+
+    Ok(borderline
+        .into_par_iter()
+        .map(|(i, j, value)| {
+            let confirmed = match_ratio(&descriptors[&i], &descriptors[&j]) >= MIN_MATCH_RATIO;
+            let corrected = if confirmed {
+                value.max(threshold)
+            } else {
+                value.min(threshold - 1e-9)
+            };
+            ((i, j), corrected)
+        })
+        .collect())
+}
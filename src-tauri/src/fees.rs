@@ -0,0 +1,213 @@
+use serde::{Deserialize, Serialize};
+use crate::crosslist::Marketplace;
+
+// eBay final value fee tiers: (rate up to the threshold, threshold, rate on
+// the amount above it), by the app's categories. Most categories pay 13.6%
+// of the total sale up to $7,500.
+const EBAY_STANDARD: (f64, f64, f64) = (0.136, 7500.0, 0.0235);
+const EBAY_BOOKS_MEDIA: (f64, f64, f64) = (0.153, 7500.0, 0.0235);
+const EBAY_HANDBAGS: (f64, f64, f64) = (0.15, 2000.0, 0.09);
+const EBAY_JEWELRY: (f64, f64, f64) = (0.15, 5000.0, 0.09);
+
+// eBay's per-order fee, higher above $10
+const EBAY_ORDER_FEE_SMALL: f64 = 0.30;
+const EBAY_ORDER_FEE: f64 = 0.40;
+const EBAY_ORDER_FEE_THRESHOLD: f64 = 10.0;
+
+// Etsy charges per listing (renewed every four months or on sale), a
+// transaction fee on the item and shipping, and payment processing on the
+// order total
+const ETSY_LISTING_FEE: f64 = 0.20;
+const ETSY_TRANSACTION_RATE: f64 = 0.065;
+const ETSY_PROCESSING_RATE: f64 = 0.03;
+const ETSY_PROCESSING_FIXED: f64 = 0.25;
+// Offsite Ads take 15% of orders they bring in, at most $100 an order
+const ETSY_OFFSITE_ADS_RATE: f64 = 0.15;
+const ETSY_OFFSITE_ADS_CAP: f64 = 100.0;
+
+// Poshmark takes a flat fee under $15 and 20% from there
+const POSHMARK_FLAT_FEE: f64 = 2.95;
+const POSHMARK_FLAT_THRESHOLD: f64 = 15.0;
+const POSHMARK_RATE: f64 = 0.20;
+
+const MERCARI_RATE: f64 = 0.10;
+const MERCARI_PROCESSING_RATE: f64 = 0.029;
+const MERCARI_PROCESSING_FIXED: f64 = 0.50;
+
+// Depop has no selling fee for US and UK sellers, only payment processing
+const DEPOP_PROCESSING_RATE: f64 = 0.033;
+const DEPOP_PROCESSING_FIXED: f64 = 0.45;
+
+// Facebook charges shipped orders a selling fee with a minimum; local
+// pickup is free
+const FACEBOOK_RATE: f64 = 0.10;
+const FACEBOOK_MIN_FEE: f64 = 0.80;
+
+// What a sale brings in and costs, in the listing's currency
+#[derive(Debug, Clone, Deserialize)]
+pub struct FeeListing {
+    pub price: f64,
+    pub currency: String,
+    // What the buyer pays for shipping, on top of the price
+    #[serde(default)]
+    pub shipping_charged: f64,
+    // What the seller pays for the label and packaging
+    #[serde(default)]
+    pub shipping_cost: f64,
+    // What the item cost the seller
+    #[serde(default)]
+    pub cost_of_goods: f64,
+    // The app's category, one of `classifier::CATEGORIES`; sets eBay's rate
+    #[serde(default)]
+    pub category: Option<String>,
+    // eBay Promoted Listings ad rate as a percentage, e.g. 5 for 5%
+    #[serde(default)]
+    pub promoted_rate: Option<f64>,
+    // Whether the sale came through Etsy Offsite Ads
+    #[serde(default)]
+    pub offsite_ads: bool,
+    // Collected in person, so Facebook charges nothing and nothing ships
+    #[serde(default)]
+    pub local_pickup: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Fee {
+    pub name: String,
+    pub amount: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeeBreakdown {
+    pub marketplace: Marketplace,
+    // Price plus shipping charged
+    pub gross: f64,
+    pub fees: Vec<Fee>,
+    pub total_fees: f64,
+    // Gross less fees, shipping cost and cost of goods
+    pub profit: f64,
+    // Profit as a percentage of gross
+    pub margin: f64,
+    // Assumptions the estimate makes
+    pub notes: Vec<String>,
+}
+
+fn round_cents(amount: f64) -> f64 {
+    (amount * 100.0).round() / 100.0
+}
+
+fn ebay_tiers(category: Option<&str>) -> (f64, f64, f64) {
+    match category {
+        Some("media") => EBAY_BOOKS_MEDIA,
+        Some("bags") => EBAY_HANDBAGS,
+        Some("jewelry") => EBAY_JEWELRY,
+        _ => EBAY_STANDARD,
+    }
+}
+
+// Fees a sale is charged on a marketplace, with notes on what's assumed
+fn marketplace_fees(listing: &FeeListing, marketplace: Marketplace, notes: &mut Vec<String>) -> Vec<Fee> {
+    let price = listing.price;
+    let total = price + listing.shipping_charged;
+    let fee = |name: &str, amount: f64| Fee { name: name.to_string(), amount: round_cents(amount) };
+    match marketplace {
+        // Charged on the total the buyer pays, shipping included; sales tax
+        // is left out, so eBay's fee will be a little higher where it applies
+        Marketplace::Ebay => {
+            let (rate, threshold, above) = ebay_tiers(listing.category.as_deref());
+            let mut fees = vec![
+                fee("Final value fee", total.min(threshold) * rate + (total - threshold).max(0.0) * above),
+                fee("Per-order fee", if total > EBAY_ORDER_FEE_THRESHOLD { EBAY_ORDER_FEE } else { EBAY_ORDER_FEE_SMALL }),
+            ];
+            if let Some(rate) = listing.promoted_rate.filter(|rate| *rate > 0.0) {
+                fees.push(fee("Promoted Listings", total * rate / 100.0));
+            }
+            notes.push("eBay fees are charged on sales tax too, which isn't included".to_string());
+            fees
+        }
+        Marketplace::Etsy => {
+            let mut fees = vec![
+                fee("Listing fee", ETSY_LISTING_FEE),
+                fee("Transaction fee", total * ETSY_TRANSACTION_RATE),
+                fee("Payment processing", total * ETSY_PROCESSING_RATE + ETSY_PROCESSING_FIXED),
+            ];
+            if listing.offsite_ads {
+                fees.push(fee("Offsite Ads", (total * ETSY_OFFSITE_ADS_RATE).min(ETSY_OFFSITE_ADS_CAP)));
+            }
+            fees
+        }
+        // Buyers pay Poshmark's flat shipping label, so only the price counts
+        Marketplace::Poshmark => {
+            let commission = if price < POSHMARK_FLAT_THRESHOLD { POSHMARK_FLAT_FEE } else { price * POSHMARK_RATE };
+            if listing.shipping_charged > 0.0 || listing.shipping_cost > 0.0 {
+                notes.push("Poshmark buyers pay its prepaid label, so shipping is usually neither charged nor paid".to_string());
+            }
+            vec![fee("Commission", commission)]
+        }
+        Marketplace::Mercari => vec![
+            fee("Selling fee", total * MERCARI_RATE),
+            fee("Payment processing", total * MERCARI_PROCESSING_RATE + MERCARI_PROCESSING_FIXED),
+        ],
+        Marketplace::Vinted => {
+            notes.push("Vinted buyers pay its Buyer Protection fee; sellers pay nothing".to_string());
+            Vec::new()
+        }
+        Marketplace::Depop => vec![fee("Payment processing", total * DEPOP_PROCESSING_RATE + DEPOP_PROCESSING_FIXED)],
+        Marketplace::Facebook if listing.local_pickup => Vec::new(),
+        Marketplace::Facebook => vec![fee("Selling fee", (total * FACEBOOK_RATE).max(FACEBOOK_MIN_FEE))],
+    }
+}
+
+// Command to estimate a sale's marketplace fees, profit and margin from its
+// price, shipping charged and paid, and cost of goods, using each
+// marketplace's current US fee schedule: eBay's final value fee by category,
+// per-order fee and Promoted Listings rate; Etsy's listing, transaction,
+// processing and Offsite Ads fees; and the others' commissions
+#[tauri::command]
+pub fn calculate_fees(listing: FeeListing, marketplace: Marketplace) -> Result<FeeBreakdown, String> {
+    for (name, value) in [
+        ("Price", listing.price),
+        ("Shipping charged", listing.shipping_charged),
+        ("Shipping cost", listing.shipping_cost),
+        ("Cost of goods", listing.cost_of_goods),
+    ] {
+        if !value.is_finite() {
+            return Err(format!("{} must be a number", name));
+        }
+        if value < 0.0 {
+            return Err(format!("{} can't be negative", name));
+        }
+    }
+    if listing.price <= 0.0 {
+        return Err("Price must be greater than zero".to_string());
+    }
+    if listing.promoted_rate.is_some_and(|rate| !(0.0..=100.0).contains(&rate)) {
+        return Err("Promoted Listings rate must be between 0 and 100%".to_string());
+    }
+
+    let mut notes = Vec::new();
+    let currency = listing.currency.trim().to_uppercase();
+    if !currency.is_empty() && currency != "USD" {
+        notes.push(format!("Fees follow US schedules; rates for {} sellers may differ", currency));
+    }
+    let (shipping_charged, shipping_cost) = if listing.local_pickup {
+        (0.0, 0.0)
+    } else {
+        (listing.shipping_charged, listing.shipping_cost)
+    };
+    let listing = FeeListing { shipping_charged, shipping_cost, ..listing };
+
+    let fees = marketplace_fees(&listing, marketplace, &mut notes);
+    let gross = listing.price + listing.shipping_charged;
+    let total_fees: f64 = fees.iter().map(|fee| fee.amount).sum();
+    let profit = gross - total_fees - listing.shipping_cost - listing.cost_of_goods;
+    Ok(FeeBreakdown {
+        marketplace,
+        gross: round_cents(gross),
+        fees,
+        total_fees: round_cents(total_fees),
+        profit: round_cents(profit),
+        margin: (profit / gross * 1000.0).round() / 10.0,
+        notes,
+    })
+}
@@ -0,0 +1,1157 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::SeekFrom;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use base64::{Engine as _, engine::general_purpose};
+use chrono::{DateTime, Utc};
+use futures_util::future::try_join_all;
+use rusqlite::{params, Connection, OptionalExtension};
+use rsa::{RsaPrivateKey, pkcs8::DecodePrivateKey};
+use rsa::signature::{SignatureEncoding, Signer};
+use rsa::pkcs1v15::SigningKey;
+use rsa::sha2::{Digest, Sha256};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State, Window};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use crate::checksum;
+use crate::db::Database;
+use crate::google_auth::AccessTokens;
+use crate::hash_cache::FileStamp;
+use crate::keychain;
+use crate::network;
+use crate::storage::{self, INITIAL_BACKOFF, MAX_UPLOAD_ATTEMPTS, PresignedRequest, StorageProvider, UploadError, UploadProgress};
+
+// Service account structure
+#[derive(Debug, Deserialize)]
+struct ServiceAccount {
+    private_key: String,
+    client_email: String,
+}
+
+const GCS_HOST: &str = "storage.googleapis.com";
+
+// Signed URL schemes supported by GCS
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SigningVersion {
+    // Legacy scheme (GoogleAccessId/Expires/Signature query parameters)
+    V2,
+    // Canonical request + credential scope, as Google now recommends
+    V4,
+}
+
+impl SigningVersion {
+    // Parse the version name used by the frontend; defaults to V2
+    fn parse(name: Option<&str>) -> Result<SigningVersion, String> {
+        match name.map(|n| n.to_lowercase()).as_deref() {
+            None | Some("v2") => Ok(SigningVersion::V2),
+            Some("v4") => Ok(SigningVersion::V4),
+            Some(other) => Err(format!("Unknown signing version: {}", other)),
+        }
+    }
+}
+
+// Registered service account key, kept in the app data directory so it is
+// found regardless of the working directory
+fn service_account_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app.path_resolver()
+        .app_data_dir()
+        .ok_or("Failed to resolve app data directory")?;
+    Ok(data_dir.join("credentials").join("google-service-account.json"))
+}
+
+// Service account to sign as through IAM, when using OAuth instead of a key
+fn iam_signing_path(app: &AppHandle) -> Result<PathBuf, String> {
+    Ok(service_account_path(app)?.with_file_name("iam-signing.json"))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct IamSigning {
+    service_account_email: String,
+}
+
+fn load_iam_signing(app: &AppHandle) -> Result<Option<IamSigning>, String> {
+    let path = iam_signing_path(app)?;
+    if !path.exists() {
+        return Ok(None);
+    }
+    let json = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read OAuth signing settings: {}", e))?;
+    serde_json::from_str(&json)
+        .map(Some)
+        .map_err(|e| format!("Failed to parse OAuth signing settings: {}", e))
+}
+
+fn parse_service_account(service_account_json: &str) -> Result<ServiceAccount, String> {
+    serde_json::from_str(service_account_json)
+        .map_err(|e| format!("Failed to parse service account JSON: {}", e))
+}
+
+// Read the service account registered with `register_service_account`
+fn load_service_account(app: &AppHandle) -> Result<ServiceAccount, String> {
+    let path = service_account_path(app)?;
+    if !path.exists() {
+        return Err("No service account registered; add a key file in settings".to_string());
+    }
+    let service_account_json = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read service account file: {}", e))?;
+    parse_service_account(&service_account_json)
+}
+
+// Command to register a service account key file for all GCS commands
+// The key is validated, then copied into the app data directory (readable only
+// by the current user on Unix). Replaces OAuth signing if it was set up.
+// Returns the service account's email.
+#[tauri::command]
+pub fn register_service_account(app: AppHandle, key_path: String) -> Result<String, String> {
+    let service_account_json = fs::read_to_string(&key_path)
+        .map_err(|e| format!("Failed to read service account file: {}", e))?;
+    let service_account = parse_service_account(&service_account_json)?;
+    GcsSigner::new(&service_account, network::client(&app))?;
+
+    let path = service_account_path(&app)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create credentials directory: {}", e))?;
+    }
+    keychain::write_private(&path, service_account_json.as_bytes())
+        .map_err(|e| format!("Failed to save service account: {}", e))?;
+    let iam_path = iam_signing_path(&app)?;
+    if iam_path.exists() {
+        fs::remove_file(&iam_path)
+            .map_err(|e| format!("Failed to remove OAuth signing settings: {}", e))?;
+    }
+    app.state::<SignerCache>().invalidate();
+
+    Ok(service_account.client_email)
+}
+
+// Command to sign GCS requests through the IAM signBlob API with an OAuth
+// access token, so no long-lived key has to be downloaded. `access_token` is
+// a token from a user OAuth flow; without one, application default credentials
+// are used (a `gcloud auth application-default login`, or the metadata server
+// on Google Cloud and with workload identity). Requests are signed as
+// `service_account_email`, which the token's principal needs the Service
+// Account Token Creator role on. A test signature is made before switching.
+#[tauri::command]
+pub async fn use_gcs_oauth(app: AppHandle, service_account_email: String, access_token: Option<String>) -> Result<(), String> {
+    let tokens = match access_token {
+        Some(token) => AccessTokens::fixed(network::client(&app), token),
+        None => AccessTokens::application_default(network::client(&app))?,
+    };
+    let signer = GcsSigner::Iam { client_email: service_account_email.clone(), tokens };
+    signer.sign(b"listing-assistant").await?;
+
+    let path = iam_signing_path(&app)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create credentials directory: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(&IamSigning { service_account_email })
+        .map_err(|e| format!("Failed to serialize OAuth signing settings: {}", e))?;
+    fs::write(&path, json)
+        .map_err(|e| format!("Failed to save OAuth signing settings: {}", e))?;
+
+    app.state::<SignerCache>().set_iam(Arc::new(signer));
+    Ok(())
+}
+
+// Command to get the email of the service account requests are signed as, if any
+#[tauri::command]
+pub fn get_service_account(app: AppHandle) -> Result<Option<String>, String> {
+    if let Some(iam) = load_iam_signing(&app)? {
+        return Ok(Some(iam.service_account_email));
+    }
+    if !service_account_path(&app)?.exists() {
+        return Ok(None);
+    }
+    load_service_account(&app).map(|service_account| Some(service_account.client_email))
+}
+
+// Signs GCS requests as a service account, either with its private key or
+// through IAM with an OAuth access token
+pub enum GcsSigner {
+    Key {
+        client_email: String,
+        key: Box<SigningKey<Sha256>>,
+        // Tokens for Google APIs that take OAuth rather than signed URLs
+        tokens: AccessTokens,
+    },
+    Iam {
+        client_email: String,
+        tokens: AccessTokens,
+    },
+}
+
+impl GcsSigner {
+    fn new(service_account: &ServiceAccount, client: reqwest::Client) -> Result<GcsSigner, String> {
+        let private_key_pem = service_account.private_key.replace("\\n", "\n");
+        let private_key = RsaPrivateKey::from_pkcs8_pem(&private_key_pem)
+            .map_err(|e| format!("Failed to parse private key: {}", e))?;
+        let key = SigningKey::<Sha256>::new(private_key);
+
+        Ok(GcsSigner::Key {
+            client_email: service_account.client_email.clone(),
+            tokens: AccessTokens::service_account(client, service_account.client_email.clone(), key.clone()),
+            key: Box::new(key),
+        })
+    }
+
+    fn client_email(&self) -> &str {
+        match self {
+            GcsSigner::Key { client_email, .. } | GcsSigner::Iam { client_email, .. } => client_email,
+        }
+    }
+
+    // Sign with RSA-SHA256
+    async fn sign(&self, data: &[u8]) -> Result<Vec<u8>, String> {
+        match self {
+            GcsSigner::Key { key, .. } => Ok(key.sign(data).to_bytes().to_vec()),
+            GcsSigner::Iam { client_email, tokens } => tokens.sign_blob(client_email, data).await,
+        }
+    }
+
+    // OAuth access token for calling Google APIs with these credentials
+    async fn access_token(&self) -> Result<String, String> {
+        match self {
+            GcsSigner::Key { tokens, .. } | GcsSigner::Iam { tokens, .. } => tokens.token().await,
+        }
+    }
+}
+
+// Parsed signer for the registered service account, kept so each signed URL
+// doesn't re-read and re-parse the key. The key file's stamp is checked on
+// every use, so replacing the file picks up the new key.
+// With OAuth signing the signer is kept instead, along with its access token.
+#[derive(Default)]
+pub struct SignerCache {
+    cached: Mutex<Option<(FileStamp, Arc<GcsSigner>)>>,
+    iam: Mutex<Option<Arc<GcsSigner>>>,
+}
+
+impl SignerCache {
+    fn get(&self, app: &AppHandle) -> Result<Arc<GcsSigner>, String> {
+        let mut iam = self.iam.lock()
+            .map_err(|_| "Signer cache lock poisoned".to_string())?;
+        if let Some(signer) = iam.as_ref() {
+            return Ok(signer.clone());
+        }
+        // Set up by `use_gcs_oauth` in an earlier run; a token given then is
+        // gone, so fall back to application default credentials
+        if let Some(settings) = load_iam_signing(app)? {
+            let signer = Arc::new(GcsSigner::Iam {
+                client_email: settings.service_account_email,
+                tokens: AccessTokens::application_default(network::client(app))?,
+            });
+            *iam = Some(signer.clone());
+            return Ok(signer);
+        }
+        drop(iam);
+
+        let path = service_account_path(app)?;
+        let stamp = FileStamp::for_path(&path.to_string_lossy())
+            .map_err(|_| "No service account registered; add a key file in settings".to_string())?;
+
+        let mut cached = self.cached.lock()
+            .map_err(|_| "Signer cache lock poisoned".to_string())?;
+        if let Some((cached_stamp, signer)) = cached.as_ref() {
+            if *cached_stamp == stamp {
+                return Ok(signer.clone());
+            }
+        }
+
+        let signer = Arc::new(GcsSigner::new(&load_service_account(app)?, network::client(app))?);
+        *cached = Some((stamp, signer.clone()));
+        Ok(signer)
+    }
+
+    fn invalidate(&self) {
+        if let Ok(mut cached) = self.cached.lock() {
+            *cached = None;
+        }
+        if let Ok(mut iam) = self.iam.lock() {
+            *iam = None;
+        }
+    }
+
+    fn set_iam(&self, signer: Arc<GcsSigner>) {
+        self.invalidate();
+        if let Ok(mut iam) = self.iam.lock() {
+            *iam = Some(signer);
+        }
+    }
+}
+
+// Signer for the registered service account, from the cache when possible
+fn load_signer(app: &AppHandle) -> Result<Arc<GcsSigner>, String> {
+    app.state::<SignerCache>().get(app)
+}
+
+// OAuth access token for Google APIs (e.g. Vision) from the registered
+// credentials, whether a service account key or OAuth signing
+pub async fn access_token(app: &AppHandle) -> Result<String, String> {
+    load_signer(app)?.access_token().await
+}
+
+// Build a V2 signed URL; `content_type` must match the request's Content-Type header
+async fn signed_url_v2(
+    signer: &GcsSigner,
+    method: &str,
+    bucket_name: &str,
+    filename: &str,
+    content_type: Option<&str>,
+    expires_in_seconds: i64,
+) -> Result<String, String> {
+    let expiration = Utc::now().timestamp() + expires_in_seconds;
+    // Signed and requested percent-encoded, as in V4
+    let resource = format!("/{}/{}", bucket_name, storage::encode_object_name(filename));
+
+    let string_to_sign = format!(
+        "{}\n\n{}\n{}\n{}",
+        method,
+        content_type.unwrap_or(""),
+        expiration,
+        resource
+    );
+    let signature = signer.sign(string_to_sign.as_bytes()).await?;
+    let signature_base64 = general_purpose::STANDARD.encode(&signature);
+
+    Ok(format!(
+        "https://{}{}?GoogleAccessId={}&Expires={}&Signature={}",
+        GCS_HOST,
+        resource,
+        urlencoding::encode(signer.client_email()),
+        expiration,
+        urlencoding::encode(&signature_base64)
+    ))
+}
+
+// Build a V4 signed URL (GOOG4-RSA-SHA256)
+// `headers` are extra headers the request must send with exactly these values and
+// `query_params` extra query parameters (e.g. a listing prefix). An empty
+// `filename` signs the bucket itself.
+async fn signed_url_v4(
+    signer: &GcsSigner,
+    method: &str,
+    bucket_name: &str,
+    filename: &str,
+    headers: &[(&str, &str)],
+    query_params: &[(&str, &str)],
+    expires_in_seconds: i64,
+) -> Result<String, String> {
+    let now = Utc::now();
+    let timestamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let credential_scope = format!("{}/auto/storage/goog4_request", now.format("%Y%m%d"));
+
+    // Object names are percent-encoded per path segment
+    let canonical_uri = if filename.is_empty() {
+        format!("/{}", bucket_name)
+    } else {
+        format!("/{}/{}", bucket_name, storage::encode_object_name(filename))
+    };
+
+    // Header names are lowercase and sorted, host always included
+    let mut signed: Vec<(String, String)> = headers
+        .iter()
+        .map(|(name, value)| (name.to_lowercase(), value.trim().to_string()))
+        .collect();
+    signed.push(("host".to_string(), GCS_HOST.to_string()));
+    signed.sort();
+    let canonical_headers: String = signed.iter().map(|(name, value)| format!("{}:{}\n", name, value)).collect();
+    let signed_headers = signed.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>().join(";");
+
+    // BTreeMap keeps query parameters in the sorted order the signature requires
+    let mut query: BTreeMap<&str, String> = query_params
+        .iter()
+        .map(|(key, value)| (*key, value.to_string()))
+        .collect();
+    query.insert("X-Goog-Algorithm", "GOOG4-RSA-SHA256".to_string());
+    query.insert("X-Goog-Credential", format!("{}/{}", signer.client_email(), credential_scope));
+    query.insert("X-Goog-Date", timestamp.clone());
+    query.insert("X-Goog-Expires", expires_in_seconds.to_string());
+    query.insert("X-Goog-SignedHeaders", signed_headers.clone());
+    let canonical_query = query
+        .iter()
+        .map(|(key, value)| format!("{}={}", urlencoding::encode(key), urlencoding::encode(value)))
+        .collect::<Vec<_>>()
+        .join("&");
+
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\nUNSIGNED-PAYLOAD",
+        method,
+        canonical_uri,
+        canonical_query,
+        canonical_headers,
+        signed_headers
+    );
+    let string_to_sign = format!(
+        "GOOG4-RSA-SHA256\n{}\n{}\n{}",
+        timestamp,
+        credential_scope,
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+    let signature = signer.sign(string_to_sign.as_bytes()).await?;
+
+    Ok(format!(
+        "https://{}{}?{}&X-Goog-Signature={}",
+        GCS_HOST,
+        canonical_uri,
+        canonical_query,
+        hex::encode(signature)
+    ))
+}
+
+async fn signed_url(
+    signer: &GcsSigner,
+    version: SigningVersion,
+    method: &str,
+    bucket_name: &str,
+    filename: &str,
+    content_type: Option<&str>,
+    expires_in_seconds: i64,
+) -> Result<String, String> {
+    if let Some(content_type) = content_type {
+        storage::validate_content_type(content_type)?;
+    }
+
+    match version {
+        SigningVersion::V2 => signed_url_v2(signer, method, bucket_name, filename, content_type, expires_in_seconds).await,
+        SigningVersion::V4 => {
+            let headers: Vec<(&str, &str)> = content_type.map(|value| ("content-type", value)).into_iter().collect();
+            signed_url_v4(signer, method, bucket_name, filename, &headers, &[], expires_in_seconds).await
+        }
+    }
+}
+
+// Generate a signed URL for GCS upload
+// The upload must send `content_type` (default "image/jpeg") as its Content-Type.
+// Valid for `expires_in_seconds` (default 15 minutes, at most 7 days).
+// `signing_version` is "v2" (default) or "v4"
+#[tauri::command]
+pub async fn generate_gcs_signed_url(
+    app: AppHandle,
+    bucket_name: String,
+    filename: String,
+    content_type: Option<String>,
+    expires_in_seconds: Option<i64>,
+    signing_version: Option<String>,
+) -> Result<String, String> {
+    let version = SigningVersion::parse(signing_version.as_deref())?;
+    let expires_in_seconds = storage::parse_expiry(expires_in_seconds, 900)?;
+    let content_type = content_type.unwrap_or_else(|| "image/jpeg".to_string());
+    signed_url(&*load_signer(&app)?, version, "PUT", &bucket_name, &filename, Some(&content_type), expires_in_seconds).await
+}
+
+// Generate a signed URL for GCS read access (for Google Lens)
+// Valid for `expires_in_seconds` (default 10 minutes - enough for Lens call).
+// `content_type` is only needed if the reader sends a Content-Type header.
+// `signing_version` is "v2" (default) or "v4"
+#[tauri::command]
+pub async fn get_read_signed_url(
+    app: AppHandle,
+    bucket_name: String,
+    filename: String,
+    content_type: Option<String>,
+    expires_in_seconds: Option<i64>,
+    signing_version: Option<String>,
+) -> Result<String, String> {
+    let version = SigningVersion::parse(signing_version.as_deref())?;
+    let expires_in_seconds = storage::parse_expiry(expires_in_seconds, 600)?;
+    signed_url(&*load_signer(&app)?, version, "GET", &bucket_name, &filename, content_type.as_deref(), expires_in_seconds).await
+}
+
+// Generate signed URLs for several objects at once, e.g. all of a listing's
+// photos, loading the key only once. `method` is "PUT" (upload) or "GET" (read);
+// other options and defaults match `generate_gcs_signed_url` and `get_read_signed_url`.
+// URLs are returned in the order of `filenames`.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn generate_gcs_signed_urls(
+    app: AppHandle,
+    bucket_name: String,
+    filenames: Vec<String>,
+    method: String,
+    content_type: Option<String>,
+    expires_in_seconds: Option<i64>,
+    signing_version: Option<String>,
+) -> Result<Vec<String>, String> {
+    let version = SigningVersion::parse(signing_version.as_deref())?;
+    let method = method.to_uppercase();
+    let (default_expiry, content_type) = match method.as_str() {
+        "PUT" => (900, Some(content_type.unwrap_or_else(|| "image/jpeg".to_string()))),
+        "GET" => (600, content_type),
+        _ => return Err(format!("Unsupported method: {}", method)),
+    };
+    let expires_in_seconds = storage::parse_expiry(expires_in_seconds, default_expiry)?;
+
+    let signer = load_signer(&app)?;
+    try_join_all(filenames.iter().map(|filename| {
+        signed_url(&signer, version, &method, &bucket_name, filename, content_type.as_deref(), expires_in_seconds)
+    }))
+    .await
+}
+
+// Lifetime of a POST policy; the form must be submitted before it expires
+const POST_POLICY_SECONDS: i64 = 900;
+
+// Target and form fields for a browser form upload. `fields` go into the
+// form as-is, followed by `Content-Type` and the `file` field last.
+#[derive(Serialize)]
+pub struct GcsPostPolicy {
+    pub url: String,
+    pub fields: BTreeMap<String, String>,
+}
+
+// Generate a V4 signed POST policy for form uploads under `key_prefix`. GCS
+// enforces the size limit and content types: the form's Content-Type must be
+// one of `content_types` (GCS accepts a comma-separated list in an "eq"
+// condition). The browser's file name is appended to the prefix via `${filename}`.
+#[tauri::command]
+pub async fn generate_gcs_post_policy(
+    app: AppHandle,
+    bucket: String,
+    key_prefix: String,
+    max_size: u64,
+    content_types: Vec<String>,
+) -> Result<GcsPostPolicy, String> {
+    if max_size == 0 {
+        return Err("max_size must be greater than 0".to_string());
+    }
+    if content_types.is_empty() {
+        return Err("At least one content type is required".to_string());
+    }
+    for content_type in &content_types {
+        storage::validate_content_type(content_type)?;
+    }
+
+    let signer = load_signer(&app)?;
+    let now = Utc::now();
+    let timestamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let credential = format!("{}/{}/auto/storage/goog4_request", signer.client_email(), now.format("%Y%m%d"));
+    let expiration = (now + chrono::Duration::seconds(POST_POLICY_SECONDS)).format("%Y-%m-%dT%H:%M:%SZ").to_string();
+
+    let policy = serde_json::json!({
+        "conditions": [
+            {"bucket": bucket},
+            ["starts-with", "$key", key_prefix],
+            ["content-length-range", 0, max_size],
+            ["eq", "$Content-Type", content_types.join(",")],
+            {"x-goog-algorithm": "GOOG4-RSA-SHA256"},
+            {"x-goog-credential": credential},
+            {"x-goog-date": timestamp},
+        ],
+        "expiration": expiration,
+    });
+    let encoded_policy = general_purpose::STANDARD.encode(policy.to_string());
+    let signature = signer.sign(encoded_policy.as_bytes()).await?;
+
+    let fields = BTreeMap::from([
+        ("key".to_string(), format!("{}${{filename}}", key_prefix)),
+        ("policy".to_string(), encoded_policy),
+        ("x-goog-algorithm".to_string(), "GOOG4-RSA-SHA256".to_string()),
+        ("x-goog-credential".to_string(), credential),
+        ("x-goog-date".to_string(), timestamp),
+        ("x-goog-signature".to_string(), hex::encode(signature)),
+    ]);
+
+    Ok(GcsPostPolicy {
+        url: format!("https://{}/{}", GCS_HOST, bucket),
+        fields,
+    })
+}
+
+// Lifetime of the signed URL used for an upload, long enough to cover retries
+const UPLOAD_URL_SECONDS: i64 = 3600;
+
+// Event used for GCS upload progress
+const PROGRESS_EVENT: &str = "gcs://upload-progress";
+
+// Public URL of an object, with the object name percent-encoded per segment
+pub fn object_url(bucket_name: &str, object: &str) -> String {
+    format!("https://{}/{}/{}", GCS_HOST, bucket_name, storage::encode_object_name(object))
+}
+
+// Bucket and object named by a `gs://bucket/object` or unsigned
+// `https://storage.googleapis.com/bucket/object` URL
+pub fn parse_object_url(url: &str) -> Option<(String, String)> {
+    let (path, encoded) = if let Some(path) = url.strip_prefix("gs://") {
+        (path, false)
+    } else {
+        let path = url.strip_prefix("https://")?.strip_prefix(GCS_HOST)?.strip_prefix('/')?;
+        if path.contains('?') {
+            return None;
+        }
+        (path, true)
+    };
+
+    let (bucket, object) = path.split_once('/')?;
+    if bucket.is_empty() || object.is_empty() {
+        return None;
+    }
+    let object = if encoded {
+        urlencoding::decode(object).ok()?.into_owned()
+    } else {
+        object.to_string()
+    };
+    Some((bucket.to_string(), object))
+}
+
+// URL to download `url` from. GCS object URLs are signed so private objects
+// can be read; other URLs are used as is.
+pub async fn download_url(app: &AppHandle, url: &str) -> Result<String, String> {
+    let Some((bucket, object)) = parse_object_url(url) else {
+        return Ok(url.to_string());
+    };
+    match load_signer(app) {
+        Ok(signer) => signed_url_v4(&signer, "GET", &bucket, &object, &[], &[], REQUEST_URL_SECONDS).await,
+        // Without credentials only public objects can be fetched
+        Err(_) if url.starts_with("https://") => Ok(url.to_string()),
+        Err(e) => Err(e),
+    }
+}
+
+// A finished upload. GCS checked the object against `crc32c` (base64, as in
+// the `x-goog-hash` header) and rejects the upload on a mismatch, so the
+// checksum can be kept with the listing as a verified record of the file.
+#[derive(Debug, Serialize)]
+pub struct GcsUpload {
+    pub url: String,
+    pub crc32c: String,
+}
+
+// Per-object headers set on upload, e.g. a long cache lifetime for CDN-served
+// photos and the listing or SKU the photo belongs to
+#[derive(Debug, Default, Deserialize)]
+pub struct ObjectMetadata {
+    #[serde(default)]
+    cache_control: Option<String>,
+    // Sent as `x-goog-meta-<key>` headers
+    #[serde(default)]
+    custom: BTreeMap<String, String>,
+}
+
+impl ObjectMetadata {
+    // Headers to sign and send, lowercase as V4 signing expects
+    fn headers(&self) -> Result<Vec<(String, String)>, String> {
+        let valid_value = |value: &str| value.chars().all(|c| c == ' ' || c.is_ascii_graphic());
+
+        let mut headers = Vec::new();
+        if let Some(cache_control) = &self.cache_control {
+            if !valid_value(cache_control) {
+                return Err(format!("Invalid Cache-Control value: {}", cache_control));
+            }
+            headers.push(("cache-control".to_string(), cache_control.clone()));
+        }
+        for (key, value) in &self.custom {
+            if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+                return Err(format!("Invalid metadata key: {}", key));
+            }
+            if !valid_value(value) {
+                return Err(format!("Invalid value for metadata {}: only printable ASCII is allowed", key));
+            }
+            headers.push((format!("x-goog-meta-{}", key.to_lowercase()), value.clone()));
+        }
+        Ok(headers)
+    }
+}
+
+// CRC32C of a file, base64-encoded big-endian as GCS expects
+async fn crc32c_base64(file_path: &str) -> Result<String, String> {
+    let crc = checksum::file_crc32c(file_path).await?;
+    Ok(general_purpose::STANDARD.encode(crc.to_be_bytes()))
+}
+
+// Command to upload a file to `bucket/object` straight from disk
+// `metadata` optionally sets the object's Cache-Control and custom metadata.
+// Progress is reported with `gcs://upload-progress` events; transient failures
+// are retried with exponential backoff. Returns the object's URL and checksum.
+#[tauri::command]
+pub async fn upload_to_gcs(
+    window: Window,
+    bucket: String,
+    object: String,
+    file_path: String,
+    metadata: Option<ObjectMetadata>,
+) -> Result<GcsUpload, String> {
+    let content_type = storage::content_type_for(&file_path);
+    let crc32c = crc32c_base64(&file_path).await?;
+    let hash = format!("crc32c={}", crc32c);
+    let metadata_headers = metadata.unwrap_or_default().headers()?;
+
+    let signer = load_signer(&window.app_handle())?;
+    let mut headers = vec![("content-type", content_type), ("x-goog-hash", hash.as_str())];
+    headers.extend(metadata_headers.iter().map(|(name, value)| (name.as_str(), value.as_str())));
+    let url = signed_url_v4(&signer, "PUT", &bucket, &object, &headers, &[], UPLOAD_URL_SECONDS).await?;
+    let request = PresignedRequest::new("PUT", url, &headers);
+
+    let progress = storage::window_progress(&window, PROGRESS_EVENT);
+    storage::upload_with_retries(&network::client(&window.app_handle()), &request, &file_path, &object, progress).await?;
+    Ok(GcsUpload { url: object_url(&bucket, &object), crc32c })
+}
+
+// Resumable uploads send the file in chunks of this size; GCS requires a
+// multiple of 256 KiB for every chunk but the last
+const RESUMABLE_CHUNK_BYTES: u64 = 32 * 256 * 1024;
+
+// An interrupted resumable upload that can be continued
+#[derive(Debug, Serialize)]
+pub struct PendingUpload {
+    bucket: String,
+    object: String,
+    file_path: String,
+    total_bytes: u64,
+    created_at: String,
+}
+
+// Resumable upload session URIs, persisted so an upload interrupted by a crash
+// or restart continues from where GCS last acknowledged.
+// GCS keeps a session open for a week.
+pub struct UploadSessions {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl UploadSessions {
+    pub fn new(db: &Database) -> UploadSessions {
+        UploadSessions { conn: db.connection() }
+    }
+
+    // Session URI of an unfinished upload, if the file hasn't changed since it started
+    fn get(&self, bucket: &str, object: &str, file_path: &str, stamp: FileStamp) -> Option<String> {
+        let conn = self.conn.lock().ok()?;
+        conn.query_row(
+            "SELECT session_uri FROM resumable_uploads
+             WHERE bucket = ?1 AND object = ?2 AND file_path = ?3 AND mtime_nanos = ?4 AND size = ?5",
+            params![bucket, object, file_path, stamp.mtime_nanos, stamp.size],
+            |row| row.get(0),
+        )
+        .optional()
+        .ok()
+        .flatten()
+    }
+
+    fn put(&self, bucket: &str, object: &str, file_path: &str, stamp: FileStamp, session_uri: &str) -> Result<(), String> {
+        let conn = self.conn.lock()
+            .map_err(|_| "Upload database lock poisoned".to_string())?;
+        conn.execute(
+            "INSERT OR REPLACE INTO resumable_uploads
+             (bucket, object, file_path, mtime_nanos, size, session_uri, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![bucket, object, file_path, stamp.mtime_nanos, stamp.size, session_uri, Utc::now().to_rfc3339()],
+        ).map_err(|e| format!("Failed to save upload session: {}", e))?;
+        Ok(())
+    }
+
+    fn remove(&self, bucket: &str, object: &str, file_path: &str) -> Result<(), String> {
+        let conn = self.conn.lock()
+            .map_err(|_| "Upload database lock poisoned".to_string())?;
+        conn.execute(
+            "DELETE FROM resumable_uploads WHERE bucket = ?1 AND object = ?2 AND file_path = ?3",
+            params![bucket, object, file_path],
+        ).map_err(|e| format!("Failed to remove upload session: {}", e))?;
+        Ok(())
+    }
+
+    fn pending(&self) -> Result<Vec<PendingUpload>, String> {
+        let conn = self.conn.lock()
+            .map_err(|_| "Upload database lock poisoned".to_string())?;
+        let mut stmt = conn
+            .prepare("SELECT bucket, object, file_path, size, created_at FROM resumable_uploads ORDER BY created_at")
+            .map_err(|e| format!("Failed to read upload sessions: {}", e))?;
+        let rows = stmt
+            .query_map([], |row| Ok(PendingUpload {
+                bucket: row.get(0)?,
+                object: row.get(1)?,
+                file_path: row.get(2)?,
+                total_bytes: row.get::<_, i64>(3)? as u64,
+                created_at: row.get(4)?,
+            }))
+            .map_err(|e| format!("Failed to read upload sessions: {}", e))?;
+        rows.collect::<Result<_, _>>()
+            .map_err(|e| format!("Failed to read upload sessions: {}", e))
+    }
+}
+
+// Ask GCS for a resumable upload session URI
+async fn initiate_resumable(
+    client: &reqwest::Client,
+    signer: &GcsSigner,
+    bucket: &str,
+    object: &str,
+    content_type: &str,
+    metadata_headers: &[(String, String)],
+) -> Result<String, String> {
+    let mut headers = vec![("content-type", content_type), ("x-goog-resumable", "start")];
+    headers.extend(metadata_headers.iter().map(|(name, value)| (name.as_str(), value.as_str())));
+    let url = signed_url_v4(signer, "POST", bucket, object, &headers, &[], UPLOAD_URL_SECONDS).await?;
+
+    let mut request = client
+        .post(&url)
+        .header(reqwest::header::CONTENT_LENGTH, 0);
+    for (name, value) in &headers {
+        request = request.header(*name, *value);
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Failed to start upload: {}", e))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("Failed to start upload ({}): {}", status, body.trim()));
+    }
+
+    response
+        .headers()
+        .get(reqwest::header::LOCATION)
+        .and_then(|location| location.to_str().ok())
+        .map(|location| location.to_string())
+        .ok_or_else(|| "GCS did not return an upload session".to_string())
+}
+
+// Interpret a response to a resumable PUT: None once the upload is complete,
+// otherwise the offset GCS has persisted up to
+async fn resumable_offset(response: reqwest::Response) -> Result<Option<u64>, UploadError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(None);
+    }
+
+    // 308 Resume Incomplete; the Range header ("bytes=0-N") is absent if nothing was stored
+    if status == reqwest::StatusCode::PERMANENT_REDIRECT {
+        let persisted = response
+            .headers()
+            .get(reqwest::header::RANGE)
+            .and_then(|range| range.to_str().ok())
+            .and_then(|range| range.rsplit('-').next())
+            .and_then(|end| end.parse::<u64>().ok())
+            .map_or(0, |end| end + 1);
+        return Ok(Some(persisted));
+    }
+
+    let body = response.text().await.unwrap_or_default();
+    let message = format!("Upload failed with status {}: {}", status, body.trim());
+    if status == reqwest::StatusCode::NOT_FOUND || status == reqwest::StatusCode::GONE {
+        Err(UploadError::Permanent("Upload session expired".to_string()))
+    } else if storage::is_transient(status) {
+        Err(UploadError::Transient(message))
+    } else {
+        Err(UploadError::Permanent(message))
+    }
+}
+
+// Check how much of the file GCS already has
+async fn query_offset(client: &reqwest::Client, session_uri: &str, total_bytes: u64) -> Result<Option<u64>, UploadError> {
+    let response = client
+        .put(session_uri)
+        .header(reqwest::header::CONTENT_RANGE, format!("bytes */{}", total_bytes))
+        .header(reqwest::header::CONTENT_LENGTH, 0)
+        .send()
+        .await
+        .map_err(|e| UploadError::Transient(format!("Failed to check upload: {}", e)))?;
+    resumable_offset(response).await
+}
+
+// Send the chunk starting at `offset`, returning the new persisted offset
+// The final chunk carries the file's checksum (`x-goog-hash`) for GCS to verify
+async fn put_chunk(
+    client: &reqwest::Client,
+    session_uri: &str,
+    file_path: &str,
+    hash: &str,
+    offset: u64,
+    total_bytes: u64,
+) -> Result<Option<u64>, UploadError> {
+    if offset >= total_bytes {
+        return query_offset(client, session_uri, total_bytes).await;
+    }
+
+    let length = RESUMABLE_CHUNK_BYTES.min(total_bytes - offset);
+    let mut chunk = vec![0u8; length as usize];
+    let mut file = tokio::fs::File::open(file_path)
+        .await
+        .map_err(|e| UploadError::Permanent(format!("Failed to open file: {}", e)))?;
+    file.seek(SeekFrom::Start(offset))
+        .await
+        .map_err(|e| UploadError::Permanent(format!("Failed to read file: {}", e)))?;
+    file.read_exact(&mut chunk)
+        .await
+        .map_err(|e| UploadError::Permanent(format!("Failed to read file: {}", e)))?;
+
+    let mut request = client
+        .put(session_uri)
+        .header(reqwest::header::CONTENT_RANGE, format!("bytes {}-{}/{}", offset, offset + length - 1, total_bytes))
+        .header(reqwest::header::CONTENT_LENGTH, length);
+    if offset + length == total_bytes {
+        request = request.header("x-goog-hash", hash);
+    }
+
+    let pieces: Vec<Result<Vec<u8>, std::io::Error>> = chunk
+        .chunks(storage::UPLOAD_CHUNK_BYTES)
+        .map(|piece| Ok(piece.to_vec()))
+        .collect();
+    let body = reqwest::Body::wrap_stream(storage::throttled(futures_util::stream::iter(pieces)));
+
+    let response = request
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| UploadError::Transient(format!("Upload failed: {}", e)))?;
+    resumable_offset(response).await
+}
+
+// Command to upload a large file with a GCS resumable upload session
+// The session is saved, so calling this again for the same file after a
+// failure or app restart continues from the last acknowledged chunk.
+// `metadata` takes effect when a new session starts; a resumed upload keeps
+// what its session was started with.
+// Progress is reported with `gcs://upload-progress` events. Returns the object's URL and checksum.
+#[tauri::command]
+pub async fn resumable_upload_to_gcs(
+    window: Window,
+    sessions: State<'_, UploadSessions>,
+    bucket: String,
+    object: String,
+    file_path: String,
+    metadata: Option<ObjectMetadata>,
+) -> Result<GcsUpload, String> {
+    let metadata_headers = metadata.unwrap_or_default().headers()?;
+    let stamp = FileStamp::for_path(&file_path)?;
+    let total_bytes = stamp.size as u64;
+    let content_type = storage::content_type_for(&file_path);
+    let crc32c = crc32c_base64(&file_path).await?;
+    let hash = format!("crc32c={}", crc32c);
+    let client = network::client(&window.app_handle());
+
+    // Continue a saved session unless it has expired
+    let saved = sessions.get(&bucket, &object, &file_path, stamp);
+    let resumed = match &saved {
+        Some(session_uri) => match query_offset(&client, session_uri, total_bytes).await {
+            Ok(offset) => Some((session_uri.clone(), offset)),
+            Err(UploadError::Permanent(_)) => None,
+            Err(UploadError::Transient(message)) => return Err(message),
+        },
+        None => None,
+    };
+    let (session_uri, mut offset) = match resumed {
+        Some(resumed) => resumed,
+        None => {
+            let signer = load_signer(&window.app_handle())?;
+            let session_uri = initiate_resumable(&client, &signer, &bucket, &object, content_type, &metadata_headers).await?;
+            sessions.put(&bucket, &object, &file_path, stamp, &session_uri)?;
+            (session_uri, Some(0))
+        }
+    };
+
+    let mut backoff = INITIAL_BACKOFF;
+    let mut failures = 0;
+    while let Some(current) = offset {
+        match put_chunk(&client, &session_uri, &file_path, &hash, current, total_bytes).await {
+            Ok(next) => {
+                offset = next;
+                backoff = INITIAL_BACKOFF;
+                failures = 0;
+                let _ = window.emit(PROGRESS_EVENT, UploadProgress {
+                    object: object.clone(),
+                    bytes_sent: next.unwrap_or(total_bytes),
+                    total_bytes,
+                });
+            }
+            Err(UploadError::Transient(_)) if failures + 1 < MAX_UPLOAD_ATTEMPTS => {
+                failures += 1;
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+                // The failed chunk may have been partly stored
+                if let Ok(persisted) = query_offset(&client, &session_uri, total_bytes).await {
+                    offset = persisted;
+                }
+            }
+            Err(UploadError::Permanent(message)) => {
+                sessions.remove(&bucket, &object, &file_path)?;
+                return Err(message);
+            }
+            Err(UploadError::Transient(message)) => return Err(message),
+        }
+    }
+
+    sessions.remove(&bucket, &object, &file_path)?;
+    Ok(GcsUpload { url: object_url(&bucket, &object), crc32c })
+}
+
+// Command to list resumable uploads that were interrupted and can be continued
+#[tauri::command]
+pub fn list_pending_uploads(sessions: State<UploadSessions>) -> Result<Vec<PendingUpload>, String> {
+    sessions.pending()
+}
+
+// Lifetime of signed URLs for the app's own list/delete requests
+const REQUEST_URL_SECONDS: i64 = 300;
+
+// One object from a bucket listing
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GcsObject {
+    #[serde(rename(deserialize = "Key"))]
+    pub name: String,
+    #[serde(rename(deserialize = "Size"))]
+    pub size: u64,
+    // RFC 3339 timestamp
+    #[serde(rename(deserialize = "LastModified"))]
+    pub last_modified: String,
+}
+
+// One page of an XML API bucket listing
+#[derive(Debug, Deserialize)]
+struct ListBucketResult {
+    #[serde(rename = "Contents", default)]
+    contents: Vec<GcsObject>,
+    #[serde(rename = "IsTruncated", default)]
+    is_truncated: bool,
+    #[serde(rename = "NextMarker")]
+    next_marker: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeleteFailure {
+    name: String,
+    error: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CleanupResult {
+    deleted: Vec<String>,
+    failed: Vec<DeleteFailure>,
+}
+
+// List every object under `prefix`, following pagination markers
+async fn list_objects(client: &reqwest::Client, signer: &GcsSigner, bucket: &str, prefix: &str) -> Result<Vec<GcsObject>, String> {
+    let mut objects = Vec::new();
+    let mut marker: Option<String> = None;
+
+    loop {
+        let mut query = vec![("prefix", prefix)];
+        if let Some(marker) = &marker {
+            query.push(("marker", marker.as_str()));
+        }
+        let url = signed_url_v4(signer, "GET", bucket, "", &[], &query, REQUEST_URL_SECONDS).await?;
+
+        let response = client
+            .get(&url)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to list bucket: {}", e))?;
+        let status = response.status();
+        let body = response.text()
+            .await
+            .map_err(|e| format!("Failed to read bucket listing: {}", e))?;
+        if !status.is_success() {
+            return Err(format!("Failed to list bucket ({}): {}", status, body.trim()));
+        }
+
+        let page: ListBucketResult = quick_xml::de::from_str(&body)
+            .map_err(|e| format!("Failed to parse bucket listing: {}", e))?;
+        let last_key = page.contents.last().map(|object| object.name.clone());
+        objects.extend(page.contents);
+
+        if !page.is_truncated {
+            return Ok(objects);
+        }
+        // NextMarker is only sent with a delimiter; otherwise continue after the last key
+        marker = page.next_marker.or(last_key);
+        if marker.is_none() {
+            return Ok(objects);
+        }
+    }
+}
+
+// Delete one object; an object that is already gone counts as deleted
+async fn delete_object(client: &reqwest::Client, signer: &GcsSigner, bucket: &str, name: &str) -> Result<(), String> {
+    let url = signed_url_v4(signer, "DELETE", bucket, name, &[], &[], REQUEST_URL_SECONDS).await?;
+    let response = client
+        .delete(&url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to delete {}: {}", name, e))?;
+
+    let status = response.status();
+    if status.is_success() || status == reqwest::StatusCode::NOT_FOUND {
+        return Ok(());
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(format!("Failed to delete {} ({}): {}", name, status, body.trim()))
+}
+
+// Command to delete an object from a bucket
+#[tauri::command]
+pub async fn delete_gcs_object(app: AppHandle, bucket: String, name: String) -> Result<(), String> {
+    let signer = load_signer(&app)?;
+    delete_object(&network::client(&app), &signer, &bucket, &name).await
+}
+
+// Command to delete objects under `prefix` last modified more than
+// `older_than_hours` ago, e.g. temporary uploads for Lens lookups.
+// A prefix is required so the whole bucket can't be wiped by mistake.
+#[tauri::command]
+pub async fn cleanup_expired_uploads(app: AppHandle, bucket: String, prefix: String, older_than_hours: u32) -> Result<CleanupResult, String> {
+    if prefix.is_empty() {
+        return Err("A prefix is required for cleanup".to_string());
+    }
+
+    let signer = load_signer(&app)?;
+    let client = network::client(&app);
+    let cutoff = Utc::now() - chrono::Duration::hours(older_than_hours as i64);
+
+    let mut result = CleanupResult { deleted: Vec::new(), failed: Vec::new() };
+    for object in list_objects(&client, &signer, &bucket, &prefix).await? {
+        let expired = DateTime::parse_from_rfc3339(&object.last_modified)
+            .is_ok_and(|modified| modified < cutoff);
+        if !expired {
+            continue;
+        }
+        match delete_object(&client, &signer, &bucket, &object.name).await {
+            Ok(()) => result.deleted.push(object.name),
+            Err(error) => result.failed.push(DeleteFailure { name: object.name, error }),
+        }
+    }
+    Ok(result)
+}
+
+// Command to list the objects under `prefix` with their sizes and
+// modification times, e.g. to see what's already uploaded for a listing
+#[tauri::command]
+pub async fn list_gcs_objects(app: AppHandle, bucket: String, prefix: Option<String>) -> Result<Vec<GcsObject>, String> {
+    let signer = load_signer(&app)?;
+    list_objects(&network::client(&app), &signer, &bucket, prefix.as_deref().unwrap_or("")).await
+}
+
+// GCS as a storage provider, signing V4 URLs with the registered service account
+pub struct GcsProvider {
+    signer: Arc<GcsSigner>,
+    bucket: String,
+}
+
+impl GcsProvider {
+    pub fn new(app: &AppHandle, bucket: String) -> Result<GcsProvider, String> {
+        Ok(GcsProvider { signer: load_signer(app)?, bucket })
+    }
+
+    // Presign a PUT of `file_path` carrying its CRC32C, which GCS checks the
+    // upload against as in `upload_to_gcs`
+    pub async fn presign_upload(&self, object: &str, file_path: &str, content_type: &str, expires_in_seconds: i64) -> Result<PresignedRequest, String> {
+        let hash = format!("crc32c={}", crc32c_base64(file_path).await?);
+        let headers = [("Content-Type", content_type), ("x-goog-hash", hash.as_str())];
+        let url = signed_url_v4(&self.signer, "PUT", &self.bucket, object, &headers, &[], expires_in_seconds).await?;
+        Ok(PresignedRequest::new("PUT", url, &headers))
+    }
+}
+
+impl StorageProvider for GcsProvider {
+    async fn presign(&self, method: &str, object: &str, content_type: Option<&str>, expires_in_seconds: i64) -> Result<PresignedRequest, String> {
+        let headers: Vec<(&str, &str)> = content_type.map(|value| ("Content-Type", value)).into_iter().collect();
+        let url = signed_url_v4(&self.signer, method, &self.bucket, object, &headers, &[], expires_in_seconds).await?;
+        Ok(PresignedRequest::new(method, url, &headers))
+    }
+
+    fn object_url(&self, object: &str) -> String {
+        object_url(&self.bucket, object)
+    }
+}
@@ -0,0 +1,250 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use base64::{Engine as _, engine::general_purpose};
+use chrono::Utc;
+use rsa::pkcs1v15::SigningKey;
+use rsa::sha2::Sha256;
+use rsa::signature::{SignatureEncoding, Signer};
+use serde::Deserialize;
+
+const TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+
+const METADATA_TOKEN_URL: &str =
+    "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token";
+
+const IAM_CREDENTIALS_URL: &str = "https://iamcredentials.googleapis.com/v1/projects/-/serviceAccounts";
+
+// Scope requested for tokens made from a service account key
+const CLOUD_PLATFORM_SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+
+// Lifetime requested for service account tokens, the most Google allows
+const JWT_LIFETIME_SECONDS: i64 = 3600;
+
+// Tokens are refreshed this long before they expire
+const EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+// Application default credentials file, as written by
+// `gcloud auth application-default login`
+#[derive(Debug, Deserialize)]
+struct AdcFile {
+    #[serde(rename = "type")]
+    kind: String,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+    refresh_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SignBlobResponse {
+    signed_blob: String,
+}
+
+// Where OAuth access tokens come from
+enum TokenSource {
+    // Token supplied by the user; it can't be refreshed
+    Fixed(String),
+    // User credentials from application default credentials
+    AuthorizedUser {
+        client_id: String,
+        client_secret: String,
+        refresh_token: String,
+    },
+    // GCE/GKE metadata server, which also serves workload identity
+    Metadata,
+    // Service account key, exchanged for tokens with a signed JWT
+    ServiceAccount {
+        client_email: String,
+        key: Box<SigningKey<Sha256>>,
+    },
+}
+
+struct CachedToken {
+    token: String,
+    expires_at: Option<Instant>,
+}
+
+// Google OAuth access tokens, refreshed as they expire
+pub struct AccessTokens {
+    client: reqwest::Client,
+    source: TokenSource,
+    cached: Mutex<Option<CachedToken>>,
+}
+
+// Application default credentials path: $GOOGLE_APPLICATION_CREDENTIALS, or
+// where gcloud keeps them
+fn adc_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("GOOGLE_APPLICATION_CREDENTIALS") {
+        return Some(PathBuf::from(path));
+    }
+    let config_dir = if cfg!(windows) {
+        PathBuf::from(env::var_os("APPDATA")?)
+    } else {
+        PathBuf::from(env::var_os("HOME")?).join(".config")
+    };
+    Some(config_dir.join("gcloud").join("application_default_credentials.json"))
+}
+
+// RS256-signed JWT asking for a cloud-platform token as `client_email`
+fn jwt_assertion(client_email: &str, key: &SigningKey<Sha256>) -> String {
+    let issued_at = Utc::now().timestamp();
+    let header = serde_json::json!({ "alg": "RS256", "typ": "JWT" });
+    let claims = serde_json::json!({
+        "iss": client_email,
+        "scope": CLOUD_PLATFORM_SCOPE,
+        "aud": TOKEN_URL,
+        "iat": issued_at,
+        "exp": issued_at + JWT_LIFETIME_SECONDS,
+    });
+    let unsigned = format!(
+        "{}.{}",
+        general_purpose::URL_SAFE_NO_PAD.encode(header.to_string()),
+        general_purpose::URL_SAFE_NO_PAD.encode(claims.to_string())
+    );
+    let signature = key.sign(unsigned.as_bytes()).to_bytes();
+    format!("{}.{}", unsigned, general_purpose::URL_SAFE_NO_PAD.encode(signature))
+}
+
+impl AccessTokens {
+    // Use a token obtained elsewhere, e.g. from a user OAuth flow
+    pub fn fixed(client: reqwest::Client, token: String) -> AccessTokens {
+        AccessTokens::new(client, TokenSource::Fixed(token))
+    }
+
+    // Find application default credentials: a gcloud user login, or failing
+    // that the metadata server when running on Google Cloud
+    pub fn application_default(client: reqwest::Client) -> Result<AccessTokens, String> {
+        let Some(path) = adc_path().filter(|path| path.exists()) else {
+            return Ok(AccessTokens::new(client, TokenSource::Metadata));
+        };
+
+        let json = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read application default credentials: {}", e))?;
+        let adc: AdcFile = serde_json::from_str(&json)
+            .map_err(|e| format!("Failed to parse application default credentials: {}", e))?;
+        match (adc.kind.as_str(), adc.client_id, adc.client_secret, adc.refresh_token) {
+            ("authorized_user", Some(client_id), Some(client_secret), Some(refresh_token)) => {
+                Ok(AccessTokens::new(client, TokenSource::AuthorizedUser { client_id, client_secret, refresh_token }))
+            }
+            ("service_account", ..) => Err(format!(
+                "{} is a service account key; register it as a key file instead",
+                path.display()
+            )),
+            (kind, ..) => Err(format!("Unsupported application default credentials type: {}", kind)),
+        }
+    }
+
+    // Tokens for a service account from its private key
+    pub fn service_account(client: reqwest::Client, client_email: String, key: SigningKey<Sha256>) -> AccessTokens {
+        AccessTokens::new(client, TokenSource::ServiceAccount { client_email, key: Box::new(key) })
+    }
+
+    fn new(client: reqwest::Client, source: TokenSource) -> AccessTokens {
+        AccessTokens {
+            client,
+            source,
+            cached: Mutex::new(None),
+        }
+    }
+
+    async fn fetch(&self) -> Result<CachedToken, String> {
+        let request = match &self.source {
+            TokenSource::Fixed(token) => return Ok(CachedToken { token: token.clone(), expires_at: None }),
+            TokenSource::AuthorizedUser { client_id, client_secret, refresh_token } => self.client
+                .post(TOKEN_URL)
+                .form(&[
+                    ("grant_type", "refresh_token"),
+                    ("client_id", client_id),
+                    ("client_secret", client_secret),
+                    ("refresh_token", refresh_token),
+                ]),
+            TokenSource::Metadata => self.client
+                .get(METADATA_TOKEN_URL)
+                .header("Metadata-Flavor", "Google"),
+            TokenSource::ServiceAccount { client_email, key } => self.client
+                .post(TOKEN_URL)
+                .form(&[
+                    ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                    ("assertion", &jwt_assertion(client_email, key)),
+                ]),
+        };
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("Failed to get an access token: {}", e))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("Failed to get an access token ({}): {}", status, body.trim()));
+        }
+        let token: TokenResponse = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse access token: {}", e))?;
+
+        Ok(CachedToken {
+            token: token.access_token,
+            expires_at: token
+                .expires_in
+                .map(|seconds| Instant::now() + Duration::from_secs(seconds).saturating_sub(EXPIRY_MARGIN)),
+        })
+    }
+
+    // A valid access token, refreshed if the cached one has expired
+    pub async fn token(&self) -> Result<String, String> {
+        if let Ok(cached) = self.cached.lock() {
+            if let Some(cached) = cached.as_ref() {
+                if cached.expires_at.is_none_or(|expires_at| Instant::now() < expires_at) {
+                    return Ok(cached.token.clone());
+                }
+            }
+        }
+
+        let fresh = self.fetch().await?;
+        let token = fresh.token.clone();
+        if let Ok(mut cached) = self.cached.lock() {
+            *cached = Some(fresh);
+        }
+        Ok(token)
+    }
+
+    // Sign `data` as `service_account` with the IAM signBlob API (RSA-SHA256,
+    // the same signature a local key would make). The token's principal needs
+    // the Service Account Token Creator role on that account.
+    pub async fn sign_blob(&self, service_account: &str, data: &[u8]) -> Result<Vec<u8>, String> {
+        let token = self.token().await?;
+        let response = self.client
+            .post(format!("{}/{}:signBlob", IAM_CREDENTIALS_URL, urlencoding::encode(service_account)))
+            .bearer_auth(token)
+            .json(&serde_json::json!({ "payload": general_purpose::STANDARD.encode(data) }))
+            .send()
+            .await
+            .map_err(|e| format!("Failed to sign with IAM: {}", e))?;
+
+        let status = response.status();
+        if status == reqwest::StatusCode::UNAUTHORIZED && matches!(self.source, TokenSource::Fixed(_)) {
+            return Err("The access token has expired; sign in again".to_string());
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("IAM signBlob failed ({}): {}", status, body.trim()));
+        }
+        let signed: SignBlobResponse = response
+            .json()
+            .await
+            .map_err(|e| format!("Failed to parse IAM signature: {}", e))?;
+        general_purpose::STANDARD
+            .decode(signed.signed_blob)
+            .map_err(|e| format!("Failed to decode IAM signature: {}", e))
+    }
+}
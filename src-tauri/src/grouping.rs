@@ -0,0 +1,256 @@
+// Transitive clustering of photos by pairwise similarity
+
+// Default gap between captures that starts a new item in time-assisted mode
+pub const DEFAULT_TIME_GAP_SECONDS: i64 = 60;
+
+// Similarity bonus for photos taken in the same burst in time-assisted mode
+const BURST_SIMILARITY_BONUS: f64 = 0.1;
+
+// How photos are compared when grouping
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupingMode {
+    // Perceptual hash similarity only
+    Visual,
+    // Visual similarity combined with EXIF capture-time bursts
+    TimeAssisted,
+    // Cosine similarity of CLIP image embeddings instead of perceptual hashes
+    Embedding,
+}
+
+impl GroupingMode {
+    pub fn parse(name: Option<&str>) -> Result<GroupingMode, String> {
+        match name.map(|n| n.to_lowercase()).as_deref() {
+            None | Some("visual") => Ok(GroupingMode::Visual),
+            Some("time_assisted") => Ok(GroupingMode::TimeAssisted),
+            Some("embedding") => Ok(GroupingMode::Embedding),
+            Some(other) => Err(format!("Unknown grouping mode: {}", other)),
+        }
+    }
+}
+
+// Split photos into capture bursts: sorted by time, a gap larger than
+// `gap_seconds` starts a new burst. Photos without a timestamp get None.
+pub fn assign_bursts(timestamps: &[Option<i64>], gap_seconds: i64) -> Vec<Option<usize>> {
+    let mut order: Vec<(usize, i64)> = timestamps
+        .iter()
+        .enumerate()
+        .filter_map(|(i, time)| time.map(|t| (i, t)))
+        .collect();
+    order.sort_by_key(|&(i, time)| (time, i));
+
+    let mut bursts = vec![None; timestamps.len()];
+    let mut burst = 0;
+    let mut previous: Option<i64> = None;
+    for (i, time) in order {
+        if let Some(previous) = previous {
+            if time - previous > gap_seconds {
+                burst += 1;
+            }
+        }
+        bursts[i] = Some(burst);
+        previous = Some(time);
+    }
+
+    bursts
+}
+
+// Combine visual similarity with burst membership. Photos from different
+// bursts are never the same item; photos in the same burst get a bonus so
+// shots of one item from different angles still link up. Photos without
+// capture times fall back to visual similarity alone.
+pub fn time_adjusted_similarity(visual: f64, burst_a: Option<usize>, burst_b: Option<usize>) -> f64 {
+    match (burst_a, burst_b) {
+        (Some(a), Some(b)) if a != b => 0.0,
+        (Some(_), Some(_)) => (visual + BURST_SIMILARITY_BONUS).min(1.0),
+        _ => visual,
+    }
+}
+
+// A cluster of photo indices (in input order) with its confidence score
+#[derive(Debug, Clone)]
+pub struct Cluster {
+    pub members: Vec<usize>,
+    pub confidence: f64,
+}
+
+// Disjoint-set forest with path compression and union by rank
+struct UnionFind {
+    parent: Vec<usize>,
+    rank: Vec<u8>,
+}
+
+impl UnionFind {
+    fn new(size: usize) -> UnionFind {
+        UnionFind {
+            parent: (0..size).collect(),
+            rank: vec![0; size],
+        }
+    }
+
+    fn find(&mut self, x: usize) -> usize {
+        let mut root = x;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+
+        // Point every node on the path straight at the root
+        let mut node = x;
+        while self.parent[node] != root {
+            let next = self.parent[node];
+            self.parent[node] = root;
+            node = next;
+        }
+
+        root
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let root_a = self.find(a);
+        let root_b = self.find(b);
+        if root_a == root_b {
+            return;
+        }
+
+        if self.rank[root_a] < self.rank[root_b] {
+            self.parent[root_a] = root_b;
+        } else if self.rank[root_a] > self.rank[root_b] {
+            self.parent[root_b] = root_a;
+        } else {
+            self.parent[root_b] = root_a;
+            self.rank[root_a] += 1;
+        }
+    }
+}
+
+// Cluster `count` items, linking every pair whose similarity meets the threshold.
+// Clusters are transitive (A~B and B~C puts A, B and C together regardless of
+// input order) and are returned ordered by their first member.
+//
+// Confidence is the mean pairwise similarity inside a cluster. For a single
+// photo it is how far it sits from its nearest neighbour (1.0 if it is alone).
+pub fn cluster_by_similarity<F>(count: usize, threshold: f64, similarity: F) -> Vec<Cluster>
+where
+    F: Fn(usize, usize) -> f64,
+{
+    let mut similarities = vec![0.0; count * count];
+    let mut sets = UnionFind::new(count);
+
+    for i in 0..count {
+        for j in (i + 1)..count {
+            let value = similarity(i, j);
+            similarities[i * count + j] = value;
+            similarities[j * count + i] = value;
+            if value >= threshold {
+                sets.union(i, j);
+            }
+        }
+    }
+
+    // Collect members per root, keeping clusters in first-member order
+    let mut root_to_cluster: Vec<Option<usize>> = vec![None; count];
+    let mut clusters: Vec<Vec<usize>> = Vec::new();
+    for i in 0..count {
+        let root = sets.find(i);
+        match root_to_cluster[root] {
+            Some(index) => clusters[index].push(i),
+            None => {
+                root_to_cluster[root] = Some(clusters.len());
+                clusters.push(vec![i]);
+            }
+        }
+    }
+
+    clusters
+        .into_iter()
+        .map(|members| {
+            let confidence = if members.len() > 1 {
+                mean_pairwise_similarity(&members, |i, j| similarities[i * count + j])
+            } else if count > 1 {
+                let i = members[0];
+                let nearest = (0..count)
+                    .filter(|&j| j != i)
+                    .map(|j| similarities[i * count + j])
+                    .fold(f64::NEG_INFINITY, f64::max);
+                1.0 - nearest
+            } else {
+                1.0
+            };
+
+            Cluster { members, confidence }
+        })
+        .collect()
+}
+
+// Mean similarity over all pairs of members (1.0 for fewer than two)
+pub fn mean_pairwise_similarity<F>(members: &[usize], similarity: F) -> f64
+where
+    F: Fn(usize, usize) -> f64,
+{
+    let mut total = 0.0;
+    let mut pairs = 0;
+    for (a, &i) in members.iter().enumerate() {
+        for &j in &members[(a + 1)..] {
+            total += similarity(i, j);
+            pairs += 1;
+        }
+    }
+
+    if pairs == 0 {
+        1.0
+    } else {
+        total / pairs as f64
+    }
+}
+
+// Next free numeric suffix for "item-N" group ids
+pub fn next_group_number<'a, I>(ids: I) -> usize
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    ids.into_iter()
+        .filter_map(|id| id.strip_prefix("item-"))
+        .filter_map(|n| n.parse::<usize>().ok())
+        .max()
+        .map_or(1, |n| n + 1)
+}
+
+// Suggest a similarity threshold from all pairwise similarities.
+// Pairs of the same item and of different items form two humps; Otsu's method
+// picks the cut that best separates them.
+pub fn suggest_threshold(similarities: &[f64]) -> Option<f64> {
+    const BINS: usize = 64;
+    if similarities.is_empty() {
+        return None;
+    }
+
+    let mut histogram = [0usize; BINS + 1];
+    for similarity in similarities {
+        histogram[(similarity.clamp(0.0, 1.0) * BINS as f64).round() as usize] += 1;
+    }
+
+    let total = similarities.len() as f64;
+    let weighted_total: f64 = histogram.iter().enumerate().map(|(bin, &count)| bin as f64 * count as f64).sum();
+
+    let mut best = None;
+    let mut best_variance = 0.0;
+    let mut below_count = 0.0;
+    let mut below_weighted = 0.0;
+    for (bin, &count) in histogram.iter().enumerate().take(BINS) {
+        below_count += count as f64;
+        below_weighted += bin as f64 * count as f64;
+        let above_count = total - below_count;
+        if below_count == 0.0 || above_count == 0.0 {
+            continue;
+        }
+
+        let below_mean = below_weighted / below_count;
+        let above_mean = (weighted_total - below_weighted) / above_count;
+        let variance = below_count * above_count * (below_mean - above_mean).powi(2);
+        if variance > best_variance {
+            best_variance = variance;
+            best = Some((bin + 1) as f64 / BINS as f64);
+        }
+    }
+
+    best
+}
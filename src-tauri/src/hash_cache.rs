@@ -0,0 +1,232 @@
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::time::UNIX_EPOCH;
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use crate::db::Database;
+use crate::embeddings;
+use crate::hashing::{ColorSignature, HashAlgorithm, ImageHash};
+use crate::quality::QualityScore;
+
+// SQLite-backed cache of perceptual hashes keyed by path + algorithm + mtime + file size
+pub struct HashCache {
+    conn: Arc<Mutex<Connection>>,
+}
+
+// File identity used to detect whether a cached hash is still valid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStamp {
+    pub mtime_nanos: i64,
+    pub size: i64,
+}
+
+impl FileStamp {
+    pub fn for_path(path: &str) -> Result<FileStamp, String> {
+        let metadata = fs::metadata(path)
+            .map_err(|e| format!("Failed to read metadata for {}: {}", path, e))?;
+        let modified = metadata.modified()
+            .map_err(|e| format!("Failed to read modification time for {}: {}", path, e))?;
+        let mtime_nanos = modified.duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos() as i64)
+            .unwrap_or(0);
+
+        Ok(FileStamp {
+            mtime_nanos,
+            size: metadata.len() as i64,
+        })
+    }
+}
+
+// Hashes cached before wider hashes were added are 64-bit integers in
+// perceptual_hashes; they're carried over from the attached "legacy" hash
+// cache as hex so photos aren't rehashed
+pub fn import_integer_hashes(tx: &Transaction) -> Result<(), String> {
+    let exists: bool = tx
+        .query_row("SELECT EXISTS (SELECT 1 FROM legacy.sqlite_master WHERE type = 'table' AND name = 'perceptual_hashes')", [], |row| row.get(0))
+        .map_err(|e| format!("Failed to read old hash cache: {}", e))?;
+    if !exists {
+        return Ok(());
+    }
+    let mut select = tx.prepare("SELECT path, algorithm, mtime_nanos, size, hash FROM legacy.perceptual_hashes")
+        .map_err(|e| format!("Failed to read old hash cache: {}", e))?;
+    let rows = select
+        .query_map([], |row| Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, i64>(2)?,
+            row.get::<_, i64>(3)?,
+            row.get::<_, i64>(4)?,
+        )))
+        .map_err(|e| format!("Failed to read old hash cache: {}", e))?;
+    let mut insert = tx.prepare(
+        "INSERT OR IGNORE INTO main.image_hashes (path, algorithm, mtime_nanos, size, hash)
+         VALUES (?1, ?2, ?3, ?4, ?5)"
+    ).map_err(|e| format!("Failed to prepare hash cache insert: {}", e))?;
+    for row in rows {
+        let (path, algorithm, mtime_nanos, size, hash) = row
+            .map_err(|e| format!("Failed to read old hash cache: {}", e))?;
+        insert.execute(params![path, algorithm, mtime_nanos, size, ImageHash::from(hash as u64).to_hex()])
+            .map_err(|e| format!("Failed to write hash cache: {}", e))?;
+    }
+    Ok(())
+}
+
+impl HashCache {
+    pub fn new(db: &Database) -> HashCache {
+        HashCache { conn: db.connection() }
+    }
+
+    // Look up a cached hash, returning None if missing or the file changed
+    pub fn get(&self, path: &str, algorithm: HashAlgorithm, stamp: FileStamp) -> Option<ImageHash> {
+        let conn = self.conn.lock().ok()?;
+        conn.query_row(
+            "SELECT hash FROM image_hashes
+             WHERE path = ?1 AND algorithm = ?2 AND mtime_nanos = ?3 AND size = ?4",
+            params![path, algorithm.name(), stamp.mtime_nanos, stamp.size],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()
+        .ok()
+        .flatten()
+        .and_then(|hash| ImageHash::from_hex(&hash))
+    }
+
+    // Store hashes for a batch of files in a single transaction
+    pub fn put_many(&self, algorithm: HashAlgorithm, entries: &[(String, FileStamp, ImageHash)]) -> Result<(), String> {
+        let mut conn = self.conn.lock()
+            .map_err(|_| "Hash cache lock poisoned".to_string())?;
+        let tx = conn.transaction()
+            .map_err(|e| format!("Failed to start hash cache transaction: {}", e))?;
+
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO image_hashes (path, algorithm, mtime_nanos, size, hash)
+                 VALUES (?1, ?2, ?3, ?4, ?5)"
+            ).map_err(|e| format!("Failed to prepare hash cache insert: {}", e))?;
+
+            for (path, stamp, hash) in entries {
+                stmt.execute(params![path, algorithm.name(), stamp.mtime_nanos, stamp.size, hash.to_hex()])
+                    .map_err(|e| format!("Failed to write hash cache: {}", e))?;
+            }
+        }
+
+        tx.commit()
+            .map_err(|e| format!("Failed to commit hash cache: {}", e))
+    }
+
+    // Look up cached quality scores, returning None if missing or the file changed
+    pub fn get_quality(&self, path: &str, stamp: FileStamp) -> Option<QualityScore> {
+        let conn = self.conn.lock().ok()?;
+        conn.query_row(
+            "SELECT sharpness, exposure, overall FROM photo_quality
+             WHERE path = ?1 AND mtime_nanos = ?2 AND size = ?3",
+            params![path, stamp.mtime_nanos, stamp.size],
+            |row| Ok(QualityScore {
+                sharpness: row.get(0)?,
+                exposure: row.get(1)?,
+                overall: row.get(2)?,
+            }),
+        )
+        .optional()
+        .ok()
+        .flatten()
+    }
+
+    // Store quality scores for a batch of files in a single transaction
+    pub fn put_quality_many(&self, entries: &[(String, FileStamp, QualityScore)]) -> Result<(), String> {
+        let mut conn = self.conn.lock()
+            .map_err(|_| "Hash cache lock poisoned".to_string())?;
+        let tx = conn.transaction()
+            .map_err(|e| format!("Failed to start hash cache transaction: {}", e))?;
+
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO photo_quality (path, mtime_nanos, size, sharpness, exposure, overall)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)"
+            ).map_err(|e| format!("Failed to prepare hash cache insert: {}", e))?;
+
+            for (path, stamp, score) in entries {
+                stmt.execute(params![path, stamp.mtime_nanos, stamp.size, score.sharpness, score.exposure, score.overall])
+                    .map_err(|e| format!("Failed to write hash cache: {}", e))?;
+            }
+        }
+
+        tx.commit()
+            .map_err(|e| format!("Failed to commit hash cache: {}", e))
+    }
+
+    // Look up a cached color signature, returning None if missing or the file changed
+    pub fn get_color(&self, path: &str, stamp: FileStamp) -> Option<ColorSignature> {
+        let conn = self.conn.lock().ok()?;
+        conn.query_row(
+            "SELECT signature FROM photo_colors
+             WHERE path = ?1 AND mtime_nanos = ?2 AND size = ?3",
+            params![path, stamp.mtime_nanos, stamp.size],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()
+        .ok()
+        .flatten()
+        .and_then(|signature| ColorSignature::from_hex(&signature))
+    }
+
+    // Store color signatures for a batch of files in a single transaction
+    pub fn put_color_many(&self, entries: &[(String, FileStamp, ColorSignature)]) -> Result<(), String> {
+        let mut conn = self.conn.lock()
+            .map_err(|_| "Hash cache lock poisoned".to_string())?;
+        let tx = conn.transaction()
+            .map_err(|e| format!("Failed to start hash cache transaction: {}", e))?;
+
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO photo_colors (path, mtime_nanos, size, signature)
+                 VALUES (?1, ?2, ?3, ?4)"
+            ).map_err(|e| format!("Failed to prepare hash cache insert: {}", e))?;
+
+            for (path, stamp, signature) in entries {
+                stmt.execute(params![path, stamp.mtime_nanos, stamp.size, signature.to_hex()])
+                    .map_err(|e| format!("Failed to write hash cache: {}", e))?;
+            }
+        }
+
+        tx.commit()
+            .map_err(|e| format!("Failed to commit hash cache: {}", e))
+    }
+
+    // Look up a cached image embedding from the given model
+    pub fn get_embedding(&self, path: &str, model: &str, stamp: FileStamp) -> Option<Vec<f32>> {
+        let conn = self.conn.lock().ok()?;
+        conn.query_row(
+            "SELECT embedding FROM photo_embeddings
+             WHERE path = ?1 AND model = ?2 AND mtime_nanos = ?3 AND size = ?4",
+            params![path, model, stamp.mtime_nanos, stamp.size],
+            |row| row.get::<_, Vec<u8>>(0),
+        )
+        .optional()
+        .ok()
+        .flatten()
+        .map(|bytes| embeddings::from_bytes(&bytes))
+    }
+
+    // Store embeddings for a batch of files in a single transaction
+    pub fn put_embedding_many(&self, model: &str, entries: &[(String, FileStamp, Vec<f32>)]) -> Result<(), String> {
+        let mut conn = self.conn.lock()
+            .map_err(|_| "Hash cache lock poisoned".to_string())?;
+        let tx = conn.transaction()
+            .map_err(|e| format!("Failed to start hash cache transaction: {}", e))?;
+
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO photo_embeddings (path, model, mtime_nanos, size, embedding)
+                 VALUES (?1, ?2, ?3, ?4, ?5)"
+            ).map_err(|e| format!("Failed to prepare hash cache insert: {}", e))?;
+
+            for (path, stamp, embedding) in entries {
+                stmt.execute(params![path, model, stamp.mtime_nanos, stamp.size, embeddings::to_bytes(embedding)])
+                    .map_err(|e| format!("Failed to write hash cache: {}", e))?;
+            }
+        }
+
+        tx.commit()
+            .map_err(|e| format!("Failed to commit hash cache: {}", e))
+    }
+}
@@ -0,0 +1,307 @@
+use image::{DynamicImage, GrayImage, imageops::FilterType};
+
+// Perceptual hash of any multiple of 64 bits, as 64-bit words (lowest bits first)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageHash(Vec<u64>);
+
+impl ImageHash {
+    pub fn bits(&self) -> u32 {
+        self.0.len() as u32 * 64
+    }
+
+    // The hash as one integer, when it's 64 bits
+    pub fn as_u64(&self) -> Option<u64> {
+        match self.0[..] {
+            [word] => Some(word),
+            _ => None,
+        }
+    }
+
+    // Hex string, most significant word first
+    pub fn to_hex(&self) -> String {
+        self.0.iter().rev().map(|word| format!("{:016x}", word)).collect()
+    }
+
+    pub fn from_hex(hex: &str) -> Option<ImageHash> {
+        if hex.is_empty() || hex.len() % 16 != 0 {
+            return None;
+        }
+        let words = (0..hex.len())
+            .step_by(16)
+            .rev()
+            .map(|start| u64::from_str_radix(hex.get(start..start + 16)?, 16).ok())
+            .collect::<Option<Vec<u64>>>()?;
+        Some(ImageHash(words))
+    }
+}
+
+impl From<u64> for ImageHash {
+    fn from(hash: u64) -> ImageHash {
+        ImageHash(vec![hash])
+    }
+}
+
+// Histogram bins per RGB channel in a color signature
+const COLOR_BINS: usize = 8;
+
+// Coarse per-channel RGB histogram. Luma hashes ignore color, so this is what
+// tells a red and a blue version of the same shirt apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColorSignature([u8; COLOR_BINS * 3]);
+
+impl ColorSignature {
+    // Histogram of a small thumbnail, each channel scaled to sum to ~255
+    pub fn compute(img: &DynamicImage) -> ColorSignature {
+        let rgb = img.thumbnail(64, 64).to_rgb8();
+        let mut counts = [0u32; COLOR_BINS * 3];
+        for pixel in rgb.pixels() {
+            for c in 0..3 {
+                let bin = pixel[c] as usize * COLOR_BINS / 256;
+                counts[c * COLOR_BINS + bin] += 1;
+            }
+        }
+
+        let total = (rgb.width() * rgb.height()).max(1) as f64;
+        ColorSignature(counts.map(|count| (count as f64 / total * 255.0).round() as u8))
+    }
+
+    // Histogram intersection, 0.0 (no shared colors) to 1.0 (identical)
+    pub fn similarity(&self, other: &ColorSignature) -> f64 {
+        let shared: u32 = self.0.iter().zip(&other.0).map(|(a, b)| *a.min(b) as u32).sum();
+        let total = self.0.iter().map(|&v| v as u32).sum::<u32>()
+            .max(other.0.iter().map(|&v| v as u32).sum())
+            .max(1);
+        shared as f64 / total as f64
+    }
+
+    pub fn to_hex(self) -> String {
+        hex::encode(self.0)
+    }
+
+    pub fn from_hex(hex: &str) -> Option<ColorSignature> {
+        hex::decode(hex).ok()?.try_into().ok().map(ColorSignature)
+    }
+}
+
+// Blend luma hash and color similarity; `color_weight` 0.0 ignores color
+pub fn combined_similarity(hash_similarity: f64, color_similarity: Option<f64>, color_weight: f64) -> f64 {
+    match color_similarity {
+        Some(color) => (1.0 - color_weight) * hash_similarity + color_weight * color,
+        None => hash_similarity,
+    }
+}
+
+// Perceptual hash algorithms available to the grouping commands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Difference,
+    // 16x16 dHash; slower to compare but tells similar silhouettes apart
+    Difference256,
+    Perceptual,
+    Average,
+    Block,
+}
+
+impl HashAlgorithm {
+    // Parse the frontend name, defaulting to dHash when none is given
+    pub fn parse(name: Option<&str>) -> Result<HashAlgorithm, String> {
+        match name.map(|n| n.to_lowercase()).as_deref() {
+            None | Some("dhash") => Ok(HashAlgorithm::Difference),
+            Some("dhash256") => Ok(HashAlgorithm::Difference256),
+            Some("phash") => Ok(HashAlgorithm::Perceptual),
+            Some("ahash") => Ok(HashAlgorithm::Average),
+            Some("blockhash") => Ok(HashAlgorithm::Block),
+            Some(other) => Err(format!("Unknown hash algorithm: {}", other)),
+        }
+    }
+
+    // Name used in cache keys
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Difference => "dhash",
+            HashAlgorithm::Difference256 => "dhash256",
+            HashAlgorithm::Perceptual => "phash",
+            HashAlgorithm::Average => "ahash",
+            HashAlgorithm::Block => "blockhash",
+        }
+    }
+
+    pub fn compute(&self, img: &DynamicImage) -> Result<ImageHash, String> {
+        match self {
+            HashAlgorithm::Difference => generate_dhash(img).map(ImageHash::from),
+            HashAlgorithm::Difference256 => generate_dhash256(img),
+            HashAlgorithm::Perceptual => generate_phash(img).map(ImageHash::from),
+            HashAlgorithm::Average => generate_ahash(img).map(ImageHash::from),
+            HashAlgorithm::Block => generate_blockhash(img).map(ImageHash::from),
+        }
+    }
+}
+
+// Generate perceptual hash (difference hash) for an image
+pub fn generate_dhash(img: &DynamicImage) -> Result<u64, String> {
+    // Resize to 9x8 grayscale
+    let resized = img.resize_exact(9, 8, FilterType::Lanczos3).to_luma8();
+
+    let mut hash: u64 = 0;
+    for y in 0..8 {
+        for x in 0..8 {
+            let left = resized.get_pixel(x, y)[0];
+            let right = resized.get_pixel(x + 1, y)[0];
+            if left > right {
+                hash |= 1 << (y * 8 + x);
+            }
+        }
+    }
+
+    Ok(hash)
+}
+
+// Generate 256-bit difference hash from a 17x16 grayscale image
+pub fn generate_dhash256(img: &DynamicImage) -> Result<ImageHash, String> {
+    const SIZE: u32 = 16;
+    let resized = img.resize_exact(SIZE + 1, SIZE, FilterType::Lanczos3).to_luma8();
+
+    let mut words = vec![0u64; (SIZE * SIZE / 64) as usize];
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let left = resized.get_pixel(x, y)[0];
+            let right = resized.get_pixel(x + 1, y)[0];
+            if left > right {
+                let bit = y * SIZE + x;
+                words[(bit / 64) as usize] |= 1 << (bit % 64);
+            }
+        }
+    }
+
+    Ok(ImageHash(words))
+}
+
+// Generate average hash: each bit is whether an 8x8 pixel is above the mean
+pub fn generate_ahash(img: &DynamicImage) -> Result<u64, String> {
+    let resized = img.resize_exact(8, 8, FilterType::Lanczos3).to_luma8();
+
+    let mean = resized.pixels().map(|p| p[0] as u32).sum::<u32>() / 64;
+
+    let mut hash: u64 = 0;
+    for (i, pixel) in resized.pixels().enumerate() {
+        if pixel[0] as u32 > mean {
+            hash |= 1 << i;
+        }
+    }
+
+    Ok(hash)
+}
+
+// Generate DCT-based perceptual hash (pHash)
+// Keeps the 8x8 lowest frequencies of a 32x32 DCT, which describe overall
+// structure and survive changes in angle, lighting and compression better
+// than pixel gradients
+pub fn generate_phash(img: &DynamicImage) -> Result<u64, String> {
+    const SIZE: usize = 32;
+    let resized = img.resize_exact(SIZE as u32, SIZE as u32, FilterType::Lanczos3).to_luma8();
+
+    let pixels: Vec<f64> = resized.pixels().map(|p| p[0] as f64).collect();
+    let dct = dct_2d(&pixels, SIZE);
+
+    let mut low_freq = Vec::with_capacity(64);
+    for y in 0..8 {
+        for x in 0..8 {
+            low_freq.push(dct[y * SIZE + x]);
+        }
+    }
+
+    // Median excluding the DC term, which only reflects average brightness
+    let mut sorted: Vec<f64> = low_freq[1..].to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let median = sorted[sorted.len() / 2];
+
+    let mut hash: u64 = 0;
+    for (i, value) in low_freq.iter().enumerate() {
+        if *value > median {
+            hash |= 1 << i;
+        }
+    }
+
+    Ok(hash)
+}
+
+// Separable 2D DCT-II over a square grid
+fn dct_2d(input: &[f64], size: usize) -> Vec<f64> {
+    let n = size as f64;
+    let mut coefficients = vec![0.0; size * size];
+    for (u, row) in coefficients.chunks_mut(size).enumerate() {
+        for (x, coefficient) in row.iter_mut().enumerate() {
+            *coefficient = ((2 * x + 1) as f64 * u as f64 * std::f64::consts::PI / (2.0 * n)).cos();
+        }
+    }
+
+    // Transform rows, then columns
+    let mut rows = vec![0.0; size * size];
+    for y in 0..size {
+        for u in 0..size {
+            rows[y * size + u] = (0..size)
+                .map(|x| input[y * size + x] * coefficients[u * size + x])
+                .sum();
+        }
+    }
+
+    let mut output = vec![0.0; size * size];
+    for v in 0..size {
+        for u in 0..size {
+            output[v * size + u] = (0..size)
+                .map(|y| rows[y * size + u] * coefficients[v * size + y])
+                .sum();
+        }
+    }
+
+    output
+}
+
+// Generate block mean hash (blockhash) over an 8x8 grid of blocks
+// Each block is compared against the median of its horizontal band so
+// uneven lighting across the frame doesn't flip whole regions
+pub fn generate_blockhash(img: &DynamicImage) -> Result<u64, String> {
+    const GRID: u32 = 8;
+    const BLOCK: u32 = 16;
+    let resized: GrayImage = img
+        .resize_exact(GRID * BLOCK, GRID * BLOCK, FilterType::Triangle)
+        .to_luma8();
+
+    let mut sums = [0u32; 64];
+    for (x, y, pixel) in resized.enumerate_pixels() {
+        let block = (y / BLOCK) * GRID + (x / BLOCK);
+        sums[block as usize] += pixel[0] as u32;
+    }
+
+    // 4 bands of 2 block rows each
+    let band_size = sums.len() / 4;
+    let mut hash: u64 = 0;
+    for (band_index, band) in sums.chunks(band_size).enumerate() {
+        let mut sorted = band.to_vec();
+        sorted.sort_unstable();
+        let median = (sorted[band_size / 2 - 1] + sorted[band_size / 2]) / 2;
+
+        for (i, sum) in band.iter().enumerate() {
+            if *sum > median {
+                hash |= 1 << (band_index * band_size + i);
+            }
+        }
+    }
+
+    Ok(hash)
+}
+
+// Calculate Hamming distance between two hashes
+// Hashes of different lengths come from different algorithms, so every bit counts as different
+pub fn hamming_distance(hash1: &ImageHash, hash2: &ImageHash) -> u32 {
+    if hash1.0.len() != hash2.0.len() {
+        return hash1.bits().max(hash2.bits());
+    }
+    hash1.0.iter().zip(&hash2.0).map(|(a, b)| (a ^ b).count_ones()).sum()
+}
+
+// Calculate similarity (0.0 to 1.0)
+pub fn calculate_similarity(hash1: &ImageHash, hash2: &ImageHash) -> f64 {
+    let distance = hamming_distance(hash1, hash2);
+    1.0 - (distance as f64 / hash1.bits().max(hash2.bits()) as f64)
+}
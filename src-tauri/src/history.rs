@@ -0,0 +1,232 @@
+use std::sync::{Arc, Mutex};
+use chrono::Utc;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+use crate::crosslist::Marketplace;
+use crate::db::Database;
+use crate::embeddings;
+use crate::image_io;
+use crate::listing::Listing;
+use crate::models::ModelStore;
+
+// Similarity above which a past listing is reported by default; CLIP
+// embeddings of the same item in different photos usually score higher
+const DEFAULT_MIN_SIMILARITY: f64 = 0.85;
+const DEFAULT_MATCH_LIMIT: usize = 5;
+
+struct IndexedListing {
+    listing_id: String,
+    embedding: Vec<f32>,
+}
+
+// Past listings with the CLIP embedding of each one's primary photo, for
+// "have I sold this before?" lookups. Embeddings are kept in memory and
+// searched exhaustively, which takes well under a millisecond for the tens of
+// thousands of listings a seller builds up.
+pub struct ListingHistory {
+    conn: Arc<Mutex<Connection>>,
+    index: Mutex<Vec<IndexedListing>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HistoryMatch {
+    pub listing_id: String,
+    pub title: String,
+    pub price: Option<f64>,
+    pub description: String,
+    pub photo_path: String,
+    // Seconds since the Unix epoch
+    pub listed_at: i64,
+    pub similarity: f64,
+}
+
+impl ListingHistory {
+    pub fn new(db: &Database) -> Result<ListingHistory, String> {
+        let conn = db.lock()?;
+        // Embeddings from another model aren't comparable and are skipped;
+        // those listings are indexed again when next added
+        let index = {
+            let mut statement = conn
+                .prepare("SELECT listing_id, embedding FROM listing_history WHERE model = ?1")
+                .map_err(|e| format!("Failed to read listing history: {}", e))?;
+            let rows = statement
+                .query_map(params![embeddings::CLIP_MODEL], |row| {
+                    Ok(IndexedListing {
+                        listing_id: row.get(0)?,
+                        embedding: embeddings::from_bytes(&row.get::<_, Vec<u8>>(1)?),
+                    })
+                })
+                .map_err(|e| format!("Failed to read listing history: {}", e))?;
+            rows.collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("Failed to read listing history: {}", e))?
+        };
+
+        Ok(ListingHistory {
+            conn: db.connection(),
+            index: Mutex::new(index),
+        })
+    }
+
+    fn add(
+        &self,
+        listing_id: &str,
+        title: &str,
+        price: Option<f64>,
+        description: &str,
+        photo_path: &str,
+        embedding: Vec<f32>,
+    ) -> Result<(), String> {
+        let conn = self.conn.lock().map_err(|_| "Listing history lock poisoned".to_string())?;
+        conn.execute(
+            "INSERT OR REPLACE INTO listing_history
+                (listing_id, title, price, description, photo_path, model, embedding, listed_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                listing_id,
+                title,
+                price,
+                description,
+                photo_path,
+                embeddings::CLIP_MODEL,
+                embeddings::to_bytes(&embedding),
+                Utc::now().timestamp(),
+            ],
+        )
+        .map_err(|e| format!("Failed to save listing history: {}", e))?;
+
+        let mut index = self.index.lock().map_err(|_| "Listing history lock poisoned".to_string())?;
+        index.retain(|listing| listing.listing_id != listing_id);
+        index.push(IndexedListing { listing_id: listing_id.to_string(), embedding });
+        Ok(())
+    }
+
+    fn remove(&self, listing_id: &str) -> Result<bool, String> {
+        let conn = self.conn.lock().map_err(|_| "Listing history lock poisoned".to_string())?;
+        let removed = conn
+            .execute("DELETE FROM listing_history WHERE listing_id = ?1", params![listing_id])
+            .map_err(|e| format!("Failed to update listing history: {}", e))?;
+        let mut index = self.index.lock().map_err(|_| "Listing history lock poisoned".to_string())?;
+        index.retain(|listing| listing.listing_id != listing_id);
+        Ok(removed > 0)
+    }
+
+    // Most similar past listings at or above `min_similarity`, best first
+    fn nearest(&self, embedding: &[f32], limit: usize, min_similarity: f64) -> Result<Vec<HistoryMatch>, String> {
+        let mut scored: Vec<(String, f64)> = {
+            let index = self.index.lock().map_err(|_| "Listing history lock poisoned".to_string())?;
+            index
+                .iter()
+                .map(|listing| (listing.listing_id.clone(), embeddings::cosine_similarity(embedding, &listing.embedding)))
+                .filter(|(_, similarity)| *similarity >= min_similarity)
+                .collect()
+        };
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scored.truncate(limit);
+
+        let conn = self.conn.lock().map_err(|_| "Listing history lock poisoned".to_string())?;
+        let mut statement = conn
+            .prepare("SELECT title, price, description, photo_path, listed_at FROM listing_history WHERE listing_id = ?1")
+            .map_err(|e| format!("Failed to read listing history: {}", e))?;
+        scored
+            .into_iter()
+            .map(|(listing_id, similarity)| {
+                statement
+                    .query_row(params![listing_id], |row| {
+                        Ok(HistoryMatch {
+                            title: row.get(0)?,
+                            price: row.get(1)?,
+                            description: row.get(2)?,
+                            photo_path: row.get(3)?,
+                            listed_at: row.get(4)?,
+                            listing_id: listing_id.clone(),
+                            similarity,
+                        })
+                    })
+                    .map_err(|e| format!("Failed to read listing history: {}", e))
+            })
+            .collect()
+    }
+}
+
+fn photo_embedding(models: &ModelStore, photo: &str) -> Result<Vec<f32>, String> {
+    let img = image_io::open_image_scaled(photo, image_io::ANALYSIS_EDGE)?;
+    embeddings::image_embedding(models, &img)
+}
+
+// Command to record a listing in the history index, keyed by `listing_id`
+// `photo` is the listing's primary photo. Adding an existing id replaces it.
+#[tauri::command(async)]
+pub fn add_listing_to_history(
+    history: State<ListingHistory>,
+    models: State<ModelStore>,
+    listing_id: String,
+    photo: String,
+    title: String,
+    price: Option<f64>,
+    description: String,
+) -> Result<(), String> {
+    index_listing(&history, &models, &listing_id, &photo, &title, price, &description)
+}
+
+// Record a listing in the history index from its primary photo, e.g. one
+// imported from a marketplace; blocks while the photo is embedded
+pub fn index_listing(
+    history: &ListingHistory,
+    models: &ModelStore,
+    listing_id: &str,
+    photo: &str,
+    title: &str,
+    price: Option<f64>,
+    description: &str,
+) -> Result<(), String> {
+    let embedding = photo_embedding(models, photo)?;
+    history.add(listing_id, title, price, description, photo, embedding)
+}
+
+// Add a listing just published or exported to the history index from its
+// first local photo, keyed by marketplace and `listing_id` (the SKU for
+// exports). Returns false when it has no local photo to index.
+pub async fn index_published(app: &AppHandle, marketplace: Marketplace, listing_id: &str, item: &Listing) -> Result<bool, String> {
+    let Some(photo) = item.photos.iter().find(|photo| !photo.starts_with("https://")).cloned() else {
+        return Ok(false);
+    };
+    let app = app.clone();
+    let listing_id = format!("{}:{}", marketplace.name(), listing_id);
+    let title = item.title.trim().to_string();
+    let price = Some(item.price);
+    let description = item.description.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let history = app.state::<ListingHistory>();
+        let models = app.state::<ModelStore>();
+        index_listing(&history, &models, &listing_id, &photo, &title, price, &description)
+    })
+    .await
+    .map_err(|e| format!("Failed to index listing: {}", e))??;
+    Ok(true)
+}
+
+// Command to drop a listing from the history index; returns whether it was there
+#[tauri::command]
+pub fn remove_listing_from_history(history: State<ListingHistory>, listing_id: String) -> Result<bool, String> {
+    history.remove(&listing_id)
+}
+
+// Command to find past listings of the item in `photo`, so an earlier
+// title, price and description can be reused
+// `min_similarity` (0-1) defaults to 0.85 and `limit` to 5
+#[tauri::command(async)]
+pub fn match_against_history(
+    history: State<ListingHistory>,
+    models: State<ModelStore>,
+    photo: String,
+    limit: Option<usize>,
+    min_similarity: Option<f64>,
+) -> Result<Vec<HistoryMatch>, String> {
+    let min_similarity = min_similarity.unwrap_or(DEFAULT_MIN_SIMILARITY);
+    if !(0.0..=1.0).contains(&min_similarity) {
+        return Err("min_similarity must be between 0 and 1".to_string());
+    }
+    let embedding = photo_embedding(&models, &photo)?;
+    history.nearest(&embedding, limit.unwrap_or(DEFAULT_MATCH_LIMIT), min_similarity)
+}
@@ -0,0 +1,391 @@
+use std::fs::File;
+use std::io::{BufReader, Cursor, Read};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use chrono::NaiveDate;
+use exif::{DateTime as ExifDateTime, Exif, In, Reader as ExifReader, Tag, Value};
+use image::{DynamicImage, ImageFormat, ImageOutputFormat, RgbImage, codecs::jpeg::JpegDecoder, io::Reader as ImageReader};
+use libheif_rs::{ColorSpace, HeifContext, LibHeif, RgbChroma};
+use rawloader::{Orientation, RawImageData};
+
+// JPEG quality used when converting formats the webview can't display
+pub const DISPLAY_JPEG_QUALITY: u8 = 90;
+
+// Check whether a path points to a HEIC/HEIF file (iPhone photos)
+pub fn is_heic(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .map(|ext| {
+            let ext_lower = ext.to_string_lossy().to_lowercase();
+            ext_lower == "heic" || ext_lower == "heif"
+        })
+        .unwrap_or(false)
+}
+
+// Camera RAW formats decoded through rawloader
+pub const RAW_EXTENSIONS: [&str; 8] = ["cr2", "nef", "nrw", "arw", "dng", "raf", "orf", "rw2"];
+
+// Check whether a path points to a camera RAW file
+pub fn is_raw(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .map(|ext| RAW_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+// Whether a path has one of the photo extensions picked up by folder scans
+pub fn is_photo_file(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| {
+            let ext_lower = ext.to_string_lossy().to_lowercase();
+            ["jpg", "jpeg", "png", "heic", "heif"].contains(&ext_lower.as_str())
+                || RAW_EXTENSIONS.contains(&ext_lower.as_str())
+        })
+        .unwrap_or(false)
+}
+
+// Whether the webview needs a JPEG conversion to display this file
+pub fn needs_jpeg_conversion(path: &str) -> bool {
+    is_heic(path) || is_raw(path)
+}
+
+// Enough leading bytes to recognise any supported image format
+pub const SNIFF_BYTES: usize = 64;
+
+// Detect an image's MIME type from its magic bytes
+pub fn sniff_mime(data: &[u8]) -> Option<&'static str> {
+    infer::get(data)
+        .filter(|kind| kind.matcher_type() == infer::MatcherType::Image)
+        .map(|kind| kind.mime_type())
+}
+
+// Sniff a file's MIME type from its first bytes
+pub fn sniff_file_mime(path: &str) -> Option<&'static str> {
+    let mut header = Vec::with_capacity(SNIFF_BYTES);
+    File::open(path).ok()?.take(SNIFF_BYTES as u64).read_to_end(&mut header).ok()?;
+    sniff_mime(&header)
+}
+
+// Fall back to guessing the MIME type from the file extension
+pub fn mime_from_extension(path: &str) -> Option<&'static str> {
+    let ext_lower = Path::new(path).extension()?.to_string_lossy().to_lowercase();
+    match ext_lower.as_str() {
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "png" => Some("image/png"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        "avif" => Some("image/avif"),
+        "bmp" => Some("image/bmp"),
+        "tif" | "tiff" => Some("image/tiff"),
+        "heic" | "heif" => Some("image/heif"),
+        _ => None,
+    }
+}
+
+// Whether a sniffed MIME type is HEIC/HEIF, for misnamed or extensionless files
+pub fn is_heif_mime(mime: &str) -> bool {
+    mime == "image/heif" || mime == "image/heic"
+}
+
+// Convert a path to a String for returning to the frontend
+pub fn path_to_string(path: &Path) -> Result<String, String> {
+    path.to_str()
+        .map(|p| p.to_string())
+        .ok_or_else(|| format!("Path is not valid UTF-8: {}", path.display()))
+}
+
+// Parse a "#rrggbb" (or "rrggbb") colour string
+pub fn parse_hex_color(color: &str) -> Result<[u8; 3], String> {
+    let hex_digits = color.trim().trim_start_matches('#');
+    let bytes = hex::decode(hex_digits)
+        .map_err(|_| format!("Invalid colour: {}", color))?;
+    match bytes.as_slice() {
+        [r, g, b] => Ok([*r, *g, *b]),
+        _ => Err(format!("Invalid colour: {}", color)),
+    }
+}
+
+// Largest image (in pixels) that will be decoded at full resolution, so a
+// folder of huge files can't exhaust memory. JPEGs opened with
+// `open_image_scaled` are decoded at reduced size and aren't limited.
+pub const DEFAULT_MAX_DECODE_PIXELS: u64 = 100_000_000;
+static MAX_DECODE_PIXELS: AtomicU64 = AtomicU64::new(DEFAULT_MAX_DECODE_PIXELS);
+
+// Longest edge images are decoded to for hashing and scoring
+pub const ANALYSIS_EDGE: u32 = 512;
+
+pub fn set_max_decode_pixels(max_pixels: u64) {
+    MAX_DECODE_PIXELS.store(max_pixels, Ordering::Relaxed);
+}
+
+fn check_decode_size(path: &str, width: u32, height: u32) -> Result<(), String> {
+    let max_pixels = MAX_DECODE_PIXELS.load(Ordering::Relaxed);
+    if width as u64 * height as u64 > max_pixels {
+        return Err(format!(
+            "Image {} is too large to decode ({}x{}, limit {} pixels)",
+            path, width, height, max_pixels
+        ));
+    }
+    Ok(())
+}
+
+// Open any supported image upright, routing HEIC files through libheif
+pub fn open_image(path: &str) -> Result<DynamicImage, String> {
+    decode(path, None)
+}
+
+// Open an image upright with its longest edge at most `max_edge`.
+// JPEGs are downscaled while decoding (by up to 8x), so even 50MP photos only
+// ever hold a small working image in memory.
+pub fn open_image_scaled(path: &str, max_edge: u32) -> Result<DynamicImage, String> {
+    let img = decode(path, Some(max_edge))?;
+    if img.width() > max_edge || img.height() > max_edge {
+        Ok(img.thumbnail(max_edge, max_edge))
+    } else {
+        Ok(img)
+    }
+}
+
+fn decode(path: &str, max_edge: Option<u32>) -> Result<DynamicImage, String> {
+    // libheif already applies the HEIC rotation/mirror transforms
+    if is_heic(path) {
+        return decode_heic(path);
+    }
+    if is_raw(path) {
+        return decode_raw(path);
+    }
+
+    // Decode by content rather than extension, so misnamed files still open
+    let reader = ImageReader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|e| format!("Failed to open image {}: {}", path, e))?;
+    if reader.format().is_none() && sniff_file_mime(path).is_some_and(is_heif_mime) {
+        return decode_heic(path);
+    }
+
+    let img = match (reader.format(), max_edge) {
+        (Some(ImageFormat::Jpeg), Some(max_edge)) => decode_jpeg_scaled(path, max_edge)?,
+        _ => {
+            let (width, height) = reader.into_dimensions()
+                .map_err(|e| format!("Failed to open image {}: {}", path, e))?;
+            check_decode_size(path, width, height)?;
+
+            ImageReader::open(path)
+                .and_then(|reader| reader.with_guessed_format())
+                .map_err(|e| format!("Failed to open image {}: {}", path, e))?
+                .decode()
+                .map_err(|e| format!("Failed to open image {}: {}", path, e))?
+        }
+    };
+
+    Ok(apply_orientation(img, read_orientation(path)))
+}
+
+// Decode a JPEG at the smallest DCT scale (1/1, 1/2, 1/4 or 1/8) that still
+// covers `max_edge`
+fn decode_jpeg_scaled(path: &str, max_edge: u32) -> Result<DynamicImage, String> {
+    let file = File::open(path)
+        .map_err(|e| format!("Failed to open image {}: {}", path, e))?;
+    let mut decoder = JpegDecoder::new(BufReader::new(file))
+        .map_err(|e| format!("Failed to open image {}: {}", path, e))?;
+
+    let target = max_edge.min(u16::MAX as u32) as u16;
+    decoder.scale(target, target)
+        .map_err(|e| format!("Failed to open image {}: {}", path, e))?;
+
+    DynamicImage::from_decoder(decoder)
+        .map_err(|e| format!("Failed to open image {}: {}", path, e))
+}
+
+// Read the EXIF block from a JPEG/TIFF/HEIF container, if present
+pub fn read_exif(path: &str) -> Option<Exif> {
+    let file = File::open(path).ok()?;
+    let mut reader = BufReader::new(file);
+    ExifReader::new().read_from_container(&mut reader).ok()
+}
+
+// Read the EXIF orientation tag (1-8), defaulting to 1 (upright)
+pub fn read_orientation(path: &str) -> u32 {
+    read_exif(path)
+        .and_then(|exif| {
+            exif.get_field(Tag::Orientation, In::PRIMARY)
+                .and_then(|field| field.value.get_uint(0))
+        })
+        .filter(|orientation| (1..=8).contains(orientation))
+        .unwrap_or(1)
+}
+
+// Read a photo's upright dimensions from its header, without decoding pixels
+pub fn read_dimensions(path: &str) -> Option<(u32, u32)> {
+    // libheif reports dimensions with the HEIC transforms already applied
+    if is_heic(path) || sniff_file_mime(path).is_some_and(is_heif_mime) {
+        let ctx = HeifContext::read_from_file(path).ok()?;
+        let handle = ctx.primary_image_handle().ok()?;
+        return Some((handle.width(), handle.height()));
+    }
+
+    let (width, height) = if is_raw(path) {
+        let exif = read_exif(path)?;
+        let dimension = |tags: [Tag; 2]| {
+            tags.iter().find_map(|&tag| exif.get_field(tag, In::PRIMARY)?.value.get_uint(0))
+        };
+        (
+            dimension([Tag::PixelXDimension, Tag::ImageWidth])?,
+            dimension([Tag::PixelYDimension, Tag::ImageLength])?,
+        )
+    } else {
+        ImageReader::open(path).ok()?.with_guessed_format().ok()?.into_dimensions().ok()?
+    };
+
+    // Orientations 5-8 rotate by 90 degrees, swapping width and height
+    if read_orientation(path) >= 5 {
+        Some((height, width))
+    } else {
+        Some((width, height))
+    }
+}
+
+// Read EXIF DateTimeOriginal as seconds since the epoch (camera local time)
+pub fn read_capture_timestamp(path: &str) -> Option<i64> {
+    let exif = read_exif(path)?;
+    let field = exif.get_field(Tag::DateTimeOriginal, In::PRIMARY)?;
+    let ascii = match &field.value {
+        Value::Ascii(values) => values.first()?,
+        _ => return None,
+    };
+
+    let datetime = ExifDateTime::from_ascii(ascii).ok()?;
+    let date = NaiveDate::from_ymd_opt(datetime.year as i32, datetime.month as u32, datetime.day as u32)?;
+    let time = date.and_hms_opt(datetime.hour as u32, datetime.minute as u32, datetime.second as u32)?;
+
+    Some(time.and_utc().timestamp())
+}
+
+// Rotate/flip an image so it displays upright for the given EXIF orientation
+pub fn apply_orientation(img: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => img.fliph(),
+        3 => img.rotate180(),
+        4 => img.flipv(),
+        5 => img.rotate90().fliph(),
+        6 => img.rotate90(),
+        7 => img.rotate270().fliph(),
+        8 => img.rotate270(),
+        _ => img,
+    }
+}
+
+// Decode the primary image of a HEIC file to RGB
+fn decode_heic(path: &str) -> Result<DynamicImage, String> {
+    let lib_heif = LibHeif::new();
+    let ctx = HeifContext::read_from_file(path)
+        .map_err(|e| format!("Failed to read HEIC file {}: {}", path, e))?;
+    let handle = ctx.primary_image_handle()
+        .map_err(|e| format!("Failed to read HEIC image {}: {}", path, e))?;
+    check_decode_size(path, handle.width(), handle.height())?;
+
+    let decoded = lib_heif.decode(&handle, ColorSpace::Rgb(RgbChroma::Rgb), None)
+        .map_err(|e| format!("Failed to decode HEIC image {}: {}", path, e))?;
+
+    let planes = decoded.planes();
+    let plane = planes.interleaved
+        .ok_or_else(|| format!("HEIC image {} has no interleaved RGB plane", path))?;
+
+    // Rows may be padded, so copy them out one at a time
+    let width = plane.width;
+    let height = plane.height;
+    let row_bytes = width as usize * 3;
+    let mut pixels = Vec::with_capacity(row_bytes * height as usize);
+    for row in plane.data.chunks(plane.stride).take(height as usize) {
+        pixels.extend_from_slice(&row[..row_bytes]);
+    }
+
+    let rgb = RgbImage::from_raw(width, height, pixels)
+        .ok_or_else(|| format!("HEIC image {} has invalid dimensions", path))?;
+
+    Ok(DynamicImage::ImageRgb8(rgb))
+}
+
+// Decode a camera RAW file to an upright RGB image.
+// Each 2x2 Bayer block becomes one pixel (half-size demosaic), which is plenty
+// for grouping, thumbnails and listing photos, and avoids interpolation artefacts.
+fn decode_raw(path: &str) -> Result<DynamicImage, String> {
+    let raw = rawloader::decode_file(path)
+        .map_err(|e| format!("Failed to decode RAW file {}: {}", path, e))?;
+    check_decode_size(path, raw.width as u32, raw.height as u32)?;
+
+    let data: Vec<f32> = match raw.data {
+        RawImageData::Integer(ref values) => values.iter().map(|&v| v as f32).collect(),
+        RawImageData::Float(ref values) => values.clone(),
+    };
+
+    // Normalize each channel to 0-1 and white balance relative to green
+    let green_wb = if raw.wb_coeffs[1].is_normal() { raw.wb_coeffs[1] } else { 1.0 };
+    let level = |value: f32, channel: usize| {
+        let black = raw.blacklevels[channel] as f32;
+        let white = (raw.whitelevels[channel] as f32 - black).max(1.0);
+        let wb = if raw.wb_coeffs[channel].is_normal() { raw.wb_coeffs[channel] / green_wb } else { 1.0 };
+        ((value - black) / white * wb).clamp(0.0, 1.0)
+    };
+    let to_srgb = |linear: f32| {
+        let encoded = if linear <= 0.003_130_8 { linear * 12.92 } else { 1.055 * linear.powf(1.0 / 2.4) - 0.055 };
+        (encoded * 255.0).round() as u8
+    };
+
+    // Usable area after the sensor's masked borders (top, right, bottom, left)
+    let [top, right, bottom, left] = raw.crops;
+    let usable_width = raw.width.saturating_sub(left + right);
+    let usable_height = raw.height.saturating_sub(top + bottom);
+
+    let rgb = if raw.cpp == 3 {
+        RgbImage::from_fn(usable_width as u32, usable_height as u32, |x, y| {
+            let offset = ((top + y as usize) * raw.width + left + x as usize) * 3;
+            image::Rgb([0, 1, 2].map(|c| to_srgb(level(data[offset + c], c))))
+        })
+    } else {
+        let cfa = raw.cfa.shift(left, top);
+        RgbImage::from_fn((usable_width / 2) as u32, (usable_height / 2) as u32, |x, y| {
+            let mut sums = [0.0f32; 3];
+            let mut counts = [0.0f32; 3];
+            for (dy, dx) in [(0, 0), (0, 1), (1, 0), (1, 1)] {
+                let row = y as usize * 2 + dy;
+                let col = x as usize * 2 + dx;
+                // Colors are R, G, B, E; the "emerald" fourth color counts as green
+                let color = cfa.color_at(row, col);
+                let channel = if color == 3 { 1 } else { color };
+                let value = data[(top + row) * raw.width + left + col];
+                sums[channel] += level(value, color);
+                counts[channel] += 1.0;
+            }
+            image::Rgb([0, 1, 2].map(|c| to_srgb(sums[c] / counts[c].max(1.0))))
+        })
+    };
+
+    let orientation = match raw.orientation {
+        Orientation::HorizontalFlip => 2,
+        Orientation::Rotate180 => 3,
+        Orientation::VerticalFlip => 4,
+        Orientation::Transpose => 5,
+        Orientation::Rotate90 => 6,
+        Orientation::Transverse => 7,
+        Orientation::Rotate270 => 8,
+        Orientation::Normal | Orientation::Unknown => 1,
+    };
+
+    Ok(apply_orientation(DynamicImage::ImageRgb8(rgb), orientation))
+}
+
+// Encode an image as JPEG bytes
+pub fn encode_jpeg(img: &DynamicImage, quality: u8) -> Result<Vec<u8>, String> {
+    let mut buffer = Cursor::new(Vec::new());
+    DynamicImage::ImageRgb8(img.to_rgb8())
+        .write_to(&mut buffer, ImageOutputFormat::Jpeg(quality))
+        .map_err(|e| format!("Failed to encode JPEG: {}", e))?;
+    Ok(buffer.into_inner())
+}
+
+// Convert a HEIC or RAW file to JPEG bytes the webview can display
+pub fn convert_to_jpeg(path: &str) -> Result<Vec<u8>, String> {
+    let img = open_image(path)?;
+    encode_jpeg(&img, DISPLAY_JPEG_QUALITY)
+}
@@ -0,0 +1,363 @@
+use chrono::{NaiveDate, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use crate::db::Database;
+use crate::ebay;
+use crate::sessions::{self, SessionStore};
+
+// Where an item is in the resale flow, in order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InventoryStatus {
+    Sourced,
+    Photographed,
+    Listed,
+    Sold,
+    Shipped,
+}
+
+impl InventoryStatus {
+    fn name(self) -> &'static str {
+        match self {
+            InventoryStatus::Sourced => "sourced",
+            InventoryStatus::Photographed => "photographed",
+            InventoryStatus::Listed => "listed",
+            InventoryStatus::Sold => "sold",
+            InventoryStatus::Shipped => "shipped",
+        }
+    }
+
+    fn parse(status: &str) -> InventoryStatus {
+        match status {
+            "photographed" => InventoryStatus::Photographed,
+            "listed" => InventoryStatus::Listed,
+            "sold" => InventoryStatus::Sold,
+            "shipped" => InventoryStatus::Shipped,
+            _ => InventoryStatus::Sourced,
+        }
+    }
+}
+
+// An item the seller has on hand, or had
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InventoryItem {
+    // Assigned when the item is created
+    #[serde(default)]
+    pub id: i64,
+    // Generated when left out; listings of the item use the same SKU
+    #[serde(default)]
+    pub sku: String,
+    pub title: String,
+    #[serde(default)]
+    pub purchase_cost: Option<f64>,
+    // YYYY-MM-DD
+    #[serde(default)]
+    pub purchase_date: Option<String>,
+    // Storage bin or shelf, e.g. "B12"
+    #[serde(default)]
+    pub location: Option<String>,
+    #[serde(default = "default_quantity")]
+    pub quantity: u32,
+    #[serde(default = "default_status")]
+    pub status: InventoryStatus,
+    #[serde(default)]
+    pub notes: String,
+    // Linked photos, primary first; read-only, see `link_photos_to_inventory`
+    #[serde(default)]
+    pub photos: Vec<String>,
+    // RFC 3339
+    #[serde(default)]
+    pub created_at: String,
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+fn default_quantity() -> u32 {
+    1
+}
+
+fn default_status() -> InventoryStatus {
+    InventoryStatus::Sourced
+}
+
+// Which inventory item a photo is linked to
+#[derive(Debug, Serialize)]
+pub struct PhotoLink {
+    pub path: String,
+    pub item_id: i64,
+    pub sku: String,
+    pub title: String,
+}
+
+// Only the parts of a saved photo group linking needs
+#[derive(Deserialize)]
+struct SessionGroup {
+    id: String,
+    photos: Vec<String>,
+}
+
+const ITEM_COLUMNS: &str = "id, sku, title, purchase_cost, purchase_date, location, quantity, status, notes, created_at, updated_at";
+
+fn item_from_row(row: &Row) -> rusqlite::Result<InventoryItem> {
+    let status: String = row.get(7)?;
+    Ok(InventoryItem {
+        id: row.get(0)?,
+        sku: row.get(1)?,
+        title: row.get(2)?,
+        purchase_cost: row.get(3)?,
+        purchase_date: row.get(4)?,
+        location: row.get(5)?,
+        quantity: row.get(6)?,
+        status: InventoryStatus::parse(&status),
+        notes: row.get(8)?,
+        photos: Vec::new(),
+        created_at: row.get(9)?,
+        updated_at: row.get(10)?,
+    })
+}
+
+fn photos_of(conn: &Connection, item_id: i64) -> Result<Vec<String>, String> {
+    let mut stmt = conn
+        .prepare("SELECT path FROM inventory_photos WHERE item_id = ?1 ORDER BY position")
+        .map_err(|e| format!("Failed to read inventory photos: {}", e))?;
+    let rows = stmt
+        .query_map(params![item_id], |row| row.get(0))
+        .map_err(|e| format!("Failed to read inventory photos: {}", e))?;
+    rows.collect::<Result<_, _>>()
+        .map_err(|e| format!("Failed to read inventory photos: {}", e))
+}
+
+fn item(conn: &Connection, id: i64) -> Result<Option<InventoryItem>, String> {
+    let item = conn
+        .query_row(&format!("SELECT {} FROM inventory_items WHERE id = ?1", ITEM_COLUMNS), params![id], item_from_row)
+        .optional()
+        .map_err(|e| format!("Failed to read inventory item: {}", e))?;
+    match item {
+        Some(mut item) => {
+            item.photos = photos_of(conn, id)?;
+            Ok(Some(item))
+        }
+        None => Ok(None),
+    }
+}
+
+fn non_empty(value: Option<&str>) -> Option<String> {
+    value.map(str::trim).filter(|value| !value.is_empty()).map(str::to_string)
+}
+
+// Link photos to an item after its existing ones, moving any linked to
+// another item, and mark a sourced item photographed
+fn link_photos(conn: &mut Connection, item_id: i64, photo_paths: &[String]) -> Result<InventoryItem, String> {
+    let tx = conn.transaction().map_err(|e| format!("Failed to link photos: {}", e))?;
+    let status: String = tx
+        .query_row("SELECT status FROM inventory_items WHERE id = ?1", params![item_id], |row| row.get(0))
+        .optional()
+        .map_err(|e| format!("Failed to read inventory item: {}", e))?
+        .ok_or_else(|| format!("No inventory item with ID {}", item_id))?;
+    let mut position: i64 = tx
+        .query_row("SELECT COALESCE(MAX(position), -1) FROM inventory_photos WHERE item_id = ?1", params![item_id], |row| row.get(0))
+        .map_err(|e| format!("Failed to read inventory photos: {}", e))?;
+    for path in photo_paths {
+        let linked: Option<i64> = tx
+            .query_row("SELECT item_id FROM inventory_photos WHERE path = ?1", params![path], |row| row.get(0))
+            .optional()
+            .map_err(|e| format!("Failed to read inventory photos: {}", e))?;
+        if linked == Some(item_id) {
+            continue;
+        }
+        position += 1;
+        tx.execute(
+            "INSERT OR REPLACE INTO inventory_photos (path, item_id, position) VALUES (?1, ?2, ?3)",
+            params![path, item_id, position],
+        )
+        .map_err(|e| format!("Failed to link photo {}: {}", path, e))?;
+    }
+    let status = match InventoryStatus::parse(&status) {
+        InventoryStatus::Sourced if !photo_paths.is_empty() => InventoryStatus::Photographed,
+        status => status,
+    };
+    tx.execute(
+        "UPDATE inventory_items SET status = ?2, updated_at = ?3 WHERE id = ?1",
+        params![item_id, status.name(), Utc::now().to_rfc3339()],
+    )
+    .map_err(|e| format!("Failed to update inventory item: {}", e))?;
+    tx.commit().map_err(|e| format!("Failed to link photos: {}", e))?;
+    item(conn, item_id)?.ok_or_else(|| format!("No inventory item with ID {}", item_id))
+}
+
+// Command to add an inventory item, or update the one with its ID. Photos
+// and timestamps in `item` are ignored; link photos separately.
+#[tauri::command]
+pub fn save_inventory_item(db: State<Database>, item: InventoryItem) -> Result<InventoryItem, String> {
+    let title = item.title.trim();
+    if title.is_empty() {
+        return Err("Title is required".to_string());
+    }
+    if item.purchase_cost.is_some_and(|cost| !cost.is_finite() || cost < 0.0) {
+        return Err("Purchase cost can't be negative".to_string());
+    }
+    let purchase_date = non_empty(item.purchase_date.as_deref());
+    if let Some(date) = &purchase_date {
+        NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| format!("Invalid purchase date: {}", date))?;
+    }
+    // An update left without a SKU keeps the stored one; new items get one
+    let sku = match non_empty(Some(&item.sku)) {
+        None if item.id <= 0 => Some(ebay::new_sku()),
+        sku => sku,
+    };
+    let location = non_empty(item.location.as_deref());
+    let now = Utc::now().to_rfc3339();
+
+    let conn = db.lock()?;
+    let saved = if item.id > 0 {
+        conn.execute(
+            "UPDATE inventory_items
+             SET sku = COALESCE(?2, sku), title = ?3, purchase_cost = ?4, purchase_date = ?5, location = ?6, quantity = ?7,
+                 status = ?8, notes = ?9, updated_at = ?10
+             WHERE id = ?1",
+            params![item.id, sku, title, item.purchase_cost, purchase_date, location, item.quantity, item.status.name(), item.notes, now],
+        )
+        .map(|updated| (updated > 0).then_some(item.id))
+    } else {
+        conn.execute(
+            "INSERT INTO inventory_items
+                (sku, title, purchase_cost, purchase_date, location, quantity, status, notes, created_at, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?9)",
+            params![sku, title, item.purchase_cost, purchase_date, location, item.quantity, item.status.name(), item.notes, now],
+        )
+        .map(|_| Some(conn.last_insert_rowid()))
+    };
+    let id = match saved {
+        Ok(Some(id)) => id,
+        Ok(None) => return Err(format!("No inventory item with ID {}", item.id)),
+        Err(rusqlite::Error::SqliteFailure(error, _)) if error.code == rusqlite::ErrorCode::ConstraintViolation => {
+            return Err(format!("Another inventory item already has SKU {}", sku.unwrap_or_default()));
+        }
+        Err(e) => return Err(format!("Failed to save inventory item: {}", e)),
+    };
+    self::item(&conn, id)?.ok_or_else(|| format!("No inventory item with ID {}", id))
+}
+
+#[tauri::command]
+pub fn get_inventory_item(db: State<Database>, item_id: i64) -> Result<Option<InventoryItem>, String> {
+    item(&*db.lock()?, item_id)
+}
+
+// Command to list inventory, most recently updated first, optionally by
+// status or storage location
+#[tauri::command]
+pub fn list_inventory(
+    db: State<Database>,
+    status: Option<InventoryStatus>,
+    location: Option<String>,
+) -> Result<Vec<InventoryItem>, String> {
+    let conn = db.lock()?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM inventory_items
+             WHERE (?1 IS NULL OR status = ?1) AND (?2 IS NULL OR location = ?2 COLLATE NOCASE)
+             ORDER BY updated_at DESC",
+            ITEM_COLUMNS
+        ))
+        .map_err(|e| format!("Failed to read inventory: {}", e))?;
+    let rows = stmt
+        .query_map(params![status.map(InventoryStatus::name), non_empty(location.as_deref())], item_from_row)
+        .map_err(|e| format!("Failed to read inventory: {}", e))?;
+    let mut items: Vec<InventoryItem> = rows
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("Failed to read inventory: {}", e))?;
+    for item in &mut items {
+        item.photos = photos_of(&conn, item.id)?;
+    }
+    Ok(items)
+}
+
+// Command to move an item along sourced → photographed → listed → sold →
+// shipped. Moving back is allowed, e.g. when a sale is cancelled.
+#[tauri::command]
+pub fn set_inventory_status(db: State<Database>, item_id: i64, status: InventoryStatus) -> Result<InventoryItem, String> {
+    let conn = db.lock()?;
+    let updated = conn
+        .execute(
+            "UPDATE inventory_items SET status = ?2, updated_at = ?3 WHERE id = ?1",
+            params![item_id, status.name(), Utc::now().to_rfc3339()],
+        )
+        .map_err(|e| format!("Failed to update inventory item: {}", e))?;
+    if updated == 0 {
+        return Err(format!("No inventory item with ID {}", item_id));
+    }
+    item(&conn, item_id)?.ok_or_else(|| format!("No inventory item with ID {}", item_id))
+}
+
+// Command to delete an inventory item; its photos are unlinked, not deleted
+#[tauri::command]
+pub fn delete_inventory_item(db: State<Database>, item_id: i64) -> Result<bool, String> {
+    let conn = db.lock()?;
+    let removed = conn
+        .execute("DELETE FROM inventory_items WHERE id = ?1", params![item_id])
+        .map_err(|e| format!("Failed to delete inventory item: {}", e))?;
+    Ok(removed > 0)
+}
+
+// Command to link photos, e.g. from `read_folder_images`, to an inventory
+// item. A photo belongs to one item, so linking it again moves it. A
+// sourced item becomes photographed.
+#[tauri::command]
+pub fn link_photos_to_inventory(db: State<Database>, item_id: i64, photo_paths: Vec<String>) -> Result<InventoryItem, String> {
+    link_photos(&mut *db.lock()?, item_id, &photo_paths)
+}
+
+// Command to link the photos of a group from the current grouping session
+// (`group_photos_by_item`) to an inventory item. Group IDs change when
+// photos are grouped again, so the photos are linked, not the group.
+#[tauri::command]
+pub fn link_group_to_inventory(
+    db: State<Database>,
+    sessions: State<SessionStore>,
+    item_id: i64,
+    group_id: String,
+) -> Result<InventoryItem, String> {
+    let groups: Vec<SessionGroup> = sessions.load(sessions::CURRENT_SESSION)?.unwrap_or_default();
+    let group = groups
+        .into_iter()
+        .find(|group| group.id == group_id)
+        .ok_or_else(|| format!("No photo group {}", group_id))?;
+    link_photos(&mut *db.lock()?, item_id, &group.photos)
+}
+
+#[tauri::command]
+pub fn unlink_photos_from_inventory(db: State<Database>, photo_paths: Vec<String>) -> Result<usize, String> {
+    let conn = db.lock()?;
+    let mut removed = 0;
+    for path in &photo_paths {
+        removed += conn
+            .execute("DELETE FROM inventory_photos WHERE path = ?1", params![path])
+            .map_err(|e| format!("Failed to unlink photo {}: {}", path, e))?;
+    }
+    Ok(removed)
+}
+
+// Command to find which of a set of photos, e.g. a folder's from
+// `read_folder_images`, are already linked to inventory items
+#[tauri::command]
+pub fn find_inventory_for_photos(db: State<Database>, photo_paths: Vec<String>) -> Result<Vec<PhotoLink>, String> {
+    let conn = db.lock()?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT p.path, i.id, i.sku, i.title FROM inventory_photos p
+             JOIN inventory_items i ON i.id = p.item_id WHERE p.path = ?1",
+        )
+        .map_err(|e| format!("Failed to read inventory photos: {}", e))?;
+    let mut links = Vec::new();
+    for path in &photo_paths {
+        let link = stmt
+            .query_row(params![path], |row| {
+                Ok(PhotoLink { path: row.get(0)?, item_id: row.get(1)?, sku: row.get(2)?, title: row.get(3)? })
+            })
+            .optional()
+            .map_err(|e| format!("Failed to read inventory photos: {}", e))?;
+        links.extend(link);
+    }
+    Ok(links)
+}
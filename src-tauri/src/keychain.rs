@@ -0,0 +1,205 @@
+// Secrets such as API keys and OAuth tokens, kept in the OS keychain rather
+// than in the app data directory: Keychain on macOS, Credential Manager on
+// Windows and the Secret Service (via `secret-tool`) elsewhere
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::Path;
+
+// Keychain service name every secret is stored under
+const SERVICE: &str = "listing-assistant";
+
+// Store `secret` for `account`, replacing any existing one
+pub fn set_secret(account: &str, secret: &str) -> Result<(), String> {
+    platform::set(SERVICE, account, secret)
+}
+
+// Secret stored for `account`, if there is one
+pub fn get_secret(account: &str) -> Result<Option<String>, String> {
+    platform::get(SERVICE, account)
+}
+
+// Remove the secret for `account`; removing a missing secret is not an error
+pub fn delete_secret(account: &str) -> Result<(), String> {
+    platform::delete(SERVICE, account)
+}
+
+// Write a file holding a secret that has to live on disk, such as a service
+// account key. On Unix it's readable only by the current user from the
+// start; a file left readable by an older version is tightened before any
+// bytes go in.
+pub fn write_private(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path).map_err(|e| e.to_string())?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(std::fs::Permissions::from_mode(0o600)).map_err(|e| e.to_string())?;
+    }
+    file.write_all(bytes).map_err(|e| e.to_string())
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use security_framework::passwords;
+
+    const ERR_SEC_ITEM_NOT_FOUND: i32 = -25300;
+
+    pub fn set(service: &str, account: &str, secret: &str) -> Result<(), String> {
+        passwords::set_generic_password(service, account, secret.as_bytes())
+            .map_err(|e| format!("Failed to save to the keychain: {}", e))
+    }
+
+    pub fn get(service: &str, account: &str) -> Result<Option<String>, String> {
+        match passwords::get_generic_password(service, account) {
+            Ok(bytes) => String::from_utf8(bytes)
+                .map(Some)
+                .map_err(|_| "Keychain item is not valid text".to_string()),
+            Err(e) if e.code() == ERR_SEC_ITEM_NOT_FOUND => Ok(None),
+            Err(e) => Err(format!("Failed to read from the keychain: {}", e)),
+        }
+    }
+
+    pub fn delete(service: &str, account: &str) -> Result<(), String> {
+        match passwords::delete_generic_password(service, account) {
+            Err(e) if e.code() != ERR_SEC_ITEM_NOT_FOUND => Err(format!("Failed to delete from the keychain: {}", e)),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::ptr;
+    use windows_sys::Win32::Foundation::{GetLastError, ERROR_NOT_FOUND};
+    use windows_sys::Win32::Security::Credentials::{
+        CredDeleteW, CredFree, CredReadW, CredWriteW, CREDENTIALW, CRED_PERSIST_LOCAL_MACHINE, CRED_TYPE_GENERIC,
+    };
+
+    // Credential Manager has a single name per credential, so the service
+    // and account are joined into one, NUL-terminated UTF-16
+    fn target_name(service: &str, account: &str) -> Vec<u16> {
+        format!("{}:{}", service, account).encode_utf16().chain(Some(0)).collect()
+    }
+
+    pub fn set(service: &str, account: &str, secret: &str) -> Result<(), String> {
+        let mut target = target_name(service, account);
+        let mut blob = secret.as_bytes().to_vec();
+        // SAFETY: CREDENTIALW is a plain C struct; all-zero is a valid empty value
+        let mut credential: CREDENTIALW = unsafe { std::mem::zeroed() };
+        credential.Type = CRED_TYPE_GENERIC;
+        credential.TargetName = target.as_mut_ptr();
+        credential.CredentialBlobSize = blob.len() as u32;
+        credential.CredentialBlob = blob.as_mut_ptr();
+        credential.Persist = CRED_PERSIST_LOCAL_MACHINE;
+
+        // SAFETY: the credential's pointers outlive the call
+        if unsafe { CredWriteW(&credential, 0) } == 0 {
+            return Err(format!("Failed to save to Credential Manager (error {})", unsafe { GetLastError() }));
+        }
+        Ok(())
+    }
+
+    pub fn get(service: &str, account: &str) -> Result<Option<String>, String> {
+        let target = target_name(service, account);
+        let mut credential: *mut CREDENTIALW = ptr::null_mut();
+        // SAFETY: on success CredReadW hands back a credential we free below
+        if unsafe { CredReadW(target.as_ptr(), CRED_TYPE_GENERIC, 0, &mut credential) } == 0 {
+            let error = unsafe { GetLastError() };
+            if error == ERROR_NOT_FOUND {
+                return Ok(None);
+            }
+            return Err(format!("Failed to read from Credential Manager (error {})", error));
+        }
+
+        // SAFETY: the blob is CredentialBlobSize bytes owned by the credential
+        let bytes = unsafe {
+            let blob = std::slice::from_raw_parts((*credential).CredentialBlob, (*credential).CredentialBlobSize as usize);
+            let bytes = blob.to_vec();
+            CredFree(credential as *const _);
+            bytes
+        };
+        String::from_utf8(bytes)
+            .map(Some)
+            .map_err(|_| "Credential Manager item is not valid text".to_string())
+    }
+
+    pub fn delete(service: &str, account: &str) -> Result<(), String> {
+        let target = target_name(service, account);
+        // SAFETY: target is NUL-terminated
+        if unsafe { CredDeleteW(target.as_ptr(), CRED_TYPE_GENERIC, 0) } == 0 {
+            let error = unsafe { GetLastError() };
+            if error != ERROR_NOT_FOUND {
+                return Err(format!("Failed to delete from Credential Manager (error {})", error));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+mod platform {
+    use std::io::Write;
+    use std::process::{Command, Output, Stdio};
+
+    // libsecret's command-line tool, talking to GNOME Keyring, KWallet etc.
+    const SECRET_TOOL: &str = "secret-tool";
+
+    fn run(args: &[&str], input: Option<&str>) -> Result<Output, String> {
+        let mut child = Command::new(SECRET_TOOL)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to run secret-tool (is it installed and on PATH?): {}", e))?;
+        // The secret goes over stdin so it never appears in the process list
+        if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+            stdin.write_all(input.as_bytes())
+                .map_err(|e| format!("Failed to write to secret-tool: {}", e))?;
+        }
+        child.wait_with_output()
+            .map_err(|e| format!("Failed to run secret-tool: {}", e))
+    }
+
+    fn failure(output: &Output) -> String {
+        format!("secret-tool failed: {}", String::from_utf8_lossy(&output.stderr).trim())
+    }
+
+    pub fn set(service: &str, account: &str, secret: &str) -> Result<(), String> {
+        let label = format!("{} {}", service, account);
+        let output = run(&["store", "--label", &label, "service", service, "account", account], Some(secret))?;
+        if !output.status.success() {
+            return Err(failure(&output));
+        }
+        Ok(())
+    }
+
+    pub fn get(service: &str, account: &str) -> Result<Option<String>, String> {
+        let output = run(&["lookup", "service", service, "account", account], None)?;
+        if output.status.success() {
+            return String::from_utf8(output.stdout)
+                .map(Some)
+                .map_err(|_| "Keyring item is not valid text".to_string());
+        }
+        // A missing secret fails without any message
+        if output.stderr.is_empty() {
+            return Ok(None);
+        }
+        Err(failure(&output))
+    }
+
+    pub fn delete(service: &str, account: &str) -> Result<(), String> {
+        let output = run(&["clear", "service", service, "account", account], None)?;
+        if !output.status.success() && !output.stderr.is_empty() {
+            return Err(failure(&output));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,285 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use chrono::Utc;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use tauri::AppHandle;
+use crate::crosslist::Marketplace;
+use crate::{image_io, network};
+use crate::photo_editing::{self, EDIT_JPEG_QUALITY};
+
+// Condition as the app records it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ListingCondition {
+    NewWithTags,
+    NewWithoutTags,
+    VeryGood,
+    Good,
+    Satisfactory,
+}
+
+// Item details shared by every marketplace. Each marketplace's listing
+// flattens this in next to its own fields, so the frontend sends one object.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Listing {
+    // Seller's stock keeping unit; generated when left out. Publishing the
+    // same SKU again updates that listing.
+    #[serde(default)]
+    pub sku: Option<String>,
+    pub title: String,
+    pub description: String,
+    // Local paths or public https URLs; the first is the primary photo
+    pub photos: Vec<String>,
+    pub price: f64,
+    pub currency: String,
+    #[serde(default = "default_quantity")]
+    pub quantity: u32,
+    pub condition: ListingCondition,
+    #[serde(default)]
+    pub condition_description: Option<String>,
+    #[serde(default)]
+    pub brand: Option<String>,
+    #[serde(default)]
+    pub size: Option<String>,
+    #[serde(default)]
+    pub colors: Vec<String>,
+    #[serde(default)]
+    pub materials: Vec<String>,
+    // Fields only one marketplace takes, by marketplace, e.g. {"ebay":
+    // {"category_id": "11450", "merchant_location_key": "home"}}; each is
+    // merged in for that marketplace when cross-listing
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub marketplace_fields: BTreeMap<Marketplace, Map<String, Value>>,
+}
+
+fn default_quantity() -> u32 {
+    1
+}
+
+// How a marketplace will see a listing, for checking it before publishing
+#[derive(Debug, Default, Serialize)]
+pub struct MappedListing {
+    // Marketplace category path, or ID where it takes one
+    pub category: Vec<String>,
+    pub condition: Option<String>,
+    pub size: Option<String>,
+    pub max_photos: usize,
+    // What has to be fixed before the listing can go up
+    pub problems: Vec<String>,
+}
+
+impl MappedListing {
+    // The first problem as an error, for publishing and exports
+    pub fn check(self) -> Result<MappedListing, String> {
+        match self.problems.first() {
+            Some(problem) => Err(problem.clone()),
+            None => Ok(self),
+        }
+    }
+}
+
+impl Listing {
+    // Problems every marketplace cares about, plus too many photos for one
+    // taking at most `max_photos`; limits that differ are checked by each
+    pub fn problems(&self, marketplace: &str, max_photos: usize) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.title.trim().is_empty() {
+            problems.push("Title is required".to_string());
+        }
+        if self.photos.is_empty() {
+            problems.push("At least one photo is required".to_string());
+        }
+        if self.photos.len() > max_photos {
+            problems.push(format!("{} allows at most {} photos", marketplace, max_photos));
+        }
+        if !(self.price.is_finite() && self.price > 0.0) {
+            problems.push("Price must be more than 0".to_string());
+        }
+        if self.quantity == 0 {
+            problems.push("Quantity must be at least 1".to_string());
+        }
+        problems
+    }
+
+    // Description with the condition notes appended, for marketplaces
+    // without a field for them
+    pub fn description_with_condition(&self) -> String {
+        match self.condition_description.as_deref().map(str::trim).filter(|d| !d.is_empty()) {
+            Some(notes) => format!("{}\n\nCondition: {}", self.description.trim_end(), notes),
+            None => self.description.clone(),
+        }
+    }
+}
+
+// Folder for a marketplace export package, named after the listing and the
+// time so exports of the same listing don't overwrite each other. Defaults
+// to the app cache's "exports" folder.
+fn package_dir(app: &AppHandle, output_dir: Option<String>, marketplace: &str, title: &str) -> Result<PathBuf, String> {
+    let parent = match output_dir {
+        Some(dir) => PathBuf::from(dir),
+        None => photo_editing::output_dir(app, "exports")?,
+    };
+    let slug: String = title
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .take(8)
+        .collect::<Vec<_>>()
+        .join("-");
+    let dir = parent.join(format!("{}-{}-{}", marketplace, slug, Utc::now().format("%Y%m%d-%H%M%S")));
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create export folder: {}", e))?;
+    Ok(dir)
+}
+
+// Write the photos into a package as 01.jpg, 02.jpg, ... in listing order,
+// each opened at most `max_edge` on its long edge and passed through `prepare`
+fn write_photos(
+    dir: &Path,
+    photos: &[String],
+    max_edge: u32,
+    prepare: impl Fn(DynamicImage) -> DynamicImage,
+) -> Result<Vec<String>, String> {
+    photos
+        .iter()
+        .enumerate()
+        .map(|(i, photo)| {
+            let img = prepare(image_io::open_image_scaled(photo, max_edge)?);
+            let path = dir.join(format!("{:02}.jpg", i + 1));
+            fs::write(&path, image_io::encode_jpeg(&img, EDIT_JPEG_QUALITY)?)
+                .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+            image_io::path_to_string(&path)
+        })
+        .collect()
+}
+
+// Numbers temporary files for downloaded photos, which share a folder
+static NEXT_TEMP_ID: AtomicU64 = AtomicU64::new(0);
+
+// Download a photo given as an https URL to a temporary file, so it can be
+// opened like a local one. The caller removes the file.
+async fn download_photo(app: &AppHandle, url: &str) -> Result<PathBuf, String> {
+    let response = network::client(app)
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| format!("Failed to download {}: {}", url, e))?;
+    let bytes = response.bytes().await.map_err(|e| format!("Failed to download {}: {}", url, e))?;
+    let path = std::env::temp_dir().join(format!(
+        "listing-assistant-photo-{}-{}",
+        std::process::id(),
+        NEXT_TEMP_ID.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::write(&path, &bytes).map_err(|e| format!("Failed to save {}: {}", url, e))?;
+    Ok(path)
+}
+
+// What a marketplace puts in its export package besides the photos
+pub struct PackageContents {
+    // Photos are opened at most this size on the long edge, then passed
+    // through `prepare`, e.g. to crop them square
+    pub photo_edge: u32,
+    pub prepare: Box<dyn Fn(DynamicImage) -> DynamicImage + Send>,
+    // Written as listing.json, with the exported photos' paths as "photos"
+    pub data: Option<Value>,
+    // Written as listing.txt, ready to paste
+    pub text: String,
+}
+
+impl PackageContents {
+    // Photos written as they are, at most `photo_edge` on the long edge
+    pub fn resized(photo_edge: u32, data: Option<Value>, text: String) -> PackageContents {
+        PackageContents { photo_edge, prepare: Box::new(|img| img), data, text }
+    }
+}
+
+// A written export package
+pub struct Package {
+    pub folder: String,
+    pub photos: Vec<String>,
+    // Set when the package has listing data
+    pub data_file: Option<String>,
+    pub text_file: String,
+}
+
+// Write a listing's export package for a marketplace without a listing API:
+// a folder with the photos in order, the marketplace's fields as JSON when
+// it has any and a text file to paste from. Fails on the mapped listing's
+// first problem before writing anything.
+pub async fn write_package(
+    app: &AppHandle,
+    marketplace: Marketplace,
+    item: &Listing,
+    mapped: &MappedListing,
+    output_dir: Option<String>,
+    contents: PackageContents,
+) -> Result<Package, String> {
+    if let Some(problem) = mapped.problems.first() {
+        return Err(problem.clone());
+    }
+    // https photos are downloaded first, and the copies removed however the
+    // package turns out
+    let mut downloads = Vec::new();
+    let package = write_package_files(app, marketplace, item, output_dir, contents, &mut downloads).await;
+    for path in &downloads {
+        let _ = fs::remove_file(path);
+    }
+    package
+}
+
+async fn write_package_files(
+    app: &AppHandle,
+    marketplace: Marketplace,
+    item: &Listing,
+    output_dir: Option<String>,
+    contents: PackageContents,
+    downloads: &mut Vec<PathBuf>,
+) -> Result<Package, String> {
+    let mut source_photos = Vec::with_capacity(item.photos.len());
+    for photo in &item.photos {
+        if photo.starts_with("https://") {
+            let path = download_photo(app, photo).await?;
+            source_photos.push(image_io::path_to_string(&path)?);
+            downloads.push(path);
+        } else {
+            source_photos.push(photo.clone());
+        }
+    }
+
+    let dir = package_dir(app, output_dir, marketplace.name(), &item.title)?;
+    let photo_dir = dir.clone();
+    let PackageContents { photo_edge, prepare, data, text } = contents;
+    let photos = tauri::async_runtime::spawn_blocking(move || {
+        write_photos(&photo_dir, &source_photos, photo_edge, prepare)
+    })
+    .await
+    .map_err(|e| format!("Failed to export photos: {}", e))??;
+
+    let data_file = match data {
+        Some(mut data) => {
+            data["photos"] = json!(photos);
+            let data_path = dir.join("listing.json");
+            let json = serde_json::to_string_pretty(&data)
+                .map_err(|e| format!("Failed to serialize listing: {}", e))?;
+            fs::write(&data_path, json)
+                .map_err(|e| format!("Failed to write listing data: {}", e))?;
+            Some(image_io::path_to_string(&data_path)?)
+        }
+        None => None,
+    };
+    let text_path = dir.join("listing.txt");
+    fs::write(&text_path, text)
+        .map_err(|e| format!("Failed to write listing text: {}", e))?;
+
+    Ok(Package {
+        folder: image_io::path_to_string(&dir)?,
+        photos,
+        data_file,
+        text_file: image_io::path_to_string(&text_path)?,
+    })
+}
@@ -0,0 +1,464 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use base64::{Engine as _, engine::general_purpose};
+use futures_util::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager, State, Window};
+use crate::operations::{CancelToken, OperationRegistry};
+use crate::{image_io, keychain, network};
+
+const OPENAI_URL: &str = "https://api.openai.com/v1";
+const ANTHROPIC_URL: &str = "https://api.anthropic.com";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+const DELTA_EVENT: &str = "listing-copy://delta";
+
+// Photos sent to the model, downscaled; marketplace copy doesn't need fine detail
+const MAX_LLM_PHOTOS: usize = 4;
+const LLM_IMAGE_EDGE: u32 = 768;
+const LLM_JPEG_QUALITY: u8 = 80;
+
+// Rough token costs used for budgeting: ~4 characters of English per token,
+// and OpenAI's fixed cost for a low-detail image
+const CHARS_PER_TOKEN: usize = 4;
+const OPENAI_IMAGE_TOKENS: usize = 85;
+
+const DEFAULT_STYLE: &str = "standard";
+
+const SYSTEM_PROMPT: &str = "You write listings for online resale marketplaces. \
+Reply with the listing title on the first line, then a blank line, then the description. \
+Don't add headings, labels, markdown or any other text. Only state details that are \
+visible in the photos or given to you; never invent brands, sizes or materials.";
+
+// Built-in prompt templates by style. `{attributes}` is replaced with the
+// detected attributes, one per line.
+const BUILT_IN_TEMPLATES: [(&str, &str); 3] = [
+    (
+        "standard",
+        "Write a listing for the item in the photos.\n\
+Title: at most 80 characters, leading with brand, item type and key details such as size, colour and model.\n\
+Description: two or three sentences, then the key details, noting any visible wear or flaws.\n\n\
+Known details:\n{attributes}",
+    ),
+    (
+        "concise",
+        "Write a short listing for the item in the photos.\n\
+Title: at most 60 characters.\n\
+Description: at most three short lines of key details, including condition.\n\n\
+Known details:\n{attributes}",
+    ),
+    (
+        "detailed",
+        "Write a thorough listing for the item in the photos.\n\
+Title: at most 80 characters, packed with the terms a buyer would search for.\n\
+Description: a friendly opening paragraph, then every known detail (brand, size, \
+measurements, materials, colour, style, era), then condition with any flaws described honestly.\n\n\
+Known details:\n{attributes}",
+    ),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LlmProvider {
+    OpenAi,
+    Anthropic,
+    // OpenAI-compatible server such as Ollama, llama.cpp or LM Studio
+    Local,
+}
+
+impl LlmProvider {
+    // Keychain account holding the provider's API key
+    fn key_account(self) -> &'static str {
+        match self {
+            LlmProvider::OpenAi => "llm-openai",
+            LlmProvider::Anthropic => "llm-anthropic",
+            LlmProvider::Local => "llm-local",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LlmSettings {
+    pub provider: LlmProvider,
+    // Base URL, e.g. "http://localhost:11434/v1"; required for local servers
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    pub model: String,
+    // Model's context window, shared by the prompt, photos and output
+    pub context_tokens: u32,
+    pub max_output_tokens: u32,
+    // Custom prompt templates by style name, overriding the built-in ones
+    #[serde(default)]
+    pub templates: BTreeMap<String, String>,
+}
+
+impl Default for LlmSettings {
+    fn default() -> LlmSettings {
+        LlmSettings {
+            provider: LlmProvider::OpenAi,
+            endpoint: None,
+            model: "gpt-4o-mini".to_string(),
+            context_tokens: 16_000,
+            max_output_tokens: 800,
+            templates: BTreeMap::new(),
+        }
+    }
+}
+
+impl LlmSettings {
+    fn validate(&self) -> Result<(), String> {
+        if self.model.trim().is_empty() {
+            return Err("Model is required".to_string());
+        }
+        if self.provider == LlmProvider::Local && self.endpoint.as_deref().is_none_or(|e| e.trim().is_empty()) {
+            return Err("Endpoint is required for a local model".to_string());
+        }
+        if self.max_output_tokens == 0 || self.max_output_tokens >= self.context_tokens {
+            return Err("max_output_tokens must be more than 0 and less than context_tokens".to_string());
+        }
+        for (style, template) in &self.templates {
+            if !template.contains("{attributes}") {
+                return Err(format!("Template \"{}\" must contain {{attributes}}", style));
+            }
+        }
+        Ok(())
+    }
+
+    fn base_url(&self) -> String {
+        let default = match self.provider {
+            LlmProvider::Anthropic => ANTHROPIC_URL,
+            _ => OPENAI_URL,
+        };
+        self.endpoint
+            .as_deref()
+            .map(str::trim)
+            .filter(|endpoint| !endpoint.is_empty())
+            .unwrap_or(default)
+            .trim_end_matches('/')
+            .to_string()
+    }
+
+    fn template(&self, style: &str) -> Result<&str, String> {
+        if let Some(template) = self.templates.get(style) {
+            return Ok(template);
+        }
+        BUILT_IN_TEMPLATES
+            .iter()
+            .find(|(name, _)| *name == style)
+            .map(|(_, template)| *template)
+            .ok_or_else(|| format!("Unknown listing style: {}", style))
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct LlmSettingsInfo {
+    #[serde(flatten)]
+    pub settings: LlmSettings,
+    // Whether an API key is stored for the provider; the key itself stays in the keychain
+    pub has_api_key: bool,
+}
+
+fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app.path_resolver()
+        .app_data_dir()
+        .ok_or("Failed to resolve app data directory")?;
+    Ok(data_dir.join("llm.json"))
+}
+
+fn load_settings(app: &AppHandle) -> Result<LlmSettings, String> {
+    let path = settings_path(app)?;
+    if !path.exists() {
+        return Ok(LlmSettings::default());
+    }
+    let json = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read LLM settings: {}", e))?;
+    serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse LLM settings: {}", e))
+}
+
+// Command to configure the model used for listing copy
+// `api_key` is stored in the OS keychain: pass a key to set it, an empty
+// string to remove it, or leave it out to keep the current one.
+#[tauri::command]
+pub fn set_llm_settings(app: AppHandle, settings: LlmSettings, api_key: Option<String>) -> Result<(), String> {
+    settings.validate()?;
+
+    let path = settings_path(&app)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create settings directory: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(&settings)
+        .map_err(|e| format!("Failed to serialize LLM settings: {}", e))?;
+    fs::write(&path, json)
+        .map_err(|e| format!("Failed to save LLM settings: {}", e))?;
+
+    match api_key.as_deref().map(str::trim) {
+        Some("") => keychain::delete_secret(settings.provider.key_account()),
+        Some(key) => keychain::set_secret(settings.provider.key_account(), key),
+        None => Ok(()),
+    }
+}
+
+// Command to get the LLM settings and whether an API key is stored
+#[tauri::command]
+pub fn get_llm_settings(app: AppHandle) -> Result<LlmSettingsInfo, String> {
+    let settings = load_settings(&app)?;
+    let has_api_key = keychain::get_secret(settings.provider.key_account())?.is_some();
+    Ok(LlmSettingsInfo { settings, has_api_key })
+}
+
+// Render detected attributes (e.g. {"brand": "Levi's", "size": "32x30"}) as
+// "key: value" lines for the prompt
+fn format_attributes(attributes: &Value) -> String {
+    let value_text = |value: &Value| match value {
+        Value::String(text) => text.trim().to_string(),
+        Value::Array(items) => items
+            .iter()
+            .map(|item| item.as_str().map(str::to_string).unwrap_or_else(|| item.to_string()))
+            .collect::<Vec<_>>()
+            .join(", "),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    };
+
+    let lines: Vec<String> = match attributes {
+        Value::Object(map) => map
+            .iter()
+            .map(|(key, value)| (key.replace('_', " "), value_text(value)))
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(key, value)| format!("{}: {}", key, value))
+            .collect(),
+        other => Some(value_text(other)).filter(|text| !text.is_empty()).into_iter().collect(),
+    };
+    if lines.is_empty() {
+        "(none)".to_string()
+    } else {
+        lines.join("\n")
+    }
+}
+
+fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN)
+}
+
+struct EncodedPhoto {
+    jpeg_base64: String,
+    tokens: usize,
+}
+
+fn encode_photo(path: &str, provider: LlmProvider) -> Result<EncodedPhoto, String> {
+    let img = image_io::open_image_scaled(path, LLM_IMAGE_EDGE)?;
+    let tokens = match provider {
+        // Anthropic charges by area, about one token per 750 pixels
+        LlmProvider::Anthropic => (img.width() as usize * img.height() as usize).div_ceil(750),
+        _ => OPENAI_IMAGE_TOKENS,
+    };
+    let jpeg = image_io::encode_jpeg(&img, LLM_JPEG_QUALITY)?;
+    Ok(EncodedPhoto { jpeg_base64: general_purpose::STANDARD.encode(jpeg), tokens })
+}
+
+// Request body for the provider, with streaming on
+fn request_body(settings: &LlmSettings, prompt: &str, photos: &[EncodedPhoto]) -> Value {
+    match settings.provider {
+        LlmProvider::Anthropic => {
+            let mut content: Vec<Value> = photos
+                .iter()
+                .map(|photo| json!({
+                    "type": "image",
+                    "source": { "type": "base64", "media_type": "image/jpeg", "data": photo.jpeg_base64 },
+                }))
+                .collect();
+            content.push(json!({ "type": "text", "text": prompt }));
+            json!({
+                "model": settings.model,
+                "max_tokens": settings.max_output_tokens,
+                "system": SYSTEM_PROMPT,
+                "stream": true,
+                "messages": [{ "role": "user", "content": content }],
+            })
+        }
+        LlmProvider::OpenAi | LlmProvider::Local => {
+            let mut content = vec![json!({ "type": "text", "text": prompt })];
+            content.extend(photos.iter().map(|photo| json!({
+                "type": "image_url",
+                "image_url": { "url": format!("data:image/jpeg;base64,{}", photo.jpeg_base64), "detail": "low" },
+            })));
+            json!({
+                "model": settings.model,
+                "max_tokens": settings.max_output_tokens,
+                "stream": true,
+                "messages": [
+                    { "role": "system", "content": SYSTEM_PROMPT },
+                    { "role": "user", "content": content },
+                ],
+            })
+        }
+    }
+}
+
+// Text carried by one server-sent event's data, or an error the stream reported
+fn event_text(provider: LlmProvider, data: &str) -> Result<Option<String>, String> {
+    if data == "[DONE]" {
+        return Ok(None);
+    }
+    let event: Value = serde_json::from_str(data)
+        .map_err(|e| format!("Failed to parse model response: {}", e))?;
+    if let Some(message) = event["error"]["message"].as_str() {
+        return Err(format!("Model request failed: {}", message));
+    }
+    let text = match provider {
+        LlmProvider::Anthropic => event["delta"]["text"].as_str(),
+        _ => event["choices"][0]["delta"]["content"].as_str(),
+    };
+    Ok(text.filter(|text| !text.is_empty()).map(str::to_string))
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ListingCopyDelta {
+    operation_id: Option<String>,
+    text: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ListingCopy {
+    pub title: String,
+    pub description: String,
+    // Photos that fit in the token budget and were sent to the model
+    pub photos_used: usize,
+}
+
+// Split the model's reply into title (first line) and description
+fn parse_copy(text: &str) -> (String, String) {
+    let text = text.trim();
+    let (title, description) = text.split_once('\n').unwrap_or((text, ""));
+    let title = title.trim().trim_start_matches("Title:").trim().trim_matches(|c| c == '"' || c == '*');
+    let description = description.trim();
+    let description = description.strip_prefix("Description:").unwrap_or(description).trim();
+    (title.trim().to_string(), description.to_string())
+}
+
+async fn stream_copy(
+    window: &Window,
+    settings: &LlmSettings,
+    api_key: Option<String>,
+    body: Value,
+    operation_id: Option<String>,
+    cancel: &CancelToken,
+) -> Result<String, String> {
+    let client = network::client(&window.app_handle());
+    let base_url = settings.base_url();
+    let request = match settings.provider {
+        LlmProvider::Anthropic => client
+            .post(format!("{}/v1/messages", base_url))
+            .header("x-api-key", api_key.unwrap_or_default())
+            .header("anthropic-version", ANTHROPIC_VERSION),
+        LlmProvider::OpenAi | LlmProvider::Local => {
+            let request = client.post(format!("{}/chat/completions", base_url));
+            match api_key {
+                Some(key) => request.bearer_auth(key),
+                None => request,
+            }
+        }
+    };
+
+    let response = request
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("Model request failed: {}", e))?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("Model request failed ({}): {}", status, body.trim()));
+    }
+
+    // Server-sent events: "data: ..." lines, possibly split across chunks
+    let mut stream = response.bytes_stream();
+    let mut pending = Vec::new();
+    let mut text = String::new();
+    while let Some(chunk) = stream
+        .try_next()
+        .await
+        .map_err(|e| format!("Model response failed: {}", e))?
+    {
+        cancel.check()?;
+        pending.extend_from_slice(&chunk);
+        while let Some(end) = pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = pending.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let Some(data) = line.trim().strip_prefix("data:") else {
+                continue;
+            };
+            if let Some(delta) = event_text(settings.provider, data.trim())? {
+                text.push_str(&delta);
+                let _ = window.emit(DELTA_EVENT, ListingCopyDelta {
+                    operation_id: operation_id.clone(),
+                    text: delta,
+                });
+            }
+        }
+    }
+    Ok(text)
+}
+
+// Command to write a listing title and description with the configured LLM
+// `detected_attributes` is an object of known details (brand, size, OCR'd
+// care tag text…); `style` picks a prompt template ("standard", "concise",
+// "detailed" or a custom one). As many photos as fit within the model's
+// context after the prompt and output budget are sent, up to four. Text is
+// streamed as `listing-copy://delta` events tagged with `operation_id`, which
+// can also be passed to `cancel_operation`.
+#[tauri::command]
+pub async fn generate_listing_copy(
+    window: Window,
+    operations: State<'_, OperationRegistry>,
+    photos: Vec<String>,
+    detected_attributes: Value,
+    style: Option<String>,
+    operation_id: Option<String>,
+) -> Result<ListingCopy, String> {
+    let app = window.app_handle();
+    let settings = load_settings(&app)?;
+    settings.validate()?;
+    let api_key = keychain::get_secret(settings.provider.key_account())?;
+    if api_key.is_none() && settings.provider != LlmProvider::Local {
+        return Err("No API key is set for the model provider".to_string());
+    }
+
+    let prompt = settings
+        .template(style.as_deref().unwrap_or(DEFAULT_STYLE))?
+        .replace("{attributes}", &format_attributes(&detected_attributes));
+    let prompt_tokens = estimate_tokens(SYSTEM_PROMPT) + estimate_tokens(&prompt);
+    let mut budget = (settings.context_tokens as usize)
+        .checked_sub(settings.max_output_tokens as usize + prompt_tokens)
+        .ok_or("The prompt and attributes don't fit in the model's context")?;
+
+    let operation = operations.register(operation_id.clone());
+    let provider = settings.provider;
+    let paths: Vec<String> = photos.into_iter().take(MAX_LLM_PHOTOS).collect();
+    let encoded = tauri::async_runtime::spawn_blocking(move || {
+        paths.iter().map(|path| encode_photo(path, provider)).collect::<Result<Vec<_>, String>>()
+    })
+    .await
+    .map_err(|e| format!("Failed to read photos: {}", e))??;
+
+    let mut included = Vec::with_capacity(encoded.len());
+    for photo in encoded {
+        if photo.tokens > budget {
+            break;
+        }
+        budget -= photo.tokens;
+        included.push(photo);
+    }
+
+    let body = request_body(&settings, &prompt, &included);
+    let text = stream_copy(&window, &settings, api_key, body, operation_id, &operation.token).await?;
+    let (title, description) = parse_copy(&text);
+    if title.is_empty() {
+        return Err("The model returned an empty listing".to_string());
+    }
+    Ok(ListingCopy { title, description, photos_used: included.len() })
+}
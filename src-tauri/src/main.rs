@@ -5,14 +5,89 @@
 
 use std::fs;
 use base64::{Engine as _, engine::general_purpose};
-use image::{DynamicImage, imageops::FilterType};
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
-use chrono::Utc;
-use rsa::{RsaPrivateKey, pkcs8::DecodePrivateKey};
-use rsa::signature::{SignatureEncoding, Signer};
-use rsa::pkcs1v15::SigningKey;
-use rsa::sha2::Sha256;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+use rsa::sha2::{Digest, Sha256};
+use tauri::{Manager, State, Window};
+
+mod amazon;
+mod b2;
+mod background;
+mod barcodes;
+mod checksum;
+mod classifier;
+mod collage;
+mod colors;
+mod condition;
+mod crosslist;
+mod db;
+mod depop;
+mod drafts;
+mod ebay;
+mod ebay_auth;
+mod ebay_import;
+mod ebay_policies;
+mod embeddings;
+mod etsy;
+mod facebook;
+mod features;
+mod fees;
+mod gcs;
+mod google_auth;
+mod grouping;
+mod hash_cache;
+mod hashing;
+mod history;
+mod image_io;
+mod inventory;
+mod keychain;
+mod listing;
+mod llm;
+mod measurements;
+mod mercari;
+mod metadata;
+mod models;
+mod network;
+mod ocr;
+mod operations;
+mod ordering;
+mod photo_editing;
+mod photo_protocol;
+mod pii;
+mod poshmark;
+mod prescreen;
+mod pricing;
+mod publishing;
+mod quality;
+mod repricing;
+mod reverse_search;
+mod s3;
+mod scheduler;
+mod sessions;
+mod sizes;
+mod storage;
+mod sync;
+mod templates;
+mod thumbnails;
+mod translation;
+mod upload_queue;
+mod video;
+mod vinted;
+mod vision;
+mod watermark;
+
+use grouping::GroupingMode;
+use hash_cache::{FileStamp, HashCache};
+use hashing::{ColorSignature, HashAlgorithm, ImageHash, calculate_similarity};
+use models::ModelStore;
+use operations::{CancelToken, OperationRegistry};
+use quality::{PhotoQuality, QualityScore};
+use sessions::{Correction, SessionStore};
+use thumbnails::ThumbnailCache;
 
 #[derive(Debug, Serialize, Deserialize)]
 struct PhotoGroup {
@@ -20,123 +95,965 @@ struct PhotoGroup {
     photos: Vec<String>,
     primary_photo: String,
     confidence: f64,
+    // Per-photo sharpness/exposure scores used to pick the primary photo
+    #[serde(default)]
+    quality_scores: Vec<PhotoQuality>,
+}
+
+// Settings for a grouping run
+struct GroupingOptions {
+    algorithm: HashAlgorithm,
+    mode: GroupingMode,
+    time_gap_seconds: i64,
+    similarity_threshold: f64,
+    color_weight: f64,
+    feature_matching: bool,
+}
+
+// Payload for `grouping://progress` events
+#[derive(Debug, Clone, Serialize)]
+struct GroupingProgress {
+    current: usize,
+    total: usize,
+    filename: String,
+}
+
+// Payload for the `grouping://complete` event
+#[derive(Debug, Clone, Serialize)]
+struct GroupingSummary {
+    total_photos: usize,
+    group_count: usize,
+    elapsed_ms: u64,
 }
 
 // Command to read an image file and return it as a base64 data URI
+// When `upright` is set, rotated photos are re-encoded using their EXIF orientation
 #[tauri::command]
-fn read_image_as_base64(file_path: String) -> Result<String, String> {
+fn read_image_as_base64(file_path: String, upright: Option<bool>) -> Result<String, String> {
+    // HEIC and RAW can't be displayed by the webview, so convert them to JPEG first
+    if image_io::needs_jpeg_conversion(&file_path) {
+        let jpeg_data = image_io::convert_to_jpeg(&file_path)?;
+        let base64_string = general_purpose::STANDARD.encode(&jpeg_data);
+        return Ok(format!("data:image/jpeg;base64,{}", base64_string));
+    }
+
+    if upright.unwrap_or(false) && image_io::read_orientation(&file_path) != 1 {
+        let img = image_io::open_image(&file_path)?;
+        let jpeg_data = image_io::encode_jpeg(&img, image_io::DISPLAY_JPEG_QUALITY)?;
+        let base64_string = general_purpose::STANDARD.encode(&jpeg_data);
+        return Ok(format!("data:image/jpeg;base64,{}", base64_string));
+    }
+
     // Read the file
     let image_data = fs::read(&file_path)
         .map_err(|e| format!("Failed to read file: {}", e))?;
 
+    // Determine MIME type from the file contents, falling back to the extension
+    let mime_type = image_io::sniff_mime(&image_data)
+        .or_else(|| image_io::mime_from_extension(&file_path))
+        .ok_or_else(|| format!("Unrecognised image format: {}", file_path))?;
+
+    if image_io::is_heif_mime(mime_type) {
+        let jpeg_data = image_io::convert_to_jpeg(&file_path)?;
+        let base64_string = general_purpose::STANDARD.encode(&jpeg_data);
+        return Ok(format!("data:image/jpeg;base64,{}", base64_string));
+    }
+
     // Encode to base64
     let base64_string = general_purpose::STANDARD.encode(&image_data);
 
-    // Determine MIME type from extension
-    let mime_type = if file_path.to_lowercase().ends_with(".png") {
-        "image/png"
-    } else if file_path.to_lowercase().ends_with(".gif") {
-        "image/gif"
-    } else {
-        "image/jpeg" // Default to JPEG for .jpg, .jpeg, and others
-    };
-
     // Return as data URI
     Ok(format!("data:{};base64,{}", mime_type, base64_string))
 }
 
-// Generate perceptual hash (difference hash) for an image
-fn generate_dhash(img: &DynamicImage) -> Result<u64, String> {
-    // Resize to 9x8 grayscale
-    let resized = img.resize_exact(9, 8, FilterType::Lanczos3).to_luma8();
+// Hash photos, only decoding files that are new or changed since the last run
+// `on_hashed` is called once per photo as soon as its hash is known
+fn hash_photos<F>(photo_paths: &[String], algorithm: HashAlgorithm, cache: &HashCache, cancel: &CancelToken, on_hashed: F) -> Result<Vec<(String, ImageHash)>, String>
+where
+    F: Fn(&str) + Sync,
+{
+    let stamps = photo_paths
+        .iter()
+        .map(|path| FileStamp::for_path(path))
+        .collect::<Result<Vec<_>, String>>()?;
 
-    let mut hash: u64 = 0;
-    for y in 0..8 {
-        for x in 0..8 {
-            let left = resized.get_pixel(x, y)[0];
-            let right = resized.get_pixel(x + 1, y)[0];
-            if left > right {
-                hash |= 1 << (y * 8 + x);
-            }
+    let mut hashes: Vec<Option<ImageHash>> = photo_paths
+        .iter()
+        .zip(&stamps)
+        .map(|(path, stamp)| cache.get(path, algorithm, *stamp))
+        .collect();
+
+    for (path, hash) in photo_paths.iter().zip(&hashes) {
+        if hash.is_some() {
+            on_hashed(path);
         }
     }
 
-    Ok(hash)
+    let missing: Vec<usize> = (0..photo_paths.len())
+        .filter(|&i| hashes[i].is_none())
+        .collect();
+
+    // Hash cache misses in parallel (collect keeps input order)
+    // Quality is scored while the image is decoded anyway
+    let fresh = missing
+        .par_iter()
+        .map(|&i| {
+            cancel.check()?;
+            let img = image_io::open_image_scaled(&photo_paths[i], image_io::ANALYSIS_EDGE)?;
+            let hash = algorithm.compute(&img)?;
+            let score = quality::score_photo(&img);
+            on_hashed(&photo_paths[i]);
+            Ok((i, hash, score))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let entries: Vec<(String, FileStamp, ImageHash)> = fresh
+        .iter()
+        .map(|(i, hash, _)| (photo_paths[*i].clone(), stamps[*i], hash.clone()))
+        .collect();
+    cache.put_many(algorithm, &entries)?;
+
+    let quality_entries: Vec<(String, FileStamp, QualityScore)> = fresh
+        .iter()
+        .map(|(i, _, score)| (photo_paths[*i].clone(), stamps[*i], *score))
+        .collect();
+    cache.put_quality_many(&quality_entries)?;
+
+    for (i, hash, _) in fresh {
+        hashes[i] = Some(hash);
+    }
+
+    Ok(photo_paths.iter().cloned().zip(hashes.into_iter().flatten()).collect())
 }
 
-// Calculate Hamming distance between two hashes
-fn hamming_distance(hash1: u64, hash2: u64) -> u32 {
-    (hash1 ^ hash2).count_ones()
+// CLIP embeddings for photos, only running the model on files without cached ones
+// `on_embedded` is called once per photo as soon as its embedding is known
+fn embed_photos<F>(photo_paths: &[String], models: &ModelStore, cache: &HashCache, cancel: &CancelToken, on_embedded: F) -> Result<Vec<Vec<f32>>, String>
+where
+    F: Fn(&str) + Sync,
+{
+    let stamps = photo_paths
+        .iter()
+        .map(|path| FileStamp::for_path(path))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let vectors = photo_paths
+        .par_iter()
+        .zip(&stamps)
+        .map(|(path, stamp)| {
+            if let Some(embedding) = cache.get_embedding(path, embeddings::CLIP_MODEL, *stamp) {
+                on_embedded(path);
+                return Ok((embedding, false));
+            }
+            cancel.check()?;
+            let img = image_io::open_image_scaled(path, image_io::ANALYSIS_EDGE)?;
+            let embedding = embeddings::image_embedding(models, &img)?;
+            on_embedded(path);
+            Ok((embedding, true))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let fresh: Vec<(String, FileStamp, Vec<f32>)> = vectors
+        .iter()
+        .zip(photo_paths.iter().zip(&stamps))
+        .filter(|((_, is_fresh), _)| *is_fresh)
+        .map(|((embedding, _), (path, stamp))| (path.clone(), *stamp, embedding.clone()))
+        .collect();
+    cache.put_embedding_many(embeddings::CLIP_MODEL, &fresh)?;
+
+    Ok(vectors.into_iter().map(|(embedding, _)| embedding).collect())
+}
+
+// Score photo quality, only decoding files without cached scores
+fn score_photos(photo_paths: &[String], cache: &HashCache, cancel: &CancelToken) -> Result<Vec<QualityScore>, String> {
+    let stamps = photo_paths
+        .iter()
+        .map(|path| FileStamp::for_path(path))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let scores = photo_paths
+        .par_iter()
+        .zip(&stamps)
+        .map(|(path, stamp)| {
+            if let Some(score) = cache.get_quality(path, *stamp) {
+                return Ok((score, false));
+            }
+            cancel.check()?;
+            let img = image_io::open_image_scaled(path, image_io::ANALYSIS_EDGE)?;
+            Ok((quality::score_photo(&img), true))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let fresh: Vec<(String, FileStamp, QualityScore)> = scores
+        .iter()
+        .zip(photo_paths.iter().zip(&stamps))
+        .filter(|((_, is_fresh), _)| *is_fresh)
+        .map(|((score, _), (path, stamp))| (path.clone(), *stamp, *score))
+        .collect();
+    cache.put_quality_many(&fresh)?;
+
+    Ok(scores.into_iter().map(|(score, _)| score).collect())
+}
+
+// Color signatures for photos, or all None when `enabled` is false
+fn color_signatures(photo_paths: &[String], cache: &HashCache, cancel: &CancelToken, enabled: bool) -> Result<Vec<Option<ColorSignature>>, String> {
+    if !enabled {
+        return Ok(vec![None; photo_paths.len()]);
+    }
+
+    let stamps = photo_paths
+        .iter()
+        .map(|path| FileStamp::for_path(path))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let signatures = photo_paths
+        .par_iter()
+        .zip(&stamps)
+        .map(|(path, stamp)| {
+            if let Some(signature) = cache.get_color(path, *stamp) {
+                return Ok((signature, false));
+            }
+            cancel.check()?;
+            let img = image_io::open_image_scaled(path, image_io::ANALYSIS_EDGE)?;
+            Ok((ColorSignature::compute(&img), true))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let fresh: Vec<(String, FileStamp, ColorSignature)> = signatures
+        .iter()
+        .zip(photo_paths.iter().zip(&stamps))
+        .filter(|((_, is_fresh), _)| *is_fresh)
+        .map(|((signature, _), (path, stamp))| (path.clone(), *stamp, *signature))
+        .collect();
+    cache.put_color_many(&fresh)?;
+
+    Ok(signatures.into_iter().map(|(signature, _)| Some(signature)).collect())
 }
 
-// Calculate similarity (0.0 to 1.0)
-fn calculate_similarity(hash1: u64, hash2: u64) -> f64 {
-    let distance = hamming_distance(hash1, hash2);
-    1.0 - (distance as f64 / 64.0)
+// Similarity of two photos from their hashes and, if computed, color signatures
+fn photo_similarity(hash_a: &ImageHash, color_a: Option<&ColorSignature>, hash_b: &ImageHash, color_b: Option<&ColorSignature>, color_weight: f64) -> f64 {
+    let color = color_a.zip(color_b).map(|(a, b)| a.similarity(b));
+    hashing::combined_similarity(calculate_similarity(hash_a, hash_b), color, color_weight)
+}
+
+// Build a group, choosing the sharpest, best-exposed photo as primary
+fn build_group(id: String, photos: Vec<String>, scores: Vec<QualityScore>, confidence: f64) -> PhotoGroup {
+    let primary = quality::best_index(&scores).unwrap_or(0);
+    PhotoGroup {
+        id,
+        primary_photo: photos[primary].clone(),
+        quality_scores: photos
+            .iter()
+            .zip(scores)
+            .map(|(path, score)| PhotoQuality { path: path.clone(), score })
+            .collect(),
+        photos,
+        confidence,
+    }
 }
 
 // Group photos by similarity
+// Hashing runs on a blocking worker and reports `grouping://progress` events,
+// followed by a single `grouping://complete` summary. Passing an `operation_id`
+// lets the frontend stop the run with `cancel_operation`.
+// `hash_algorithm` is one of "dhash" (default), "dhash256", "phash", "ahash" or "blockhash".
+// `grouping_mode` "time_assisted" also splits items on EXIF capture-time gaps
+// longer than `time_gap_seconds` (default 60). "embedding" compares photos by
+// CLIP image embeddings from a local ONNX model instead of perceptual hashes,
+// grouping by what the item is even across different backgrounds and angles.
+// `color_weight` (0.0-1.0, default 0) blends in a color histogram comparison so
+// color variants of the same item end up in separate groups.
+// `feature_matching` re-checks pairs close to the threshold with ORB-style
+// keypoint matching, which copes better with different angles and distances.
+// Manual corrections (see `merge_groups`) are applied, and the result becomes
+// the current grouping session.
 #[tauri::command]
-fn group_photos_by_item(photo_paths: Vec<String>, similarity_threshold: f64) -> Result<Vec<PhotoGroup>, String> {
+#[allow(clippy::too_many_arguments)]
+async fn group_photos_by_item(
+    window: Window,
+    photo_paths: Vec<String>,
+    similarity_threshold: f64,
+    hash_algorithm: Option<String>,
+    grouping_mode: Option<String>,
+    time_gap_seconds: Option<i64>,
+    color_weight: Option<f64>,
+    feature_matching: Option<bool>,
+    operation_id: Option<String>,
+) -> Result<Vec<PhotoGroup>, String> {
+    let options = GroupingOptions {
+        algorithm: HashAlgorithm::parse(hash_algorithm.as_deref())?,
+        mode: GroupingMode::parse(grouping_mode.as_deref())?,
+        time_gap_seconds: time_gap_seconds.unwrap_or(grouping::DEFAULT_TIME_GAP_SECONDS),
+        similarity_threshold,
+        color_weight: parse_color_weight(color_weight)?,
+        feature_matching: feature_matching.unwrap_or(false),
+    };
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let cache = window.state::<HashCache>();
+        let models = window.state::<ModelStore>();
+        let sessions = window.state::<SessionStore>();
+        let operations = window.state::<OperationRegistry>();
+        let operation = operations.register(operation_id);
+        let corrections = sessions.corrections()?;
+        let groups = group_photos(&window, &cache, &models, &operation.token, photo_paths, &corrections, &options)?;
+        sessions.save(sessions::CURRENT_SESSION, &groups)?;
+        Ok(groups)
+    })
+    .await
+    .map_err(|e| format!("Photo grouping task failed: {}", e))?
+}
+
+#[allow(clippy::too_many_arguments)]
+fn group_photos(
+    window: &Window,
+    cache: &HashCache,
+    models: &ModelStore,
+    cancel: &CancelToken,
+    photo_paths: Vec<String>,
+    corrections: &[Correction],
+    options: &GroupingOptions,
+) -> Result<Vec<PhotoGroup>, String> {
     if photo_paths.is_empty() {
         return Ok(vec![]);
     }
 
-    // Generate hashes for all photos
-    let mut hashes: Vec<(String, u64)> = Vec::new();
-    for path in &photo_paths {
-        let img = image::open(path)
-            .map_err(|e| format!("Failed to open image {}: {}", path, e))?;
-        let hash = generate_dhash(&img)?;
-        hashes.push((path.clone(), hash));
+    let started = Instant::now();
+    let total = photo_paths.len();
+
+    let completed = AtomicUsize::new(0);
+    let on_progress = |path: &str| {
+        let current = completed.fetch_add(1, Ordering::SeqCst) + 1;
+        let _ = window.emit("grouping://progress", GroupingProgress {
+            current,
+            total,
+            filename: file_name(path),
+        });
+    };
+
+    // Generate hashes (or embeddings in embedding mode) for all photos, reusing cached ones
+    let (hashes, vectors) = match options.mode {
+        GroupingMode::Embedding => (Vec::new(), embed_photos(&photo_paths, models, cache, cancel, on_progress)?),
+        _ => (hash_photos(&photo_paths, options.algorithm, cache, cancel, on_progress)?, Vec::new()),
+    };
+
+    // Capture-time bursts, only read in time-assisted mode
+    let bursts = match options.mode {
+        GroupingMode::Visual | GroupingMode::Embedding => vec![None; photo_paths.len()],
+        GroupingMode::TimeAssisted => {
+            let timestamps: Vec<Option<i64>> = photo_paths
+                .par_iter()
+                .map(|path| image_io::read_capture_timestamp(path))
+                .collect();
+            grouping::assign_bursts(&timestamps, options.time_gap_seconds)
+        }
+    };
+
+    let colors = color_signatures(&photo_paths, cache, cancel, options.color_weight > 0.0)?;
+
+    let similarity = |i: usize, j: usize| {
+        let visual = match options.mode {
+            GroupingMode::Embedding => {
+                let color = colors[i].as_ref().zip(colors[j].as_ref()).map(|(a, b)| a.similarity(b));
+                hashing::combined_similarity(embeddings::cosine_similarity(&vectors[i], &vectors[j]), color, options.color_weight)
+            }
+            _ => photo_similarity(&hashes[i].1, colors[i].as_ref(), &hashes[j].1, colors[j].as_ref(), options.color_weight),
+        };
+        grouping::time_adjusted_similarity(visual, bursts[i], bursts[j])
+    };
+
+    // Optional second opinion from keypoint matching on borderline pairs
+    cancel.check()?;
+    let mut overrides = if options.feature_matching {
+        features::verify_borderline(&photo_paths, options.similarity_threshold, similarity)?
+    } else {
+        HashMap::new()
+    };
+
+    // Manual corrections pin pairs together or apart
+    let index: HashMap<&str, usize> = photo_paths.iter().enumerate().map(|(i, path)| (path.as_str(), i)).collect();
+    for correction in corrections {
+        if let (Some(&a), Some(&b)) = (index.get(correction.photo_a.as_str()), index.get(correction.photo_b.as_str())) {
+            overrides.insert((a.min(b), a.max(b)), if correction.together { 1.0 } else { 0.0 });
+        }
     }
 
-    // Group photos by similarity
-    let mut groups: Vec<PhotoGroup> = Vec::new();
-    let mut assigned: HashSet<usize> = HashSet::new();
+    // Cluster transitively over all pairs above the threshold
+    cancel.check()?;
+    let clusters = grouping::cluster_by_similarity(total, options.similarity_threshold, |i, j| {
+        overrides.get(&(i, j)).copied().unwrap_or_else(|| similarity(i, j))
+    });
+
+    // Pick each group's primary photo by quality
+    let scores = score_photos(&photo_paths, cache, cancel)?;
+
+    let groups: Vec<PhotoGroup> = clusters
+        .into_iter()
+        .enumerate()
+        .map(|(index, cluster)| {
+            let photos: Vec<String> = cluster.members
+                .iter()
+                .map(|&i| photo_paths[i].clone())
+                .collect();
+            let group_scores: Vec<QualityScore> = cluster.members
+                .iter()
+                .map(|&i| scores[i])
+                .collect();
+            build_group(format!("item-{}", index + 1), photos, group_scores, cluster.confidence)
+        })
+        .collect();
+
+    let _ = window.emit("grouping://complete", GroupingSummary {
+        total_photos: total,
+        group_count: groups.len(),
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    });
+
+    Ok(groups)
+}
+
+fn parse_color_weight(color_weight: Option<f64>) -> Result<f64, String> {
+    let color_weight = color_weight.unwrap_or(0.0);
+    if !(0.0..=1.0).contains(&color_weight) {
+        return Err("color_weight must be between 0 and 1".to_string());
+    }
+    Ok(color_weight)
+}
 
-    for i in 0..hashes.len() {
-        if assigned.contains(&i) {
+// Extract the file name from a path for display in progress events
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string())
+}
+
+// Add newly shot photos to an existing grouping without regrouping everything
+// Each new photo joins the existing group holding its most similar photo if
+// that meets the threshold; the rest are clustered into new groups. Existing
+// group ids are preserved and new ones continue the "item-N" numbering.
+// The result becomes the current grouping session.
+#[tauri::command]
+async fn add_photos_to_groups(
+    window: Window,
+    existing_groups: Vec<PhotoGroup>,
+    new_paths: Vec<String>,
+    similarity_threshold: f64,
+    hash_algorithm: Option<String>,
+    color_weight: Option<f64>,
+) -> Result<Vec<PhotoGroup>, String> {
+    let algorithm = HashAlgorithm::parse(hash_algorithm.as_deref())?;
+    let color_weight = parse_color_weight(color_weight)?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let cache = window.state::<HashCache>();
+        let sessions = window.state::<SessionStore>();
+        let groups = add_to_groups(&cache, existing_groups, new_paths, algorithm, similarity_threshold, color_weight)?;
+        sessions.save(sessions::CURRENT_SESSION, &groups)?;
+        Ok(groups)
+    })
+    .await
+    .map_err(|e| format!("Photo grouping task failed: {}", e))?
+}
+
+// A photo's hash and, when color weighting is on, its color signature
+type Fingerprint = (ImageHash, Option<ColorSignature>);
+
+fn fingerprint_photos(photo_paths: &[String], algorithm: HashAlgorithm, cache: &HashCache, color_weight: f64) -> Result<Vec<(String, Fingerprint)>, String> {
+    let none = CancelToken::none();
+    let hashes = hash_photos(photo_paths, algorithm, cache, &none, |_| {})?;
+    let colors = color_signatures(photo_paths, cache, &none, color_weight > 0.0)?;
+    Ok(hashes
+        .into_iter()
+        .zip(colors)
+        .map(|((path, hash), color)| (path, (hash, color)))
+        .collect())
+}
+
+fn add_to_groups(
+    cache: &HashCache,
+    mut groups: Vec<PhotoGroup>,
+    new_paths: Vec<String>,
+    algorithm: HashAlgorithm,
+    similarity_threshold: f64,
+    color_weight: f64,
+) -> Result<Vec<PhotoGroup>, String> {
+    // Skip photos that are already grouped
+    let grouped: HashSet<&String> = groups.iter().flat_map(|g| &g.photos).collect();
+    let new_paths: Vec<String> = new_paths.into_iter().filter(|p| !grouped.contains(p)).collect();
+    if new_paths.is_empty() {
+        return Ok(groups);
+    }
+
+    let similarity = |a: &Fingerprint, b: &Fingerprint| {
+        photo_similarity(&a.0, a.1.as_ref(), &b.0, b.1.as_ref(), color_weight)
+    };
+
+    // Existing photos are normally cache hits, so only new photos get decoded
+    let none = CancelToken::none();
+    let existing_prints: Vec<Vec<Fingerprint>> = groups
+        .iter()
+        .map(|group| {
+            fingerprint_photos(&group.photos, algorithm, cache, color_weight)
+                .map(|prints| prints.into_iter().map(|(_, print)| print).collect())
+        })
+        .collect::<Result<_, String>>()?;
+    let new_prints = fingerprint_photos(&new_paths, algorithm, cache, color_weight)?;
+
+    // Assign each new photo to its best matching existing group
+    let mut additions: Vec<Vec<(String, Fingerprint)>> = vec![Vec::new(); groups.len()];
+    let mut unmatched: Vec<(String, Fingerprint)> = Vec::new();
+    for (path, print) in new_prints {
+        let best = existing_prints
+            .iter()
+            .enumerate()
+            .filter_map(|(index, prints)| {
+                prints.iter()
+                    .map(|existing| similarity(existing, &print))
+                    .reduce(f64::max)
+                    .map(|similarity| (index, similarity))
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+
+        match best {
+            Some((index, similarity)) if similarity >= similarity_threshold => {
+                additions[index].push((path, print));
+            }
+            _ => unmatched.push((path, print)),
+        }
+    }
+
+    for ((group, prints), added) in groups.iter_mut().zip(existing_prints).zip(additions) {
+        if added.is_empty() {
             continue;
         }
 
-        let mut group_photos = vec![hashes[i].0.clone()];
-        assigned.insert(i);
+        // Added photos get quality scores; the existing primary photo is kept
+        let added_paths: Vec<String> = added.iter().map(|(path, _)| path.clone()).collect();
+        let added_scores = score_photos(&added_paths, cache, &none)?;
+        for (path, score) in added_paths.into_iter().zip(added_scores) {
+            group.quality_scores.push(PhotoQuality { path, score });
+        }
+
+        let mut all_prints = prints;
+        for (path, print) in added {
+            group.photos.push(path);
+            all_prints.push(print);
+        }
+
+        let members: Vec<usize> = (0..all_prints.len()).collect();
+        group.confidence = grouping::mean_pairwise_similarity(&members, |i, j| {
+            similarity(&all_prints[i], &all_prints[j])
+        });
+    }
+
+    // Remaining photos form new groups among themselves
+    let first_number = grouping::next_group_number(groups.iter().map(|g| g.id.as_str()));
+    let clusters = grouping::cluster_by_similarity(unmatched.len(), similarity_threshold, |i, j| {
+        similarity(&unmatched[i].1, &unmatched[j].1)
+    });
+    for (number, cluster) in (first_number..).zip(clusters) {
+        let photos: Vec<String> = cluster.members
+            .iter()
+            .map(|&i| unmatched[i].0.clone())
+            .collect();
+        let scores = score_photos(&photos, cache, &none)?;
+        groups.push(build_group(format!("item-{}", number), photos, scores, cluster.confidence));
+    }
+
+    Ok(groups)
+}
+
+// Load the groups of the current grouping session
+fn current_groups(sessions: &SessionStore) -> Result<Vec<PhotoGroup>, String> {
+    sessions.load(sessions::CURRENT_SESSION)?
+        .ok_or_else(|| "No grouping session; group photos first".to_string())
+}
 
-        // Find similar photos
-        for j in (i + 1)..hashes.len() {
-            if assigned.contains(&j) {
-                continue;
+fn group_index(groups: &[PhotoGroup], id: &str) -> Result<usize, String> {
+    groups.iter()
+        .position(|group| group.id == id)
+        .ok_or_else(|| format!("Group not found: {}", id))
+}
+
+// Rebuild a manually edited group; groups the user has confirmed get full confidence
+fn rebuild_group(cache: &HashCache, id: String, photos: Vec<String>) -> Result<PhotoGroup, String> {
+    let scores = score_photos(&photos, cache, &CancelToken::none())?;
+    Ok(build_group(id, photos, scores, 1.0))
+}
+
+// Remember that photos in the same edited group belong together and photos in
+// different edited groups don't, so later grouping runs honour the edit
+fn record_corrections(sessions: &SessionStore, groups: &[&PhotoGroup]) -> Result<(), String> {
+    let mut corrections = Vec::new();
+    for (index, group) in groups.iter().enumerate() {
+        for (a, photo_a) in group.photos.iter().enumerate() {
+            for photo_b in &group.photos[a + 1..] {
+                corrections.push(Correction { photo_a: photo_a.clone(), photo_b: photo_b.clone(), together: true });
+            }
+            for other in &groups[index + 1..] {
+                for photo_b in &other.photos {
+                    corrections.push(Correction { photo_a: photo_a.clone(), photo_b: photo_b.clone(), together: false });
+                }
             }
+        }
+    }
+    sessions.put_corrections(&corrections)
+}
 
-            let similarity = calculate_similarity(hashes[i].1, hashes[j].1);
-            if similarity >= similarity_threshold {
-                group_photos.push(hashes[j].0.clone());
-                assigned.insert(j);
+// Command to merge groups of the current session into the first of `ids`
+// Returns the updated session groups
+#[tauri::command(async)]
+fn merge_groups(cache: State<HashCache>, sessions: State<SessionStore>, ids: Vec<String>) -> Result<Vec<PhotoGroup>, String> {
+    if ids.iter().collect::<HashSet<_>>().len() < 2 {
+        return Err("Choose at least two groups to merge".to_string());
+    }
+
+    let mut groups = current_groups(&sessions)?;
+    let mut photos: Vec<String> = Vec::new();
+    for id in &ids {
+        for photo in &groups[group_index(&groups, id)?].photos {
+            if !photos.contains(photo) {
+                photos.push(photo.clone());
             }
         }
+    }
 
-        // Create group
-        let confidence = if group_photos.len() > 1 { 0.85 } else { 0.5 };
-        groups.push(PhotoGroup {
-            id: format!("item-{}", groups.len() + 1),
-            photos: group_photos.clone(),
-            primary_photo: group_photos[0].clone(),
-            confidence,
-        });
+    let target = group_index(&groups, &ids[0])?;
+    groups[target] = rebuild_group(&cache, ids[0].clone(), photos)?;
+    groups.retain(|group| group.id == ids[0] || !ids.contains(&group.id));
+
+    record_corrections(&sessions, &[&groups[group_index(&groups, &ids[0])?]])?;
+    sessions.save(sessions::CURRENT_SESSION, &groups)?;
+    Ok(groups)
+}
+
+// Command to split `photo_paths` out of a group of the current session into a new group
+// Returns the updated session groups
+#[tauri::command(async)]
+fn split_group(cache: State<HashCache>, sessions: State<SessionStore>, id: String, photo_paths: Vec<String>) -> Result<Vec<PhotoGroup>, String> {
+    let mut groups = current_groups(&sessions)?;
+    let index = group_index(&groups, &id)?;
+
+    if let Some(missing) = photo_paths.iter().find(|path| !groups[index].photos.contains(path)) {
+        return Err(format!("Photo {} is not in group {}", missing, id));
+    }
+    let (moved, kept): (Vec<String>, Vec<String>) = groups[index].photos
+        .iter()
+        .cloned()
+        .partition(|path| photo_paths.contains(path));
+    if moved.is_empty() || kept.is_empty() {
+        return Err("Choose some, but not all, of the group's photos to split off".to_string());
     }
 
+    let new_id = format!("item-{}", grouping::next_group_number(groups.iter().map(|g| g.id.as_str())));
+    groups[index] = rebuild_group(&cache, id, kept)?;
+    groups.insert(index + 1, rebuild_group(&cache, new_id, moved)?);
+
+    record_corrections(&sessions, &[&groups[index], &groups[index + 1]])?;
+    sessions.save(sessions::CURRENT_SESSION, &groups)?;
+    Ok(groups)
+}
+
+// Command to move one photo between groups of the current session
+// A group left empty is removed. Returns the updated session groups.
+#[tauri::command(async)]
+fn move_photo(cache: State<HashCache>, sessions: State<SessionStore>, photo: String, from_group: String, to_group: String) -> Result<Vec<PhotoGroup>, String> {
+    if from_group == to_group {
+        return Err("Source and destination groups are the same".to_string());
+    }
+
+    let mut groups = current_groups(&sessions)?;
+    let from = group_index(&groups, &from_group)?;
+    let to = group_index(&groups, &to_group)?;
+    if !groups[from].photos.contains(&photo) {
+        return Err(format!("Photo {} is not in group {}", photo, from_group));
+    }
+
+    let remaining: Vec<String> = groups[from].photos.iter().filter(|path| **path != photo).cloned().collect();
+    let mut photos = groups[to].photos.clone();
+    photos.push(photo);
+    groups[to] = rebuild_group(&cache, to_group.clone(), photos)?;
+
+    if remaining.is_empty() {
+        groups.remove(from);
+    } else {
+        groups[from] = rebuild_group(&cache, from_group.clone(), remaining)?;
+    }
+
+    let edited: Vec<&PhotoGroup> = groups.iter()
+        .filter(|group| group.id == from_group || group.id == to_group)
+        .collect();
+    record_corrections(&sessions, &edited)?;
+    sessions.save(sessions::CURRENT_SESSION, &groups)?;
+    Ok(groups)
+}
+
+// Command to suggest the order to list a group's photos in (front, back,
+// label, then flaws and other close-ups), returned with each photo's role
+#[tauri::command(async)]
+fn suggest_photo_order(group: PhotoGroup) -> Result<Vec<ordering::OrderedPhoto>, String> {
+    ordering::suggest_order(&group.photos)
+}
+
+// Command to save groups under `name`, e.g. to continue listing another day
+// The groups also become the current session.
+#[tauri::command(async)]
+fn save_grouping_session(sessions: State<SessionStore>, name: String, groups: Vec<PhotoGroup>) -> Result<(), String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Session name is empty".to_string());
+    }
+    sessions.save(name, &groups)?;
+    sessions.save(sessions::CURRENT_SESSION, &groups)
+}
+
+// Command to load groups saved with `save_grouping_session`, making them the
+// current session so they can be edited further
+#[tauri::command(async)]
+fn load_grouping_session(sessions: State<SessionStore>, name: String) -> Result<Vec<PhotoGroup>, String> {
+    let name = name.trim();
+    let groups: Vec<PhotoGroup> = sessions.load(name)?
+        .ok_or_else(|| format!("No saved grouping session named {}", name))?;
+    sessions.save(sessions::CURRENT_SESSION, &groups)?;
     Ok(groups)
 }
 
+// One pair of photos at or above the requested similarity floor
+#[derive(Debug, Serialize)]
+struct SimilarPair {
+    a: usize,
+    b: usize,
+    similarity: f64,
+}
+
+// Similarities between every pair of photos, indexed in input order
+#[derive(Debug, Serialize)]
+struct SimilarityMatrix {
+    paths: Vec<String>,
+    // Full NxN matrix, only when no floor is given
+    matrix: Option<Vec<Vec<f64>>>,
+    // Pairs with similarity >= floor, only when a floor is given
+    pairs: Option<Vec<SimilarPair>>,
+    // Threshold separating same-item pairs from different-item pairs
+    suggested_threshold: Option<f64>,
+}
+
+// Command to compute pairwise similarities for a threshold-tuning heatmap
+// Without `floor` the full matrix is returned; with it, only pairs at or above it
+#[tauri::command(async)]
+fn compute_similarity_matrix(
+    cache: State<HashCache>,
+    paths: Vec<String>,
+    hash_algorithm: Option<String>,
+    floor: Option<f64>,
+) -> Result<SimilarityMatrix, String> {
+    let algorithm = HashAlgorithm::parse(hash_algorithm.as_deref())?;
+    let hashes = hash_photos(&paths, algorithm, &cache, &CancelToken::none(), |_| {})?;
+
+    let count = hashes.len();
+    let mut matrix = vec![vec![1.0; count]; count];
+    let mut similarities = Vec::with_capacity(count * count.saturating_sub(1) / 2);
+    for i in 0..count {
+        for j in (i + 1)..count {
+            let similarity = calculate_similarity(&hashes[i].1, &hashes[j].1);
+            matrix[i][j] = similarity;
+            matrix[j][i] = similarity;
+            similarities.push(similarity);
+        }
+    }
+
+    let suggested_threshold = grouping::suggest_threshold(&similarities);
+    let (matrix, pairs) = match floor {
+        Some(floor) => {
+            let pairs = (0..count)
+                .flat_map(|i| ((i + 1)..count).map(move |j| (i, j)))
+                .filter(|&(i, j)| matrix[i][j] >= floor)
+                .map(|(a, b)| SimilarPair { a, b, similarity: matrix[a][b] })
+                .collect();
+            (None, Some(pairs))
+        }
+        None => (Some(matrix), None),
+    };
+
+    Ok(SimilarityMatrix { paths, matrix, pairs, suggested_threshold })
+}
+
+// Photos that are copies of each other, with the one to keep
+#[derive(Debug, Serialize)]
+struct DuplicateCluster {
+    photos: Vec<String>,
+    keeper: String,
+    // True when every photo in the cluster is byte-for-byte identical
+    exact: bool,
+    quality_scores: Vec<PhotoQuality>,
+}
+
+// Command to find exact and near-duplicate photos
+// Identical files are matched by content hash; near-duplicates are photos whose
+// dHashes differ by at most `near_threshold` bits (out of 64). Only clusters with
+// more than one photo are returned; the keeper is the best-quality photo.
+#[tauri::command(async)]
+fn find_duplicates(cache: State<HashCache>, paths: Vec<String>, near_threshold: u32) -> Result<Vec<DuplicateCluster>, String> {
+    if near_threshold > 64 {
+        return Err("near_threshold must be between 0 and 64".to_string());
+    }
+
+    let none = CancelToken::none();
+    let content_hashes = paths
+        .par_iter()
+        .map(|path| {
+            fs::read(path)
+                .map(|bytes| Sha256::digest(&bytes))
+                .map_err(|e| format!("Failed to read {}: {}", path, e))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let hashes = hash_photos(&paths, HashAlgorithm::Difference, &cache, &none, |_| {})?;
+
+    let threshold = 1.0 - near_threshold as f64 / 64.0;
+    let clusters = grouping::cluster_by_similarity(paths.len(), threshold, |i, j| {
+        if content_hashes[i] == content_hashes[j] {
+            1.0
+        } else {
+            calculate_similarity(&hashes[i].1, &hashes[j].1)
+        }
+    });
+
+    clusters
+        .into_iter()
+        .filter(|cluster| cluster.members.len() > 1)
+        .map(|cluster| {
+            let photos: Vec<String> = cluster.members
+                .iter()
+                .map(|&i| paths[i].clone())
+                .collect();
+            let exact = cluster.members
+                .iter()
+                .all(|&i| content_hashes[i] == content_hashes[cluster.members[0]]);
+            let scores = score_photos(&photos, &cache, &none)?;
+            let keeper = photos[quality::best_index(&scores).unwrap_or(0)].clone();
+
+            Ok(DuplicateCluster {
+                quality_scores: photos
+                    .iter()
+                    .zip(scores)
+                    .map(|(path, score)| PhotoQuality { path: path.clone(), score })
+                    .collect(),
+                photos,
+                keeper,
+                exact,
+            })
+        })
+        .collect()
+}
+
+// A library photo matching a `find_similar_photos` query
+#[derive(Debug, Serialize)]
+struct SimilarPhoto {
+    path: String,
+    similarity: f64,
+}
+
+// Recursively collect photo paths under a folder, skipping hidden directories
+fn scan_photos(dir: &Path, paths: &mut Vec<String>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let hidden = entry.file_name().to_string_lossy().starts_with('.');
+        if path.is_dir() && !hidden {
+            scan_photos(&path, paths);
+        } else if path.is_file() && image_io::is_photo_file(&path) {
+            if let Some(path_str) = path.to_str() {
+                paths.push(path_str.to_string());
+            }
+        }
+    }
+}
+
+// Command to search a photo library for photos similar to `query_path`, e.g. to
+// check whether an item was photographed before. Every image under
+// `library_root` is hashed (cached hashes are reused); unreadable files are
+// skipped. Returns up to `limit` matches at or above `threshold`, best first.
+#[tauri::command(async)]
+#[allow(clippy::too_many_arguments)]
+fn find_similar_photos(
+    cache: State<HashCache>,
+    operations: State<OperationRegistry>,
+    query_path: String,
+    library_root: String,
+    threshold: f64,
+    limit: usize,
+    hash_algorithm: Option<String>,
+    operation_id: Option<String>,
+) -> Result<Vec<SimilarPhoto>, String> {
+    let algorithm = HashAlgorithm::parse(hash_algorithm.as_deref())?;
+    let operation = operations.register(operation_id);
+    let cancel = &operation.token;
+
+    let (_, query_hash) = hash_photos(std::slice::from_ref(&query_path), algorithm, &cache, cancel, |_| {})?
+        .pop()
+        .ok_or("Failed to hash query photo")?;
+
+    let mut library = Vec::new();
+    scan_photos(Path::new(&library_root), &mut library);
+    library.retain(|path| Path::new(path) != Path::new(&query_path));
+
+    // Hash files one at a time so a single corrupt photo doesn't fail the search
+    let mut matches = library
+        .par_iter()
+        .map(|path| {
+            cancel.check()?;
+            let similarity = hash_photos(std::slice::from_ref(path), algorithm, &cache, cancel, |_| {})
+                .ok()
+                .and_then(|mut hashes| hashes.pop())
+                .map(|(_, hash)| calculate_similarity(&query_hash, &hash));
+            Ok(similarity
+                .filter(|&similarity| similarity >= threshold)
+                .map(|similarity| SimilarPhoto { path: path.clone(), similarity }))
+        })
+        .collect::<Result<Vec<_>, String>>()?
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+
+    matches.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+    matches.truncate(limit);
+    Ok(matches)
+}
+
+// Command to cancel a running operation started with an `operation_id`
+// Returns false if no operation with that id is running
+#[tauri::command]
+fn cancel_operation(operations: State<OperationRegistry>, operation_id: String) -> bool {
+    operations.cancel(&operation_id)
+}
+
+// Command to change the largest image (in pixels) decoded at full resolution
+// Lower it on low-memory machines; scaled JPEG decodes aren't affected
+#[tauri::command]
+fn set_max_decode_pixels(max_pixels: u64) -> Result<(), String> {
+    if max_pixels == 0 {
+        return Err("max_pixels must be greater than 0".to_string());
+    }
+    image_io::set_max_decode_pixels(max_pixels);
+    Ok(())
+}
+
 // Command to generate perceptual hash for a single image
 #[tauri::command]
-fn generate_perceptual_hash(file_path: String) -> Result<String, String> {
-    let img = image::open(&file_path)
-        .map_err(|e| format!("Failed to open image {}: {}", file_path, e))?;
-    let hash = generate_dhash(&img)?;
-    // Return as string for JavaScript BigInt compatibility
-    Ok(hash.to_string())
+fn generate_perceptual_hash(cache: State<HashCache>, file_path: String, hash_algorithm: Option<String>) -> Result<String, String> {
+    let algorithm = HashAlgorithm::parse(hash_algorithm.as_deref())?;
+    let (_, hash) = hash_photos(&[file_path], algorithm, &cache, &CancelToken::none(), |_| {})?
+        .pop()
+        .ok_or("Failed to hash image")?;
+    // 64-bit hashes stay decimal strings (for JavaScript BigInt) so ones the
+    // frontend saved earlier still compare; wider hashes are hex
+    if let Some(hash) = hash.as_u64() {
+        return Ok(hash.to_string());
+    }
+    Ok(hash.to_hex())
 }
 
 // Command to read all image paths from a folder
@@ -148,21 +1065,13 @@ fn read_folder_images(folder_path: String) -> Result<Vec<String>, String> {
     let entries = std::fs::read_dir(&folder_path)
         .map_err(|e| format!("Failed to read directory: {}", e))?;
 
-    for entry in entries {
-        if let Ok(entry) = entry {
-            let path = entry.path();
-
-            // Check if it's a file with image extension
-            if path.is_file() {
-                if let Some(ext) = path.extension() {
-                    let ext_lower = ext.to_string_lossy().to_lowercase();
-                    if ext_lower == "jpg" || ext_lower == "jpeg" ||
-                       ext_lower == "png" || ext_lower == "heic" {
-                        if let Some(path_str) = path.to_str() {
-                            image_paths.push(path_str.to_string());
-                        }
-                    }
-                }
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        // Check if it's a file with image extension
+        if path.is_file() && image_io::is_photo_file(&path) {
+            if let Some(path_str) = path.to_str() {
+                image_paths.push(path_str.to_string());
             }
         }
     }
@@ -173,111 +1082,6 @@ fn read_folder_images(folder_path: String) -> Result<Vec<String>, String> {
     Ok(image_paths)
 }
 
-// Service account structure
-#[derive(Debug, Deserialize)]
-struct ServiceAccount {
-    private_key: String,
-    client_email: String,
-}
-
-// Generate a signed URL for GCS upload
-#[tauri::command]
-fn generate_gcs_signed_url(bucket_name: String, filename: String) -> Result<String, String> {
-    // Read service account JSON from project root (one level up from src-tauri)
-    let service_account_path = "../google-service-account.json";
-    let service_account_json = fs::read_to_string(service_account_path)
-        .map_err(|e| format!("Failed to read service account file: {}", e))?;
-
-    let service_account: ServiceAccount = serde_json::from_str(&service_account_json)
-        .map_err(|e| format!("Failed to parse service account JSON: {}", e))?;
-
-    // Generate expiration (15 minutes from now)
-    let expiration = Utc::now().timestamp() + 900;
-
-    // Build the canonical request for signed URL
-    let method = "PUT";
-    let resource = format!("/{}/{}", bucket_name, filename);
-    let content_type = "image/jpeg";
-
-    let string_to_sign = format!(
-        "{}\n\n{}\n{}\n{}",
-        method,
-        content_type,
-        expiration,
-        resource
-    );
-
-    // Parse the RSA private key from service account
-    let private_key_pem = service_account.private_key.replace("\\n", "\n");
-    let private_key = RsaPrivateKey::from_pkcs8_pem(&private_key_pem)
-        .map_err(|e| format!("Failed to parse private key: {}", e))?;
-
-    // Sign with RSA-SHA256
-    let signing_key = SigningKey::<Sha256>::new(private_key);
-    let signature = signing_key.sign(string_to_sign.as_bytes());
-    let signature_bytes = signature.to_bytes();
-    let signature_base64 = general_purpose::STANDARD.encode(&signature_bytes);
-
-    // Build signed URL
-    let signed_url = format!(
-        "https://storage.googleapis.com{}?GoogleAccessId={}&Expires={}&Signature={}",
-        resource,
-        urlencoding::encode(&service_account.client_email),
-        expiration,
-        urlencoding::encode(&signature_base64)
-    );
-
-    Ok(signed_url)
-}
-
-// Generate a signed URL for GCS read access (for Google Lens)
-#[tauri::command]
-fn get_read_signed_url(bucket_name: String, filename: String) -> Result<String, String> {
-    // Read service account JSON from project root
-    let service_account_path = "../google-service-account.json";
-    let service_account_json = fs::read_to_string(service_account_path)
-        .map_err(|e| format!("Failed to read service account file: {}", e))?;
-
-    let service_account: ServiceAccount = serde_json::from_str(&service_account_json)
-        .map_err(|e| format!("Failed to parse service account JSON: {}", e))?;
-
-    // Generate expiration (10 minutes from now - enough for Lens call)
-    let expiration = Utc::now().timestamp() + 600;
-
-    // Build the canonical request for READ signed URL
-    let method = "GET";
-    let resource = format!("/{}/{}", bucket_name, filename);
-
-    let string_to_sign = format!(
-        "{}\n\n\n{}\n{}",
-        method,
-        expiration,
-        resource
-    );
-
-    // Parse the RSA private key from service account
-    let private_key_pem = service_account.private_key.replace("\\n", "\n");
-    let private_key = RsaPrivateKey::from_pkcs8_pem(&private_key_pem)
-        .map_err(|e| format!("Failed to parse private key: {}", e))?;
-
-    // Sign with RSA-SHA256
-    let signing_key = SigningKey::<Sha256>::new(private_key);
-    let signature = signing_key.sign(string_to_sign.as_bytes());
-    let signature_bytes = signature.to_bytes();
-    let signature_base64 = general_purpose::STANDARD.encode(&signature_bytes);
-
-    // Build signed READ URL
-    let signed_url = format!(
-        "https://storage.googleapis.com{}?GoogleAccessId={}&Expires={}&Signature={}",
-        resource,
-        urlencoding::encode(&service_account.client_email),
-        expiration,
-        urlencoding::encode(&signature_base64)
-    );
-
-    Ok(signed_url)
-}
-
 fn main() {
   let context = tauri::generate_context!();
   tauri::Builder::default()
@@ -286,7 +1090,44 @@ fn main() {
     } else {
       tauri::Menu::default()
     })
-    .invoke_handler(tauri::generate_handler![read_image_as_base64, group_photos_by_item, generate_perceptual_hash, read_folder_images, generate_gcs_signed_url, get_read_signed_url])
+    .setup(|app| {
+      let data_dir = app.path_resolver()
+        .app_data_dir()
+        .ok_or("Failed to resolve app data directory")?;
+      app.manage(network::HttpClient::new(&app.handle()));
+      let db = db::Database::open(&data_dir.join("app.sqlite"))?;
+      app.manage(HashCache::new(&db));
+      app.manage(SessionStore::new(&db));
+      app.manage(gcs::SignerCache::default());
+      app.manage(gcs::UploadSessions::new(&db));
+      app.manage(upload_queue::UploadQueue::new(&db)?);
+      app.manage(history::ListingHistory::new(&db)?);
+      app.manage(ebay_import::EbayImports::new(&db));
+      app.manage(publishing::PublishLog::new(&db)?);
+      app.manage(templates::TemplateStore::new(&db));
+      app.manage(drafts::DraftStore::new(&db));
+      app.manage(scheduler::Scheduler::new(&db)?);
+      app.manage(sync::SyncStore::new(&db));
+      app.manage(repricing::PriceRules::new(&db));
+      app.manage(db);
+      app.manage(ThumbnailCache::new(data_dir.join("thumbnails")));
+      app.manage(ModelStore::new(data_dir.join("models")));
+      app.manage(OperationRegistry::default());
+      app.manage(ebay_auth::EbayAuth::default());
+      app.manage(ebay::EbayTaxonomy::default());
+      app.manage(etsy::EtsyAuth::default());
+      app.manage(amazon::AmazonAuth::default());
+      upload_queue::start(app.handle());
+      scheduler::start(app.handle());
+      sync::start(app.handle());
+      repricing::start(app.handle());
+      Ok(())
+    })
+    .register_uri_scheme_protocol(photo_protocol::SCHEME, |_app, request| {
+      photo_protocol::handle_request(request)
+    })
+    .invoke_handler(tauri::generate_handler![read_image_as_base64, group_photos_by_item, add_photos_to_groups, merge_groups, split_group, move_photo, suggest_photo_order, save_grouping_session, load_grouping_session, find_duplicates, compute_similarity_matrix, find_similar_photos, cancel_operation, set_max_decode_pixels, generate_perceptual_hash, read_folder_images, thumbnails::get_thumbnail, thumbnails::smart_crop_thumbnail, photo_editing::prepare_image_for_upload, photo_editing::auto_crop, watermark::apply_watermark, collage::create_collage, photo_editing::convert_images, photo_editing::transform_image, photo_editing::pad_to_square, photo_editing::auto_enhance, photo_editing::blur_regions, metadata::strip_exif, metadata::get_image_metadata, background::remove_background, background::make_marketplace_primary, measurements::estimate_dimensions, classifier::classify_item, condition::suggest_condition, colors::extract_dominant_colors, video::extract_video_frames, gcs::register_service_account, gcs::get_service_account, gcs::use_gcs_oauth, gcs::generate_gcs_signed_url, gcs::generate_gcs_signed_urls, gcs::generate_gcs_post_policy,
+      gcs::get_read_signed_url, gcs::upload_to_gcs, gcs::resumable_upload_to_gcs, gcs::list_pending_uploads, gcs::delete_gcs_object, gcs::cleanup_expired_uploads, gcs::list_gcs_objects, storage::set_storage_settings, storage::get_storage_settings, storage::set_upload_rate_limit, storage::host_listing_photo, network::set_proxy_settings, network::get_proxy_settings, storage::storage_presign, storage::storage_upload, storage::download_from_url, vision::identify_item, vision::detect_brand, reverse_search::reverse_search, reverse_search::set_reverse_search_key, history::add_listing_to_history, history::remove_listing_from_history, history::match_against_history, ocr::ocr_image, sizes::parse_size_text, pii::scan_for_pii, prescreen::prescreen_listing, prescreen::get_prescreen_rules, prescreen::set_prescreen_rules, barcodes::detect_barcodes, llm::set_llm_settings, llm::get_llm_settings, llm::generate_listing_copy, translation::set_translation_settings, translation::get_translation_settings, translation::translate_text, ebay_auth::set_ebay_settings, ebay_auth::get_ebay_settings, ebay_auth::ebay_connect_account, ebay_auth::ebay_handle_redirect, ebay_auth::ebay_get_token, ebay_auth::ebay_disconnect_account, ebay::publish_to_ebay, ebay::upload_to_eps, ebay::suggest_ebay_category, ebay::get_ebay_aspects, ebay_policies::get_ebay_policies, ebay_import::import_ebay_listings, ebay_import::list_imported_listings, etsy::set_etsy_settings, etsy::get_etsy_settings, etsy::etsy_connect_account, etsy::etsy_handle_redirect, etsy::etsy_disconnect_account, etsy::get_etsy_shop, etsy::create_etsy_draft_listing, poshmark::set_poshmark_settings, poshmark::get_poshmark_settings, poshmark::export_to_poshmark, mercari::export_to_mercari, vinted::export_to_vinted, depop::export_to_depop, facebook::export_for_facebook, facebook::export_facebook_catalog, crosslist::cross_list, crosslist::validate_listing, fees::calculate_fees, amazon::set_amazon_settings, amazon::get_amazon_settings, amazon::lookup_amazon_catalog, amazon::create_amazon_listings, amazon::get_amazon_feed_status, templates::save_template, templates::get_template, templates::list_templates, templates::delete_template, templates::render_template, db::save_listing, db::get_listing, db::list_listings, db::delete_listing, db::get_app_setting, db::set_app_setting, inventory::save_inventory_item, inventory::get_inventory_item, inventory::list_inventory, inventory::set_inventory_status, inventory::delete_inventory_item, inventory::link_photos_to_inventory, inventory::link_group_to_inventory, inventory::unlink_photos_from_inventory, inventory::find_inventory_for_photos, drafts::save_draft, drafts::list_drafts, drafts::list_draft_snapshots, drafts::restore_draft, drafts::delete_draft, pricing::research_sold_prices, repricing::set_price_drop_rule, repricing::list_price_drop_rules, repricing::delete_price_drop_rule, repricing::send_offer_to_watchers, publishing::publish_batch, publishing::retry_failed_publishes, publishing::list_publish_results, scheduler::schedule_listing, scheduler::list_scheduled_listings, scheduler::cancel_scheduled_listing, sync::set_sync_settings, sync::get_sync_settings, sync::sync_now, sync::register_synced_listing, sync::list_synced_listings, sync::list_sync_log, upload_queue::enqueue_uploads, upload_queue::list_upload_queue, upload_queue::pause_uploads, upload_queue::resume_uploads, upload_queue::retry_uploads, upload_queue::clear_finished_uploads])
     .run(context)
     .expect("error while running tauri application");
 }
@@ -0,0 +1,274 @@
+use image::{GrayImage, Luma};
+use imageproc::contours::find_contours;
+use imageproc::distance_transform::Norm;
+use imageproc::edges::canny;
+use imageproc::filter::gaussian_blur_f32;
+use imageproc::geometry::{approximate_polygon_dp, convex_hull, min_area_rect};
+use imageproc::morphology::close;
+use imageproc::point::Point;
+use imageproc::region_labelling::{connected_components, Connectivity};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use crate::background::{self, FOREGROUND_THRESHOLD};
+use crate::image_io;
+use crate::models::ModelStore;
+
+// Working size for measuring; enough for a card to span a good few pixels
+const MEASURE_EDGE: u32 = 1600;
+
+// Canny thresholds for finding the reference's outline
+const EDGE_LOW: f32 = 30.0;
+const EDGE_HIGH: f32 = 80.0;
+
+// Outline area over its bounding rectangle's area for a shape to count as a
+// rectangle, and how far its aspect ratio may be from the reference's
+const MIN_RECTANGULARITY: f64 = 0.9;
+const ASPECT_TOLERANCE: f64 = 0.06;
+
+// Reference sizes as a share of the photo's area
+const MIN_REFERENCE_AREA: f64 = 0.002;
+const MAX_REFERENCE_AREA: f64 = 0.4;
+
+// Garment width drops below this share of the shoulder/sleeve span at the armpits
+const ARMPIT_DROP: f64 = 0.85;
+
+const MM_PER_INCH: f64 = 25.4;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReferenceObject {
+    // ISO/IEC 7810 ID-1: bank cards, driving licences
+    CreditCard,
+    A4,
+    UsLetter,
+}
+
+impl ReferenceObject {
+    // (long, short) sides in millimetres
+    fn size_mm(self) -> (f64, f64) {
+        match self {
+            ReferenceObject::CreditCard => (85.60, 53.98),
+            ReferenceObject::A4 => (297.0, 210.0),
+            ReferenceObject::UsLetter => (279.4, 215.9),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Measurement {
+    pub cm: f64,
+    pub inches: f64,
+}
+
+impl Measurement {
+    fn from_mm(mm: f64) -> Measurement {
+        let round = |value: f64| (value * 10.0).round() / 10.0;
+        Measurement { cm: round(mm / 10.0), inches: round(mm / MM_PER_INCH) }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct DimensionEstimate {
+    // Top to bottom of the item as laid out
+    pub length: Measurement,
+    // Widest point, e.g. sleeve tip to sleeve tip
+    pub width: Measurement,
+    // Chest width just below the armpits; None when it can't be found
+    pub pit_to_pit: Option<Measurement>,
+    pub mm_per_pixel: f64,
+    // Reasons the estimate may be off
+    pub warnings: Vec<String>,
+}
+
+fn polygon_area(points: &[Point<i32>]) -> f64 {
+    let n = points.len();
+    let twice: i64 = (0..n)
+        .map(|i| {
+            let (a, b) = (points[i], points[(i + 1) % n]);
+            a.x as i64 * b.y as i64 - b.x as i64 * a.y as i64
+        })
+        .sum();
+    twice.abs() as f64 / 2.0
+}
+
+fn distance(a: Point<i32>, b: Point<i32>) -> f64 {
+    (((a.x - b.x) as f64).powi(2) + ((a.y - b.y) as f64).powi(2)).sqrt()
+}
+
+// A rectangle found in the photo: corners and (long, short) sides in pixels
+struct FoundReference {
+    corners: [Point<i32>; 4],
+    long_px: f64,
+    short_px: f64,
+    // Relative difference between opposite sides, a sign of a tilted camera
+    skew: f64,
+}
+
+// Find the largest outline with the reference's proportions
+fn find_reference(gray: &GrayImage, reference: ReferenceObject) -> Option<FoundReference> {
+    let (long_mm, short_mm) = reference.size_mm();
+    let expected_aspect = long_mm / short_mm;
+    let image_area = gray.width() as f64 * gray.height() as f64;
+
+    let edges = close(&canny(&gaussian_blur_f32(gray, 1.5), EDGE_LOW, EDGE_HIGH), Norm::LInf, 2);
+    find_contours::<i32>(&edges)
+        .into_iter()
+        .filter(|contour| contour.points.len() >= 20)
+        .filter_map(|contour| {
+            let hull = convex_hull(&contour.points);
+            let corners = min_area_rect(&hull);
+            let sides = [distance(corners[0], corners[1]), distance(corners[1], corners[2])];
+            let (long_px, short_px) = (sides[0].max(sides[1]), sides[0].min(sides[1]));
+            let rect_area = long_px * short_px;
+            if short_px < 1.0 || !(MIN_REFERENCE_AREA..=MAX_REFERENCE_AREA).contains(&(rect_area / image_area)) {
+                return None;
+            }
+            let aspect = long_px / short_px;
+            if (aspect - expected_aspect).abs() / expected_aspect > ASPECT_TOLERANCE
+                || polygon_area(&hull) / rect_area < MIN_RECTANGULARITY
+            {
+                return None;
+            }
+
+            // A tilted camera turns the rectangle into a trapezoid, with
+            // opposite sides of different lengths
+            let perimeter: f64 = (0..hull.len()).map(|i| distance(hull[i], hull[(i + 1) % hull.len()])).sum();
+            let quad = approximate_polygon_dp(&hull, perimeter * 0.02, true);
+            let skew = if quad.len() == 4 {
+                let side = |i: usize| distance(quad[i], quad[(i + 1) % 4]);
+                let difference = |a: f64, b: f64| (a - b).abs() / a.max(b);
+                difference(side(0), side(2)).max(difference(side(1), side(3)))
+            } else {
+                0.0
+            };
+            Some(FoundReference { corners, long_px, short_px, skew })
+        })
+        .max_by(|a, b| (a.long_px * a.short_px).total_cmp(&(b.long_px * b.short_px)))
+}
+
+// Whether a point lies inside a convex quadrilateral
+fn inside(corners: &[Point<i32>; 4], x: f64, y: f64) -> bool {
+    let signs: Vec<bool> = (0..4)
+        .map(|i| {
+            let (a, b) = (corners[i], corners[(i + 1) % 4]);
+            (b.x - a.x) as f64 * (y - a.y as f64) - (b.y - a.y) as f64 * (x - a.x as f64) >= 0.0
+        })
+        .collect();
+    signs.iter().all(|&s| s) || signs.iter().all(|&s| !s)
+}
+
+// Largest connected foreground region of the mask, outside the reference
+fn item_region(mask: &GrayImage, reference: &FoundReference) -> GrayImage {
+    let mut binary = GrayImage::new(mask.width(), mask.height());
+    for (x, y, value) in mask.enumerate_pixels() {
+        if value[0] > FOREGROUND_THRESHOLD && !inside(&reference.corners, x as f64 + 0.5, y as f64 + 0.5) {
+            binary.put_pixel(x, y, Luma([255]));
+        }
+    }
+
+    let labels = connected_components(&binary, Connectivity::Eight, Luma([0u8]));
+    let mut sizes: Vec<u64> = Vec::new();
+    for label in labels.pixels() {
+        let label = label[0] as usize;
+        if label > 0 {
+            if sizes.len() < label {
+                sizes.resize(label, 0);
+            }
+            sizes[label - 1] += 1;
+        }
+    }
+    let Some(largest) = (0..sizes.len()).max_by_key(|&i| sizes[i]).map(|i| i as u32 + 1) else {
+        return GrayImage::new(mask.width(), mask.height());
+    };
+    let mut item = GrayImage::new(mask.width(), mask.height());
+    for (x, y, label) in labels.enumerate_pixels() {
+        if label[0] == largest {
+            item.put_pixel(x, y, Luma([255]));
+        }
+    }
+    item
+}
+
+// (length, width, pit-to-pit) of the item, in pixels
+fn measure_item(item: &GrayImage) -> Option<(f64, f64, Option<f64>)> {
+    let (width, height) = item.dimensions();
+    let set = |x: u32, y: u32| item.get_pixel(x, y)[0] > 0;
+    let rows: Vec<u32> = (0..height).filter(|&y| (0..width).any(|x| set(x, y))).collect();
+    let (&top, &bottom) = (rows.first()?, rows.last()?);
+    let columns: Vec<u32> = (0..width).filter(|&x| (top..=bottom).any(|y| set(x, y))).collect();
+    let (&left, &right) = (columns.first()?, columns.last()?);
+    let length = (bottom - top + 1) as f64;
+    let full_width = (right - left + 1) as f64;
+
+    // Width of the run through the item's centre line in each row. Below the
+    // armpits the sleeves separate from the body, so the run narrows to the chest.
+    let centre = (left + right) / 2;
+    let run_width = |y: u32| -> f64 {
+        if !set(centre, y) {
+            return 0.0;
+        }
+        let run_left = (left..=centre).rev().take_while(|&x| set(x, y)).last().unwrap_or(centre);
+        let run_right = (centre..=right).take_while(|&x| set(x, y)).last().unwrap_or(centre);
+        (run_right - run_left + 1) as f64
+    };
+    let widths: Vec<f64> = (top..=bottom).map(run_width).collect();
+    let upper_half = &widths[..widths.len() / 2];
+    let (widest_row, widest) = upper_half
+        .iter()
+        .copied()
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+    let armpit = (widest_row..widths.len() / 2).find(|&row| widths[row] > 0.0 && widths[row] < widest * ARMPIT_DROP);
+
+    // Median width over a short band under the armpits
+    let pit_to_pit = armpit.and_then(|armpit| {
+        let band_end = (armpit + (widths.len() / 20).max(1)).min(widths.len());
+        let mut band: Vec<f64> = widths[armpit..band_end].iter().copied().filter(|w| *w > 0.0).collect();
+        band.sort_by(f64::total_cmp);
+        band.get(band.len() / 2).copied()
+    });
+    Some((length, full_width, pit_to_pit))
+}
+
+// Command to estimate a flat-laid item's measurements from a photo that also
+// shows a reference object of known size (`credit_card`, `a4` or
+// `us_letter`), for clothing listings: length, overall width and
+// pit-to-pit. Works best photographed from directly above with the
+// reference flat beside the item.
+#[tauri::command(async)]
+pub fn estimate_dimensions(
+    models: State<ModelStore>,
+    path: String,
+    reference_object: ReferenceObject,
+) -> Result<DimensionEstimate, String> {
+    let img = image_io::open_image_scaled(&path, MEASURE_EDGE)?;
+    let gray = img.to_luma8();
+    let reference = find_reference(&gray, reference_object)
+        .ok_or("Couldn't find the reference object; make sure all four edges are visible")?;
+
+    let (long_mm, short_mm) = reference_object.size_mm();
+    let mm_per_pixel = (long_mm / reference.long_px + short_mm / reference.short_px) / 2.0;
+
+    let mask = background::foreground_mask(&models, &img)?;
+    let item = item_region(&mask, &reference);
+    let (length_px, width_px, pit_to_pit_px) = measure_item(&item).ok_or("No item detected in photo")?;
+
+    let mut warnings = Vec::new();
+    if reference.skew > ASPECT_TOLERANCE {
+        warnings.push("The reference looks tilted; photograph from directly above for accurate measurements".to_string());
+    }
+    if reference.long_px < 80.0 {
+        warnings.push("The reference is small in the photo, so measurements are approximate".to_string());
+    }
+    if pit_to_pit_px.is_none() {
+        warnings.push("Couldn't find the armpits; measure pit-to-pit by hand".to_string());
+    }
+
+    Ok(DimensionEstimate {
+        length: Measurement::from_mm(length_px * mm_per_pixel),
+        width: Measurement::from_mm(width_px * mm_per_pixel),
+        pit_to_pit: pit_to_pit_px.map(|px| Measurement::from_mm(px * mm_per_pixel)),
+        mm_per_pixel,
+        warnings,
+    })
+}
@@ -0,0 +1,199 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::AppHandle;
+use crate::crosslist::Marketplace;
+use crate::listing::{self, Listing, ListingCondition, MappedListing, PackageContents};
+use crate::sizes::SizeCategory;
+
+// Mercari takes up to 12 photos, shown square and capped at 10MB each;
+// 1280px JPEGs stay well under that
+const MAX_PHOTOS: usize = 12;
+const PHOTO_EDGE: u32 = 1280;
+const MAX_TITLE_CHARS: usize = 80;
+const MAX_DESCRIPTION_CHARS: usize = 1000;
+
+// Mercari US price range, in dollars
+const MIN_PRICE: f64 = 1.0;
+const MAX_PRICE: f64 = 2000.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShippingPayer {
+    Buyer,
+    Seller,
+}
+
+fn default_shipping_payer() -> ShippingPayer {
+    ShippingPayer::Buyer
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MercariListing {
+    #[serde(flatten)]
+    pub item: Listing,
+    // The app's category, one of `classifier::CATEGORIES`, mapped to
+    // Mercari's with `size_category` telling women's, men's and kids' apart
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub size_category: Option<SizeCategory>,
+    // Mercari category path, e.g. ["Women", "Coats & jackets"], used in
+    // place of the mapped one
+    #[serde(default)]
+    pub mercari_category: Vec<String>,
+    #[serde(default = "default_shipping_payer")]
+    pub shipping_paid_by: ShippingPayer,
+    // Packed weight, which sets the shipping label price
+    #[serde(default)]
+    pub weight_oz: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MercariExport {
+    pub folder: String,
+    pub photos: Vec<String>,
+    // Mercari's fields as JSON, for tools that fill in the form
+    pub data_file: String,
+    // The same fields as text, ready to paste
+    pub text_file: String,
+    pub category: Vec<String>,
+}
+
+// Mercari's condition names
+fn mercari_condition(condition: ListingCondition) -> &'static str {
+    match condition {
+        ListingCondition::NewWithTags => "New",
+        ListingCondition::NewWithoutTags => "Like new",
+        ListingCondition::VeryGood | ListingCondition::Good => "Good",
+        ListingCondition::Satisfactory => "Fair",
+    }
+}
+
+// Mercari category path for one of the app's categories. Clothing and
+// accessories go under the department their size category points to,
+// women's when it doesn't say.
+fn mercari_category(category: &str, size_category: Option<SizeCategory>) -> Vec<String> {
+    let department = match size_category {
+        Some(SizeCategory::MensClothing | SizeCategory::MensShoes) => "Men",
+        Some(SizeCategory::KidsClothing) => "Kids",
+        _ => "Women",
+    };
+    let path: &[&str] = match category {
+        "clothing" => &[department],
+        "shoes" => &[department, "Shoes"],
+        "bags" if department == "Men" => &["Men", "Bags"],
+        "bags" => &["Women", "Women's handbags"],
+        "accessories" if department == "Men" => &["Men", "Men's accessories"],
+        "accessories" => &["Women", "Women's accessories"],
+        "jewelry" => &[department, "Jewelry"],
+        "electronics" => &["Electronics"],
+        "toys" => &["Toys & Collectibles"],
+        "media" => &["Other", "Books"],
+        "home" => &["Home"],
+        "sports" => &["Sports & outdoors"],
+        "beauty" => &["Beauty"],
+        _ => &["Other"],
+    };
+    path.iter().map(|part| part.to_string()).collect()
+}
+
+// How Mercari will see the listing, with what stops it being exported
+pub fn map_listing(listing: &MercariListing) -> MappedListing {
+    let mut problems = listing.item.problems("Mercari", MAX_PHOTOS);
+    if listing.item.title.chars().count() > MAX_TITLE_CHARS {
+        problems.push(format!("Mercari titles are limited to {} characters", MAX_TITLE_CHARS));
+    }
+    if listing.item.description_with_condition().chars().count() > MAX_DESCRIPTION_CHARS {
+        problems.push(format!("Mercari descriptions are limited to {} characters", MAX_DESCRIPTION_CHARS));
+    }
+    if !(MIN_PRICE..=MAX_PRICE).contains(&listing.item.price) {
+        problems.push(format!("Mercari prices are between ${:.0} and ${:.0}", MIN_PRICE, MAX_PRICE));
+    }
+    let category = if listing.mercari_category.is_empty() {
+        mercari_category(listing.category.as_deref().unwrap_or("other"), listing.size_category)
+    } else {
+        listing.mercari_category.clone()
+    };
+    MappedListing {
+        category,
+        condition: Some(mercari_condition(listing.item.condition).to_string()),
+        size: listing.item.size.clone(),
+        max_photos: MAX_PHOTOS,
+        problems,
+    }
+}
+
+fn listing_text(listing: &MercariListing, category: &[String]) -> String {
+    let item = &listing.item;
+    let mut lines = vec![
+        format!("Title: {}", item.title.trim()),
+        String::new(),
+        "Description:".to_string(),
+        item.description_with_condition(),
+        String::new(),
+        format!("Category: {}", category.join(" > ")),
+        format!("Condition: {}", mercari_condition(item.condition)),
+    ];
+    if let Some(brand) = item.brand.as_deref().filter(|brand| !brand.trim().is_empty()) {
+        lines.push(format!("Brand: {}", brand.trim()));
+    }
+    if let Some(size) = item.size.as_deref().filter(|size| !size.trim().is_empty()) {
+        lines.push(format!("Size: {}", size.trim()));
+    }
+    if !item.colors.is_empty() {
+        lines.push(format!("Color: {}", item.colors.join(", ")));
+    }
+    if let Some(weight) = listing.weight_oz {
+        lines.push(format!("Weight: {} oz", weight));
+    }
+    let payer = match listing.shipping_paid_by {
+        ShippingPayer::Buyer => "buyer",
+        ShippingPayer::Seller => "seller",
+    };
+    lines.push(format!("Shipping paid by: {}", payer));
+    lines.push(format!("Price: {:.2}", item.price));
+    lines.join("\n") + "\n"
+}
+
+// Write a listing's Mercari export package; used by the command and by
+// cross-listing
+pub async fn export(app: &AppHandle, listing: &MercariListing, output_dir: Option<String>) -> Result<MercariExport, String> {
+    let mapped = map_listing(listing);
+    let item = &listing.item;
+    let data = json!({
+        "name": item.title.trim(),
+        "description": item.description_with_condition(),
+        "price": item.price,
+        "condition": mercari_condition(item.condition),
+        "category": mapped.category,
+        "brand": item.brand,
+        "size": item.size,
+        "color": item.colors.first(),
+        "shipping_paid_by": listing.shipping_paid_by,
+        "weight_oz": listing.weight_oz,
+    });
+    let text = listing_text(listing, &mapped.category);
+    let contents = PackageContents::resized(PHOTO_EDGE, Some(data), text);
+    let package = listing::write_package(app, Marketplace::Mercari, item, &mapped, output_dir, contents).await?;
+
+    Ok(MercariExport {
+        folder: package.folder,
+        photos: package.photos,
+        data_file: package.data_file.unwrap_or_default(),
+        text_file: package.text_file,
+        category: mapped.category,
+    })
+}
+
+// Command to export a listing for Mercari. Mercari has no listing API for
+// sellers outside Japan, so this writes a folder with the photos (at most
+// 12), the fields as JSON and as text to paste, with the condition and the
+// app's category mapped to Mercari's.
+#[tauri::command]
+pub async fn export_to_mercari(
+    app: AppHandle,
+    listing: MercariListing,
+    output_dir: Option<String>,
+) -> Result<MercariExport, String> {
+    export(&app, &listing, output_dir).await
+}
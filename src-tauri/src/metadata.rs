@@ -0,0 +1,170 @@
+use std::fs;
+use std::path::Path;
+use exif::{Exif, In, Tag, Value};
+use image::ImageFormat;
+use rayon::prelude::*;
+use serde::Serialize;
+use tauri::AppHandle;
+use crate::image_io;
+use crate::photo_editing::{self, TargetFormat};
+
+// JPEG markers
+const MARKER_SOI: u8 = 0xD8;
+const MARKER_SOS: u8 = 0xDA;
+const MARKER_APP1: u8 = 0xE1;
+const MARKER_APP2: u8 = 0xE2;
+const MARKER_APP15: u8 = 0xEF;
+const MARKER_COM: u8 = 0xFE;
+
+#[derive(Debug, Serialize)]
+pub struct StripResult {
+    path: String,
+    output_path: Option<String>,
+    had_gps: bool,
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImageMetadata {
+    // Upright dimensions, i.e. after applying the EXIF orientation
+    width: Option<u32>,
+    height: Option<u32>,
+    file_size: u64,
+    // MIME type detected from the file contents
+    format: Option<String>,
+    // EXIF capture time as seconds since the epoch (camera local time)
+    captured_at: Option<i64>,
+    camera_make: Option<String>,
+    camera_model: Option<String>,
+    orientation: u32,
+    has_gps: bool,
+}
+
+// Read an EXIF text field such as the camera model
+fn exif_string(exif: &Exif, tag: Tag) -> Option<String> {
+    let text = match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Ascii(values) => String::from_utf8_lossy(values.first()?).trim().to_string(),
+        _ => return None,
+    };
+    Some(text).filter(|text| !text.is_empty())
+}
+
+// Command to read a photo's details from its header and EXIF, without
+// decoding the pixels
+#[tauri::command(async)]
+pub fn get_image_metadata(path: String) -> Result<ImageMetadata, String> {
+    let file_size = photo_editing::file_size(Path::new(&path))?;
+    let dimensions = image_io::read_dimensions(&path);
+    let exif = image_io::read_exif(&path);
+
+    Ok(ImageMetadata {
+        width: dimensions.map(|(width, _)| width),
+        height: dimensions.map(|(_, height)| height),
+        file_size,
+        format: image_io::sniff_file_mime(&path)
+            .or_else(|| image_io::mime_from_extension(&path))
+            .map(|mime| mime.to_string()),
+        captured_at: image_io::read_capture_timestamp(&path),
+        camera_make: exif.as_ref().and_then(|exif| exif_string(exif, Tag::Make)),
+        camera_model: exif.as_ref().and_then(|exif| exif_string(exif, Tag::Model)),
+        orientation: image_io::read_orientation(&path),
+        has_gps: has_gps(&path),
+    })
+}
+
+// Whether a photo's EXIF carries GPS coordinates
+pub fn has_gps(path: &str) -> bool {
+    image_io::read_exif(path)
+        .map(|exif| {
+            exif.get_field(Tag::GPSLatitude, In::PRIMARY).is_some()
+                || exif.get_field(Tag::GPSLongitude, In::PRIMARY).is_some()
+        })
+        .unwrap_or(false)
+}
+
+// Remove metadata segments from a JPEG without re-encoding the image data.
+// Keeps JFIF (APP0) and ICC colour profiles (APP2) so colours don't shift;
+// drops EXIF/XMP (APP1), IPTC (APP13), other APPn segments and comments.
+fn strip_jpeg_segments(data: &[u8]) -> Result<Vec<u8>, String> {
+    if data.len() < 4 || data[0] != 0xFF || data[1] != MARKER_SOI {
+        return Err("Not a JPEG file".to_string());
+    }
+
+    let mut output = Vec::with_capacity(data.len());
+    output.extend_from_slice(&data[..2]);
+
+    let mut pos = 2;
+    while pos + 4 <= data.len() {
+        if data[pos] != 0xFF {
+            return Err("Corrupt JPEG segment".to_string());
+        }
+        let marker = data[pos + 1];
+
+        // Everything from the start of scan onwards is image data
+        if marker == MARKER_SOS {
+            output.extend_from_slice(&data[pos..]);
+            return Ok(output);
+        }
+
+        let length = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        let end = pos + 2 + length;
+        if end > data.len() {
+            return Err("Truncated JPEG segment".to_string());
+        }
+
+        let is_metadata = (MARKER_APP1..=MARKER_APP15).contains(&marker) && marker != MARKER_APP2
+            || marker == MARKER_COM;
+        if !is_metadata {
+            output.extend_from_slice(&data[pos..end]);
+        }
+
+        pos = end;
+    }
+
+    Err("JPEG has no image data".to_string())
+}
+
+fn strip_file(path: &str, keep_orientation: bool, output_dir: &Path) -> Result<String, String> {
+    let data = fs::read(path)
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    let format = image::guess_format(&data).ok();
+    let needs_rotation = keep_orientation && image_io::read_orientation(path) != 1;
+
+    // JPEGs that don't need rotating are stripped losslessly; everything else
+    // is decoded (applying orientation) and re-encoded, which writes no metadata
+    let (output, extension) = match format {
+        Some(ImageFormat::Jpeg) if !needs_rotation => (strip_jpeg_segments(&data)?, "jpg"),
+        Some(ImageFormat::Png) => {
+            let img = image_io::open_image(path)?;
+            (photo_editing::encode_as(&img, TargetFormat::Png, 0)?, "png")
+        }
+        _ => {
+            let img = image_io::open_image(path)?;
+            (image_io::encode_jpeg(&img, photo_editing::EDIT_JPEG_QUALITY)?, "jpg")
+        }
+    };
+
+    let output_path = output_dir.join(photo_editing::output_file_name(path, "clean", extension));
+    fs::write(&output_path, output)
+        .map_err(|e| format!("Failed to write stripped image: {}", e))?;
+    image_io::path_to_string(&output_path)
+}
+
+// Command to strip EXIF/XMP metadata (GPS, device serials) from copies of photos
+// With `keep_orientation`, rotated photos are re-encoded upright first so they
+// don't display sideways once the orientation tag is gone
+#[tauri::command(async)]
+pub fn strip_exif(app: AppHandle, paths: Vec<String>, keep_orientation: bool) -> Result<Vec<StripResult>, String> {
+    let output_dir = photo_editing::output_dir(&app, "stripped")?;
+
+    Ok(paths
+        .into_par_iter()
+        .map(|path| {
+            let had_gps = has_gps(&path);
+            match strip_file(&path, keep_orientation, &output_dir) {
+                Ok(output_path) => StripResult { path, output_path: Some(output_path), had_gps, error: None },
+                Err(error) => StripResult { path, output_path: None, had_gps, error: Some(error) },
+            }
+        })
+        .collect())
+}
@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tract_onnx::prelude::*;
+
+// Optimized, ready-to-run ONNX model
+pub type Model = SimplePlan<TypedFact, Box<dyn TypedOp>, Graph<TypedFact, Box<dyn TypedOp>>>;
+
+// Loads ONNX models from the app's models directory and keeps them in memory,
+// so each model is only parsed and optimized once per session.
+// Inference runs locally through tract, so no network access is needed.
+pub struct ModelStore {
+    dir: PathBuf,
+    loaded: Mutex<HashMap<String, Arc<Model>>>,
+}
+
+impl ModelStore {
+    pub fn new(dir: PathBuf) -> ModelStore {
+        ModelStore {
+            dir,
+            loaded: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Load `<models dir>/<file_name>` with a fixed NCHW float input shape
+    pub fn get(&self, file_name: &str, input_shape: [usize; 4]) -> Result<Arc<Model>, String> {
+        let key = format!("{}:{:?}", file_name, input_shape);
+        if let Some(model) = self.loaded.lock().map_err(|_| "Model store lock poisoned")?.get(&key) {
+            return Ok(model.clone());
+        }
+
+        let model_path = self.dir.join(file_name);
+        if !model_path.exists() {
+            return Err(format!(
+                "Model {} not found. Download it into {}",
+                file_name,
+                self.dir.display()
+            ));
+        }
+
+        let model = tract_onnx::onnx()
+            .model_for_path(&model_path)
+            .and_then(|m| m.with_input_fact(0, f32::fact(input_shape).into()))
+            .and_then(|m| m.into_optimized())
+            .and_then(|m| m.into_runnable())
+            .map_err(|e| format!("Failed to load model {}: {}", file_name, e))?;
+
+        let model = Arc::new(model);
+        self.loaded
+            .lock()
+            .map_err(|_| "Model store lock poisoned")?
+            .insert(key, model.clone());
+
+        Ok(model)
+    }
+}
@@ -0,0 +1,301 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use crate::keychain;
+
+// Keychain account the proxy password is kept under
+const PASSWORD_ACCOUNT: &str = "proxy-password";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyScheme {
+    Http,
+    Https,
+    Socks5,
+}
+
+// How outbound requests reach the internet
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum ProxySettings {
+    // Proxy from the environment (HTTP_PROXY/HTTPS_PROXY, and the system
+    // settings on Windows and macOS)
+    #[default]
+    System,
+    // Connect directly, ignoring any system proxy
+    Direct,
+    Manual {
+        scheme: ProxyScheme,
+        host: String,
+        port: u16,
+        #[serde(default)]
+        username: Option<String>,
+        #[serde(default)]
+        password: Option<String>,
+    },
+}
+
+impl ProxySettings {
+    // Copy safe to send to the frontend, with the password blanked
+    fn redacted(&self) -> ProxySettings {
+        match self.clone() {
+            ProxySettings::Manual { scheme, host, port, username, password } => ProxySettings::Manual {
+                scheme,
+                host,
+                port,
+                username,
+                password: password.map(|_| String::new()),
+            },
+            other => other,
+        }
+    }
+
+    // Password of a manual proxy, if one is set
+    fn password(&self) -> Option<&str> {
+        match self {
+            ProxySettings::Manual { password: Some(password), .. } if !password.is_empty() => Some(password),
+            _ => None,
+        }
+    }
+
+    // Settings come back from the UI redacted; a blank password for the same
+    // proxy and user means "unchanged"
+    fn keep_password(self, stored: ProxySettings) -> ProxySettings {
+        match (self, stored) {
+            (
+                ProxySettings::Manual { scheme, host, port, username, password },
+                ProxySettings::Manual { host: stored_host, port: stored_port, username: stored_username, password: stored_password, .. },
+            ) if password.as_deref().unwrap_or("").is_empty() && host == stored_host && port == stored_port && username == stored_username => {
+                ProxySettings::Manual { scheme, host, port, username, password: stored_password }
+            }
+            (settings, _) => settings,
+        }
+    }
+
+    fn build_client(&self) -> Result<reqwest::Client, String> {
+        let builder = reqwest::Client::builder();
+        let builder = match self {
+            ProxySettings::System => builder,
+            ProxySettings::Direct => builder.no_proxy(),
+            ProxySettings::Manual { scheme, host, port, username, password } => {
+                if host.trim().is_empty() {
+                    return Err("Proxy host is required".to_string());
+                }
+                let username = username.as_deref().filter(|username| !username.is_empty());
+                let password = password.as_deref().unwrap_or("");
+                let proxy = match scheme {
+                    ProxyScheme::Http | ProxyScheme::Https => {
+                        let scheme = if *scheme == ProxyScheme::Http { "http" } else { "https" };
+                        let proxy = reqwest::Proxy::all(format!("{}://{}:{}", scheme, host.trim(), port))
+                            .map_err(|e| format!("Invalid proxy: {}", e))?;
+                        match username {
+                            Some(username) => proxy.basic_auth(username, password),
+                            None => proxy,
+                        }
+                    }
+                    // SOCKS credentials go in the URL. socks5h resolves host
+                    // names through the proxy, so DNS doesn't leak around it.
+                    ProxyScheme::Socks5 => {
+                        let credentials = username
+                            .map(|username| format!("{}:{}@", urlencoding::encode(username), urlencoding::encode(password)))
+                            .unwrap_or_default();
+                        reqwest::Proxy::all(format!("socks5h://{}{}:{}", credentials, host.trim(), port))
+                            .map_err(|e| format!("Invalid proxy: {}", e))?
+                    }
+                };
+                builder.proxy(proxy)
+            }
+        };
+        builder.build().map_err(|e| format!("Failed to create HTTP client: {}", e))
+    }
+}
+
+// HTTP client shared by everything that talks to cloud and marketplace APIs,
+// built from the proxy settings. reqwest clients are cheap to clone and share
+// a connection pool.
+pub struct HttpClient {
+    client: RwLock<reqwest::Client>,
+}
+
+impl HttpClient {
+    // Client for the saved proxy settings. Broken settings fall back to the
+    // system proxy so the app still starts; they can be fixed in settings.
+    pub fn new(app: &AppHandle) -> HttpClient {
+        let client = load_settings(app)
+            .and_then(|settings| settings.build_client())
+            .or_else(|_| ProxySettings::System.build_client())
+            .unwrap_or_default();
+        HttpClient { client: RwLock::new(client) }
+    }
+
+    pub fn get(&self) -> reqwest::Client {
+        match self.client.read() {
+            Ok(client) => client.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+}
+
+// The shared HTTP client
+pub fn client(app: &AppHandle) -> reqwest::Client {
+    app.state::<HttpClient>().get()
+}
+
+// One multipart/form-data part: name, optional file name, content type, data
+pub type FormPart<'a> = (&'a str, Option<&'a str>, &'a str, &'a [u8]);
+
+// multipart/form-data body and its content type; reqwest is built without
+// its multipart feature
+pub fn multipart(parts: &[FormPart]) -> (Vec<u8>, String) {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or_default();
+    let boundary = format!("listing-assistant-{:x}", nanos);
+    let mut body = Vec::new();
+    for (name, file_name, content_type, data) in parts {
+        body.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+        match file_name {
+            Some(file_name) => body.extend_from_slice(
+                format!("Content-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\n", name, file_name).as_bytes(),
+            ),
+            None => body.extend_from_slice(format!("Content-Disposition: form-data; name=\"{}\"\r\n", name).as_bytes()),
+        }
+        body.extend_from_slice(format!("Content-Type: {}\r\n\r\n", content_type).as_bytes());
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+    (body, format!("multipart/form-data; boundary={}", boundary))
+}
+
+// Decoded key/value pairs of a URL query string
+pub fn query_params(query: &str) -> Vec<(String, String)> {
+    let decode = |value: &str| {
+        let value = value.replace('+', " ");
+        urlencoding::decode(&value).map(|decoded| decoded.into_owned()).unwrap_or(value)
+    };
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (key.to_string(), decode(value)))
+        .collect()
+}
+
+// Serve an OAuth redirect on localhost until `deliver` accepts a request's
+// query, answering that request with `page`
+pub async fn serve_redirect(listener: TcpListener, page: &'static str, deliver: impl Fn(&str) -> bool) {
+    loop {
+        let Ok((mut stream, _)) = listener.accept().await else {
+            return;
+        };
+        let mut buffer = [0u8; 4096];
+        let read = stream.read(&mut buffer).await.unwrap_or(0);
+        let request = String::from_utf8_lossy(&buffer[..read]);
+        // "GET /path?query HTTP/1.1"
+        let query = request
+            .split_whitespace()
+            .nth(1)
+            .and_then(|target| target.split_once('?'))
+            .map(|(_, query)| query.to_string());
+
+        let delivered = query.is_some_and(|query| deliver(&query));
+        let (status, body) = if delivered {
+            ("200 OK", page)
+        } else {
+            ("404 Not Found", "Not found")
+        };
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        let _ = stream.write_all(response.as_bytes()).await;
+        if delivered {
+            return;
+        }
+    }
+}
+
+fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app.path_resolver()
+        .app_data_dir()
+        .ok_or("Failed to resolve app data directory")?;
+    Ok(data_dir.join("network.json"))
+}
+
+// Settings as saved in network.json, where a set password is left blank
+fn read_settings(app: &AppHandle) -> Result<ProxySettings, String> {
+    let path = settings_path(app)?;
+    if !path.exists() {
+        return Ok(ProxySettings::default());
+    }
+    let json = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read network settings: {}", e))?;
+    let settings: ProxySettings = serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse network settings: {}", e))?;
+    // Older versions kept the password in the file; move it to the keychain,
+    // leaving it in place if the keychain can't be reached
+    if settings.password().is_some() {
+        let _ = save_settings(app, &settings, &settings);
+    }
+    Ok(settings)
+}
+
+// Settings with the proxy password from the keychain
+fn load_settings(app: &AppHandle) -> Result<ProxySettings, String> {
+    match read_settings(app)? {
+        ProxySettings::Manual { scheme, host, port, username, password: Some(password) } if password.is_empty() => {
+            let password = keychain::get_secret(PASSWORD_ACCOUNT)?;
+            Ok(ProxySettings::Manual { scheme, host, port, username, password })
+        }
+        settings => Ok(settings),
+    }
+}
+
+// Save the password in the keychain and the rest in network.json, removing
+// the `stored` settings' password when there no longer is one
+fn save_settings(app: &AppHandle, settings: &ProxySettings, stored: &ProxySettings) -> Result<(), String> {
+    match settings.password() {
+        Some(password) => keychain::set_secret(PASSWORD_ACCOUNT, password)?,
+        None if stored.password().is_some() => keychain::delete_secret(PASSWORD_ACCOUNT)?,
+        None => {}
+    }
+
+    let path = settings_path(app)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create settings directory: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(&settings.redacted())
+        .map_err(|e| format!("Failed to serialize network settings: {}", e))?;
+    fs::write(&path, json)
+        .map_err(|e| format!("Failed to save network settings: {}", e))
+}
+
+// Command to set the proxy used for all outbound requests
+// Takes effect for new requests straight away. A proxy password goes in the
+// OS keychain and the rest in network.json.
+#[tauri::command]
+pub fn set_proxy_settings(app: AppHandle, http: State<HttpClient>, settings: ProxySettings) -> Result<(), String> {
+    // Unreadable stored settings are being replaced, so there is nothing to keep
+    let stored = load_settings(&app).unwrap_or_default();
+    let settings = settings.keep_password(stored.clone());
+    let client = settings.build_client()?;
+    save_settings(&app, &settings, &stored)?;
+
+    let mut current = http.client.write()
+        .map_err(|_| "HTTP client lock poisoned".to_string())?;
+    *current = client;
+    Ok(())
+}
+
+// Command to get the proxy settings, with the password blanked; leave it
+// blank in `set_proxy_settings` to keep the stored one
+#[tauri::command]
+pub fn get_proxy_settings(app: AppHandle) -> Result<ProxySettings, String> {
+    read_settings(&app).map(|settings| settings.redacted())
+}
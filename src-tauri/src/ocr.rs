@@ -0,0 +1,183 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use crate::vision::{self, TextAnnotation};
+use crate::image_io;
+
+// Tesseract is run as an external tool, like ffmpeg, for offline OCR
+const TESSERACT: &str = "tesseract";
+
+// Care tags are small in frame, so OCR works on a larger image than other analysis
+const OCR_EDGE: u32 = 2400;
+pub const OCR_JPEG_QUALITY: u8 = 90;
+
+// Keeps temporary files of concurrent OCR runs apart
+static NEXT_TEMP_ID: AtomicU64 = AtomicU64::new(0);
+
+// Part of the photo to read, as fractions (0.0-1.0) of its width and height
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct OcrRegion {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl OcrRegion {
+    pub const WHOLE: OcrRegion = OcrRegion { x: 0.0, y: 0.0, width: 1.0, height: 1.0 };
+
+    fn validate(&self) -> Result<(), String> {
+        let inside = |start: f64, length: f64| start >= 0.0 && length > 0.0 && start + length <= 1.0 + 1e-9;
+        if inside(self.x, self.width) && inside(self.y, self.height) {
+            Ok(())
+        } else {
+            Err("region must lie within the photo (fractions from 0 to 1)".to_string())
+        }
+    }
+
+    // Map a box in pixels of a `width` x `height` crop of this region back to
+    // fractions of the whole photo
+    pub fn to_photo(self, left: f64, top: f64, right: f64, bottom: f64, width: f64, height: f64) -> OcrRegion {
+        OcrRegion {
+            x: self.x + left / width * self.width,
+            y: self.y + top / height * self.height,
+            width: (right - left) / width * self.width,
+            height: (bottom - top) / height * self.height,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OcrEngine {
+    Vision,
+    Tesseract,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OcrWord {
+    pub text: String,
+    // Position in the whole photo, as fractions of its size
+    pub bounds: OcrRegion,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OcrResult {
+    pub text: String,
+    pub words: Vec<OcrWord>,
+    pub engine: OcrEngine,
+}
+
+// Read `region` of a photo at the size used for OCR
+pub fn crop(path: &str, region: OcrRegion) -> Result<DynamicImage, String> {
+    let img = image_io::open_image_scaled(path, OCR_EDGE)?;
+    let (width, height) = (img.width() as f64, img.height() as f64);
+    let x = (region.x * width).floor() as u32;
+    let y = (region.y * height).floor() as u32;
+    let w = ((region.width * width).round() as u32).clamp(1, img.width() - x.min(img.width() - 1));
+    let h = ((region.height * height).round() as u32).clamp(1, img.height() - y.min(img.height() - 1));
+    Ok(img.crop_imm(x, y, w, h))
+}
+
+async fn vision_ocr(app: &AppHandle, img: &DynamicImage, region: OcrRegion) -> Result<OcrResult, String> {
+    let jpeg = image_io::encode_jpeg(img, OCR_JPEG_QUALITY)?;
+    let annotations = vision::annotate_jpeg(app, &jpeg, &["TEXT_DETECTION"]).await?;
+    Ok(from_vision(annotations.text_annotations, img.width(), img.height(), region))
+}
+
+// OCR result from Vision text annotations of a `width` x `height` crop of `region`
+pub fn from_vision(annotations: Vec<TextAnnotation>, width: u32, height: u32, region: OcrRegion) -> OcrResult {
+    let (width, height) = (width as f64, height as f64);
+    let mut annotations = annotations.into_iter();
+    let text = annotations.next().map(|full| full.description).unwrap_or_default();
+    let words = annotations
+        .filter(|word| !word.bounding_poly.vertices.is_empty())
+        .map(|word| {
+            let (left, top, right, bottom) = word.bounding_poly.extent();
+            OcrWord { text: word.description, bounds: region.to_photo(left, top, right, bottom, width, height) }
+        })
+        .collect();
+
+    OcrResult { text: text.trim().to_string(), words, engine: OcrEngine::Vision }
+}
+
+// Read text with the tesseract CLI, using its TSV output for word boxes
+pub fn tesseract_ocr(img: &DynamicImage, region: OcrRegion) -> Result<OcrResult, String> {
+    let input = std::env::temp_dir().join(format!(
+        "listing-assistant-ocr-{}-{}.png",
+        std::process::id(),
+        NEXT_TEMP_ID.fetch_add(1, Ordering::Relaxed)
+    ));
+    img.save(&input)
+        .map_err(|e| format!("Failed to write OCR image: {}", e))?;
+    let output = Command::new(TESSERACT)
+        .arg(&input)
+        .args(["stdout", "tsv"])
+        .output();
+    let _ = fs::remove_file(&input);
+
+    let output = output
+        .map_err(|e| format!("Failed to run tesseract (is it installed and on PATH?): {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("tesseract failed: {}", stderr.trim()));
+    }
+
+    let (width, height) = (img.width() as f64, img.height() as f64);
+    let mut words = Vec::new();
+    // Words grouped by (block, paragraph, line) to rebuild the text
+    let mut lines: BTreeMap<(u32, u32, u32), Vec<String>> = BTreeMap::new();
+    for row in String::from_utf8_lossy(&output.stdout).lines().skip(1) {
+        let columns: Vec<&str> = row.split('\t').collect();
+        if columns.len() < 12 || columns[0] != "5" {
+            continue;
+        }
+        let text = columns[11].trim();
+        let confidence: f64 = columns[10].parse().unwrap_or(-1.0);
+        if text.is_empty() || confidence < 0.0 {
+            continue;
+        }
+        let number = |index: usize| columns[index].parse::<u32>().unwrap_or(0);
+        let (left, top, w, h) = (number(6) as f64, number(7) as f64, number(8) as f64, number(9) as f64);
+
+        lines.entry((number(2), number(3), number(4))).or_default().push(text.to_string());
+        words.push(OcrWord {
+            text: text.to_string(),
+            bounds: region.to_photo(left, top, left + w, top + h, width, height),
+        });
+    }
+
+    let text = lines.values().map(|line| line.join(" ")).collect::<Vec<_>>().join("\n");
+    Ok(OcrResult { text, words, engine: OcrEngine::Tesseract })
+}
+
+// Command to read the text in a photo (or a `region` of it), e.g. a care tag
+// for brand, size and material. Uses Vision TEXT_DETECTION, falling back to
+// the `tesseract` command-line tool when Vision isn't set up or can't be
+// reached. Word boxes are fractions of the whole photo.
+#[tauri::command]
+pub async fn ocr_image(app: AppHandle, path: String, region: Option<OcrRegion>) -> Result<OcrResult, String> {
+    let region = region.unwrap_or(OcrRegion::WHOLE);
+    region.validate()?;
+
+    let img = tauri::async_runtime::spawn_blocking(move || crop(&path, region))
+        .await
+        .map_err(|e| format!("Failed to read image: {}", e))??;
+    read_text(&app, img, region).await
+}
+
+// Read `img`, a crop of `region`, with Vision, or tesseract when Vision fails
+pub async fn read_text(app: &AppHandle, img: DynamicImage, region: OcrRegion) -> Result<OcrResult, String> {
+    let vision_error = match vision_ocr(app, &img, region).await {
+        Ok(result) => return Ok(result),
+        Err(e) => e,
+    };
+    tauri::async_runtime::spawn_blocking(move || tesseract_ocr(&img, region))
+        .await
+        .map_err(|e| format!("OCR failed: {}", e))?
+        .map_err(|e| format!("OCR failed. Vision: {}. Offline: {}", vision_error, e))
+}
@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+// Registry of cancellation flags for long-running commands, keyed by a
+// frontend-supplied operation id
+#[derive(Default)]
+pub struct OperationRegistry {
+    tokens: Mutex<HashMap<String, CancelToken>>,
+}
+
+// Shared flag checked by long-running work between items
+#[derive(Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+    // Wakes `cancelled()` waiters
+    notify: Arc<Notify>,
+}
+
+impl CancelToken {
+    // Token that is never cancelled, for callers without an operation id
+    pub fn none() -> CancelToken {
+        CancelToken::default()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.notify.notify_waiters();
+    }
+
+    // Wait until the operation is cancelled, e.g. to cut a sleep short in
+    // `tokio::select!`
+    pub async fn cancelled(&self) {
+        let notified = self.notify.notified();
+        tokio::pin!(notified);
+        // Registered before the flag is checked, so a cancel in between
+        // isn't missed
+        notified.as_mut().enable();
+        if !self.is_cancelled() {
+            notified.await;
+        }
+    }
+
+    // Return an error if the operation has been cancelled
+    pub fn check(&self) -> Result<(), String> {
+        if self.is_cancelled() {
+            Err("Operation cancelled".to_string())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+// Keeps an operation registered until dropped
+pub struct OperationGuard<'a> {
+    registry: &'a OperationRegistry,
+    id: Option<String>,
+    pub token: CancelToken,
+}
+
+impl Drop for OperationGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(id) = &self.id {
+            if let Ok(mut tokens) = self.registry.tokens.lock() {
+                tokens.remove(id);
+            }
+        }
+    }
+}
+
+impl OperationRegistry {
+    // Register an operation; without an id the returned token can't be cancelled
+    pub fn register(&self, id: Option<String>) -> OperationGuard<'_> {
+        let token = CancelToken::default();
+        if let Some(id) = &id {
+            if let Ok(mut tokens) = self.tokens.lock() {
+                tokens.insert(id.clone(), token.clone());
+            }
+        }
+
+        OperationGuard { registry: self, id, token }
+    }
+
+    // Flag an operation as cancelled, returning false if it isn't running
+    pub fn cancel(&self, id: &str) -> bool {
+        match self.tokens.lock() {
+            Ok(tokens) => match tokens.get(id) {
+                Some(token) => {
+                    token.cancel();
+                    true
+                }
+                None => false,
+            },
+            Err(_) => false,
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use image::GrayImage;
+use rayon::prelude::*;
+use serde::Serialize;
+use crate::{image_io, photo_editing, quality};
+
+// Brightness jump between neighbouring pixels that counts as a stroke edge
+const STROKE_CONTRAST: i16 = 60;
+
+// Stroke edges per pixel of row width typical of printed text; plain fabric
+// has fewer, busy patterns more
+const TEXT_ROW_DENSITY: (f64, f64) = (0.03, 0.3);
+
+// Share of text-like rows above which a photo is treated as a label shot
+const LABEL_TEXT_SCORE: f64 = 0.25;
+
+// Subject covering more of the frame than this is a close-up (detail or flaw)
+const CLOSE_UP_COVERAGE: f64 = 0.85;
+
+// Role a photo plays in a listing, in eBay's suggested order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PhotoRole {
+    Front,
+    Back,
+    // Further whole-item shots (sides, alternate angles)
+    Item,
+    Label,
+    // Close-ups such as flaws, fabric or hardware
+    Detail,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrderedPhoto {
+    pub path: String,
+    pub role: PhotoRole,
+    // How strongly the photo suits its role, 0.0-1.0
+    pub score: f64,
+}
+
+struct PhotoTraits {
+    sharpness: f64,
+    overall: f64,
+    text: f64,
+    coverage: f64,
+    centered: f64,
+}
+
+// Share of rows with the dense, high-contrast transitions of printed text.
+// A cheap stand-in for OCR that only needs to spot that text is present.
+fn text_score(gray: &GrayImage) -> f64 {
+    let (width, height) = gray.dimensions();
+    if width < 2 || height == 0 {
+        return 0.0;
+    }
+
+    let text_rows = (0..height)
+        .filter(|&y| {
+            let strokes = (1..width)
+                .filter(|&x| (gray.get_pixel(x, y)[0] as i16 - gray.get_pixel(x - 1, y)[0] as i16).abs() >= STROKE_CONTRAST)
+                .count();
+            let density = strokes as f64 / width as f64;
+            (TEXT_ROW_DENSITY.0..=TEXT_ROW_DENSITY.1).contains(&density)
+        })
+        .count();
+
+    text_rows as f64 / height as f64
+}
+
+fn analyze(path: &str) -> Result<PhotoTraits, String> {
+    let img = image_io::open_image_scaled(path, photo_editing::DETECTION_EDGE)?;
+    let score = quality::score_photo(&img);
+    let gray = img.to_luma8();
+    let (width, height) = (gray.width() as f64, gray.height() as f64);
+
+    // A photo with no distinct subject is filled edge to edge, like a close-up
+    let (coverage, centered) = match photo_editing::detect_subject_bounds(&gray) {
+        Some((x, y, w, h)) => {
+            let offset_x = ((x as f64 + w as f64 / 2.0) / width - 0.5).abs();
+            let offset_y = ((y as f64 + h as f64 / 2.0) / height - 0.5).abs();
+            ((w as f64 * h as f64) / (width * height), 1.0 - 2.0 * offset_x.max(offset_y))
+        }
+        None => (1.0, 1.0),
+    };
+
+    Ok(PhotoTraits {
+        sharpness: score.sharpness,
+        overall: score.overall,
+        text: text_score(&gray),
+        coverage,
+        centered,
+    })
+}
+
+// Propose a listing order for one item's photos: front, back, other whole-item
+// shots, then labels and close-ups. Whole-item shots ranked by framing and
+// quality, labels by how much text they show, close-ups by sharpness.
+// Photos of the same role otherwise keep their original order.
+pub fn suggest_order(paths: &[String]) -> Result<Vec<OrderedPhoto>, String> {
+    let traits = paths
+        .par_iter()
+        .map(|path| analyze(path))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let mut photos: Vec<OrderedPhoto> = paths
+        .iter()
+        .zip(&traits)
+        .map(|(path, t)| {
+            let (role, score) = if t.text >= LABEL_TEXT_SCORE {
+                (PhotoRole::Label, t.text)
+            } else if t.coverage >= CLOSE_UP_COVERAGE {
+                (PhotoRole::Detail, t.sharpness)
+            } else {
+                (PhotoRole::Item, 0.5 * t.overall + 0.5 * t.centered.max(0.0))
+            };
+            OrderedPhoto { path: path.clone(), role, score }
+        })
+        .collect();
+
+    // Best framed whole-item shot leads; the first remaining one is the back
+    let front = photos
+        .iter()
+        .enumerate()
+        .filter(|(_, photo)| photo.role == PhotoRole::Item)
+        .max_by(|a, b| a.1.score.total_cmp(&b.1.score))
+        .map(|(index, _)| index);
+    if let Some(front) = front {
+        photos[front].role = PhotoRole::Front;
+        if let Some(back) = photos.iter_mut().find(|photo| photo.role == PhotoRole::Item) {
+            back.role = PhotoRole::Back;
+        }
+    }
+
+    // Stable sort keeps capture order within Item shots
+    photos.sort_by(|a, b| {
+        a.role.cmp(&b.role).then_with(|| match a.role {
+            PhotoRole::Label | PhotoRole::Detail => b.score.total_cmp(&a.score),
+            _ => std::cmp::Ordering::Equal,
+        })
+    });
+    Ok(photos)
+}
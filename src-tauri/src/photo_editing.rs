@@ -0,0 +1,546 @@
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use image::{DynamicImage, ImageOutputFormat};
+use rayon::prelude::*;
+use rsa::sha2::{Digest, Sha256};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use crate::ocr::OcrRegion;
+use crate::image_io;
+
+// Outcome for one file in a batch command
+#[derive(Debug, Serialize)]
+pub struct FileResult {
+    path: String,
+    output_path: Option<String>,
+    error: Option<String>,
+}
+
+impl FileResult {
+    pub fn new(path: String, result: Result<String, String>) -> FileResult {
+        match result {
+            Ok(output_path) => FileResult { path, output_path: Some(output_path), error: None },
+            Err(error) => FileResult { path, output_path: None, error: Some(error) },
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PreparedImage {
+    output_path: String,
+    original_size: u64,
+    prepared_size: u64,
+    width: u32,
+    height: u32,
+}
+
+// Directory under the app cache where processed copies are written
+pub fn output_dir(app: &AppHandle, name: &str) -> Result<PathBuf, String> {
+    let dir = app.path_resolver()
+        .app_cache_dir()
+        .ok_or("Failed to resolve app cache directory")?
+        .join(name);
+    fs::create_dir_all(&dir)
+        .map_err(|e| format!("Failed to create output directory: {}", e))?;
+    Ok(dir)
+}
+
+// Output file name that won't collide for same-named photos from different folders
+pub fn output_file_name(source_path: &str, suffix: &str, extension: &str) -> String {
+    let stem = Path::new(source_path)
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "image".to_string());
+    let path_hash = hex::encode(Sha256::digest(source_path.as_bytes()));
+    format!("{}-{}-{}.{}", stem, &path_hash[..8], suffix, extension)
+}
+
+pub fn file_size(path: &Path) -> Result<u64, String> {
+    fs::metadata(path)
+        .map(|m| m.len())
+        .map_err(|e| format!("Failed to read metadata for {}: {}", path.display(), e))
+}
+
+// Command to downsize and re-encode a photo as JPEG for marketplace upload
+// Re-encoding drops EXIF and other metadata; orientation is baked in first
+#[tauri::command(async)]
+pub fn prepare_image_for_upload(
+    app: AppHandle,
+    path: String,
+    max_dimension: u32,
+    jpeg_quality: u8,
+) -> Result<PreparedImage, String> {
+    if max_dimension == 0 {
+        return Err("max_dimension must be greater than 0".to_string());
+    }
+    if !(1..=100).contains(&jpeg_quality) {
+        return Err("jpeg_quality must be between 1 and 100".to_string());
+    }
+
+    let original_size = file_size(Path::new(&path))?;
+
+    let mut img = image_io::open_image(&path)?;
+    if img.width() > max_dimension || img.height() > max_dimension {
+        img = img.resize(max_dimension, max_dimension, image::imageops::FilterType::Lanczos3);
+    }
+
+    let jpeg_data = image_io::encode_jpeg(&img, jpeg_quality)?;
+
+    let output_path = output_dir(&app, "uploads")?
+        .join(output_file_name(&path, &format!("{}q{}", max_dimension, jpeg_quality), "jpg"));
+    fs::write(&output_path, &jpeg_data)
+        .map_err(|e| format!("Failed to write prepared image: {}", e))?;
+
+    Ok(PreparedImage {
+        output_path: image_io::path_to_string(&output_path)?,
+        original_size,
+        prepared_size: jpeg_data.len() as u64,
+        width: img.width(),
+        height: img.height(),
+    })
+}
+
+// JPEG quality for edited copies of photos
+pub const EDIT_JPEG_QUALITY: u8 = 92;
+
+// Working size for subject detection
+pub const DETECTION_EDGE: u32 = 512;
+
+// Default margin around the detected subject, as a fraction of its size
+const DEFAULT_CROP_PADDING: f64 = 0.05;
+
+#[derive(Debug, Serialize)]
+pub struct CropResult {
+    output_path: String,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+// Find the bounding box of the subject from Sobel edge density, in the
+// coordinates of `gray`. Returns None if no clear subject stands out.
+pub fn detect_subject_bounds(gray: &image::GrayImage) -> Option<(u32, u32, u32, u32)> {
+    let (width, height) = gray.dimensions();
+    if width < 3 || height < 3 {
+        return None;
+    }
+
+    let mut magnitudes = vec![0.0f64; (width * height) as usize];
+    for y in 1..(height - 1) {
+        for x in 1..(width - 1) {
+            let p = |dx: i32, dy: i32| gray.get_pixel((x as i32 + dx) as u32, (y as i32 + dy) as u32)[0] as f64;
+            let gx = p(1, -1) + 2.0 * p(1, 0) + p(1, 1) - p(-1, -1) - 2.0 * p(-1, 0) - p(-1, 1);
+            let gy = p(-1, 1) + 2.0 * p(0, 1) + p(1, 1) - p(-1, -1) - 2.0 * p(0, -1) - p(1, -1);
+            magnitudes[(y * width + x) as usize] = (gx * gx + gy * gy).sqrt();
+        }
+    }
+
+    // Edges well above the backdrop's texture count as subject
+    let count = magnitudes.len() as f64;
+    let mean = magnitudes.iter().sum::<f64>() / count;
+    let std_dev = (magnitudes.iter().map(|m| (m - mean).powi(2)).sum::<f64>() / count).sqrt();
+    let threshold = (mean + 2.0 * std_dev).max(30.0);
+
+    let mut row_counts = vec![0u32; height as usize];
+    let mut col_counts = vec![0u32; width as usize];
+    for y in 0..height {
+        for x in 0..width {
+            if magnitudes[(y * width + x) as usize] > threshold {
+                row_counts[y as usize] += 1;
+                col_counts[x as usize] += 1;
+            }
+        }
+    }
+
+    // Ignore rows/columns with only stray specks of edges
+    let min_row = (width / 200).max(2);
+    let min_col = (height / 200).max(2);
+    let top = row_counts.iter().position(|&c| c >= min_row)? as u32;
+    let bottom = row_counts.iter().rposition(|&c| c >= min_row)? as u32;
+    let left = col_counts.iter().position(|&c| c >= min_col)? as u32;
+    let right = col_counts.iter().rposition(|&c| c >= min_col)? as u32;
+
+    if bottom <= top || right <= left {
+        return None;
+    }
+
+    Some((left, top, right - left + 1, bottom - top + 1))
+}
+
+// Resolve where an edited copy goes, refusing to overwrite the original
+pub fn edited_output_path(app: &AppHandle, source_path: &str, output_path: Option<String>, suffix: &str, extension: &str) -> Result<PathBuf, String> {
+    match output_path {
+        Some(output_path) if Path::new(&output_path) == Path::new(source_path) => {
+            Err("Output path must differ from the original photo".to_string())
+        }
+        Some(output_path) => Ok(PathBuf::from(output_path)),
+        None => Ok(output_dir(app, "edited")?.join(output_file_name(source_path, suffix, extension))),
+    }
+}
+
+// Command to crop a photo to the detected item, writing a new JPEG file
+// `padding` is the margin around the item as a fraction of its size (default 0.05)
+#[tauri::command(async)]
+pub fn auto_crop(
+    app: AppHandle,
+    path: String,
+    padding: Option<f64>,
+    output_path: Option<String>,
+) -> Result<CropResult, String> {
+    let padding = padding.unwrap_or(DEFAULT_CROP_PADDING);
+    if !(0.0..=1.0).contains(&padding) {
+        return Err("padding must be between 0 and 1".to_string());
+    }
+
+    let img = image_io::open_image(&path)?;
+    let small = img.thumbnail(DETECTION_EDGE, DETECTION_EDGE).to_luma8();
+    let (left, top, width, height) = detect_subject_bounds(&small)
+        .ok_or("No subject detected in photo")?;
+
+    // Scale the box back to full resolution and add padding
+    let scale_x = img.width() as f64 / small.width() as f64;
+    let scale_y = img.height() as f64 / small.height() as f64;
+    let pad_x = width as f64 * padding;
+    let pad_y = height as f64 * padding;
+    let x0 = ((left as f64 - pad_x) * scale_x).max(0.0) as u32;
+    let y0 = ((top as f64 - pad_y) * scale_y).max(0.0) as u32;
+    let x1 = (((left + width) as f64 + pad_x) * scale_x).min(img.width() as f64) as u32;
+    let y1 = (((top + height) as f64 + pad_y) * scale_y).min(img.height() as f64) as u32;
+
+    let cropped = img.crop_imm(x0, y0, x1 - x0, y1 - y0);
+
+    let output_path = edited_output_path(&app, &path, output_path, "crop", "jpg")?;
+    fs::write(&output_path, image_io::encode_jpeg(&cropped, EDIT_JPEG_QUALITY)?)
+        .map_err(|e| format!("Failed to write cropped image: {}", e))?;
+
+    Ok(CropResult {
+        output_path: image_io::path_to_string(&output_path)?,
+        x: x0,
+        y: y0,
+        width: x1 - x0,
+        height: y1 - y0,
+    })
+}
+
+// Output formats supported by `convert_images`
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TargetFormat {
+    Jpeg,
+    Png,
+    Webp,
+}
+
+impl TargetFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            TargetFormat::Jpeg => "jpg",
+            TargetFormat::Png => "png",
+            TargetFormat::Webp => "webp",
+        }
+    }
+}
+
+// Encode an image in the target format; quality applies to JPEG and WebP
+pub fn encode_as(img: &DynamicImage, format: TargetFormat, quality: u8) -> Result<Vec<u8>, String> {
+    match format {
+        TargetFormat::Jpeg => image_io::encode_jpeg(img, quality),
+        TargetFormat::Png => {
+            let mut buffer = Cursor::new(Vec::new());
+            img.write_to(&mut buffer, ImageOutputFormat::Png)
+                .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+            Ok(buffer.into_inner())
+        }
+        TargetFormat::Webp => {
+            let rgba = img.to_rgba8();
+            let encoded = webp::Encoder::from_rgba(rgba.as_raw(), rgba.width(), rgba.height())
+                .encode(quality as f32);
+            Ok(encoded.to_vec())
+        }
+    }
+}
+
+fn convert_file(path: &str, format: TargetFormat, quality: u8, output_dir: &Path) -> Result<String, String> {
+    let img = image_io::open_image(path)?;
+    let data = encode_as(&img, format, quality)?;
+
+    let output_path = output_dir.join(output_file_name(path, "converted", format.extension()));
+    fs::write(&output_path, data)
+        .map_err(|e| format!("Failed to write converted image: {}", e))?;
+    image_io::path_to_string(&output_path)
+}
+
+// Command to convert a batch of photos (including HEIC) to JPEG, PNG or WebP
+// `quality` (1-100, default 90) applies to JPEG and WebP
+#[tauri::command(async)]
+pub fn convert_images(
+    app: AppHandle,
+    paths: Vec<String>,
+    target_format: TargetFormat,
+    quality: Option<u8>,
+) -> Result<Vec<FileResult>, String> {
+    let quality = quality.unwrap_or(image_io::DISPLAY_JPEG_QUALITY);
+    if !(1..=100).contains(&quality) {
+        return Err("quality must be between 1 and 100".to_string());
+    }
+
+    let output_dir = output_dir(&app, "converted")?;
+
+    Ok(paths
+        .into_par_iter()
+        .map(|path| {
+            let result = convert_file(&path, target_format, quality, &output_dir);
+            FileResult::new(path, result)
+        })
+        .collect())
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Flip {
+    Horizontal,
+    Vertical,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TransformResult {
+    output_path: String,
+}
+
+// Rotate clockwise by a multiple of 90 degrees, then flip
+fn transform(img: DynamicImage, rotation: u32, flip: Option<Flip>) -> DynamicImage {
+    let rotated = match rotation {
+        90 => img.rotate90(),
+        180 => img.rotate180(),
+        270 => img.rotate270(),
+        _ => img,
+    };
+    match flip {
+        Some(Flip::Horizontal) => rotated.fliph(),
+        Some(Flip::Vertical) => rotated.flipv(),
+        None => rotated,
+    }
+}
+
+// Command to rotate (clockwise, in degrees) and/or flip a photo. The
+// pixels themselves are turned and the photo re-encoded, JPEGs included:
+// an EXIF orientation alone is lost when metadata is stripped on export or
+// by a marketplace.
+#[tauri::command(async)]
+pub fn transform_image(
+    app: AppHandle,
+    path: String,
+    rotation: u32,
+    flip: Option<Flip>,
+    output_path: Option<String>,
+) -> Result<TransformResult, String> {
+    if rotation % 90 != 0 {
+        return Err("rotation must be a multiple of 90 degrees".to_string());
+    }
+    let rotation = rotation % 360;
+
+    let format = match Path::new(&path).extension().map(|e| e.to_string_lossy().to_lowercase()).as_deref() {
+        Some("png") => TargetFormat::Png,
+        Some("webp") => TargetFormat::Webp,
+        _ => TargetFormat::Jpeg,
+    };
+    let img = transform(image_io::open_image(&path)?, rotation, flip);
+
+    let output_path = edited_output_path(&app, &path, output_path, "rotate", format.extension())?;
+    fs::write(&output_path, encode_as(&img, format, EDIT_JPEG_QUALITY)?)
+        .map_err(|e| format!("Failed to write rotated image: {}", e))?;
+
+    Ok(TransformResult {
+        output_path: image_io::path_to_string(&output_path)?,
+    })
+}
+
+// Letterbox an image onto a square canvas of `background`, centred
+pub fn pad_square(img: &DynamicImage, background: [u8; 3]) -> DynamicImage {
+    let img = img.to_rgb8();
+    let side = img.width().max(img.height());
+    let mut canvas = image::RgbImage::from_pixel(side, side, image::Rgb(background));
+    image::imageops::overlay(
+        &mut canvas,
+        &img,
+        ((side - img.width()) / 2) as i64,
+        ((side - img.height()) / 2) as i64,
+    );
+    DynamicImage::ImageRgb8(canvas)
+}
+
+// Command to letterbox a photo onto a square canvas without distorting it
+// `background_color` is a hex colour like "#ffffff" (default white)
+#[tauri::command(async)]
+pub fn pad_to_square(
+    app: AppHandle,
+    path: String,
+    background_color: Option<String>,
+    output_path: Option<String>,
+) -> Result<String, String> {
+    let background = background_color
+        .as_deref()
+        .map(image_io::parse_hex_color)
+        .transpose()?
+        .unwrap_or([255, 255, 255]);
+
+    let canvas = pad_square(&image_io::open_image(&path)?, background);
+    let output_path = edited_output_path(&app, &path, output_path, "square", "jpg")?;
+    fs::write(&output_path, image_io::encode_jpeg(&canvas, EDIT_JPEG_QUALITY)?)
+        .map_err(|e| format!("Failed to write padded image: {}", e))?;
+    image_io::path_to_string(&output_path)
+}
+
+// Fraction of darkest/brightest pixels clipped by the contrast stretch
+const STRETCH_CLIP: f64 = 0.01;
+
+// Limit on white balance channel gains, so strongly coloured items aren't neutralised
+const MAX_WB_GAIN: f64 = 1.6;
+
+// Command to fix dim, colour-cast photos: gray-world white balance, contrast
+// stretch and mild sharpening. Writes a new file so before/after can be compared.
+#[tauri::command(async)]
+pub fn auto_enhance(app: AppHandle, path: String, output_path: Option<String>) -> Result<String, String> {
+    let mut rgb = image_io::open_image(&path)?.to_rgb8();
+    let pixel_count = (rgb.width() as f64 * rgb.height() as f64).max(1.0);
+
+    // Gray world: scale each channel so its mean matches the overall mean
+    let mut sums = [0.0f64; 3];
+    for pixel in rgb.pixels() {
+        for c in 0..3 {
+            sums[c] += pixel[c] as f64;
+        }
+    }
+    let gray = (sums[0] + sums[1] + sums[2]) / 3.0;
+    let gains = sums.map(|sum| (gray / sum.max(1.0)).clamp(1.0 / MAX_WB_GAIN, MAX_WB_GAIN));
+
+    // Luminance histogram after white balance, for the contrast stretch
+    let mut histogram = [0u64; 256];
+    for pixel in rgb.pixels_mut() {
+        for c in 0..3 {
+            pixel[c] = (pixel[c] as f64 * gains[c]).round().min(255.0) as u8;
+        }
+        let luma = 0.299 * pixel[0] as f64 + 0.587 * pixel[1] as f64 + 0.114 * pixel[2] as f64;
+        histogram[luma.round() as usize] += 1;
+    }
+
+    let percentile = |fraction: f64| {
+        let target = (pixel_count * fraction) as u64;
+        let mut seen = 0;
+        for (value, count) in histogram.iter().enumerate() {
+            seen += count;
+            if seen > target {
+                return value as f64;
+            }
+        }
+        255.0
+    };
+    let low = percentile(STRETCH_CLIP);
+    let high = percentile(1.0 - STRETCH_CLIP);
+
+    // Skip the stretch for near-uniform photos, where it would only amplify noise
+    if high - low > 10.0 {
+        let scale = 255.0 / (high - low);
+        for pixel in rgb.pixels_mut() {
+            for c in 0..3 {
+                pixel[c] = ((pixel[c] as f64 - low) * scale).round().clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+
+    let enhanced = DynamicImage::ImageRgb8(rgb).unsharpen(1.0, 2);
+
+    let output_path = edited_output_path(&app, &path, output_path, "enhanced", "jpg")?;
+    fs::write(&output_path, image_io::encode_jpeg(&enhanced, EDIT_JPEG_QUALITY)?)
+        .map_err(|e| format!("Failed to write enhanced image: {}", e))?;
+    image_io::path_to_string(&output_path)
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RedactionStyle {
+    #[default]
+    Pixelate,
+    Blur,
+}
+
+// Margin added around each redacted rectangle, as a fraction of its size,
+// since detected text and face boxes are tight
+const REDACT_MARGIN: f64 = 0.1;
+
+// Pixelation blocks per rectangle side, and the smallest block in pixels
+const PIXELATE_BLOCKS: u32 = 8;
+const MIN_PIXELATE_BLOCK: u32 = 6;
+
+// Command to pixelate or blur rectangles of a photo (faces, addresses,
+// serial numbers), writing a redacted JPEG copy for upload
+// `rects` are fractions (0.0-1.0) of the photo's width and height, as
+// returned by `scan_for_pii` and `ocr_image`. Re-encoding drops EXIF too.
+#[tauri::command(async)]
+pub fn blur_regions(
+    app: AppHandle,
+    path: String,
+    rects: Vec<OcrRegion>,
+    style: Option<RedactionStyle>,
+    output_path: Option<String>,
+) -> Result<String, String> {
+    if rects.is_empty() {
+        return Err("No regions to redact".to_string());
+    }
+    let mut rgb = image_io::open_image(&path)?.to_rgb8();
+    let (width, height) = (rgb.width() as f64, rgb.height() as f64);
+
+    for rect in rects {
+        let margin_x = rect.width * REDACT_MARGIN;
+        let margin_y = rect.height * REDACT_MARGIN;
+        let x0 = ((rect.x - margin_x) * width).clamp(0.0, width) as u32;
+        let y0 = ((rect.y - margin_y) * height).clamp(0.0, height) as u32;
+        let x1 = ((rect.x + rect.width + margin_x) * width).ceil().clamp(0.0, width) as u32;
+        let y1 = ((rect.y + rect.height + margin_y) * height).ceil().clamp(0.0, height) as u32;
+        if x1 <= x0 || y1 <= y0 {
+            continue;
+        }
+        let (w, h) = (x1 - x0, y1 - y0);
+
+        match style.unwrap_or_default() {
+            RedactionStyle::Pixelate => {
+                let block = (w.min(h) / PIXELATE_BLOCKS).max(MIN_PIXELATE_BLOCK);
+                for by in (y0..y1).step_by(block as usize) {
+                    for bx in (x0..x1).step_by(block as usize) {
+                        let (bx1, by1) = ((bx + block).min(x1), (by + block).min(y1));
+                        let mut sums = [0u64; 3];
+                        for y in by..by1 {
+                            for x in bx..bx1 {
+                                let pixel = rgb.get_pixel(x, y);
+                                for c in 0..3 {
+                                    sums[c] += pixel[c] as u64;
+                                }
+                            }
+                        }
+                        let count = ((bx1 - bx) * (by1 - by)) as u64;
+                        let average = image::Rgb(sums.map(|sum| (sum / count) as u8));
+                        for y in by..by1 {
+                            for x in bx..bx1 {
+                                rgb.put_pixel(x, y, average);
+                            }
+                        }
+                    }
+                }
+            }
+            RedactionStyle::Blur => {
+                // Strong enough that text and faces can't be made out
+                let sigma = (w.min(h) as f32 / 4.0).max(8.0);
+                let region = image::imageops::crop_imm(&rgb, x0, y0, w, h).to_image();
+                let blurred = image::imageops::blur(&region, sigma);
+                image::imageops::replace(&mut rgb, &blurred, x0 as i64, y0 as i64);
+            }
+        }
+    }
+
+    let output_path = edited_output_path(&app, &path, output_path, "redacted", "jpg")?;
+    fs::write(&output_path, image_io::encode_jpeg(&DynamicImage::ImageRgb8(rgb), EDIT_JPEG_QUALITY)?)
+        .map_err(|e| format!("Failed to write redacted image: {}", e))?;
+    image_io::path_to_string(&output_path)
+}
@@ -0,0 +1,112 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use tauri::http::{HttpRange, Request, Response, ResponseBuilder};
+use tauri::http::header::{ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, RANGE};
+use crate::image_io;
+
+// URI scheme the webview uses for local photos
+// This replaces Tauri's built-in asset protocol, so `convertFileSrc(path)`
+// on the frontend produces URLs handled here
+pub const SCHEME: &str = "asset";
+
+// Extensions the protocol will serve; anything else is refused so the
+// webview can't read arbitrary files from disk
+const SERVABLE_EXTENSIONS: [&str; 7] = ["jpg", "jpeg", "png", "gif", "webp", "heic", "heif"];
+
+// Handle an `asset://localhost/<percent-encoded path>` request
+// (`https://asset.localhost/...` on Windows), streaming the file with range support
+pub fn handle_request(request: &Request) -> Result<Response, Box<dyn Error>> {
+    let path = match request_path(request.uri()) {
+        Some(path) => path,
+        None => return ResponseBuilder::new().status(400).body(Vec::new()),
+    };
+
+    if !is_servable(&path) {
+        return ResponseBuilder::new().status(403).body(Vec::new());
+    }
+
+    // HEIC and RAW can't be displayed by the webview, so serve a JPEG conversion
+    if image_io::needs_jpeg_conversion(&path) {
+        let jpeg_data = match image_io::convert_to_jpeg(&path) {
+            Ok(data) => data,
+            Err(_) => return ResponseBuilder::new().status(404).body(Vec::new()),
+        };
+        return ResponseBuilder::new()
+            .header(CONTENT_TYPE, "image/jpeg")
+            .header(CONTENT_LENGTH, jpeg_data.len())
+            .body(jpeg_data);
+    }
+
+    let mut file = match File::open(&path) {
+        Ok(file) => file,
+        Err(_) => return ResponseBuilder::new().status(404).body(Vec::new()),
+    };
+    let len = file.metadata()?.len();
+
+    // Sniff the content type from the first bytes, then rewind for serving
+    let mut header = [0; image_io::SNIFF_BYTES];
+    let header_len = file.read(&mut header)?;
+    file.seek(SeekFrom::Start(0))?;
+    let content_type = image_io::sniff_mime(&header[..header_len])
+        .or_else(|| image_io::mime_from_extension(&path))
+        .unwrap_or("application/octet-stream");
+
+    let response = ResponseBuilder::new()
+        .header(CONTENT_TYPE, content_type)
+        .header(ACCEPT_RANGES, "bytes");
+
+    // Serve only the requested byte range when the webview asks for one
+    if let Some(range_header) = request.headers().get(RANGE).and_then(|v| v.to_str().ok()) {
+        let range = match HttpRange::parse(range_header, len) {
+            Ok(ranges) if !ranges.is_empty() => ranges[0],
+            _ => {
+                return ResponseBuilder::new()
+                    .status(416)
+                    .header(CONTENT_RANGE, format!("bytes */{}", len))
+                    .body(Vec::new());
+            }
+        };
+
+        let mut buffer = vec![0; range.length as usize];
+        file.seek(SeekFrom::Start(range.start))?;
+        file.read_exact(&mut buffer)?;
+
+        return response
+            .status(206)
+            .header(CONTENT_RANGE, format!("bytes {}-{}/{}", range.start, range.start + range.length - 1, len))
+            .header(CONTENT_LENGTH, range.length)
+            .body(buffer);
+    }
+
+    let mut buffer = Vec::with_capacity(len as usize);
+    file.read_to_end(&mut buffer)?;
+
+    response
+        .header(CONTENT_LENGTH, len)
+        .body(buffer)
+}
+
+// Extract and decode the file path from the request URI
+fn request_path(uri: &str) -> Option<String> {
+    let encoded = uri
+        .strip_prefix("asset://localhost/")
+        .or_else(|| uri.strip_prefix("https://asset.localhost/"))?;
+
+    // Drop any query string or fragment the webview appended
+    let encoded = encoded.split(['?', '#']).next().unwrap_or("");
+
+    urlencoding::decode(encoded).ok().map(|path| path.into_owned())
+}
+
+fn is_servable(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .map(|ext| {
+            let ext_lower = ext.to_string_lossy().to_lowercase();
+            SERVABLE_EXTENSIONS.contains(&ext_lower.as_str())
+                || image_io::RAW_EXTENSIONS.contains(&ext_lower.as_str())
+        })
+        .unwrap_or(false)
+}
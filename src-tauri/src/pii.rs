@@ -0,0 +1,233 @@
+use futures_util::StreamExt;
+use serde::Serialize;
+use tauri::AppHandle;
+use crate::ocr::{self, OcrRegion, OcrWord};
+use crate::{image_io, vision};
+
+// Photos scanned at once; each is one Vision request
+const PII_CONCURRENCY: usize = 4;
+
+// Faces Vision is less sure of than this are ignored
+const MIN_FACE_CONFIDENCE: f64 = 0.5;
+
+// Last words of street addresses, compared without trailing punctuation
+const STREET_SUFFIXES: [&str; 30] = [
+    "STREET", "ST", "ROAD", "RD", "AVENUE", "AVE", "LANE", "LN", "DRIVE", "DR", "CLOSE", "COURT", "CT",
+    "WAY", "BOULEVARD", "BLVD", "PLACE", "PL", "CRESCENT", "TERRACE", "GARDENS", "GROVE", "SQUARE", "SQ",
+    "HIGHWAY", "HWY", "PARKWAY", "PKWY", "CIRCLE", "APT",
+];
+
+// US state abbreviations, which precede ZIP codes in addresses
+const US_STATES: [&str; 51] = [
+    "AL", "AK", "AZ", "AR", "CA", "CO", "CT", "DE", "DC", "FL", "GA", "HI", "ID", "IL", "IN", "IA", "KS",
+    "KY", "LA", "ME", "MD", "MA", "MI", "MN", "MS", "MO", "MT", "NE", "NV", "NH", "NJ", "NM", "NY", "NC",
+    "ND", "OH", "OK", "OR", "PA", "RI", "SC", "SD", "TN", "TX", "UT", "VT", "VA", "WA", "WV", "WI", "WY",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PiiKind {
+    Face,
+    PhoneNumber,
+    Email,
+    Address,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PiiFinding {
+    pub kind: PiiKind,
+    // Matched text; faces have none
+    pub text: Option<String>,
+    // Area to blur, as fractions of the photo
+    pub bounds: OcrRegion,
+    // Vision's confidence, for faces
+    pub confidence: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PiiScan {
+    pub path: String,
+    pub findings: Vec<PiiFinding>,
+    // False when Vision couldn't be used and only text was checked, offline
+    pub faces_checked: bool,
+    pub error: Option<String>,
+}
+
+fn bare(word: &str) -> String {
+    word.trim_matches(|c: char| !c.is_alphanumeric()).to_uppercase()
+}
+
+fn is_phone_char(c: char) -> bool {
+    c.is_ascii_digit() || matches!(c, '+' | '(' | ')' | '-' | '.')
+}
+
+// Phone numbers: 10-13 digits, written with a leading +, separators, or a
+// leading 0 (trunk prefix). Runs of bare digits are more likely barcodes or
+// serial numbers.
+fn is_phone_number(text: &str) -> bool {
+    let digits = text.chars().filter(char::is_ascii_digit).count();
+    let formatted = text.starts_with('+') || text.contains(['-', '.', '(', ' ']) || (text.starts_with('0') && digits <= 11);
+    (10..=13).contains(&digits) && formatted
+}
+
+fn is_email(word: &str) -> bool {
+    let word = word.trim_matches(|c: char| matches!(c, '<' | '>' | ',' | ';' | '(' | ')'));
+    match word.split_once('@') {
+        Some((user, domain)) => !user.is_empty() && domain.contains('.') && !domain.ends_with('.'),
+        None => false,
+    }
+}
+
+// House number like "221" or "12B"
+fn is_house_number(word: &str) -> bool {
+    let digits = word.chars().take_while(char::is_ascii_digit).count();
+    (1..=5).contains(&digits) && word.len() - digits <= 1
+}
+
+// UK postcode split over two words, e.g. "SW1A 1AA"
+fn is_uk_postcode(outward: &str, inward: &str) -> bool {
+    let outward: Vec<char> = outward.chars().collect();
+    let inward: Vec<char> = inward.chars().collect();
+    let letters = outward.iter().take_while(|c| c.is_ascii_uppercase()).count();
+    (2..=4).contains(&outward.len())
+        && (1..=2).contains(&letters)
+        && outward.get(letters).is_some_and(char::is_ascii_digit)
+        && outward[letters..].iter().all(char::is_ascii_alphanumeric)
+        && inward.len() == 3
+        && inward[0].is_ascii_digit()
+        && inward[1..].iter().all(char::is_ascii_uppercase)
+}
+
+fn is_zip_code(word: &str) -> bool {
+    let (zip, plus_four) = word.split_once('-').unwrap_or((word, ""));
+    zip.len() == 5
+        && zip.chars().all(|c| c.is_ascii_digit())
+        && (plus_four.is_empty() || (plus_four.len() == 4 && plus_four.chars().all(|c| c.is_ascii_digit())))
+}
+
+// Box covering all of `words`
+fn span_bounds(words: &[OcrWord]) -> OcrRegion {
+    let left = words.iter().map(|w| w.bounds.x).fold(f64::MAX, f64::min);
+    let top = words.iter().map(|w| w.bounds.y).fold(f64::MAX, f64::min);
+    let right = words.iter().map(|w| w.bounds.x + w.bounds.width).fold(0.0, f64::max);
+    let bottom = words.iter().map(|w| w.bounds.y + w.bounds.height).fold(0.0, f64::max);
+    OcrRegion { x: left, y: top, width: right - left, height: bottom - top }
+}
+
+fn finding(kind: PiiKind, words: &[OcrWord]) -> PiiFinding {
+    let text = words.iter().map(|w| w.text.as_str()).collect::<Vec<_>>().join(" ");
+    PiiFinding { kind, text: Some(text), bounds: span_bounds(words), confidence: None }
+}
+
+// Phone numbers, emails and addresses among OCR words, in reading order
+fn text_findings(words: &[OcrWord]) -> Vec<PiiFinding> {
+    let mut findings = Vec::new();
+    let mut i = 0;
+    while i < words.len() {
+        let word = words[i].text.as_str();
+
+        if is_email(word) {
+            findings.push(finding(PiiKind::Email, &words[i..=i]));
+            i += 1;
+            continue;
+        }
+
+        // Phone numbers are often split over words: "+44 20 7946 0958"
+        if word.chars().all(is_phone_char) && word.chars().any(|c| c.is_ascii_digit()) {
+            let mut end = i + 1;
+            while end < words.len() && end - i < 6 && words[end].text.chars().all(is_phone_char) {
+                end += 1;
+            }
+            if let Some(last) = (i + 1..=end).rev().find(|&last| {
+                let joined = words[i..last].iter().map(|w| w.text.as_str()).collect::<Vec<_>>().join(" ");
+                is_phone_number(&joined)
+            }) {
+                findings.push(finding(PiiKind::PhoneNumber, &words[i..last]));
+                i = last;
+                continue;
+            }
+        }
+
+        // "221B Baker Street": a house number then a street suffix within four words
+        if is_house_number(word) {
+            if let Some(suffix) = (i + 2..words.len().min(i + 6)).find(|&j| STREET_SUFFIXES.contains(&bare(&words[j].text).as_str())) {
+                findings.push(finding(PiiKind::Address, &words[i..=suffix]));
+                i = suffix + 1;
+                continue;
+            }
+        }
+
+        if let Some(next) = words.get(i + 1) {
+            let (first, second) = (bare(word), next.text.trim_end_matches([',', '.']).to_uppercase());
+            if is_uk_postcode(&first, &second) || (US_STATES.contains(&first.as_str()) && is_zip_code(&second)) {
+                findings.push(finding(PiiKind::Address, &words[i..=i + 1]));
+                i += 2;
+                continue;
+            }
+        }
+        i += 1;
+    }
+    findings
+}
+
+async fn scan_photo(app: &AppHandle, path: &str) -> Result<(Vec<PiiFinding>, bool), String> {
+    let image_path = path.to_string();
+    let img = tauri::async_runtime::spawn_blocking(move || ocr::crop(&image_path, OcrRegion::WHOLE))
+        .await
+        .map_err(|e| format!("Failed to read image: {}", e))??;
+    let jpeg = image_io::encode_jpeg(&img, ocr::OCR_JPEG_QUALITY)?;
+
+    match vision::annotate_jpeg(app, &jpeg, &["FACE_DETECTION", "TEXT_DETECTION"]).await {
+        Ok(annotations) => {
+            let (width, height) = (img.width() as f64, img.height() as f64);
+            let mut findings: Vec<PiiFinding> = annotations
+                .face_annotations
+                .iter()
+                .filter(|face| face.detection_confidence >= MIN_FACE_CONFIDENCE && !face.bounding_poly.vertices.is_empty())
+                .map(|face| {
+                    let (left, top, right, bottom) = face.bounding_poly.extent();
+                    PiiFinding {
+                        kind: PiiKind::Face,
+                        text: None,
+                        bounds: OcrRegion::WHOLE.to_photo(left, top, right, bottom, width, height),
+                        confidence: Some(face.detection_confidence),
+                    }
+                })
+                .collect();
+            let text = ocr::from_vision(annotations.text_annotations, img.width(), img.height(), OcrRegion::WHOLE);
+            findings.extend(text_findings(&text.words));
+            Ok((findings, true))
+        }
+        // Without Vision, text can still be checked with tesseract
+        Err(vision_error) => {
+            let text = tauri::async_runtime::spawn_blocking(move || ocr::tesseract_ocr(&img, OcrRegion::WHOLE))
+                .await
+                .map_err(|e| format!("OCR failed: {}", e))?
+                .map_err(|e| format!("Vision: {}. Offline: {}", vision_error, e))?;
+            Ok((text_findings(&text.words), false))
+        }
+    }
+}
+
+// Command to flag photos showing faces, phone numbers, email addresses or
+// street addresses (e.g. a receipt or shipping label in shot) so they can be
+// left out or blurred before upload. Faces need Vision; without it only text
+// is checked, with tesseract. A photo that can't be scanned gets an `error`
+// rather than failing the batch.
+#[tauri::command]
+pub async fn scan_for_pii(app: AppHandle, paths: Vec<String>) -> Result<Vec<PiiScan>, String> {
+    let scans = futures_util::stream::iter(paths)
+        .map(|path| {
+            let app = app.clone();
+            async move {
+                match scan_photo(&app, &path).await {
+                    Ok((findings, faces_checked)) => PiiScan { path, findings, faces_checked, error: None },
+                    Err(e) => PiiScan { path, findings: Vec::new(), faces_checked: false, error: Some(e) },
+                }
+            }
+        })
+        .buffered(PII_CONCURRENCY)
+        .collect()
+        .await;
+    Ok(scans)
+}
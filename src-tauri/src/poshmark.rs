@@ -0,0 +1,387 @@
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use image::imageops::FilterType;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::AppHandle;
+use crate::crosslist::Marketplace;
+use crate::listing::{self, Listing, ListingCondition, MappedListing, PackageContents};
+
+// Poshmark shows photos square; it recommends at least 1280px
+const PHOTO_SIZE: u32 = 1280;
+const MAX_PHOTOS: usize = 16;
+const MAX_TITLE_CHARS: usize = 80;
+const MAX_DESCRIPTION_CHARS: usize = 1500;
+
+// Poshmark's lowest listing price, in dollars
+const MIN_PRICE: f64 = 3.0;
+
+const CREATE_LISTING_URL: &str = "https://poshmark.com/create-listing";
+
+// Form fields on the create listing page. Poshmark changes its markup from
+// time to time, so these are the first thing to check when automation fails.
+const PHOTO_INPUT: &str = "input[type='file']";
+const TITLE_INPUT: &str = "input[data-vv-name='title']";
+const DESCRIPTION_INPUT: &str = "textarea[data-vv-name='description']";
+const BRAND_INPUT: &str = "input[data-vv-name='brand']";
+const ORIGINAL_PRICE_INPUT: &str = "input[data-vv-name='originalPrice']";
+const PRICE_INPUT: &str = "input[data-vv-name='listingPrice']";
+const NEXT_BUTTON: &str = "button[data-et-name='next']";
+const LIST_BUTTON: &str = "button[data-et-name='list_item']";
+
+// W3C WebDriver's key for element references
+const ELEMENT_KEY: &str = "element-6066-11e4-a52e-4f735466cecf";
+
+// How long to wait for the page to show a form field or finish listing
+const ELEMENT_TIMEOUT: Duration = Duration::from_secs(20);
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoshmarkSettings {
+    // Filling in Poshmark's website automatically may go against its terms,
+    // so it stays off until the user turns it on
+    #[serde(default)]
+    pub automation_enabled: bool,
+    // WebDriver server driving the browser, e.g. chromedriver
+    #[serde(default = "default_webdriver_url")]
+    pub webdriver_url: String,
+    // Off to watch the browser, or to sign in to Poshmark the first time
+    #[serde(default = "default_headless")]
+    pub headless: bool,
+}
+
+impl Default for PoshmarkSettings {
+    fn default() -> PoshmarkSettings {
+        PoshmarkSettings {
+            automation_enabled: false,
+            webdriver_url: default_webdriver_url(),
+            headless: default_headless(),
+        }
+    }
+}
+
+fn default_webdriver_url() -> String {
+    "http://localhost:9515".to_string()
+}
+
+fn default_headless() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoshmarkListing {
+    #[serde(flatten)]
+    pub item: Listing,
+    // Retail price shown struck through next to the price
+    #[serde(default)]
+    pub original_price: Option<f64>,
+    // e.g. "Women" > "Jackets & Coats" > "Bomber Jackets"
+    #[serde(default)]
+    pub department: Option<String>,
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub subcategory: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PoshmarkExport {
+    pub folder: String,
+    // Square photos in listing order
+    pub photos: Vec<String>,
+    // Title, description, brand, size, category and prices, ready to paste
+    pub text_file: String,
+    // Set when automation listed the item
+    pub listing_url: Option<String>,
+}
+
+fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app.path_resolver()
+        .app_data_dir()
+        .ok_or("Failed to resolve app data directory")?;
+    Ok(data_dir.join("poshmark.json"))
+}
+
+fn load_settings(app: &AppHandle) -> Result<PoshmarkSettings, String> {
+    let path = settings_path(app)?;
+    if !path.exists() {
+        return Ok(PoshmarkSettings::default());
+    }
+    let json = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read Poshmark settings: {}", e))?;
+    serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse Poshmark settings: {}", e))
+}
+
+fn poshmark_condition(condition: ListingCondition) -> &'static str {
+    match condition {
+        ListingCondition::NewWithTags => "New With Tags",
+        ListingCondition::NewWithoutTags => "Like New",
+        ListingCondition::VeryGood | ListingCondition::Good => "Good",
+        ListingCondition::Satisfactory => "Fair",
+    }
+}
+
+// How Poshmark will see the listing, with what stops it being exported
+pub fn map_listing(listing: &PoshmarkListing) -> MappedListing {
+    let mut problems = listing.item.problems("Poshmark", MAX_PHOTOS);
+    if listing.item.title.chars().count() > MAX_TITLE_CHARS {
+        problems.push(format!("Poshmark titles are limited to {} characters", MAX_TITLE_CHARS));
+    }
+    if listing.item.description_with_condition().chars().count() > MAX_DESCRIPTION_CHARS {
+        problems.push(format!("Poshmark descriptions are limited to {} characters", MAX_DESCRIPTION_CHARS));
+    }
+    if listing.item.price < MIN_PRICE {
+        problems.push(format!("Poshmark prices start at ${:.0}", MIN_PRICE));
+    }
+    let category = [&listing.department, &listing.category, &listing.subcategory]
+        .into_iter()
+        .filter_map(|part| part.as_deref().map(str::trim).filter(|part| !part.is_empty()).map(str::to_string))
+        .collect();
+    MappedListing {
+        category,
+        condition: Some(poshmark_condition(listing.item.condition).to_string()),
+        size: listing.item.size.clone(),
+        max_photos: MAX_PHOTOS,
+        problems,
+    }
+}
+
+fn listing_text(listing: &PoshmarkListing, mapped: &MappedListing) -> String {
+    let item = &listing.item;
+    let mut lines = vec![
+        format!("Title: {}", item.title.trim()),
+        String::new(),
+        "Description:".to_string(),
+        item.description_with_condition(),
+        String::new(),
+    ];
+    if !mapped.category.is_empty() {
+        lines.push(format!("Category: {}", mapped.category.join(" > ")));
+    }
+    if let Some(condition) = &mapped.condition {
+        lines.push(format!("Condition: {}", condition));
+    }
+    if let Some(brand) = item.brand.as_deref().filter(|brand| !brand.trim().is_empty()) {
+        lines.push(format!("Brand: {}", brand.trim()));
+    }
+    if let Some(size) = item.size.as_deref().filter(|size| !size.trim().is_empty()) {
+        lines.push(format!("Size: {}", size.trim()));
+    }
+    if !item.colors.is_empty() {
+        lines.push(format!("Color: {}", item.colors.join(", ")));
+    }
+    if let Some(original_price) = listing.original_price {
+        lines.push(format!("Original price: {:.2} {}", original_price, item.currency.trim().to_uppercase()));
+    }
+    lines.push(format!("Price: {:.2} {}", item.price, item.currency.trim().to_uppercase()));
+    lines.join("\n") + "\n"
+}
+
+// Minimal W3C WebDriver client for the automation
+struct WebDriver {
+    client: reqwest::Client,
+    session: String,
+}
+
+impl WebDriver {
+    async fn start(app: &AppHandle, settings: &PoshmarkSettings) -> Result<WebDriver, String> {
+        // A profile of its own keeps the Poshmark sign-in between runs
+        let profile = app.path_resolver()
+            .app_data_dir()
+            .ok_or("Failed to resolve app data directory")?
+            .join("poshmark-browser");
+        let mut args = vec![format!("--user-data-dir={}", profile.display()), "--window-size=1280,1800".to_string()];
+        if settings.headless {
+            args.push("--headless=new".to_string());
+        }
+        let capabilities = json!({
+            "capabilities": { "alwaysMatch": { "browserName": "chrome", "goog:chromeOptions": { "args": args } } },
+        });
+        // The WebDriver server is local, so requests bypass any proxy
+        let client = reqwest::Client::builder()
+            .no_proxy()
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+        let base = settings.webdriver_url.trim_end_matches('/');
+        let result = command(client.post(format!("{}/session", base)).json(&capabilities), "start the browser").await?;
+        let session = result["sessionId"].as_str().ok_or("WebDriver returned no session")?;
+        Ok(WebDriver { client, session: format!("{}/session/{}", base, session) })
+    }
+
+    async fn navigate(&self, url: &str) -> Result<(), String> {
+        let request = self.client.post(format!("{}/url", self.session)).json(&json!({ "url": url }));
+        command(request, "open Poshmark").await.map(|_| ())
+    }
+
+    async fn current_url(&self) -> Result<String, String> {
+        let url = command(self.client.get(format!("{}/url", self.session)), "read the page address").await?;
+        Ok(url.as_str().unwrap_or_default().to_string())
+    }
+
+    // Wait for an element matching a CSS selector
+    async fn find(&self, selector: &str) -> Result<String, String> {
+        let request = json!({ "using": "css selector", "value": selector });
+        let deadline = tokio::time::Instant::now() + ELEMENT_TIMEOUT;
+        loop {
+            let found = command(self.client.post(format!("{}/element", self.session)).json(&request), "find").await;
+            if let Some(element) = found.ok().and_then(|element| element[ELEMENT_KEY].as_str().map(str::to_string)) {
+                return Ok(element);
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(format!("Poshmark's page has no \"{}\"; its layout may have changed", selector));
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    async fn type_into(&self, selector: &str, text: &str) -> Result<(), String> {
+        let element = self.find(selector).await?;
+        let request = self
+            .client
+            .post(format!("{}/element/{}/value", self.session, element))
+            .json(&json!({ "text": text }));
+        command(request, "fill in the form").await.map(|_| ())
+    }
+
+    async fn click(&self, selector: &str) -> Result<(), String> {
+        let element = self.find(selector).await?;
+        let request = self.client.post(format!("{}/element/{}/click", self.session, element)).json(&json!({}));
+        command(request, "press a button").await.map(|_| ())
+    }
+
+    async fn quit(self) {
+        let _ = self.client.delete(&self.session).send().await;
+    }
+}
+
+// Send a WebDriver command and return its `value`
+async fn command(request: reqwest::RequestBuilder, action: &str) -> Result<Value, String> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("Failed to {}; is the WebDriver server running? {}", action, e))?;
+    let status = response.status();
+    let body: Value = response.json().await.unwrap_or_default();
+    if !status.is_success() {
+        let message = body["value"]["message"].as_str().unwrap_or_default();
+        return Err(format!("Failed to {} ({}): {}", action, status, message));
+    }
+    Ok(body["value"].clone())
+}
+
+// Fill in and submit Poshmark's create listing form with the package's
+// photos, returning the new listing's address
+async fn automate_listing(app: &AppHandle, settings: &PoshmarkSettings, listing: &PoshmarkListing, photos: &[String]) -> Result<String, String> {
+    let driver = WebDriver::start(app, settings).await?;
+    let result = async {
+        driver.navigate(CREATE_LISTING_URL).await?;
+        if driver.current_url().await?.contains("/login") {
+            return Err("Sign in to Poshmark in the automation browser first (turn headless off to see it)".to_string());
+        }
+        // File inputs take newline-separated paths
+        driver.type_into(PHOTO_INPUT, &photos.join("\n")).await?;
+        driver.type_into(TITLE_INPUT, listing.item.title.trim()).await?;
+        driver.type_into(DESCRIPTION_INPUT, &listing.item.description_with_condition()).await?;
+        if let Some(brand) = listing.item.brand.as_deref().filter(|brand| !brand.trim().is_empty()) {
+            driver.type_into(BRAND_INPUT, brand.trim()).await?;
+        }
+        if let Some(original_price) = listing.original_price {
+            driver.type_into(ORIGINAL_PRICE_INPUT, &format!("{:.0}", original_price)).await?;
+        }
+        driver.type_into(PRICE_INPUT, &format!("{:.0}", listing.item.price)).await?;
+        driver.click(NEXT_BUTTON).await?;
+        driver.click(LIST_BUTTON).await?;
+
+        // Poshmark moves to the new listing once it is live
+        let deadline = tokio::time::Instant::now() + ELEMENT_TIMEOUT;
+        loop {
+            let url = driver.current_url().await?;
+            if url.contains("/listing/") {
+                return Ok(url);
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err("Poshmark didn't confirm the listing; check the form in the browser".to_string());
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+    .await;
+    driver.quit().await;
+    result
+}
+
+// Command to save the Poshmark automation settings
+#[tauri::command]
+pub fn set_poshmark_settings(app: AppHandle, settings: PoshmarkSettings) -> Result<(), String> {
+    if settings.automation_enabled && !settings.webdriver_url.starts_with("http") {
+        return Err("WebDriver URL must start with http:// or https://".to_string());
+    }
+    let path = settings_path(&app)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create settings directory: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(&settings)
+        .map_err(|e| format!("Failed to serialize Poshmark settings: {}", e))?;
+    fs::write(&path, json)
+        .map_err(|e| format!("Failed to save Poshmark settings: {}", e))
+}
+
+// Command to get the Poshmark automation settings
+#[tauri::command]
+pub fn get_poshmark_settings(app: AppHandle) -> Result<PoshmarkSettings, String> {
+    load_settings(&app)
+}
+
+// Command to export a listing for Poshmark, which has no public API: writes a
+// folder with the photos square-cropped in order and a text file with the
+// title, description, brand, size, category and prices to paste into the
+// app. With `automate` (and automation turned on in settings) a browser
+// driven through WebDriver then fills in and submits the listing form.
+#[tauri::command]
+pub async fn export_to_poshmark(
+    app: AppHandle,
+    listing: PoshmarkListing,
+    output_dir: Option<String>,
+    automate: Option<bool>,
+) -> Result<PoshmarkExport, String> {
+    export(&app, &listing, output_dir, automate.unwrap_or(false)).await
+}
+
+// Write a listing's Poshmark export package, then list it with automation
+// when asked; used by the command and by cross-listing
+pub async fn export(
+    app: &AppHandle,
+    listing: &PoshmarkListing,
+    output_dir: Option<String>,
+    automate: bool,
+) -> Result<PoshmarkExport, String> {
+    let mapped = map_listing(listing);
+    let settings = load_settings(app)?;
+    if automate && !settings.automation_enabled {
+        return Err("Poshmark automation is off; turn it on in settings first".to_string());
+    }
+
+    // Opened at twice the size, so a landscape photo's short edge still fills the square
+    let contents = PackageContents {
+        photo_edge: PHOTO_SIZE * 2,
+        prepare: Box::new(|img| img.resize_to_fill(PHOTO_SIZE, PHOTO_SIZE, FilterType::Lanczos3)),
+        data: None,
+        text: listing_text(listing, &mapped),
+    };
+    let package = listing::write_package(app, Marketplace::Poshmark, &listing.item, &mapped, output_dir, contents).await?;
+
+    let listing_url = if automate {
+        Some(automate_listing(app, &settings, listing, &package.photos).await?)
+    } else {
+        None
+    };
+    Ok(PoshmarkExport {
+        folder: package.folder,
+        photos: package.photos,
+        text_file: package.text_file,
+        listing_url,
+    })
+}
@@ -0,0 +1,230 @@
+use std::fs;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    // Worth a second look, e.g. a brand that polices listings
+    Caution,
+    // Allowed only with conditions or on some marketplaces
+    Restricted,
+    // Not allowed on mainstream marketplaces
+    Prohibited,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeywordRule {
+    // Word or phrase, matched case-insensitively on whole words (a trailing
+    // "s" is allowed, so "knife" also matches "knifes")
+    pub keyword: String,
+    // Grouping shown to the user, e.g. "weapons", "recalled", "vero"
+    pub category: String,
+    pub severity: Severity,
+    #[serde(default)]
+    pub note: Option<String>,
+    // Phrases containing the keyword that don't count as a match, e.g.
+    // "fake fur" for "fake"
+    #[serde(default)]
+    pub except: Vec<String>,
+}
+
+const VERO_NOTE: &str = "This brand reports listings through eBay's VeRO programme; make sure the item is genuine and use your own photos";
+const RECALL_NOTE: &str = "Recalled product; most marketplaces ban resale";
+
+// Rules used until the user saves their own
+fn default_rules() -> Vec<KeywordRule> {
+    let rule = |keyword: &str, category: &str, severity: Severity, note: Option<&str>| KeywordRule {
+        keyword: keyword.to_string(),
+        category: category.to_string(),
+        severity,
+        note: note.map(str::to_string),
+        except: Vec::new(),
+    };
+    let mut rules = Vec::new();
+    for keyword in ["firearm", "gun", "handgun", "rifle", "shotgun", "ammunition", "ammo", "silencer", "suppressor", "switchblade", "brass knuckles", "knuckle duster", "butterfly knife", "taser", "stun gun"] {
+        rules.push(rule(keyword, "weapons", Severity::Prohibited, None));
+    }
+    for keyword in ["knife", "pepper spray", "crossbow", "airsoft", "bb gun", "machete"] {
+        rules.push(rule(keyword, "weapons", Severity::Restricted, Some("Allowed with conditions on some marketplaces; check the policy before listing")));
+    }
+    for keyword in ["rock n play", "drop side crib", "inclined sleeper", "crib bumper", "magnetic balls"] {
+        rules.push(rule(keyword, "recalled", Severity::Prohibited, Some(RECALL_NOTE)));
+    }
+    for keyword in ["replica", "counterfeit", "knockoff", "fake", "dupe"] {
+        rules.push(rule(keyword, "counterfeit", Severity::Prohibited, Some("Replicas and unauthorised copies can't be listed")));
+    }
+    // Imitation materials are a description of the item, not a copy of a brand
+    if let Some(fake) = rules.iter_mut().find(|rule| rule.keyword == "fake") {
+        fake.except = ["fake fur", "fake leather", "fake suede", "fake snakeskin", "fake pearl", "fake flowers", "fake plant"]
+            .map(str::to_string)
+            .to_vec();
+    }
+    for keyword in ["louis vuitton", "chanel", "hermes", "gucci", "prada", "rolex", "cartier", "tiffany", "oakley", "ugg", "pandora", "the north face", "canada goose", "moncler"] {
+        rules.push(rule(keyword, "vero", Severity::Caution, Some(VERO_NOTE)));
+    }
+    rules
+}
+
+#[derive(Debug, Serialize)]
+pub struct PrescreenWarning {
+    pub keyword: String,
+    pub category: String,
+    pub severity: Severity,
+    pub note: Option<String>,
+    // Where it was found: "labels", "title" or "description"
+    pub field: String,
+}
+
+fn rules_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app.path_resolver()
+        .app_data_dir()
+        .ok_or("Failed to resolve app data directory")?;
+    Ok(data_dir.join("prescreen_rules.json"))
+}
+
+fn load_rules(app: &AppHandle) -> Result<Vec<KeywordRule>, String> {
+    let path = rules_path(app)?;
+    if !path.exists() {
+        return Ok(default_rules());
+    }
+    let json = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read prescreen rules: {}", e))?;
+    serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse prescreen rules: {}", e))
+}
+
+// Lower-case words separated by single spaces, with a space at each end so
+// phrases can be matched on word boundaries. Apostrophes are dropped so
+// "Rock 'n Play" reads as "rock n play".
+fn normalize(text: &str) -> String {
+    let words: String = text
+        .to_lowercase()
+        .replace(['\'', '’'], "")
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect();
+    format!(" {} ", words.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+fn contains_phrase(normalized_text: &str, phrase: &str) -> bool {
+    normalized_text.contains(&format!(" {} ", phrase)) || normalized_text.contains(&format!(" {}s ", phrase))
+}
+
+// Whether the rule's keyword appears outside its exception phrases
+fn matches(normalized_text: &str, rule: &KeywordRule) -> bool {
+    let keyword = normalize(&rule.keyword);
+    let keyword = keyword.trim();
+    if keyword.is_empty() {
+        return false;
+    }
+    let mut text = normalized_text.to_string();
+    for phrase in &rule.except {
+        let phrase = normalize(phrase);
+        let phrase = phrase.trim();
+        if phrase.is_empty() {
+            continue;
+        }
+        // Each replacement takes the space after it, so a repeated phrase
+        // needs another pass
+        for phrase in [format!(" {}s ", phrase), format!(" {} ", phrase)] {
+            while text.contains(&phrase) {
+                text = text.replace(&phrase, " ");
+            }
+        }
+    }
+    contains_phrase(&text, keyword)
+}
+
+// Check text against the rules, one warning per rule (at its first field),
+// most severe first
+pub fn prescreen(rules: &[KeywordRule], labels: &[String], title: &str, description: &str) -> Vec<PrescreenWarning> {
+    let fields = [
+        ("labels", normalize(&labels.join(" | "))),
+        ("title", normalize(title)),
+        ("description", normalize(description)),
+    ];
+
+    let mut warnings: Vec<PrescreenWarning> = rules
+        .iter()
+        .filter_map(|rule| {
+            let (field, _) = fields.iter().find(|(_, text)| matches(text, rule))?;
+            Some(PrescreenWarning {
+                keyword: rule.keyword.clone(),
+                category: rule.category.clone(),
+                severity: rule.severity,
+                note: rule.note.clone(),
+                field: field.to_string(),
+            })
+        })
+        .collect();
+    warnings.sort_by_key(|warning| std::cmp::Reverse(warning.severity));
+    warnings
+}
+
+// Command to check detected labels and draft listing text for prohibited or
+// restricted items before publishing. Warnings are advisory; the user decides.
+#[tauri::command]
+pub fn prescreen_listing(app: AppHandle, labels: Vec<String>, title: String, description: String) -> Result<Vec<PrescreenWarning>, String> {
+    let rules = load_rules(&app)?;
+    Ok(prescreen(&rules, &labels, &title, &description))
+}
+
+// Command to get the keyword rules in use (the built-in list until edited)
+#[tauri::command]
+pub fn get_prescreen_rules(app: AppHandle) -> Result<Vec<KeywordRule>, String> {
+    load_rules(&app)
+}
+
+// Command to replace the keyword rules; `None` restores the built-in list
+#[tauri::command]
+pub fn set_prescreen_rules(app: AppHandle, rules: Option<Vec<KeywordRule>>) -> Result<(), String> {
+    let path = rules_path(&app)?;
+    let Some(rules) = rules else {
+        if path.exists() {
+            fs::remove_file(&path)
+                .map_err(|e| format!("Failed to reset prescreen rules: {}", e))?;
+        }
+        return Ok(());
+    };
+    if let Some(rule) = rules.iter().find(|rule| normalize(&rule.keyword).trim().is_empty()) {
+        return Err(format!("Rule in category \"{}\" has no keyword", rule.category));
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create settings directory: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(&rules)
+        .map_err(|e| format!("Failed to serialize prescreen rules: {}", e))?;
+    fs::write(&path, json)
+        .map_err(|e| format!("Failed to save prescreen rules: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keywords(title: &str) -> Vec<String> {
+        prescreen(&default_rules(), &[], title, "").into_iter().map(|warning| warning.keyword).collect()
+    }
+
+    #[test]
+    fn matches_whole_words() {
+        assert_eq!(keywords("Replica designer bag"), ["replica"]);
+        assert_eq!(keywords("Chef knives set"), Vec::<String>::new());
+        assert_eq!(keywords("Chef knifes set"), ["knife"]);
+        assert!(keywords("Gunmetal grey jacket").is_empty());
+    }
+
+    #[test]
+    fn imitation_materials_are_not_counterfeits() {
+        assert!(keywords("Fake fur coat").is_empty());
+        assert!(keywords("Faux fur collar, fake leather trim").is_empty());
+        assert!(keywords("Vegan leather jacket").is_empty());
+        assert!(keywords("Set of fake furs and fake flowers").is_empty());
+        // The word still counts anywhere else in the text
+        assert_eq!(keywords("Fake leather jacket, fake logo"), ["fake"]);
+    }
+}
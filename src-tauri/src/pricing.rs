@@ -0,0 +1,241 @@
+use serde::Serialize;
+use serde_json::Value;
+use tauri::AppHandle;
+use crate::ebay_auth::{self, ApplicationScope};
+use crate::network;
+
+// Items fetched from each API; both allow up to 200 a page
+const SAMPLE_LIMIT: &str = "200";
+
+// Marketplace Insights covers sales of the last 90 days
+const SOLD_PERIOD_DAYS: u32 = 90;
+
+// Comparables returned for display
+const MAX_COMPARABLES: usize = 20;
+
+const DEFAULT_MARKETPLACE: &str = "EBAY_US";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceSource {
+    // Sold items from Marketplace Insights
+    Sold,
+    // Active listings from Browse, when the app has no Insights access
+    Active,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Comparable {
+    pub title: String,
+    pub price: f64,
+    pub condition: Option<String>,
+    // RFC 3339, for sold items
+    pub sold_at: Option<String>,
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SoldPriceResearch {
+    pub source: PriceSource,
+    // Prices in other currencies are left out of the figures
+    pub currency: Option<String>,
+    // Items the figures are based on
+    pub sample_size: usize,
+    pub median: Option<f64>,
+    pub lower_quartile: Option<f64>,
+    pub upper_quartile: Option<f64>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    // Matching sales over the period; None without Insights access
+    pub sold_count: Option<u64>,
+    pub active_count: Option<u64>,
+    // Sold / (sold + active) over the period
+    pub sell_through_rate: Option<f64>,
+    pub period_days: Option<u32>,
+    pub comparables: Vec<Comparable>,
+    pub warnings: Vec<String>,
+}
+
+struct Sample {
+    total: u64,
+    comparables: Vec<(Comparable, String)>,
+}
+
+// Percentile by linear interpolation between the closest ranks
+fn percentile(sorted: &[f64], fraction: f64) -> Option<f64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = fraction * (sorted.len() - 1) as f64;
+    let (low, high) = (rank.floor() as usize, rank.ceil() as usize);
+    Some(sorted[low] + (sorted[high] - sorted[low]) * (rank - low as f64))
+}
+
+fn round_price(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+async fn search(
+    app: &AppHandle,
+    url: String,
+    token: &str,
+    marketplace_id: &str,
+    query: &str,
+    category_id: Option<&str>,
+) -> Result<(reqwest::StatusCode, Value), String> {
+    let mut params = vec![("limit", SAMPLE_LIMIT)];
+    if !query.is_empty() {
+        params.push(("q", query));
+    }
+    if let Some(category_id) = category_id {
+        params.push(("category_ids", category_id));
+    }
+    let response = network::client(app)
+        .get(url)
+        .bearer_auth(token)
+        .header("X-EBAY-C-MARKETPLACE-ID", marketplace_id)
+        .query(&params)
+        .send()
+        .await
+        .map_err(|e| format!("eBay search failed: {}", e))?;
+    let status = response.status();
+    if !status.is_success() {
+        return Ok((status, Value::Null));
+    }
+    let result = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse eBay search: {}", e))?;
+    Ok((status, result))
+}
+
+// (comparable, currency) from an item's JSON; `price_field` differs by API
+fn comparable(item: &Value, price_field: &str) -> Option<(Comparable, String)> {
+    let price = &item[price_field];
+    let amount = price["value"].as_str().and_then(|value| value.parse::<f64>().ok())?;
+    Some((
+        Comparable {
+            title: item["title"].as_str().unwrap_or_default().to_string(),
+            price: amount,
+            condition: item["condition"].as_str().map(str::to_string),
+            sold_at: item["lastSoldDate"].as_str().map(str::to_string),
+            url: item["itemWebUrl"].as_str().or(item["itemHref"].as_str()).map(str::to_string),
+        },
+        price["currency"].as_str().unwrap_or_default().to_string(),
+    ))
+}
+
+fn sample(result: &Value, items_field: &str, price_field: &str) -> Sample {
+    let comparables = result[items_field]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|item| comparable(item, price_field))
+        .collect::<Vec<_>>();
+    Sample { total: result["total"].as_u64().unwrap_or(comparables.len() as u64), comparables }
+}
+
+// Command to research prices of recently sold items like the one being
+// listed, for the pricing panel: median and quartile prices and the
+// sell-through rate over 90 days. Sold data comes from eBay's Marketplace
+// Insights API, which needs approved access; without it the figures are
+// based on active listings and there is no sell-through rate.
+#[tauri::command]
+pub async fn research_sold_prices(
+    app: AppHandle,
+    query: String,
+    category_id: Option<String>,
+    marketplace_id: Option<String>,
+) -> Result<SoldPriceResearch, String> {
+    let query = query.trim().to_string();
+    let category_id = category_id.map(|id| id.trim().to_string()).filter(|id| !id.is_empty());
+    if query.is_empty() && category_id.is_none() {
+        return Err("Enter a search or pick a category".to_string());
+    }
+    let environment = ebay_auth::load_settings(&app)?.environment;
+    let marketplace_id = marketplace_id.unwrap_or_else(|| DEFAULT_MARKETPLACE.to_string());
+    let api = environment.api_url();
+    let mut warnings = Vec::new();
+
+    // Insights access shows up as a scope the token endpoint refuses, or a
+    // 403 from the API
+    let sold = match ebay_auth::application_token(&app, ApplicationScope::MarketplaceInsights).await {
+        Ok(token) => {
+            let url = format!("{}/buy/marketplace_insights/v1_beta/item_sales/search", api);
+            match search(&app, url, &token, &marketplace_id, &query, category_id.as_deref()).await? {
+                (status, result) if status.is_success() => Some(sample(&result, "itemSales", "lastSoldPrice")),
+                (status, _) => {
+                    warnings.push(format!("Sold items aren't available ({}); showing active listings instead", status));
+                    None
+                }
+            }
+        }
+        Err(_) => {
+            warnings.push("This app has no Marketplace Insights access, so prices are from active listings".to_string());
+            None
+        }
+    };
+
+    let token = ebay_auth::application_token(&app, ApplicationScope::Public).await?;
+    let url = format!("{}/buy/browse/v1/item_summary/search", api);
+    let active = match search(&app, url, &token, &marketplace_id, &query, category_id.as_deref()).await? {
+        (status, result) if status.is_success() => Some(sample(&result, "itemSummaries", "price")),
+        (status, _) => {
+            warnings.push(format!("Active listings aren't available ({})", status));
+            None
+        }
+    };
+
+    let sold_count = sold.as_ref().map(|sold| sold.total);
+    let active_count = active.as_ref().map(|active| active.total);
+    let (source, mut comparables) = match (sold, active) {
+        (Some(sold), _) => (PriceSource::Sold, sold.comparables),
+        (None, Some(active)) => (PriceSource::Active, active.comparables),
+        (None, None) => return Err(warnings.join("; ")),
+    };
+
+    // Figures use the most common currency only
+    let mut currencies: Vec<&str> = comparables.iter().map(|(_, currency)| currency.as_str()).collect();
+    currencies.sort_unstable();
+    let currency = currencies
+        .chunk_by(|a, b| a == b)
+        .max_by_key(|run| run.len())
+        .map(|run| run[0].to_string())
+        .filter(|currency| !currency.is_empty());
+    if let Some(currency) = &currency {
+        let before = comparables.len();
+        comparables.retain(|(_, item_currency)| item_currency == currency);
+        if comparables.len() < before {
+            warnings.push(format!("{} items priced in other currencies were left out", before - comparables.len()));
+        }
+    }
+
+    let mut comparables: Vec<Comparable> = comparables.into_iter().map(|(item, _)| item).collect();
+    let mut prices: Vec<f64> = comparables.iter().map(|item| item.price).collect();
+    prices.sort_by(f64::total_cmp);
+    let sell_through_rate = match (sold_count, active_count) {
+        (Some(sold), Some(active)) if sold + active > 0 => Some(sold as f64 / (sold + active) as f64),
+        _ => None,
+    };
+    let sample_size = prices.len();
+    // The best matches, as eBay ranks them, in price order
+    comparables.truncate(MAX_COMPARABLES);
+    comparables.sort_by(|a, b| a.price.total_cmp(&b.price));
+
+    Ok(SoldPriceResearch {
+        source,
+        currency,
+        sample_size,
+        median: percentile(&prices, 0.5).map(round_price),
+        lower_quartile: percentile(&prices, 0.25).map(round_price),
+        upper_quartile: percentile(&prices, 0.75).map(round_price),
+        min: prices.first().copied(),
+        max: prices.last().copied(),
+        sold_count,
+        active_count,
+        sell_through_rate,
+        period_days: (source == PriceSource::Sold).then_some(SOLD_PERIOD_DAYS),
+        comparables,
+        warnings,
+    })
+}
@@ -0,0 +1,308 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use chrono::Utc;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use rusqlite::{params, Connection, Row};
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+use crate::db::Database;
+use crate::ebay::{self, EbayListing, EbayTaxonomy};
+use crate::operations::{CancelToken, OperationRegistry};
+
+// Pause between listings so a batch stays well inside eBay's call limits
+const PUBLISH_INTERVAL: Duration = Duration::from_secs(1);
+
+// When eBay says too many requests without saying for how long, wait this
+// long before trying the same listing again, doubling each time
+const RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(30);
+const MAX_RATE_LIMIT_RETRIES: u32 = 3;
+
+// Emitted with a `PublishRecord` whenever a listing changes status
+const STATUS_EVENT: &str = "publish-batch://status";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PublishStatus {
+    Pending,
+    Publishing,
+    Published,
+    Failed,
+}
+
+impl PublishStatus {
+    fn parse(status: &str) -> PublishStatus {
+        match status {
+            "publishing" => PublishStatus::Publishing,
+            "published" => PublishStatus::Published,
+            "failed" => PublishStatus::Failed,
+            _ => PublishStatus::Pending,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PublishRecord {
+    pub id: i64,
+    pub batch_id: String,
+    // Index of the listing in the batch
+    pub position: u32,
+    pub sku: String,
+    pub title: String,
+    pub status: PublishStatus,
+    pub attempts: u32,
+    pub error: Option<String>,
+    pub listing_id: Option<String>,
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchResult {
+    pub batch_id: String,
+    pub published: usize,
+    pub failed: usize,
+    // False when the batch was cancelled before every listing was tried
+    pub completed: bool,
+    pub records: Vec<PublishRecord>,
+}
+
+const RECORD_COLUMNS: &str = "id, batch_id, position, sku, title, status, attempts, error, listing_id, url";
+
+fn record_from_row(row: &Row) -> rusqlite::Result<PublishRecord> {
+    Ok(PublishRecord {
+        id: row.get(0)?,
+        batch_id: row.get(1)?,
+        position: row.get(2)?,
+        sku: row.get(3)?,
+        title: row.get(4)?,
+        status: PublishStatus::parse(&row.get::<_, String>(5)?),
+        attempts: row.get(6)?,
+        error: row.get(7)?,
+        listing_id: row.get(8)?,
+        url: row.get(9)?,
+    })
+}
+
+// Listings published in batches and each one's outcome, stored in SQLite
+// with the listing itself so failures can be retried after a restart
+pub struct PublishLog {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl PublishLog {
+    // Listings that were being published when the app last stopped are
+    // marked failed, since they may or may not have gone live; retrying
+    // reuses the SKU, so it's safe.
+    pub fn new(db: &Database) -> Result<PublishLog, String> {
+        db.lock()?
+            .execute(
+                "UPDATE publish_log SET status = 'failed', error = 'Interrupted while publishing' WHERE status = 'publishing'",
+                [],
+            )
+            .map_err(|e| format!("Failed to initialize publish log: {}", e))?;
+        Ok(PublishLog { conn: db.connection() })
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Connection>, String> {
+        self.conn.lock().map_err(|_| "Publish log lock poisoned".to_string())
+    }
+
+    fn record(conn: &Connection, id: i64) -> Result<PublishRecord, String> {
+        conn.query_row(
+            &format!("SELECT {} FROM publish_log WHERE id = ?1", RECORD_COLUMNS),
+            params![id],
+            record_from_row,
+        ).map_err(|e| format!("Failed to read publish record {}: {}", id, e))
+    }
+
+    fn records(&self, batch_id: Option<&str>) -> Result<Vec<PublishRecord>, String> {
+        let conn = self.lock()?;
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {} FROM publish_log WHERE ?1 IS NULL OR batch_id = ?1 ORDER BY id",
+                RECORD_COLUMNS
+            ))
+            .map_err(|e| format!("Failed to read publish log: {}", e))?;
+        let rows = stmt
+            .query_map(params![batch_id], record_from_row)
+            .map_err(|e| format!("Failed to read publish log: {}", e))?;
+        rows.collect::<Result<_, _>>()
+            .map_err(|e| format!("Failed to read publish log: {}", e))
+    }
+
+    fn add_batch(&self, batch_id: &str, listings: &[EbayListing]) -> Result<Vec<PublishRecord>, String> {
+        let mut conn = self.lock()?;
+        let tx = conn.transaction()
+            .map_err(|e| format!("Failed to start publish log transaction: {}", e))?;
+        let mut ids = Vec::with_capacity(listings.len());
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO publish_log (batch_id, position, sku, title, listing, status, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, 'pending', ?6, ?6)"
+            ).map_err(|e| format!("Failed to prepare publish log insert: {}", e))?;
+            let now = Utc::now().to_rfc3339();
+            for (position, listing) in listings.iter().enumerate() {
+                let sku = listing.item.sku.clone().unwrap_or_default();
+                let json = serde_json::to_string(listing)
+                    .map_err(|e| format!("Failed to serialize listing: {}", e))?;
+                stmt.execute(params![batch_id, position as u32, sku, listing.item.title, json, now])
+                    .map_err(|e| format!("Failed to log listing \"{}\": {}", listing.item.title, e))?;
+                ids.push(tx.last_insert_rowid());
+            }
+        }
+        tx.commit()
+            .map_err(|e| format!("Failed to commit publish log: {}", e))?;
+        ids.into_iter().map(|id| PublishLog::record(&conn, id)).collect()
+    }
+
+    // The stored listing for a record, ready to publish again
+    fn listing(&self, id: i64) -> Result<EbayListing, String> {
+        let conn = self.lock()?;
+        let json: String = conn
+            .query_row("SELECT listing FROM publish_log WHERE id = ?1", params![id], |row| row.get(0))
+            .map_err(|e| format!("Failed to read publish record {}: {}", id, e))?;
+        serde_json::from_str(&json).map_err(|e| format!("Failed to parse stored listing: {}", e))
+    }
+
+    fn start(&self, id: i64) -> Result<PublishRecord, String> {
+        let conn = self.lock()?;
+        conn.execute(
+            "UPDATE publish_log SET status = 'publishing', attempts = attempts + 1, updated_at = ?2 WHERE id = ?1",
+            params![id, Utc::now().to_rfc3339()],
+        ).map_err(|e| format!("Failed to update publish record {}: {}", id, e))?;
+        PublishLog::record(&conn, id)
+    }
+
+    fn finish(&self, id: i64, result: Result<ebay::PublishedListing, String>) -> Result<PublishRecord, String> {
+        let conn = self.lock()?;
+        let now = Utc::now().to_rfc3339();
+        match result {
+            Ok(published) => conn.execute(
+                "UPDATE publish_log SET status = 'published', error = NULL, listing_id = ?2, url = ?3, updated_at = ?4
+                 WHERE id = ?1",
+                params![id, published.listing_id, published.url, now],
+            ),
+            Err(error) => conn.execute(
+                "UPDATE publish_log SET status = 'failed', error = ?2, updated_at = ?3 WHERE id = ?1",
+                params![id, error, now],
+            ),
+        }.map_err(|e| format!("Failed to update publish record {}: {}", id, e))?;
+        PublishLog::record(&conn, id)
+    }
+}
+
+fn emit_status(app: &AppHandle, record: &PublishRecord) {
+    let _ = app.emit_all(STATUS_EVENT, record);
+}
+
+// Publish one listing, waiting out eBay's rate limit when it's hit unless
+// the batch is cancelled meanwhile
+async fn publish_with_backoff(
+    app: &AppHandle,
+    taxonomy: &EbayTaxonomy,
+    listing: &EbayListing,
+    cancel: &CancelToken,
+) -> Result<ebay::PublishedListing, String> {
+    let mut retries = 0;
+    loop {
+        match ebay::publish(app, taxonomy, listing).await {
+            Err(e) if e.is_rate_limited() && retries < MAX_RATE_LIMIT_RETRIES => {
+                let wait = e.retry_after.unwrap_or(RATE_LIMIT_BACKOFF * 2u32.pow(retries));
+                tokio::select! {
+                    _ = tokio::time::sleep(wait) => {}
+                    _ = cancel.cancelled() => return Err(format!("Cancelled while waiting out eBay's rate limit: {}", e)),
+                }
+                retries += 1;
+            }
+            result => return Ok(result?),
+        }
+    }
+}
+
+// Publish logged listings one after another, stopping early if cancelled
+async fn run(app: &AppHandle, batch_id: &str, ids: Vec<i64>, operation_id: Option<String>) -> Result<BatchResult, String> {
+    let log = app.state::<PublishLog>();
+    let taxonomy = app.state::<EbayTaxonomy>();
+    let operations = app.state::<OperationRegistry>();
+    let operation = operations.register(operation_id);
+
+    let mut completed = true;
+    for (i, id) in ids.iter().enumerate() {
+        if operation.token.is_cancelled() {
+            completed = false;
+            break;
+        }
+        if i > 0 {
+            tokio::time::sleep(PUBLISH_INTERVAL).await;
+        }
+        let listing = log.listing(*id)?;
+        emit_status(app, &log.start(*id)?);
+        let result = publish_with_backoff(app, &taxonomy, &listing, &operation.token).await;
+        emit_status(app, &log.finish(*id, result)?);
+    }
+
+    let records: Vec<PublishRecord> = log.records(Some(batch_id))?;
+    Ok(BatchResult {
+        batch_id: batch_id.to_string(),
+        published: records.iter().filter(|record| record.status == PublishStatus::Published).count(),
+        failed: records.iter().filter(|record| record.status == PublishStatus::Failed).count(),
+        completed,
+        records,
+    })
+}
+
+// Command to publish a session's listings to eBay one at a time, pausing
+// between them and backing off when eBay's rate limit is hit. Each listing's
+// progress is emitted as a `publish-batch://status` event and logged, so
+// failures can be retried with `retry_failed_publishes`. Listings without a
+// SKU are given one first, so a retry updates rather than duplicates them.
+// Cancel with `cancel_operation(operation_id)`; listings not yet tried stay
+// pending until retried.
+#[tauri::command]
+pub async fn publish_batch(
+    app: AppHandle,
+    log: State<'_, PublishLog>,
+    mut listings: Vec<EbayListing>,
+    operation_id: Option<String>,
+) -> Result<BatchResult, String> {
+    if listings.is_empty() {
+        return Err("No listings to publish".to_string());
+    }
+    for listing in &mut listings {
+        if listing.item.sku.as_deref().is_none_or(|sku| sku.trim().is_empty()) {
+            listing.item.sku = Some(ebay::new_sku());
+        }
+    }
+    let suffix: String = rand::thread_rng().sample_iter(&Alphanumeric).take(6).map(char::from).collect();
+    let batch_id = format!("{}-{}", Utc::now().format("%Y%m%d%H%M%S"), suffix);
+
+    let records = log.add_batch(&batch_id, &listings)?;
+    for record in &records {
+        emit_status(&app, record);
+    }
+    run(&app, &batch_id, records.iter().map(|record| record.id).collect(), operation_id).await
+}
+
+// Command to publish a batch's failed (and never tried) listings again,
+// e.g. after fixing the cause or once eBay is reachable
+#[tauri::command]
+pub async fn retry_failed_publishes(
+    app: AppHandle,
+    log: State<'_, PublishLog>,
+    batch_id: String,
+    operation_id: Option<String>,
+) -> Result<BatchResult, String> {
+    let ids: Vec<i64> = log
+        .records(Some(&batch_id))?
+        .into_iter()
+        .filter(|record| matches!(record.status, PublishStatus::Failed | PublishStatus::Pending))
+        .map(|record| record.id)
+        .collect();
+    run(&app, &batch_id, ids, operation_id).await
+}
+
+// Command to list logged listings, for one batch or all of them, oldest first
+#[tauri::command]
+pub fn list_publish_results(log: State<PublishLog>, batch_id: Option<String>) -> Result<Vec<PublishRecord>, String> {
+    log.records(batch_id.as_deref())
+}
@@ -0,0 +1,111 @@
+use image::{DynamicImage, GrayImage};
+use serde::{Deserialize, Serialize};
+
+// Longest edge photos are scaled to before scoring, so scores are comparable
+// across resolutions and cheap to compute
+const SCORING_EDGE: u32 = 512;
+
+// Laplacian variance at which sharpness scores 0.5
+const SHARPNESS_MIDPOINT: f64 = 100.0;
+
+// Pixel values treated as crushed shadows / blown highlights
+const SHADOW_CLIP: u8 = 5;
+const HIGHLIGHT_CLIP: u8 = 250;
+
+// Quality scores for one photo, all 0.0 (worst) to 1.0 (best)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct QualityScore {
+    pub sharpness: f64,
+    pub exposure: f64,
+    pub overall: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhotoQuality {
+    pub path: String,
+    #[serde(flatten)]
+    pub score: QualityScore,
+}
+
+// Score a photo for sharpness (Laplacian variance) and exposure (histogram)
+pub fn score_photo(img: &DynamicImage) -> QualityScore {
+    let gray = img.thumbnail(SCORING_EDGE, SCORING_EDGE).to_luma8();
+
+    let sharpness = sharpness_score(&gray);
+    let exposure = exposure_score(&gray);
+
+    QualityScore {
+        sharpness,
+        exposure,
+        overall: 0.6 * sharpness + 0.4 * exposure,
+    }
+}
+
+// Variance of the Laplacian, mapped to 0-1; blurry photos have few edges
+// and so a low variance
+fn sharpness_score(gray: &GrayImage) -> f64 {
+    let (width, height) = gray.dimensions();
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+
+    let mut sum = 0.0;
+    let mut sum_sq = 0.0;
+    let mut count = 0.0;
+    for y in 1..(height - 1) {
+        for x in 1..(width - 1) {
+            let center = gray.get_pixel(x, y)[0] as f64;
+            let laplacian = gray.get_pixel(x - 1, y)[0] as f64
+                + gray.get_pixel(x + 1, y)[0] as f64
+                + gray.get_pixel(x, y - 1)[0] as f64
+                + gray.get_pixel(x, y + 1)[0] as f64
+                - 4.0 * center;
+            sum += laplacian;
+            sum_sq += laplacian * laplacian;
+            count += 1.0;
+        }
+    }
+
+    let mean = sum / count;
+    let variance = sum_sq / count - mean * mean;
+    variance / (variance + SHARPNESS_MIDPOINT)
+}
+
+// Penalise photos whose mean brightness is far from mid-grey or with
+// many clipped shadows/highlights
+fn exposure_score(gray: &GrayImage) -> f64 {
+    let mut histogram = [0u64; 256];
+    for pixel in gray.pixels() {
+        histogram[pixel[0] as usize] += 1;
+    }
+
+    let total: u64 = histogram.iter().sum();
+    if total == 0 {
+        return 0.0;
+    }
+
+    let mean = histogram
+        .iter()
+        .enumerate()
+        .map(|(value, count)| value as f64 * *count as f64)
+        .sum::<f64>() / total as f64;
+
+    let clipped: u64 = histogram[..=SHADOW_CLIP as usize].iter().sum::<u64>()
+        + histogram[HIGHLIGHT_CLIP as usize..].iter().sum::<u64>();
+    let clipped_fraction = clipped as f64 / total as f64;
+
+    let brightness = 1.0 - ((mean - 128.0).abs() / 128.0);
+    (brightness * (1.0 - clipped_fraction)).clamp(0.0, 1.0)
+}
+
+// Index of the best photo by overall score (first photo wins ties)
+pub fn best_index(scores: &[QualityScore]) -> Option<usize> {
+    scores
+        .iter()
+        .enumerate()
+        .fold(None, |best: Option<(usize, f64)>, (i, score)| match best {
+            Some((_, best_score)) if best_score >= score.overall => best,
+            _ => Some((i, score.overall)),
+        })
+        .map(|(i, _)| i)
+}
@@ -0,0 +1,392 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::{AppHandle, Manager, State};
+use tokio::sync::Notify;
+use crate::db::Database;
+use crate::ebay;
+use crate::ebay_auth;
+use crate::network;
+
+// Emitted with a `PriceDropRule` when the worker lowers a price or a drop fails
+const DROP_EVENT: &str = "repricing://drop";
+
+// Longest the worker sleeps before checking for due drops again
+const IDLE_POLL: Duration = Duration::from_secs(15 * 60);
+
+// A drop that failed is tried again after this long
+const RETRY_MINUTES: i64 = 60;
+
+// Smallest discount eBay accepts on an offer to watchers
+const MIN_OFFER_DISCOUNT: f64 = 5.0;
+
+// A listing's automatic price drops, e.g. 5% every 7 days down to a floor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PriceDropRule {
+    pub sku: String,
+    // Percentage taken off the current price at each drop
+    pub drop_percent: f64,
+    pub every_days: u32,
+    // The price never goes below this
+    pub floor_price: f64,
+    // Also send watchers an offer of this percentage off after each drop
+    #[serde(default)]
+    pub offer_percent: Option<f64>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    // Set from the listing when the rule is saved; read-only
+    #[serde(default)]
+    pub current_price: f64,
+    #[serde(default)]
+    pub currency: String,
+    // RFC 3339
+    #[serde(default)]
+    pub next_drop_at: String,
+    #[serde(default)]
+    pub last_dropped_at: Option<String>,
+    // active, at_floor (no more drops) or ended (no live listing)
+    #[serde(default)]
+    pub status: String,
+    #[serde(default)]
+    pub last_error: Option<String>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Serialize)]
+pub struct SentOffers {
+    pub listing_id: String,
+    // Watchers and other interested buyers the offer went to
+    pub offers_sent: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WatcherOffer {
+    pub sku: String,
+    // Either a percentage off the listing price, at least 5, or a price
+    #[serde(default)]
+    pub discount_percent: Option<f64>,
+    #[serde(default)]
+    pub price: Option<f64>,
+    #[serde(default)]
+    pub message: Option<String>,
+    #[serde(default)]
+    pub allow_counter_offers: bool,
+}
+
+fn round_cents(amount: f64) -> f64 {
+    (amount * 100.0).round() / 100.0
+}
+
+fn timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+const RULE_COLUMNS: &str = "sku, drop_percent, every_days, floor_price, offer_percent, enabled, current_price, currency,
+    next_drop_at, last_dropped_at, status, last_error";
+
+fn rule_from_row(row: &Row) -> rusqlite::Result<PriceDropRule> {
+    Ok(PriceDropRule {
+        sku: row.get(0)?,
+        drop_percent: row.get(1)?,
+        every_days: row.get(2)?,
+        floor_price: row.get(3)?,
+        offer_percent: row.get(4)?,
+        enabled: row.get(5)?,
+        current_price: row.get(6)?,
+        currency: row.get(7)?,
+        next_drop_at: row.get(8)?,
+        last_dropped_at: row.get(9)?,
+        status: row.get(10)?,
+        last_error: row.get(11)?,
+    })
+}
+
+// Price drop rules by SKU, stored in SQLite
+pub struct PriceRules {
+    conn: Arc<Mutex<Connection>>,
+    wake: Notify,
+}
+
+impl PriceRules {
+    pub fn new(db: &Database) -> PriceRules {
+        PriceRules { conn: db.connection(), wake: Notify::new() }
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Connection>, String> {
+        self.conn.lock().map_err(|_| "Price rule store lock poisoned".to_string())
+    }
+
+    fn put(&self, rule: &PriceDropRule) -> Result<(), String> {
+        let conn = self.lock()?;
+        conn.execute(
+            &format!("INSERT OR REPLACE INTO price_drop_rules ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)", RULE_COLUMNS),
+            params![
+                rule.sku,
+                rule.drop_percent,
+                rule.every_days,
+                rule.floor_price,
+                rule.offer_percent,
+                rule.enabled,
+                rule.current_price,
+                rule.currency,
+                rule.next_drop_at,
+                rule.last_dropped_at,
+                rule.status,
+                rule.last_error,
+            ],
+        )
+        .map_err(|e| format!("Failed to save price rule for {}: {}", rule.sku, e))?;
+        Ok(())
+    }
+
+    fn list(&self) -> Result<Vec<PriceDropRule>, String> {
+        let conn = self.lock()?;
+        let mut stmt = conn
+            .prepare(&format!("SELECT {} FROM price_drop_rules ORDER BY next_drop_at", RULE_COLUMNS))
+            .map_err(|e| format!("Failed to read price rules: {}", e))?;
+        let rows = stmt
+            .query_map([], rule_from_row)
+            .map_err(|e| format!("Failed to read price rules: {}", e))?;
+        rows.collect::<Result<_, _>>()
+            .map_err(|e| format!("Failed to read price rules: {}", e))
+    }
+
+    // The earliest enabled rule whose drop is due
+    fn next_due(&self) -> Result<Option<PriceDropRule>, String> {
+        let conn = self.lock()?;
+        conn.query_row(
+            &format!(
+                "SELECT {} FROM price_drop_rules WHERE enabled = 1 AND status = 'active' AND next_drop_at <= ?1
+                 ORDER BY next_drop_at LIMIT 1",
+                RULE_COLUMNS
+            ),
+            params![timestamp(Utc::now())],
+            rule_from_row,
+        )
+        .optional()
+        .map_err(|e| format!("Failed to read price rules: {}", e))
+    }
+
+    fn delete(&self, sku: &str) -> Result<bool, String> {
+        let conn = self.lock()?;
+        let removed = conn
+            .execute("DELETE FROM price_drop_rules WHERE sku = ?1", params![sku])
+            .map_err(|e| format!("Failed to delete price rule: {}", e))?;
+        Ok(removed > 0)
+    }
+}
+
+// An SKU's live eBay listing: (offer, price, currency)
+async fn live_offer(app: &AppHandle, sku: &str) -> Result<Option<(Value, f64, String)>, String> {
+    let offer = ebay::published_offers(app, sku).await?.into_iter().next();
+    Ok(offer.map(|offer| {
+        let price = &offer["pricingSummary"]["price"];
+        let value = price["value"].as_str().and_then(|value| value.parse().ok()).unwrap_or(0.0);
+        let currency = price["currency"].as_str().unwrap_or_default().to_string();
+        (offer, value, currency)
+    }))
+}
+
+// Send an offer to a listing's watchers through the Negotiation API. eBay
+// only allows it for listings with interested buyers who haven't had one.
+async fn send_offer(
+    app: &AppHandle,
+    offer: &Value,
+    offered: Value,
+    message: Option<&str>,
+    allow_counter_offers: bool,
+) -> Result<SentOffers, String> {
+    let listing_id = offer["listing"]["listingId"]
+        .as_str()
+        .ok_or("The eBay offer has no listing")?
+        .to_string();
+    let marketplace_id = offer["marketplaceId"].as_str().unwrap_or(ebay::DEFAULT_MARKETPLACE);
+    let environment = ebay_auth::load_settings(app)?.environment;
+    let token = ebay_auth::access_token(app).await?;
+
+    let mut item = json!({ "listingId": listing_id, "quantity": 1 });
+    if let (Some(item), Some(offered)) = (item.as_object_mut(), offered.as_object()) {
+        item.extend(offered.clone());
+    }
+    let mut body = json!({ "allowCounterOffer": allow_counter_offers, "offeredItems": [item] });
+    if let Some(message) = message.map(str::trim).filter(|message| !message.is_empty()) {
+        body["message"] = json!(message);
+    }
+    let request = network::client(app)
+        .post(format!("{}/sell/negotiation/v1/send_offer_to_interested_buyers", environment.api_url()))
+        .bearer_auth(&token)
+        .header("X-EBAY-C-MARKETPLACE-ID", marketplace_id)
+        .json(&body);
+    let result = ebay::send(request, "send offer to watchers").await?;
+    Ok(SentOffers {
+        listing_id,
+        offers_sent: result["offers"].as_array().map(Vec::len).unwrap_or(0),
+    })
+}
+
+fn discount(percent: f64) -> Value {
+    json!({ "discountPercentage": format!("{}", percent.round() as u32) })
+}
+
+// Take one due drop: lower the price, or retire the rule once it's at the
+// floor or the listing has ended
+async fn apply_drop(app: &AppHandle, rules: &PriceRules, mut rule: PriceDropRule) -> PriceDropRule {
+    let now = Utc::now();
+    let live = match live_offer(app, &rule.sku).await {
+        Ok(live) => live,
+        Err(e) => {
+            rule.last_error = Some(e);
+            rule.next_drop_at = timestamp(now + chrono::Duration::minutes(RETRY_MINUTES));
+            return rule;
+        }
+    };
+    let Some((offer, price, currency)) = live else {
+        rule.status = "ended".to_string();
+        rule.last_error = None;
+        return rule;
+    };
+
+    // Priced from the listing, so a price changed by hand is dropped from
+    let new_price = round_cents(price * (1.0 - rule.drop_percent / 100.0)).max(rule.floor_price);
+    if new_price >= price {
+        rule.current_price = price;
+        rule.status = "at_floor".to_string();
+        return rule;
+    }
+
+    // The drop is saved before eBay is asked for it, so a rule that can't be
+    // saved afterwards isn't dropped again straight away
+    let dropped = PriceDropRule {
+        current_price: new_price,
+        currency,
+        last_dropped_at: Some(timestamp(now)),
+        next_drop_at: timestamp(now + chrono::Duration::days(rule.every_days as i64)),
+        status: if new_price <= rule.floor_price { "at_floor".to_string() } else { rule.status.clone() },
+        last_error: None,
+        ..rule.clone()
+    };
+    let result = match rules.put(&dropped) {
+        Ok(()) => ebay::set_price(app, &rule.sku, new_price).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        rule.current_price = price;
+        rule.last_error = Some(e);
+        rule.next_drop_at = timestamp(now + chrono::Duration::minutes(RETRY_MINUTES));
+        return rule;
+    }
+
+    let mut rule = dropped;
+    // Watchers are told about the lower price with an offer on top. eBay
+    // refuses when nobody is watching, so a failure doesn't undo the drop,
+    // but it's shown on the rule.
+    if let Some(percent) = rule.offer_percent {
+        if let Err(e) = send_offer(app, &offer, discount(percent), None, false).await {
+            rule.last_error = Some(format!("Price lowered, but no offer was sent to watchers: {}", e));
+        }
+    }
+    rule
+}
+
+// Start the background worker that takes price drops as they come due. Call
+// once the rule store is managed.
+pub fn start(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let rules = app.state::<PriceRules>();
+            let rule = match rules.next_due() {
+                Ok(Some(rule)) => rule,
+                Ok(None) | Err(_) => {
+                    let _ = tokio::time::timeout(IDLE_POLL, rules.wake.notified()).await;
+                    continue;
+                }
+            };
+            let rule = apply_drop(&app, &rules, rule).await;
+            if rules.put(&rule).is_ok() {
+                let _ = app.emit_all(DROP_EVENT, &rule);
+            }
+        }
+    });
+}
+
+// Command to set up automatic price drops for an eBay listing, e.g. 5%
+// every 7 days down to a floor, replacing any rule for the SKU. The first
+// drop is `every_days` from now. Optionally each drop also sends watchers
+// an offer. Needs the listing to be live.
+#[tauri::command]
+pub async fn set_price_drop_rule(app: AppHandle, rule: PriceDropRule) -> Result<PriceDropRule, String> {
+    let sku = rule.sku.trim().to_string();
+    if sku.is_empty() {
+        return Err("SKU is required".to_string());
+    }
+    if !(rule.drop_percent > 0.0 && rule.drop_percent < 100.0) {
+        return Err("Drop must be between 0 and 100%".to_string());
+    }
+    if rule.every_days == 0 {
+        return Err("Drops must be at least a day apart".to_string());
+    }
+    if !rule.floor_price.is_finite() || rule.floor_price <= 0.0 {
+        return Err("Floor price must be greater than zero".to_string());
+    }
+    if rule.offer_percent.is_some_and(|percent| !(MIN_OFFER_DISCOUNT..100.0).contains(&percent)) {
+        return Err(format!("Offers to watchers must be at least {}% off", MIN_OFFER_DISCOUNT));
+    }
+    let (_, price, currency) = live_offer(&app, &sku)
+        .await?
+        .ok_or_else(|| format!("{} has no live eBay listing", sku))?;
+    if price <= rule.floor_price {
+        return Err(format!("{} is already at or below the floor price", sku));
+    }
+
+    let rule = PriceDropRule {
+        sku,
+        current_price: price,
+        currency,
+        next_drop_at: timestamp(Utc::now() + chrono::Duration::days(rule.every_days as i64)),
+        last_dropped_at: None,
+        status: "active".to_string(),
+        last_error: None,
+        ..rule
+    };
+    let rules = app.state::<PriceRules>();
+    rules.put(&rule)?;
+    rules.wake.notify_one();
+    Ok(rule)
+}
+
+// Command to list price drop rules, next drop first
+#[tauri::command]
+pub fn list_price_drop_rules(rules: State<PriceRules>) -> Result<Vec<PriceDropRule>, String> {
+    rules.list()
+}
+
+#[tauri::command]
+pub fn delete_price_drop_rule(rules: State<PriceRules>, sku: String) -> Result<bool, String> {
+    rules.delete(sku.trim())
+}
+
+// Command to send an offer to an eBay listing's watchers and other
+// interested buyers, as a percentage off or a set price. eBay sends it for
+// two days; listings without interested buyers are refused.
+#[tauri::command]
+pub async fn send_offer_to_watchers(app: AppHandle, offer: WatcherOffer) -> Result<SentOffers, String> {
+    let (listing, price, currency) = live_offer(&app, offer.sku.trim())
+        .await?
+        .ok_or_else(|| format!("{} has no live eBay listing", offer.sku.trim()))?;
+    let offered = match (offer.discount_percent, offer.price) {
+        (Some(percent), None) if (MIN_OFFER_DISCOUNT..100.0).contains(&percent) => discount(percent),
+        (Some(_), None) => return Err(format!("Offers must be at least {}% off", MIN_OFFER_DISCOUNT)),
+        (None, Some(offer_price)) if offer_price > 0.0 && offer_price < price => {
+            json!({ "price": { "value": format!("{:.2}", offer_price), "currency": currency } })
+        }
+        (None, Some(_)) => return Err("Offer price must be below the listing price".to_string()),
+        _ => return Err("Give either a discount or a price".to_string()),
+    };
+    send_offer(&app, &listing, offered, offer.message.as_deref(), offer.allow_counter_offers).await
+}
@@ -0,0 +1,321 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::AppHandle;
+use crate::{gcs, image_io, keychain, network, vision};
+
+const BING_VISUAL_SEARCH_URL: &str = "https://api.bing.microsoft.com/v7.0/images/visualsearch";
+const TINEYE_SEARCH_URL: &str = "https://api.tineye.com/rest/search/";
+
+// Local photos are uploaded downscaled; the engines match on overall appearance
+const SEARCH_EDGE: u32 = 1000;
+const SEARCH_JPEG_QUALITY: u8 = 85;
+
+// Results kept per engine
+const MAX_ENGINE_RESULTS: usize = 25;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchEngine {
+    Google,
+    Bing,
+    Tineye,
+}
+
+impl SearchEngine {
+    // Keychain account holding the engine's API key. Google uses the
+    // registered GCS credentials instead.
+    fn key_account(self) -> Option<&'static str> {
+        match self {
+            SearchEngine::Google => None,
+            SearchEngine::Bing => Some("reverse-search-bing"),
+            SearchEngine::Tineye => Some("reverse-search-tineye"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Price {
+    pub amount: f64,
+    pub currency: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchMatch {
+    pub title: String,
+    pub url: String,
+    pub image_url: Option<String>,
+    pub price: Option<Price>,
+    // Engines that found this page, so matches found by several rank first
+    pub engines: Vec<SearchEngine>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EngineError {
+    pub engine: SearchEngine,
+    pub error: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReverseSearchResults {
+    pub matches: Vec<SearchMatch>,
+    // Engines that were queried (those with credentials)
+    pub engines: Vec<SearchEngine>,
+    pub errors: Vec<EngineError>,
+}
+
+// What is being searched for
+enum SearchImage {
+    // Publicly fetchable URL
+    Url(String),
+    Jpeg(Vec<u8>),
+}
+
+async fn google_matches(app: &AppHandle, image: &str) -> Result<Vec<SearchMatch>, String> {
+    let annotations = vision::annotate(app, image, &["WEB_DETECTION"]).await?;
+    let web = annotations.web_detection.unwrap_or_default();
+    Ok(web
+        .pages_with_matching_images
+        .into_iter()
+        .filter(|page| !page.url.is_empty())
+        .take(MAX_ENGINE_RESULTS)
+        .map(|page| SearchMatch {
+            title: vision::strip_tags(&page.page_title),
+            url: page.url,
+            image_url: None,
+            price: None,
+            engines: vec![SearchEngine::Google],
+        })
+        .collect())
+}
+
+async fn bing_matches(app: &AppHandle, key: &str, image: &SearchImage) -> Result<Vec<SearchMatch>, String> {
+    let (body, content_type) = match image {
+        SearchImage::Url(url) => {
+            let request = serde_json::json!({ "imageInfo": { "url": url } }).to_string();
+            network::multipart(&[("knowledgeRequest", None, "application/json", request.as_bytes())])
+        }
+        SearchImage::Jpeg(jpeg) => network::multipart(&[("image", Some("image.jpg"), "image/jpeg", jpeg)]),
+    };
+    let response = network::client(app)
+        .post(BING_VISUAL_SEARCH_URL)
+        .header("Ocp-Apim-Subscription-Key", key)
+        .header(reqwest::header::CONTENT_TYPE, content_type)
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| format!("Bing request failed: {}", e))?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("Bing request failed ({}): {}", status, body.trim()));
+    }
+    let result: Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse Bing response: {}", e))?;
+
+    // Results are grouped under tags and actions; pages and products carry
+    // the same image fields
+    let mut matches = Vec::new();
+    for tag in result["tags"].as_array().into_iter().flatten() {
+        for action in tag["actions"].as_array().into_iter().flatten() {
+            if !matches!(action["actionType"].as_str(), Some("PagesIncluding" | "VisualSearch" | "ProductVisualSearch")) {
+                continue;
+            }
+            for item in action["data"]["value"].as_array().into_iter().flatten() {
+                let Some(url) = item["hostPageUrl"].as_str() else {
+                    continue;
+                };
+                let offer = &item["insightsMetadata"]["aggregateOffer"];
+                matches.push(SearchMatch {
+                    title: item["name"].as_str().unwrap_or_default().trim().to_string(),
+                    url: url.to_string(),
+                    image_url: item["contentUrl"].as_str().map(str::to_string),
+                    price: offer["lowPrice"].as_f64().map(|amount| Price {
+                        amount,
+                        currency: offer["priceCurrency"].as_str().map(str::to_string),
+                    }),
+                    engines: vec![SearchEngine::Bing],
+                });
+            }
+        }
+    }
+    matches.truncate(MAX_ENGINE_RESULTS);
+    Ok(matches)
+}
+
+async fn tineye_matches(app: &AppHandle, key: &str, image: &SearchImage) -> Result<Vec<SearchMatch>, String> {
+    let request = network::client(app)
+        .post(TINEYE_SEARCH_URL)
+        .header("x-api-key", key)
+        .query(&[("limit", MAX_ENGINE_RESULTS.to_string())]);
+    let request = match image {
+        SearchImage::Url(url) => request.query(&[("image_url", url)]),
+        SearchImage::Jpeg(jpeg) => {
+            let (body, content_type) = network::multipart(&[("image_upload", Some("image.jpg"), "image/jpeg", jpeg)]);
+            request.header(reqwest::header::CONTENT_TYPE, content_type).body(body)
+        }
+    };
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("TinEye request failed: {}", e))?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("TinEye request failed ({}): {}", status, body.trim()));
+    }
+    let result: Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse TinEye response: {}", e))?;
+
+    // TinEye reports where an image appears, without page titles
+    let mut matches = Vec::new();
+    for found in result["results"]["matches"].as_array().into_iter().flatten() {
+        let domain = found["domain"].as_str().unwrap_or_default();
+        for backlink in found["backlinks"].as_array().into_iter().flatten() {
+            if let Some(url) = backlink["backlink"].as_str() {
+                matches.push(SearchMatch {
+                    title: domain.to_string(),
+                    url: url.to_string(),
+                    image_url: backlink["url"].as_str().or(found["image_url"].as_str()).map(str::to_string),
+                    price: None,
+                    engines: vec![SearchEngine::Tineye],
+                });
+            }
+        }
+    }
+    matches.truncate(MAX_ENGINE_RESULTS);
+    Ok(matches)
+}
+
+// URL identity for deduping: no scheme, "www.", fragment, tracking
+// parameters or trailing slash
+fn dedupe_key(url: &str) -> String {
+    let url = url.split('#').next().unwrap_or(url);
+    let url = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let url = url.strip_prefix("www.").unwrap_or(url);
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let query: Vec<&str> = query
+        .split('&')
+        .filter(|param| !param.is_empty() && !param.starts_with("utm_"))
+        .collect();
+    let path = path.trim_end_matches('/').to_lowercase();
+    if query.is_empty() { path } else { format!("{}?{}", path, query.join("&")) }
+}
+
+// Merge matches of the same page, keeping the first-seen order within each
+// number of engines
+fn merge(results: Vec<Vec<SearchMatch>>) -> Vec<SearchMatch> {
+    let mut merged: Vec<(String, SearchMatch)> = Vec::new();
+    for found in results.into_iter().flatten() {
+        let key = dedupe_key(&found.url);
+        match merged.iter_mut().find(|(existing, _)| *existing == key) {
+            Some((_, existing)) => {
+                for engine in found.engines {
+                    if !existing.engines.contains(&engine) {
+                        existing.engines.push(engine);
+                    }
+                }
+                // Prefer a real page title over TinEye's domain
+                if existing.title.is_empty() || !existing.title.contains(' ') && found.title.contains(' ') {
+                    existing.title = found.title;
+                }
+                existing.image_url = existing.image_url.take().or(found.image_url);
+                existing.price = existing.price.take().or(found.price);
+            }
+            None => merged.push((key, found)),
+        }
+    }
+    let mut matches: Vec<SearchMatch> = merged.into_iter().map(|(_, found)| found).collect();
+    matches.sort_by_key(|found| std::cmp::Reverse(found.engines.len()));
+    matches
+}
+
+// Image to send to Bing and TinEye: URLs are passed through (GCS objects
+// signed so the engines can fetch them), local photos uploaded
+async fn search_image(app: &AppHandle, image: &str) -> Result<SearchImage, String> {
+    if image.starts_with("gs://") || image.starts_with("https://") || image.starts_with("http://") {
+        return Ok(SearchImage::Url(gcs::download_url(app, image).await?));
+    }
+    let path = image.to_string();
+    tauri::async_runtime::spawn_blocking(move || {
+        let img = image_io::open_image_scaled(&path, SEARCH_EDGE)?;
+        image_io::encode_jpeg(&img, SEARCH_JPEG_QUALITY).map(SearchImage::Jpeg)
+    })
+    .await
+    .map_err(|e| format!("Failed to read image: {}", e))?
+}
+
+// Command to store (or, with `None` or "", remove) the API key for Bing
+// Visual Search or TinEye in the OS keychain
+#[tauri::command]
+pub fn set_reverse_search_key(engine: SearchEngine, api_key: Option<String>) -> Result<(), String> {
+    let account = engine
+        .key_account()
+        .ok_or("Google reverse search uses the registered Google Cloud credentials")?;
+    match api_key.as_deref().map(str::trim) {
+        Some(key) if !key.is_empty() => keychain::set_secret(account, key),
+        _ => keychain::delete_secret(account),
+    }
+}
+
+// Command to find pages showing the item in a photo with every reverse image
+// search engine that has credentials: Google (Vision web detection, with the
+// registered GCS credentials), Bing Visual Search and TinEye (API keys from
+// `set_reverse_search_key`). Matches from all engines are merged by URL;
+// pages several engines found come first. An engine that fails is reported
+// in `errors` without failing the search.
+#[tauri::command]
+pub async fn reverse_search(app: AppHandle, image: String) -> Result<ReverseSearchResults, String> {
+    let api_key = |engine: SearchEngine| engine.key_account().map(keychain::get_secret).transpose().map(Option::flatten);
+    let bing_key = api_key(SearchEngine::Bing)?;
+    let tineye_key = api_key(SearchEngine::Tineye)?;
+    let google_available = gcs::access_token(&app).await.is_ok();
+    if !google_available && bing_key.is_none() && tineye_key.is_none() {
+        return Err("No reverse image search engine is set up".to_string());
+    }
+
+    let upload = if bing_key.is_some() || tineye_key.is_some() {
+        Some(search_image(&app, &image).await?)
+    } else {
+        None
+    };
+
+    let google = async {
+        if google_available { Some(google_matches(&app, &image).await) } else { None }
+    };
+    let bing = async {
+        match (&bing_key, &upload) {
+            (Some(key), Some(upload)) => Some(bing_matches(&app, key, upload).await),
+            _ => None,
+        }
+    };
+    let tineye = async {
+        match (&tineye_key, &upload) {
+            (Some(key), Some(upload)) => Some(tineye_matches(&app, key, upload).await),
+            _ => None,
+        }
+    };
+    let (google, bing, tineye) = futures_util::join!(google, bing, tineye);
+
+    let mut engines = Vec::new();
+    let mut results = Vec::new();
+    let mut errors = Vec::new();
+    for (engine, outcome) in [(SearchEngine::Google, google), (SearchEngine::Bing, bing), (SearchEngine::Tineye, tineye)] {
+        match outcome {
+            Some(Ok(matches)) => {
+                engines.push(engine);
+                results.push(matches);
+            }
+            Some(Err(error)) => {
+                engines.push(engine);
+                errors.push(EngineError { engine, error });
+            }
+            None => {}
+        }
+    }
+
+    Ok(ReverseSearchResults { matches: merge(results), engines, errors })
+}
@@ -0,0 +1,207 @@
+use std::collections::BTreeMap;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use rsa::sha2::{Digest, Sha256};
+use serde::{Deserialize, Serialize};
+use crate::storage::{self, PresignedRequest, StorageProvider};
+
+// Connection details for AWS S3 or an S3-compatible service (MinIO, Wasabi, ...)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct S3Config {
+    // e.g. "https://s3.eu-west-2.amazonaws.com" or "http://localhost:9000"
+    pub endpoint: String,
+    pub region: String,
+    pub bucket: String,
+    pub access_key_id: String,
+    #[serde(default)]
+    pub secret_access_key: String,
+    // Address the bucket in the path rather than the host name; MinIO needs this
+    #[serde(default)]
+    pub path_style: bool,
+    // Base URL objects are served from (e.g. a CDN), if not the endpoint itself
+    #[serde(default)]
+    pub public_url: Option<String>,
+}
+
+impl S3Config {
+    pub fn redacted(&self) -> S3Config {
+        S3Config { secret_access_key: String::new(), ..self.clone() }
+    }
+
+    // Settings come back from the UI redacted; a blank secret for the same
+    // access key means "unchanged"
+    pub fn keep_secret(self, stored: &S3Config) -> S3Config {
+        if self.secret_access_key.is_empty() && self.access_key_id == stored.access_key_id {
+            S3Config { secret_access_key: stored.secret_access_key.clone(), ..self }
+        } else {
+            self
+        }
+    }
+}
+
+// S3 as a storage provider, presigning with AWS Signature Version 4
+pub struct S3Provider {
+    config: S3Config,
+    scheme: String,
+    // Host (with port, if not the default) the bucket is addressed at
+    host: String,
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    // HMAC accepts keys of any length
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC key");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+impl S3Provider {
+    pub fn new(config: S3Config) -> Result<S3Provider, String> {
+        if config.bucket.is_empty() || config.region.is_empty() || config.access_key_id.is_empty() || config.secret_access_key.is_empty() {
+            return Err("S3 settings need a bucket, region, access key and secret access key".to_string());
+        }
+
+        let endpoint = reqwest::Url::parse(&config.endpoint)
+            .map_err(|e| format!("Invalid S3 endpoint {}: {}", config.endpoint, e))?;
+        let endpoint_host = endpoint.host_str()
+            .ok_or_else(|| format!("Invalid S3 endpoint: {}", config.endpoint))?;
+        let endpoint_host = match endpoint.port() {
+            Some(port) => format!("{}:{}", endpoint_host, port),
+            None => endpoint_host.to_string(),
+        };
+
+        let host = if config.path_style {
+            endpoint_host
+        } else {
+            format!("{}.{}", config.bucket, endpoint_host)
+        };
+
+        Ok(S3Provider { scheme: endpoint.scheme().to_string(), host, config })
+    }
+
+    fn canonical_uri(&self, object: &str) -> String {
+        if self.config.path_style {
+            format!("/{}/{}", self.config.bucket, storage::encode_object_name(object))
+        } else {
+            format!("/{}", storage::encode_object_name(object))
+        }
+    }
+
+    // Presigned URL (X-Amz-* query parameters) for `method` on `object`
+    // `headers` are extra headers the request must send with exactly these values
+    pub fn presigned_url(&self, method: &str, object: &str, headers: &[(&str, &str)], expires_in_seconds: i64) -> String {
+        let now = Utc::now();
+        let timestamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let credential_scope = format!("{}/{}/s3/aws4_request", date, self.config.region);
+        let canonical_uri = self.canonical_uri(object);
+
+        // Header names are lowercase and sorted, host always included
+        let mut signed: Vec<(String, String)> = headers
+            .iter()
+            .map(|(name, value)| (name.to_lowercase(), value.trim().to_string()))
+            .collect();
+        signed.push(("host".to_string(), self.host.clone()));
+        signed.sort();
+        let canonical_headers: String = signed.iter().map(|(name, value)| format!("{}:{}\n", name, value)).collect();
+        let signed_headers = signed.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>().join(";");
+
+        let mut query = BTreeMap::new();
+        query.insert("X-Amz-Algorithm", "AWS4-HMAC-SHA256".to_string());
+        query.insert("X-Amz-Credential", format!("{}/{}", self.config.access_key_id, credential_scope));
+        query.insert("X-Amz-Date", timestamp.clone());
+        query.insert("X-Amz-Expires", expires_in_seconds.to_string());
+        query.insert("X-Amz-SignedHeaders", signed_headers.clone());
+        let canonical_query = query
+            .iter()
+            .map(|(key, value)| format!("{}={}", urlencoding::encode(key), urlencoding::encode(value)))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let canonical_request = format!(
+            "{}\n{}\n{}\n{}\n{}\nUNSIGNED-PAYLOAD",
+            method,
+            canonical_uri,
+            canonical_query,
+            canonical_headers,
+            signed_headers
+        );
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            timestamp,
+            credential_scope,
+            hex::encode(Sha256::digest(canonical_request.as_bytes()))
+        );
+
+        // Signing key is derived from the secret through the credential scope
+        let key = hmac_sha256(format!("AWS4{}", self.config.secret_access_key).as_bytes(), &date);
+        let key = hmac_sha256(&key, &self.config.region);
+        let key = hmac_sha256(&key, "s3");
+        let key = hmac_sha256(&key, "aws4_request");
+        let signature = hex::encode(hmac_sha256(&key, &string_to_sign));
+
+        format!(
+            "{}://{}{}?{}&X-Amz-Signature={}",
+            self.scheme,
+            self.host,
+            canonical_uri,
+            canonical_query,
+            signature
+        )
+    }
+}
+
+impl StorageProvider for S3Provider {
+    async fn presign(&self, method: &str, object: &str, content_type: Option<&str>, expires_in_seconds: i64) -> Result<PresignedRequest, String> {
+        let headers: Vec<(&str, &str)> = content_type.map(|value| ("Content-Type", value)).into_iter().collect();
+        let url = self.presigned_url(method, object, &headers, expires_in_seconds);
+        Ok(PresignedRequest::new(method, url, &headers))
+    }
+
+    fn object_url(&self, object: &str) -> String {
+        match &self.config.public_url {
+            Some(base) => format!("{}/{}", base.trim_end_matches('/'), storage::encode_object_name(object)),
+            None => format!("{}://{}{}", self.scheme, self.host, self.canonical_uri(object)),
+        }
+    }
+}
+
+// Cloudflare R2 uses the S3 API at an account-specific endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct R2Config {
+    pub account_id: String,
+    pub bucket: String,
+    pub access_key_id: String,
+    #[serde(default)]
+    pub secret_access_key: String,
+    // r2.dev subdomain or custom domain the bucket is published on, if any
+    #[serde(default)]
+    pub public_url: Option<String>,
+}
+
+impl R2Config {
+    pub fn redacted(&self) -> R2Config {
+        R2Config { secret_access_key: String::new(), ..self.clone() }
+    }
+
+    // As `S3Config::keep_secret`
+    pub fn keep_secret(self, stored: &R2Config) -> R2Config {
+        if self.secret_access_key.is_empty() && self.access_key_id == stored.access_key_id {
+            R2Config { secret_access_key: stored.secret_access_key.clone(), ..self }
+        } else {
+            self
+        }
+    }
+
+    // R2 takes "auto" as its region and path-style addressing
+    pub fn to_s3(&self) -> S3Config {
+        S3Config {
+            endpoint: format!("https://{}.r2.cloudflarestorage.com", self.account_id),
+            region: "auto".to_string(),
+            bucket: self.bucket.clone(),
+            access_key_id: self.access_key_id.clone(),
+            secret_access_key: self.secret_access_key.clone(),
+            path_style: true,
+            public_url: self.public_url.clone(),
+        }
+    }
+}
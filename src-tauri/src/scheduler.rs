@@ -0,0 +1,308 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+use tokio::sync::Notify;
+use crate::crosslist::{self, CrossListTarget, Marketplace};
+use crate::db::Database;
+use crate::ebay::EbayTaxonomy;
+use crate::etsy::EtsyAuth;
+use crate::listing::Listing;
+
+// Emitted with a `ScheduledListing` whenever a job changes status
+const STATUS_EVENT: &str = "scheduler://status";
+
+// Longest the worker sleeps before checking for due jobs again, in case the
+// clock jumps, e.g. after the computer wakes
+const IDLE_POLL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleStatus {
+    Scheduled,
+    Publishing,
+    Published,
+    Failed,
+}
+
+impl ScheduleStatus {
+    fn parse(status: &str) -> ScheduleStatus {
+        match status {
+            "publishing" => ScheduleStatus::Publishing,
+            "published" => ScheduleStatus::Published,
+            "failed" => ScheduleStatus::Failed,
+            _ => ScheduleStatus::Scheduled,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScheduledListing {
+    pub id: i64,
+    // The caller's ID for the listing; one job per listing and marketplace
+    pub listing_id: String,
+    pub marketplace: Marketplace,
+    pub title: String,
+    // RFC 3339
+    pub publish_at: String,
+    pub status: ScheduleStatus,
+    // Set once published: the live listing or draft, or the export package
+    pub url: Option<String>,
+    pub folder: Option<String>,
+    pub error: Option<String>,
+    // When it was published or failed
+    pub finished_at: Option<String>,
+}
+
+const JOB_COLUMNS: &str = "id, listing_id, marketplace, title, publish_at, status, url, folder, error, finished_at";
+
+fn job_from_row(row: &Row) -> rusqlite::Result<ScheduledListing> {
+    let marketplace: String = row.get(2)?;
+    Ok(ScheduledListing {
+        id: row.get(0)?,
+        listing_id: row.get(1)?,
+        marketplace: serde_json::from_value(serde_json::Value::String(marketplace))
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, Box::new(e)))?,
+        title: row.get(3)?,
+        publish_at: row.get(4)?,
+        status: ScheduleStatus::parse(&row.get::<_, String>(5)?),
+        url: row.get(6)?,
+        folder: row.get(7)?,
+        error: row.get(8)?,
+        finished_at: row.get(9)?,
+    })
+}
+
+// Whole seconds in UTC, so times stored as text sort in time order
+fn timestamp(time: DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn marketplace_name(marketplace: Marketplace) -> String {
+    serde_json::to_value(marketplace)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+// Listings to publish at a set time, stored in SQLite with the listing
+// itself. Jobs that came due while the app was closed are published when
+// it next starts.
+pub struct Scheduler {
+    conn: Arc<Mutex<Connection>>,
+    wake: Notify,
+}
+
+impl Scheduler {
+    // Jobs that were being published when the app last stopped are marked
+    // failed, since they may or may not have gone live.
+    pub fn new(db: &Database) -> Result<Scheduler, String> {
+        db.lock()?
+            .execute(
+                "UPDATE scheduled_listings SET status = 'failed', error = 'Interrupted while publishing' WHERE status = 'publishing'",
+                [],
+            )
+            .map_err(|e| format!("Failed to initialize schedule: {}", e))?;
+        Ok(Scheduler { conn: db.connection(), wake: Notify::new() })
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Connection>, String> {
+        self.conn.lock().map_err(|_| "Schedule lock poisoned".to_string())
+    }
+
+    fn job(conn: &Connection, id: i64) -> Result<ScheduledListing, String> {
+        conn.query_row(
+            &format!("SELECT {} FROM scheduled_listings WHERE id = ?1", JOB_COLUMNS),
+            params![id],
+            job_from_row,
+        )
+        .map_err(|e| format!("Failed to read scheduled listing {}: {}", id, e))
+    }
+
+    // Schedule a listing, replacing a job for the same listing and
+    // marketplace that hasn't run yet
+    fn add(&self, listing_id: &str, target: &CrossListTarget, publish_at: DateTime<Utc>) -> Result<ScheduledListing, String> {
+        let marketplace = marketplace_name(target.marketplace());
+        let json = serde_json::to_string(target)
+            .map_err(|e| format!("Failed to serialize listing: {}", e))?;
+        let mut conn = self.lock()?;
+        let tx = conn.transaction()
+            .map_err(|e| format!("Failed to start schedule transaction: {}", e))?;
+        tx.execute(
+            "DELETE FROM scheduled_listings WHERE listing_id = ?1 AND marketplace = ?2 AND status = 'scheduled'",
+            params![listing_id, marketplace],
+        )
+        .map_err(|e| format!("Failed to update schedule: {}", e))?;
+        tx.execute(
+            "INSERT INTO scheduled_listings (listing_id, marketplace, title, target, publish_at, status)
+             VALUES (?1, ?2, ?3, ?4, ?5, 'scheduled')",
+            params![listing_id, marketplace, target.title(), json, timestamp(publish_at)],
+        )
+        .map_err(|e| format!("Failed to schedule listing: {}", e))?;
+        let id = tx.last_insert_rowid();
+        tx.commit()
+            .map_err(|e| format!("Failed to commit schedule: {}", e))?;
+        Scheduler::job(&conn, id)
+    }
+
+    fn jobs(&self, listing_id: Option<&str>) -> Result<Vec<ScheduledListing>, String> {
+        let conn = self.lock()?;
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {} FROM scheduled_listings WHERE ?1 IS NULL OR listing_id = ?1 ORDER BY publish_at, id",
+                JOB_COLUMNS
+            ))
+            .map_err(|e| format!("Failed to read schedule: {}", e))?;
+        let rows = stmt
+            .query_map(params![listing_id], job_from_row)
+            .map_err(|e| format!("Failed to read schedule: {}", e))?;
+        rows.collect::<Result<_, _>>()
+            .map_err(|e| format!("Failed to read schedule: {}", e))
+    }
+
+    // Remove a job that hasn't run yet; returns whether there was one
+    fn cancel(&self, id: i64) -> Result<bool, String> {
+        let conn = self.lock()?;
+        let removed = conn
+            .execute("DELETE FROM scheduled_listings WHERE id = ?1 AND status = 'scheduled'", params![id])
+            .map_err(|e| format!("Failed to update schedule: {}", e))?;
+        Ok(removed > 0)
+    }
+
+    // Mark the earliest due job as publishing and return it with its listing
+    fn claim_due(&self) -> Result<Option<(ScheduledListing, CrossListTarget)>, String> {
+        let conn = self.lock()?;
+        let due: Option<(i64, String)> = conn
+            .query_row(
+                "SELECT id, target FROM scheduled_listings
+                 WHERE status = 'scheduled' AND publish_at <= ?1
+                 ORDER BY publish_at, id LIMIT 1",
+                params![timestamp(Utc::now())],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(|e| format!("Failed to read schedule: {}", e))?;
+        let Some((id, json)) = due else {
+            return Ok(None);
+        };
+        conn.execute("UPDATE scheduled_listings SET status = 'publishing' WHERE id = ?1", params![id])
+            .map_err(|e| format!("Failed to update scheduled listing {}: {}", id, e))?;
+        let job = Scheduler::job(&conn, id)?;
+        match serde_json::from_str(&json) {
+            Ok(target) => Ok(Some((job, target))),
+            Err(e) => {
+                drop(conn);
+                self.finish(id, Err(format!("Failed to parse stored listing: {}", e)))?;
+                Ok(None)
+            }
+        }
+    }
+
+    // How long until the next job is due; `None` when nothing is scheduled
+    fn next_due_in(&self) -> Result<Option<Duration>, String> {
+        let conn = self.lock()?;
+        let next: Option<String> = conn
+            .query_row(
+                "SELECT MIN(publish_at) FROM scheduled_listings WHERE status = 'scheduled'",
+                [],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Failed to read schedule: {}", e))?;
+        Ok(next
+            .and_then(|next| DateTime::parse_from_rfc3339(&next).ok())
+            .map(|next| (next.with_timezone(&Utc) - Utc::now()).to_std().unwrap_or(Duration::ZERO)))
+    }
+
+    fn finish(&self, id: i64, result: Result<crosslist::CrossListResult, String>) -> Result<ScheduledListing, String> {
+        let conn = self.lock()?;
+        let now = Utc::now().to_rfc3339();
+        match result {
+            Ok(listed) => conn.execute(
+                "UPDATE scheduled_listings SET status = 'published', url = ?2, folder = ?3, error = ?4, finished_at = ?5
+                 WHERE id = ?1",
+                params![id, listed.url, listed.folder, listed.error, now],
+            ),
+            Err(error) => conn.execute(
+                "UPDATE scheduled_listings SET status = 'failed', error = ?2, finished_at = ?3 WHERE id = ?1",
+                params![id, error, now],
+            ),
+        }
+        .map_err(|e| format!("Failed to update scheduled listing {}: {}", id, e))?;
+        Scheduler::job(&conn, id)
+    }
+}
+
+fn emit_status(app: &AppHandle, job: &ScheduledListing) {
+    let _ = app.emit_all(STATUS_EVENT, job);
+}
+
+// Start the background worker that publishes scheduled listings as they
+// come due, one at a time. Overdue ones, e.g. from while the app was
+// closed, go first. Call once the scheduler is managed.
+pub fn start(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let scheduler = app.state::<Scheduler>();
+            let (job, target) = match scheduler.claim_due() {
+                Ok(Some(due)) => due,
+                Ok(None) | Err(_) => {
+                    let wait = match scheduler.next_due_in() {
+                        Ok(Some(wait)) => wait.min(IDLE_POLL),
+                        _ => IDLE_POLL,
+                    };
+                    let _ = tokio::time::timeout(wait, scheduler.wake.notified()).await;
+                    continue;
+                }
+            };
+            emit_status(&app, &job);
+
+            let taxonomy = app.state::<EbayTaxonomy>();
+            let etsy_auth = app.state::<EtsyAuth>();
+            let result = crosslist::list_on(&app, &taxonomy, &etsy_auth, &target, None).await;
+            if let Ok(job) = scheduler.finish(job.id, result) {
+                emit_status(&app, &job);
+            }
+        }
+    });
+}
+
+// Command to publish a listing at a set time: through the API for eBay and
+// Etsy, or as an export package for the others, as `cross_list` does, with
+// the marketplace's own fields from the listing's `marketplace_fields`.
+// `publish_at` is an RFC 3339 time; a time in the past publishes right away.
+// Scheduling the same listing and marketplace again replaces the job.
+// Progress is emitted as `scheduler://status` events.
+#[tauri::command]
+pub fn schedule_listing(
+    scheduler: State<Scheduler>,
+    listing_id: String,
+    listing: Listing,
+    marketplace: Marketplace,
+    publish_at: String,
+) -> Result<ScheduledListing, String> {
+    if listing_id.trim().is_empty() {
+        return Err("Listing ID is required".to_string());
+    }
+    let target = crosslist::target(&listing, marketplace)?;
+    let publish_at = DateTime::parse_from_rfc3339(publish_at.trim())
+        .map_err(|e| format!("Invalid publish time \"{}\": {}", publish_at, e))?
+        .with_timezone(&Utc);
+    let job = scheduler.add(listing_id.trim(), &target, publish_at)?;
+    scheduler.wake.notify_one();
+    Ok(job)
+}
+
+// Command to list scheduled listings, for one listing or all, soonest first
+#[tauri::command]
+pub fn list_scheduled_listings(scheduler: State<Scheduler>, listing_id: Option<String>) -> Result<Vec<ScheduledListing>, String> {
+    scheduler.jobs(listing_id.as_deref())
+}
+
+// Command to cancel a scheduled listing before it's published; returns
+// whether it was still waiting
+#[tauri::command]
+pub fn cancel_scheduled_listing(scheduler: State<Scheduler>, id: i64) -> Result<bool, String> {
+    scheduler.cancel(id)
+}
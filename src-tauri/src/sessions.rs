@@ -0,0 +1,107 @@
+use std::sync::{Arc, Mutex};
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{de::DeserializeOwned, Serialize};
+use crate::db::Database;
+
+// Session the latest grouping run and manual corrections are kept in
+pub const CURRENT_SESSION: &str = "current";
+
+// A manual decision that two photos do (or don't) show the same item
+pub struct Correction {
+    pub photo_a: String,
+    pub photo_b: String,
+    pub together: bool,
+}
+
+// Grouping sessions and manual grouping corrections, stored in SQLite so they
+// survive app restarts. Unlike the hash cache this is user data and is never
+// rebuilt.
+pub struct SessionStore {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SessionStore {
+    pub fn new(db: &Database) -> SessionStore {
+        SessionStore { conn: db.connection() }
+    }
+
+    // Load a session's groups, or None if no session has that name
+    pub fn load<T: DeserializeOwned>(&self, name: &str) -> Result<Option<T>, String> {
+        let conn = self.conn.lock()
+            .map_err(|_| "Session store lock poisoned".to_string())?;
+        let groups: Option<String> = conn
+            .query_row(
+                "SELECT groups FROM grouping_sessions WHERE name = ?1",
+                params![name],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| format!("Failed to read session {}: {}", name, e))?;
+
+        groups
+            .map(|json| serde_json::from_str(&json)
+                .map_err(|e| format!("Session {} is corrupt: {}", name, e)))
+            .transpose()
+    }
+
+    // Save a session's groups, replacing any earlier copy
+    pub fn save<T: Serialize>(&self, name: &str, groups: &T) -> Result<(), String> {
+        let json = serde_json::to_string(groups)
+            .map_err(|e| format!("Failed to serialize session: {}", e))?;
+        let conn = self.conn.lock()
+            .map_err(|_| "Session store lock poisoned".to_string())?;
+        conn.execute(
+            "INSERT OR REPLACE INTO grouping_sessions (name, groups, updated_at) VALUES (?1, ?2, ?3)",
+            params![name, json, Utc::now().to_rfc3339()],
+        ).map_err(|e| format!("Failed to save session {}: {}", name, e))?;
+        Ok(())
+    }
+
+    // All manual corrections, photo pairs stored in sorted order
+    pub fn corrections(&self) -> Result<Vec<Correction>, String> {
+        let conn = self.conn.lock()
+            .map_err(|_| "Session store lock poisoned".to_string())?;
+        let mut stmt = conn
+            .prepare("SELECT photo_a, photo_b, together FROM grouping_corrections")
+            .map_err(|e| format!("Failed to read corrections: {}", e))?;
+        let rows = stmt
+            .query_map([], |row| Ok(Correction {
+                photo_a: row.get(0)?,
+                photo_b: row.get(1)?,
+                together: row.get(2)?,
+            }))
+            .map_err(|e| format!("Failed to read corrections: {}", e))?;
+        rows.collect::<Result<_, _>>()
+            .map_err(|e| format!("Failed to read corrections: {}", e))
+    }
+
+    // Record corrections in a single transaction; newer decisions about a pair
+    // replace older ones
+    pub fn put_corrections(&self, corrections: &[Correction]) -> Result<(), String> {
+        let mut conn = self.conn.lock()
+            .map_err(|_| "Session store lock poisoned".to_string())?;
+        let tx = conn.transaction()
+            .map_err(|e| format!("Failed to start session transaction: {}", e))?;
+
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO grouping_corrections (photo_a, photo_b, together)
+                 VALUES (?1, ?2, ?3)"
+            ).map_err(|e| format!("Failed to prepare correction insert: {}", e))?;
+
+            for correction in corrections {
+                let (a, b) = if correction.photo_a <= correction.photo_b {
+                    (&correction.photo_a, &correction.photo_b)
+                } else {
+                    (&correction.photo_b, &correction.photo_a)
+                };
+                stmt.execute(params![a, b, correction.together])
+                    .map_err(|e| format!("Failed to write correction: {}", e))?;
+            }
+        }
+
+        tx.commit()
+            .map_err(|e| format!("Failed to commit corrections: {}", e))
+    }
+}
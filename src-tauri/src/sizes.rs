@@ -0,0 +1,536 @@
+use serde::{Deserialize, Serialize};
+
+// Kind of item a size tag belongs to; size systems differ between them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SizeCategory {
+    // Clothing of unknown gender; numeric sizes aren't converted
+    Clothing,
+    WomensClothing,
+    MensClothing,
+    KidsClothing,
+    WomensShoes,
+    MensShoes,
+    // Shoes of unknown gender; numeric sizes aren't converted
+    Shoes,
+}
+
+impl SizeCategory {
+    fn is_shoes(self) -> bool {
+        matches!(self, SizeCategory::WomensShoes | SizeCategory::MensShoes | SizeCategory::Shoes)
+    }
+
+    // Offsets from the UK size to the US and EU sizes, where they are fixed
+    fn offsets(self) -> Option<(f64, f64)> {
+        match self {
+            // UK 10 = US 6 = EU 38
+            SizeCategory::WomensClothing => Some((-4.0, 28.0)),
+            // Chest in inches, the same in the UK and US; UK 40 = EU 50
+            SizeCategory::MensClothing => Some((0.0, 10.0)),
+            // UK 5 = US 7 = EU 38
+            SizeCategory::WomensShoes => Some((2.0, 33.0)),
+            // UK 9 = US 10 = EU 43
+            SizeCategory::MensShoes => Some((1.0, 34.0)),
+            _ => None,
+        }
+    }
+
+    // Smallest number that is an EU size rather than a UK or US one. Men's
+    // chest sizes overlap EU sizes, so there is none for them.
+    fn eu_minimum(self) -> Option<f64> {
+        match self {
+            SizeCategory::MensClothing => None,
+            _ if self.is_shoes() => Some(34.0),
+            _ => Some(30.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Region {
+    Us,
+    Uk,
+    Eu,
+}
+
+fn region_label(token: &str) -> Option<Region> {
+    match token {
+        "US" | "USA" => Some(Region::Us),
+        "UK" | "AU" | "AUS" => Some(Region::Uk),
+        "EU" | "EUR" | "EUROPE" => Some(Region::Eu),
+        _ => None,
+    }
+}
+
+// A single size or a range like 10-12
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SizeValue {
+    low: f64,
+    high: Option<f64>,
+}
+
+impl SizeValue {
+    fn parse(text: &str) -> Option<SizeValue> {
+        let number = |part: &str| part.parse::<f64>().ok().filter(|n| (0.0..200.0).contains(n));
+        match text.split_once(['-', '/']) {
+            Some((low, high)) => {
+                let (low, high) = (number(low)?, number(high)?);
+                (high > low).then_some(SizeValue { low, high: Some(high) })
+            }
+            None => Some(SizeValue { low: number(text)?, high: None }),
+        }
+    }
+
+    fn shifted(self, offset: f64) -> SizeValue {
+        SizeValue { low: self.low + offset, high: self.high.map(|high| high + offset) }
+    }
+
+    fn is_whole(self) -> bool {
+        self.low.fract() == 0.0 && self.high.is_none_or(|high| high.fract() == 0.0)
+    }
+}
+
+impl std::fmt::Display for SizeValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.high {
+            Some(high) => write!(f, "{}-{}", self.low, high),
+            None => write!(f, "{}", self.low),
+        }
+    }
+}
+
+// Letter sizes, normalized to their short form
+fn letter_size(token: &str) -> Option<&'static str> {
+    Some(match token {
+        "XXXS" | "3XS" => "XXXS",
+        "XXS" | "2XS" => "XXS",
+        "XS" | "X-SMALL" => "XS",
+        "S" | "SMALL" => "S",
+        "M" | "MEDIUM" => "M",
+        "L" | "LARGE" => "L",
+        "XL" | "X-LARGE" => "XL",
+        "XXL" | "2XL" | "XX-LARGE" => "XXL",
+        "XXXL" | "3XL" => "XXXL",
+        "4XL" | "XXXXL" => "4XL",
+        "5XL" => "5XL",
+        _ => return None,
+    })
+}
+
+// Waist or length in inches, as on jeans
+fn inches(text: &str) -> Option<u32> {
+    text.parse::<u32>().ok().filter(|n| (22..=50).contains(n))
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct SizeAttribute {
+    pub letter: Option<String>,
+    pub us: Option<String>,
+    pub uk: Option<String>,
+    pub eu: Option<String>,
+    // Jeans and trousers, in inches
+    pub waist: Option<u32>,
+    pub length: Option<u32>,
+    // Kids' sizes, e.g. "10-12 years" or "3-6 months"
+    pub age: Option<String>,
+    // Numbers without a region that couldn't be placed
+    pub unlabeled: Vec<String>,
+    // Regions ("us", "uk", "eu") filled in by conversion rather than read
+    pub converted: Vec<String>,
+    // Listing-ready size, e.g. "M (UK 10-12, EU 38)"
+    pub display: Option<String>,
+}
+
+#[derive(Default)]
+struct ParsedSizes {
+    letter: Option<String>,
+    regions: Vec<(Region, SizeValue)>,
+    waist: Option<u32>,
+    length: Option<u32>,
+    age: Option<String>,
+    unlabeled: Vec<SizeValue>,
+}
+
+impl ParsedSizes {
+    fn region(&self, region: Region) -> Option<SizeValue> {
+        self.regions.iter().find(|(r, _)| *r == region).map(|(_, value)| *value)
+    }
+
+    fn set_region(&mut self, region: Region, value: SizeValue) {
+        if self.region(region).is_none() {
+            self.regions.push((region, value));
+        }
+    }
+}
+
+// Split tag text into upper-case tokens, keeping ranges ("10-12"), decimals
+// ("8.5") and labels stuck to numbers ("UK10") together
+fn tokenize(text: &str) -> Vec<String> {
+    text.to_uppercase()
+        .replace('½', ".5")
+        .replace(" - ", "-")
+        .split(|c: char| c.is_whitespace() || matches!(c, ',' | ';' | ':' | '(' | ')' | '|' | '='))
+        .filter(|token| !token.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+// Split a label glued to a number, e.g. "EUR38" or "38EU"
+fn split_label(token: &str) -> Option<(Region, &str)> {
+    let digits_start = token.find(|c: char| c.is_ascii_digit())?;
+    let (head, tail) = token.split_at(digits_start);
+    if let Some(region) = region_label(head) {
+        return Some((region, tail));
+    }
+    let digits_end = token.rfind(|c: char| c.is_ascii_digit())? + 1;
+    let (value, label) = token.split_at(digits_end);
+    region_label(label).filter(|_| digits_start == 0).map(|region| (region, value))
+}
+
+fn parse_tokens(tokens: &[String], category: SizeCategory) -> ParsedSizes {
+    let mut parsed = ParsedSizes::default();
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = tokens[i].as_str();
+        let next = tokens.get(i + 1).map(String::as_str);
+
+        // "UK 10", "EU 38"
+        if let Some(region) = region_label(token) {
+            if let Some(value) = next.and_then(SizeValue::parse) {
+                parsed.set_region(region, value);
+                i += 2;
+                continue;
+            }
+        }
+        // "UK10", "38EU"
+        if let Some((region, value)) = split_label(token) {
+            if let Some(value) = SizeValue::parse(value) {
+                parsed.set_region(region, value);
+                i += 1;
+                continue;
+            }
+        }
+        // "AGE 10-12", "10-12 YEARS", "10-12Y", "3-6M"
+        if token == "AGE" {
+            if let Some(value) = next.and_then(SizeValue::parse) {
+                parsed.age = Some(format!("{} years", value));
+                i += 2;
+                continue;
+            }
+        }
+        let (number, suffix) = token.split_at(token.rfind(|c: char| c.is_ascii_digit()).map_or(0, |end| end + 1));
+        if let Some(value) = SizeValue::parse(number) {
+            let months = matches!(suffix, "M" | "MO" | "MTH" | "MTHS" | "MONTHS");
+            let years = matches!(suffix, "Y" | "YR" | "YRS" | "YEARS");
+            let unit_next = next.filter(|_| suffix.is_empty());
+            if months || (number.contains('-') && unit_next.is_some_and(|n| n.starts_with("MONTH") || n == "M")) {
+                parsed.age = Some(format!("{} months", value));
+                i += if months { 1 } else { 2 };
+                continue;
+            }
+            if years || unit_next.is_some_and(|n| matches!(n, "Y" | "YR" | "YRS" | "YEARS")) {
+                parsed.age = Some(format!("{} years", value));
+                i += if years { 1 } else { 2 };
+                continue;
+            }
+            // "38 EU", unless the label goes with the number after it
+            let label_leads = tokens.get(i + 2).is_some_and(|after| SizeValue::parse(after).is_some());
+            if let Some(region) = unit_next.and_then(region_label).filter(|_| !label_leads) {
+                parsed.set_region(region, value);
+                i += 2;
+                continue;
+            }
+        }
+        // "W32 L30", "32W 30L"
+        if let Some(waist) = token.strip_prefix('W').or_else(|| token.strip_suffix('W')).and_then(inches) {
+            parsed.waist = Some(waist);
+            i += 1;
+            continue;
+        }
+        if let Some(length) = token.strip_prefix('L').or_else(|| token.strip_suffix('L')).and_then(inches) {
+            parsed.length = Some(length);
+            i += 1;
+            continue;
+        }
+        // "32X30", or "32/30" on trousers (on women's tags it's a range)
+        if let Some((waist, length)) = token.split_once(['X', '/']) {
+            if let (Some(waist), Some(length)) = (inches(waist), inches(length)) {
+                let trousers = token.contains('X') || !matches!(category, SizeCategory::WomensClothing | SizeCategory::KidsClothing);
+                if trousers && !category.is_shoes() {
+                    parsed.waist = Some(waist);
+                    parsed.length = Some(length);
+                    i += 1;
+                    continue;
+                }
+            }
+        }
+        // "M/L", "S-M": a size between two letter sizes
+        let between = token
+            .split_once(['/', '-'])
+            .and_then(|(low, high)| Some(format!("{}/{}", letter_size(low)?, letter_size(high)?)));
+        if let Some(letter) = between.or_else(|| letter_size(token).map(str::to_string)) {
+            parsed.letter.get_or_insert(letter);
+        } else if let Some(value) = SizeValue::parse(token) {
+            parsed.unlabeled.push(value);
+        }
+        i += 1;
+    }
+    parsed
+}
+
+// Give unlabeled numbers a region where the category makes it clear: EU
+// sizes are much larger, and a number matching a labeled size converted
+// to another region is that region's size
+fn place_unlabeled(parsed: &mut ParsedSizes, category: SizeCategory) {
+    let offsets = category.offsets();
+    let mut remaining = Vec::new();
+    for value in std::mem::take(&mut parsed.unlabeled) {
+        if category.eu_minimum().is_some_and(|minimum| value.low >= minimum) && parsed.region(Region::Eu).is_none() && offsets.is_some() {
+            parsed.set_region(Region::Eu, value);
+            continue;
+        }
+        let placed = offsets.and_then(|(us, eu)| {
+            let uk = parsed
+                .region(Region::Uk)
+                .or_else(|| parsed.region(Region::Eu).map(|v| v.shifted(-eu)))
+                .or_else(|| parsed.region(Region::Us).map(|v| v.shifted(-us)))?;
+            let close = |a: SizeValue, b: SizeValue| (a.low - b.low).abs() <= 1.0;
+            if parsed.region(Region::Uk).is_none() && close(value, uk) {
+                Some(Region::Uk)
+            } else if parsed.region(Region::Us).is_none() && close(value, uk.shifted(us)) {
+                Some(Region::Us)
+            } else {
+                None
+            }
+        });
+        match placed {
+            Some(region) => parsed.set_region(region, value),
+            None => remaining.push(value),
+        }
+    }
+    parsed.unlabeled = remaining;
+}
+
+// Fill in missing regions from the ones on the tag
+fn convert_regions(parsed: &mut ParsedSizes, category: SizeCategory) -> Vec<String> {
+    let Some((us, eu)) = category.offsets() else {
+        return Vec::new();
+    };
+    let uk = parsed
+        .region(Region::Uk)
+        .or_else(|| parsed.region(Region::Eu).map(|v| v.shifted(-eu)))
+        .or_else(|| parsed.region(Region::Us).map(|v| v.shifted(-us)));
+    let Some(uk) = uk else {
+        return Vec::new();
+    };
+
+    let mut converted = Vec::new();
+    for (region, value, name) in [
+        (Region::Uk, uk, "uk"),
+        (Region::Us, uk.shifted(us), "us"),
+        (Region::Eu, uk.shifted(eu), "eu"),
+    ] {
+        // Clothing sizes are whole numbers; a fractional result means the
+        // tag's numbers don't follow the usual system
+        let plausible = value.low > 0.0 && (category.is_shoes() || value.is_whole());
+        if parsed.region(region).is_none() && plausible {
+            parsed.set_region(region, value);
+            converted.push(name.to_string());
+        }
+    }
+    converted
+}
+
+fn display(parsed: &ParsedSizes, category: SizeCategory) -> Option<String> {
+    let regions: Vec<String> = [(Region::Uk, "UK"), (Region::Us, "US"), (Region::Eu, "EU")]
+        .iter()
+        .filter_map(|(region, label)| parsed.region(*region).map(|value| format!("{} {}", label, value)))
+        .collect();
+    let trousers = match (parsed.waist, parsed.length) {
+        (Some(waist), Some(length)) => Some(format!("W{} L{}", waist, length)),
+        (Some(waist), None) => Some(format!("W{}", waist)),
+        _ => None,
+    };
+
+    let main = parsed
+        .letter
+        .clone()
+        .or_else(|| trousers.clone())
+        .or_else(|| parsed.age.clone())
+        .or_else(|| regions.first().cloned())?;
+    let mut details: Vec<String> = Vec::new();
+    if parsed.letter.is_some() {
+        details.extend(trousers);
+    }
+    if category == SizeCategory::KidsClothing || parsed.letter.is_some() || parsed.waist.is_some() {
+        details.extend(parsed.age.clone().filter(|age| *age != main));
+    }
+    details.extend(regions.into_iter().filter(|region| *region != main));
+
+    if details.is_empty() {
+        Some(main)
+    } else {
+        Some(format!("{} ({})", main, details.join(", ")))
+    }
+}
+
+// Parse size text read off a tag, e.g. "M 10-12 EUR 38", into a structured
+// size, converting between US, UK and EU sizes where the category has a
+// fixed conversion
+pub fn parse_size(text: &str, category: SizeCategory) -> SizeAttribute {
+    let mut parsed = parse_tokens(&tokenize(text), category);
+    place_unlabeled(&mut parsed, category);
+    let converted = convert_regions(&mut parsed, category);
+
+    SizeAttribute {
+        letter: parsed.letter.clone(),
+        us: parsed.region(Region::Us).map(|value| value.to_string()),
+        uk: parsed.region(Region::Uk).map(|value| value.to_string()),
+        eu: parsed.region(Region::Eu).map(|value| value.to_string()),
+        waist: parsed.waist,
+        length: parsed.length,
+        age: parsed.age.clone(),
+        unlabeled: parsed.unlabeled.iter().map(|value| value.to_string()).collect(),
+        converted,
+        display: display(&parsed, category),
+    }
+}
+
+// Command to turn OCR text from a size tag into a structured size attribute
+// `category` defaults to clothing of unknown gender, which reads sizes
+// without converting them
+#[tauri::command]
+pub fn parse_size_text(ocr_text: String, category: Option<SizeCategory>) -> SizeAttribute {
+    parse_size(&ocr_text, category.unwrap_or(SizeCategory::Clothing))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn letter_sizes() {
+        assert_eq!(parse_size("M", SizeCategory::Clothing).display.as_deref(), Some("M"));
+        assert_eq!(parse_size("x-large", SizeCategory::Clothing).letter.as_deref(), Some("XL"));
+        assert_eq!(parse_size("2XL", SizeCategory::MensClothing).letter.as_deref(), Some("XXL"));
+    }
+
+    #[test]
+    fn labeled_regions() {
+        let size = parse_size("UK 10", SizeCategory::WomensClothing);
+        assert_eq!(size.uk.as_deref(), Some("10"));
+        assert_eq!(size.us.as_deref(), Some("6"));
+        assert_eq!(size.eu.as_deref(), Some("38"));
+        assert_eq!(size.converted, strings(&["us", "eu"]));
+        assert_eq!(size.display.as_deref(), Some("UK 10 (US 6, EU 38)"));
+
+        // Labels glued to the number, before or after it
+        assert_eq!(parse_size("EUR38", SizeCategory::WomensClothing).uk.as_deref(), Some("10"));
+        assert_eq!(parse_size("38EU", SizeCategory::WomensClothing).uk.as_deref(), Some("10"));
+        assert_eq!(parse_size("40 EU", SizeCategory::WomensClothing).uk.as_deref(), Some("12"));
+    }
+
+    #[test]
+    fn letter_with_ranges_and_unlabeled_numbers() {
+        let size = parse_size("M 10-12 EUR 38", SizeCategory::WomensClothing);
+        assert_eq!(size.letter.as_deref(), Some("M"));
+        assert_eq!(size.uk.as_deref(), Some("10-12"));
+        assert_eq!(size.us.as_deref(), Some("6-8"));
+        assert_eq!(size.eu.as_deref(), Some("38"));
+        assert!(size.unlabeled.is_empty());
+        assert_eq!(size.display.as_deref(), Some("M (UK 10-12, US 6-8, EU 38)"));
+    }
+
+    #[test]
+    fn mens_chest_sizes() {
+        let size = parse_size("UK 40", SizeCategory::MensClothing);
+        assert_eq!(size.us.as_deref(), Some("40"));
+        assert_eq!(size.eu.as_deref(), Some("50"));
+    }
+
+    #[test]
+    fn shoe_sizes() {
+        let size = parse_size("US 8½", SizeCategory::WomensShoes);
+        assert_eq!(size.us.as_deref(), Some("8.5"));
+        assert_eq!(size.uk.as_deref(), Some("6.5"));
+        assert_eq!(size.eu.as_deref(), Some("39.5"));
+
+        let size = parse_size("UK 9 EUR 43", SizeCategory::MensShoes);
+        assert_eq!(size.us.as_deref(), Some("10"));
+        assert_eq!(size.converted, strings(&["us"]));
+
+        // Unknown gender: read, not converted
+        let size = parse_size("EU 42", SizeCategory::Shoes);
+        assert_eq!(size.eu.as_deref(), Some("42"));
+        assert_eq!(size.uk, None);
+        assert!(size.converted.is_empty());
+    }
+
+    #[test]
+    fn waist_and_length() {
+        for text in ["W32 L30", "32W 30L", "32x30", "32/30"] {
+            let size = parse_size(text, SizeCategory::MensClothing);
+            assert_eq!((size.waist, size.length), (Some(32), Some(30)), "{}", text);
+            assert_eq!(size.display.as_deref(), Some("W32 L30"), "{}", text);
+        }
+        assert_eq!(parse_size("W30", SizeCategory::Clothing).display.as_deref(), Some("W30"));
+        // On women's tags 28/30 is a range of sizes, not waist and length
+        let size = parse_size("28/30", SizeCategory::WomensClothing);
+        assert_eq!(size.waist, None);
+        assert_eq!(size.unlabeled, strings(&["28-30"]));
+    }
+
+    #[test]
+    fn kids_ages() {
+        assert_eq!(parse_size("10-12 years", SizeCategory::KidsClothing).age.as_deref(), Some("10-12 years"));
+        assert_eq!(parse_size("10-12Y", SizeCategory::KidsClothing).age.as_deref(), Some("10-12 years"));
+        assert_eq!(parse_size("Age 7", SizeCategory::KidsClothing).age.as_deref(), Some("7 years"));
+        assert_eq!(parse_size("3-6M", SizeCategory::KidsClothing).age.as_deref(), Some("3-6 months"));
+        assert_eq!(parse_size("3-6 months", SizeCategory::KidsClothing).age.as_deref(), Some("3-6 months"));
+        // A lone M after a number is medium only when it isn't a range
+        assert_eq!(parse_size("S 8 M", SizeCategory::Clothing).letter.as_deref(), Some("S"));
+    }
+
+    #[test]
+    fn bare_number_is_left_unlabeled() {
+        // "8" could be a US or UK size; nothing on the tag says which
+        for category in [SizeCategory::Clothing, SizeCategory::WomensClothing] {
+            let size = parse_size("8", category);
+            assert_eq!(size.unlabeled, strings(&["8"]));
+            assert_eq!((size.us, size.uk, size.eu), (None, None, None));
+            assert!(size.converted.is_empty());
+            assert_eq!(size.display, None);
+        }
+    }
+
+    #[test]
+    fn size_between_letters() {
+        assert_eq!(parse_size("M/L", SizeCategory::Clothing).letter.as_deref(), Some("M/L"));
+        assert_eq!(parse_size("s-m", SizeCategory::Clothing).display.as_deref(), Some("S/M"));
+    }
+
+    #[test]
+    fn waist_and_length_with_x() {
+        // "32x30" is waist and length whatever the category, shoes aside
+        let size = parse_size("32x30", SizeCategory::WomensClothing);
+        assert_eq!((size.waist, size.length), (Some(32), Some(30)));
+        assert!(size.unlabeled.is_empty());
+        let size = parse_size("32x30", SizeCategory::Shoes);
+        assert_eq!(size.waist, None);
+    }
+
+    #[test]
+    fn unplaced_numbers_beside_labels() {
+        // 8 isn't within one of UK 10 or its US 6, so it can't be placed
+        let size = parse_size("UK 10 8", SizeCategory::WomensClothing);
+        assert_eq!(size.unlabeled, strings(&["8"]));
+        // 6 is the US size of UK 10
+        let size = parse_size("UK 10 6", SizeCategory::WomensClothing);
+        assert!(size.unlabeled.is_empty());
+        assert!(size.converted.iter().all(|region| region != "us"));
+    }
+}
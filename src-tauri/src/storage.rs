@@ -0,0 +1,717 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use futures_util::{Stream, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, Window};
+use tokio::io::AsyncWriteExt;
+use tokio_util::io::ReaderStream;
+use crate::crosslist::Marketplace;
+use crate::ebay;
+use crate::ebay_auth;
+use crate::gcs::{self, GcsProvider};
+use crate::image_io;
+use crate::keychain;
+use crate::network;
+use crate::b2::{B2Config, B2Provider};
+use crate::s3::{R2Config, S3Config, S3Provider};
+
+// Upload attempts before giving up on transient failures
+pub const MAX_UPLOAD_ATTEMPTS: u32 = 5;
+
+// Delay before the first retry, doubled after each failed attempt
+pub const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+// Read size for streamed uploads; a progress event is sent per chunk
+pub const UPLOAD_CHUNK_BYTES: usize = 256 * 1024;
+
+// Lifetime of the presigned request used by `storage_upload`, long enough to cover retries
+const UPLOAD_URL_SECONDS: i64 = 3600;
+
+// Progress event for uploads through the configured provider
+const PROGRESS_EVENT: &str = "storage://upload-progress";
+
+// Progress event for `download_from_url`
+const DOWNLOAD_PROGRESS_EVENT: &str = "storage://download-progress";
+
+// Upload rate cap in bytes per second, shared by all uploads; 0 is unlimited
+static UPLOAD_RATE_LIMIT: AtomicU64 = AtomicU64::new(0);
+
+// When the next throttled chunk may be sent
+static NEXT_SEND: Mutex<Option<Instant>> = Mutex::new(None);
+
+// Payload for upload progress events
+#[derive(Debug, Clone, Serialize)]
+pub struct UploadProgress {
+    pub object: String,
+    pub bytes_sent: u64,
+    pub total_bytes: u64,
+}
+
+// Payload for download progress events; `total_bytes` is None when the
+// server doesn't send a length
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadProgress {
+    pub url: String,
+    pub bytes_received: u64,
+    pub total_bytes: Option<u64>,
+}
+
+// Why an upload (or download) attempt failed; only transient failures are retried
+pub enum UploadError {
+    Transient(String),
+    Permanent(String),
+}
+
+// A presigned request: the client sends `method` to `url` with exactly these headers
+#[derive(Debug, Clone, Serialize)]
+pub struct PresignedRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: BTreeMap<String, String>,
+}
+
+impl PresignedRequest {
+    pub fn new(method: &str, url: String, headers: &[(&str, &str)]) -> PresignedRequest {
+        PresignedRequest {
+            method: method.to_string(),
+            url,
+            headers: headers.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect(),
+        }
+    }
+}
+
+// A place listing photos can be uploaded to. Providers only sign requests;
+// uploads themselves go through `upload_with_presign`, so any provider gets
+// streaming, progress events and retries.
+pub trait StorageProvider: Send + Sync {
+    // Presign a request for `object`; `content_type`, when given, must be sent as is
+    fn presign(
+        &self,
+        method: &str,
+        object: &str,
+        content_type: Option<&str>,
+        expires_in_seconds: i64,
+    ) -> impl Future<Output = Result<PresignedRequest, String>> + Send;
+
+    // URL the object can be fetched from once uploaded
+    fn object_url(&self, object: &str) -> String;
+}
+
+// Which provider the storage commands use
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "provider", rename_all = "snake_case")]
+pub enum ProviderSettings {
+    Gcs { bucket: String },
+    S3(S3Config),
+    R2(R2Config),
+    B2(B2Config),
+}
+
+impl ProviderSettings {
+    // Copy safe to show in the UI, with secrets blanked
+    fn redacted(&self) -> ProviderSettings {
+        match self {
+            ProviderSettings::Gcs { .. } => self.clone(),
+            ProviderSettings::S3(config) => ProviderSettings::S3(config.redacted()),
+            ProviderSettings::R2(config) => ProviderSettings::R2(config.redacted()),
+            ProviderSettings::B2(config) => ProviderSettings::B2(config.redacted()),
+        }
+    }
+
+    // Keychain account the provider's secret is kept under, and the secret
+    fn secret(&self) -> Option<(&'static str, &str)> {
+        match self {
+            ProviderSettings::Gcs { .. } => None,
+            ProviderSettings::S3(config) => Some(("storage-s3-secret", &config.secret_access_key)),
+            ProviderSettings::R2(config) => Some(("storage-r2-secret", &config.secret_access_key)),
+            ProviderSettings::B2(config) => Some(("storage-b2-key", &config.application_key)),
+        }
+    }
+
+    fn with_secret(self, secret: String) -> ProviderSettings {
+        match self {
+            ProviderSettings::Gcs { .. } => self,
+            ProviderSettings::S3(config) => ProviderSettings::S3(S3Config { secret_access_key: secret, ..config }),
+            ProviderSettings::R2(config) => ProviderSettings::R2(R2Config { secret_access_key: secret, ..config }),
+            ProviderSettings::B2(config) => ProviderSettings::B2(B2Config { application_key: secret, ..config }),
+        }
+    }
+
+    // Fill secrets left blank from the stored settings for the same provider
+    fn keep_secrets(self, stored: Option<ProviderSettings>) -> ProviderSettings {
+        match (self, stored) {
+            (ProviderSettings::S3(config), Some(ProviderSettings::S3(stored))) => ProviderSettings::S3(config.keep_secret(&stored)),
+            (ProviderSettings::R2(config), Some(ProviderSettings::R2(stored))) => ProviderSettings::R2(config.keep_secret(&stored)),
+            (ProviderSettings::B2(config), Some(ProviderSettings::B2(stored))) => ProviderSettings::B2(config.keep_secret(&stored)),
+            (settings, _) => settings,
+        }
+    }
+}
+
+// Where a marketplace's listing photos are hosted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PhotoHost {
+    // The configured storage provider
+    Storage,
+    // eBay Picture Services, through the connected eBay account. eBay removes
+    // photos not used in an eBay listing after a while, so other
+    // marketplaces are better off with storage.
+    Eps,
+}
+
+// Saved storage settings: the provider, and where each marketplace's
+// listing photos go
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageSettings {
+    #[serde(flatten)]
+    pub provider: ProviderSettings,
+    // Marketplaces left out use EPS for eBay and storage for the rest
+    #[serde(default)]
+    pub hosts: BTreeMap<Marketplace, PhotoHost>,
+}
+
+// Where a marketplace's photos go when its host hasn't been chosen
+fn default_host(marketplace: Marketplace) -> PhotoHost {
+    if marketplace == Marketplace::Ebay {
+        PhotoHost::Eps
+    } else {
+        PhotoHost::Storage
+    }
+}
+
+// The configured provider, dispatching to the concrete implementation
+pub enum Provider {
+    Gcs(GcsProvider),
+    // Also used for R2
+    S3(S3Provider),
+    B2(B2Provider),
+}
+
+impl Provider {
+    pub fn from_settings(app: &AppHandle, settings: ProviderSettings) -> Result<Provider, String> {
+        match settings {
+            ProviderSettings::Gcs { bucket } => Ok(Provider::Gcs(GcsProvider::new(app, bucket)?)),
+            ProviderSettings::S3(config) => Ok(Provider::S3(S3Provider::new(config)?)),
+            ProviderSettings::R2(config) => Ok(Provider::S3(S3Provider::new(config.to_s3())?)),
+            ProviderSettings::B2(config) => Ok(Provider::B2(B2Provider::new(config, network::client(app))?)),
+        }
+    }
+
+    // Presign the upload of a file; GCS uploads also carry the file's checksum
+    async fn presign_upload(&self, object: &str, file_path: &str, expires_in_seconds: i64) -> Result<PresignedRequest, String> {
+        let content_type = content_type_for(file_path);
+        match self {
+            Provider::Gcs(provider) => provider.presign_upload(object, file_path, content_type, expires_in_seconds).await,
+            _ => self.presign("PUT", object, Some(content_type), expires_in_seconds).await,
+        }
+    }
+}
+
+impl StorageProvider for Provider {
+    async fn presign(&self, method: &str, object: &str, content_type: Option<&str>, expires_in_seconds: i64) -> Result<PresignedRequest, String> {
+        match self {
+            Provider::Gcs(provider) => provider.presign(method, object, content_type, expires_in_seconds).await,
+            Provider::S3(provider) => provider.presign(method, object, content_type, expires_in_seconds).await,
+            Provider::B2(provider) => provider.presign(method, object, content_type, expires_in_seconds).await,
+        }
+    }
+
+    fn object_url(&self, object: &str) -> String {
+        match self {
+            Provider::Gcs(provider) => provider.object_url(object),
+            Provider::S3(provider) => provider.object_url(object),
+            Provider::B2(provider) => provider.object_url(object),
+        }
+    }
+}
+
+// Longest lifetime GCS and S3 accept for a signed URL (7 days)
+const MAX_SIGNED_URL_SECONDS: i64 = 604_800;
+
+pub fn parse_expiry(expires_in_seconds: Option<i64>, default: i64) -> Result<i64, String> {
+    let expires_in_seconds = expires_in_seconds.unwrap_or(default);
+    if !(1..=MAX_SIGNED_URL_SECONDS).contains(&expires_in_seconds) {
+        return Err(format!("expires_in_seconds must be between 1 and {}", MAX_SIGNED_URL_SECONDS));
+    }
+    Ok(expires_in_seconds)
+}
+
+// Content types are signed into the URL, so reject anything that isn't a plain
+// "type/subtype" value
+pub fn validate_content_type(content_type: &str) -> Result<(), String> {
+    let valid = content_type
+        .split_once('/')
+        .is_some_and(|(kind, subtype)| {
+            !kind.is_empty()
+                && !subtype.is_empty()
+                && content_type.chars().all(|c| c.is_ascii_graphic() && c != ',')
+        });
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid content type: {}", content_type))
+    }
+}
+
+// Object names are percent-encoded per path segment
+pub fn encode_object_name(object: &str) -> String {
+    object
+        .split('/')
+        .map(|segment| urlencoding::encode(segment).into_owned())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+// Content-Type to upload a file with, from its contents or extension
+pub fn content_type_for(path: &str) -> &'static str {
+    image_io::sniff_file_mime(path)
+        .or_else(|| image_io::mime_from_extension(path))
+        .unwrap_or("application/octet-stream")
+}
+
+// Timeouts, throttling and server errors are worth retrying; other statuses
+// (bad signature, missing bucket) will fail the same way again
+pub fn is_transient(status: reqwest::StatusCode) -> bool {
+    status == reqwest::StatusCode::REQUEST_TIMEOUT
+        || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+        || status.is_server_error()
+}
+
+// Wait until `bytes` more may be sent under the upload rate limit. Chunks
+// reserve consecutive time slots, so concurrent uploads share the limit.
+async fn throttle(bytes: usize) {
+    let rate = UPLOAD_RATE_LIMIT.load(Ordering::Relaxed);
+    if rate == 0 {
+        return;
+    }
+
+    let now = Instant::now();
+    let start = {
+        let Ok(mut next) = NEXT_SEND.lock() else {
+            return;
+        };
+        let start = next.filter(|next| *next > now).unwrap_or(now);
+        *next = Some(start + Duration::from_secs_f64(bytes as f64 / rate as f64));
+        start
+    };
+    if start > now {
+        tokio::time::sleep(start - now).await;
+    }
+}
+
+// Apply the upload rate limit to a request body stream
+pub fn throttled<S, T, E>(stream: S) -> impl Stream<Item = Result<T, E>> + Send + Sync + 'static
+where
+    S: Stream<Item = Result<T, E>> + Send + Sync + 'static,
+    T: AsRef<[u8]> + Send + Sync + 'static,
+    E: Send + Sync + 'static,
+{
+    stream.and_then(|chunk| async move {
+        throttle(chunk.as_ref().len()).await;
+        Ok(chunk)
+    })
+}
+
+// Progress callback that emits `event` to a window
+pub fn window_progress(window: &Window, event: &'static str) -> impl Fn(UploadProgress) + Clone + Send + Sync + 'static {
+    let window = window.clone();
+    move |progress| {
+        let _ = window.emit(event, progress);
+    }
+}
+
+// Stream the file with a presigned request, reporting progress per chunk
+async fn send_file<F>(
+    client: &reqwest::Client,
+    request: &PresignedRequest,
+    file_path: &str,
+    object: &str,
+    on_progress: F,
+) -> Result<(), UploadError>
+where
+    F: Fn(UploadProgress) + Send + Sync + 'static,
+{
+    let total_bytes = fs::metadata(file_path)
+        .map_err(|e| UploadError::Permanent(format!("Failed to read file: {}", e)))?
+        .len();
+    let file = tokio::fs::File::open(file_path)
+        .await
+        .map_err(|e| UploadError::Permanent(format!("Failed to open file: {}", e)))?;
+
+    let sent = Arc::new(AtomicU64::new(0));
+    let progress_object = object.to_string();
+    let stream = ReaderStream::with_capacity(file, UPLOAD_CHUNK_BYTES).inspect_ok(move |chunk| {
+        let bytes_sent = sent.fetch_add(chunk.len() as u64, Ordering::SeqCst) + chunk.len() as u64;
+        on_progress(UploadProgress {
+            object: progress_object.clone(),
+            bytes_sent,
+            total_bytes,
+        });
+    });
+
+    let method = reqwest::Method::from_bytes(request.method.as_bytes())
+        .map_err(|_| UploadError::Permanent(format!("Invalid method: {}", request.method)))?;
+    let mut builder = client
+        .request(method, &request.url)
+        .header(reqwest::header::CONTENT_LENGTH, total_bytes);
+    for (name, value) in &request.headers {
+        builder = builder.header(name, value);
+    }
+
+    let response = builder
+        .body(reqwest::Body::wrap_stream(throttled(stream)))
+        .send()
+        .await
+        .map_err(|e| UploadError::Transient(format!("Upload failed: {}", e)))?;
+
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+
+    let body = response.text().await.unwrap_or_default();
+    let message = format!("Upload failed with status {}: {}", status, body.trim());
+    if is_transient(status) {
+        Err(UploadError::Transient(message))
+    } else {
+        Err(UploadError::Permanent(message))
+    }
+}
+
+// Upload a file with a presigned request, retrying transient failures with
+// exponential backoff
+pub async fn upload_with_retries<F>(
+    client: &reqwest::Client,
+    request: &PresignedRequest,
+    file_path: &str,
+    object: &str,
+    on_progress: F,
+) -> Result<(), String>
+where
+    F: Fn(UploadProgress) + Clone + Send + Sync + 'static,
+{
+    upload_with_presign(client, || async { Ok(request.clone()) }, file_path, object, on_progress).await
+}
+
+// Upload a file, retrying transient failures with exponential backoff and a
+// request from `presign` for each attempt. B2 needs this: after a timeout,
+// 503 or dropped connection its upload URL is dead and a new one is needed.
+pub async fn upload_with_presign<F, P, R>(
+    client: &reqwest::Client,
+    presign: P,
+    file_path: &str,
+    object: &str,
+    on_progress: F,
+) -> Result<(), String>
+where
+    F: Fn(UploadProgress) + Clone + Send + Sync + 'static,
+    P: Fn() -> R,
+    R: Future<Output = Result<PresignedRequest, String>>,
+{
+    let mut backoff = INITIAL_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        let request = presign().await?;
+        match send_file(client, &request, file_path, object, on_progress.clone()).await {
+            Ok(()) => return Ok(()),
+            Err(UploadError::Transient(_)) if attempt < MAX_UPLOAD_ATTEMPTS => {
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+                attempt += 1;
+            }
+            Err(UploadError::Transient(message)) | Err(UploadError::Permanent(message)) => return Err(message),
+        }
+    }
+}
+
+// Download `request_url` into `path`, reporting progress against `url`
+async fn fetch_to_file(
+    window: &Window,
+    client: &reqwest::Client,
+    request_url: &str,
+    url: &str,
+    path: &Path,
+) -> Result<(), UploadError> {
+    let response = client
+        .get(request_url)
+        .send()
+        .await
+        .map_err(|e| UploadError::Transient(format!("Download failed: {}", e)))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let message = format!("Download failed with status {}", status);
+        return Err(if is_transient(status) { UploadError::Transient(message) } else { UploadError::Permanent(message) });
+    }
+
+    let total_bytes = response.content_length();
+    let mut file = tokio::fs::File::create(path)
+        .await
+        .map_err(|e| UploadError::Permanent(format!("Failed to create file: {}", e)))?;
+    let mut stream = response.bytes_stream();
+    let mut bytes_received = 0;
+    while let Some(chunk) = stream
+        .try_next()
+        .await
+        .map_err(|e| UploadError::Transient(format!("Download failed: {}", e)))?
+    {
+        file.write_all(&chunk)
+            .await
+            .map_err(|e| UploadError::Permanent(format!("Failed to write file: {}", e)))?;
+        bytes_received += chunk.len() as u64;
+        let _ = window.emit(DOWNLOAD_PROGRESS_EVENT, DownloadProgress {
+            url: url.to_string(),
+            bytes_received,
+            total_bytes,
+        });
+    }
+    file.flush()
+        .await
+        .map_err(|e| UploadError::Permanent(format!("Failed to write file: {}", e)))
+}
+
+// Command to download a remote photo (e.g. a previously uploaded listing
+// image) to `destination`, replacing any file there
+// `gs://` and storage.googleapis.com URLs are signed with the registered
+// credentials, so private GCS objects work. Progress is reported with
+// `storage://download-progress` events; transient failures are retried with
+// exponential backoff. Returns the destination path.
+#[tauri::command]
+pub async fn download_from_url(window: Window, url: String, destination: String) -> Result<String, String> {
+    let app = window.app_handle();
+    let request_url = gcs::download_url(&app, &url).await?;
+    if !request_url.starts_with("https://") && !request_url.starts_with("http://") {
+        return Err(format!("Unsupported URL: {}", url));
+    }
+
+    let destination = PathBuf::from(destination);
+    let file_name = destination
+        .file_name()
+        .ok_or_else(|| format!("Invalid destination: {}", destination.display()))?;
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create destination directory: {}", e))?;
+    }
+
+    // Written beside the destination and renamed once complete, so a failed
+    // download never leaves a truncated photo behind
+    let mut partial_name = file_name.to_os_string();
+    partial_name.push(".part");
+    let partial = destination.with_file_name(partial_name);
+
+    let client = network::client(&app);
+    let mut backoff = INITIAL_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        match fetch_to_file(&window, &client, &request_url, &url, &partial).await {
+            Ok(()) => break,
+            Err(UploadError::Transient(_)) if attempt < MAX_UPLOAD_ATTEMPTS => {
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+                attempt += 1;
+            }
+            Err(UploadError::Transient(message)) | Err(UploadError::Permanent(message)) => {
+                let _ = fs::remove_file(&partial);
+                return Err(message);
+            }
+        }
+    }
+
+    fs::rename(&partial, &destination)
+        .map_err(|e| format!("Failed to save download: {}", e))?;
+    Ok(destination.to_string_lossy().to_string())
+}
+
+fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app.path_resolver()
+        .app_data_dir()
+        .ok_or("Failed to resolve app data directory")?;
+    Ok(data_dir.join("storage.json"))
+}
+
+// Settings as saved in storage.json, without the provider's secret
+fn read_settings(app: &AppHandle) -> Result<StorageSettings, String> {
+    let path = settings_path(app)?;
+    if !path.exists() {
+        return Err("No storage provider configured; choose one in settings".to_string());
+    }
+    let json = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read storage settings: {}", e))?;
+    let settings: StorageSettings = serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse storage settings: {}", e))?;
+    // Older versions kept the secret in the file; move it to the keychain,
+    // leaving it in place if the keychain can't be reached
+    if settings.provider.secret().is_some_and(|(_, secret)| !secret.is_empty()) {
+        let _ = save_settings(app, &settings, Some(&settings.provider));
+    }
+    Ok(settings)
+}
+
+// Settings with the provider's secret from the keychain
+fn load_settings(app: &AppHandle) -> Result<StorageSettings, String> {
+    let settings = read_settings(app)?;
+    match settings.provider.secret() {
+        Some((account, "")) => {
+            let secret = keychain::get_secret(account)?.unwrap_or_default();
+            Ok(StorageSettings { provider: settings.provider.with_secret(secret), ..settings })
+        }
+        _ => Ok(settings),
+    }
+}
+
+// Save the provider's secret in the keychain and the rest in storage.json,
+// removing the secret of a `stored` provider that's being replaced
+fn save_settings(app: &AppHandle, settings: &StorageSettings, stored: Option<&ProviderSettings>) -> Result<(), String> {
+    let secret = settings.provider.secret();
+    if let Some((account, secret)) = secret {
+        keychain::set_secret(account, secret)?;
+    }
+    if let Some((stored_account, _)) = stored.and_then(ProviderSettings::secret) {
+        if secret.is_none_or(|(account, _)| account != stored_account) {
+            keychain::delete_secret(stored_account)?;
+        }
+    }
+
+    let path = settings_path(app)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create settings directory: {}", e))?;
+    }
+    let redacted = StorageSettings { provider: settings.provider.redacted(), hosts: settings.hosts.clone() };
+    let json = serde_json::to_string_pretty(&redacted)
+        .map_err(|e| format!("Failed to serialize storage settings: {}", e))?;
+    fs::write(&path, json)
+        .map_err(|e| format!("Failed to save storage settings: {}", e))
+}
+
+fn configured_provider(app: &AppHandle) -> Result<Provider, String> {
+    Provider::from_settings(app, load_settings(app)?.provider)
+}
+
+// Where a marketplace's listing photos are hosted; eBay's go to EPS until
+// storage is set up
+pub fn photo_host(app: &AppHandle, marketplace: Marketplace) -> Result<PhotoHost, String> {
+    if !settings_path(app)?.exists() {
+        return Ok(default_host(marketplace));
+    }
+    let settings = read_settings(app)?;
+    Ok(settings.hosts.get(&marketplace).copied().unwrap_or_else(|| default_host(marketplace)))
+}
+
+// Command to choose the storage provider: GCS, S3 (or an S3-compatible
+// service), Cloudflare R2 or Backblaze B2, and per marketplace whether
+// listing photos are hosted there or by eBay Picture Services
+// Access keys go in the OS keychain and the rest in storage.json.
+#[tauri::command]
+pub fn set_storage_settings(app: AppHandle, settings: StorageSettings) -> Result<(), String> {
+    // `get_storage_settings` blanks secrets, so saving its settings back
+    // unchanged must keep them
+    let stored = load_settings(&app).ok().map(|stored| stored.provider);
+    let settings = StorageSettings { provider: settings.provider.keep_secrets(stored.clone()), ..settings };
+    // Fail now rather than on the first upload
+    Provider::from_settings(&app, settings.provider.clone())?;
+
+    save_settings(&app, &settings, stored.as_ref())
+}
+
+// Command to cap the upload rate, in KB/s across all uploads; 0 or no value
+// removes the cap. Takes effect from the next chunk of running uploads.
+#[tauri::command]
+pub fn set_upload_rate_limit(kb_per_second: Option<u32>) {
+    UPLOAD_RATE_LIMIT.store(kb_per_second.unwrap_or(0) as u64 * 1024, Ordering::Relaxed);
+}
+
+// Command to get the storage settings, with secrets blanked; leave them
+// blank in `set_storage_settings` to keep the stored ones
+#[tauri::command]
+pub fn get_storage_settings(app: AppHandle) -> Result<Option<StorageSettings>, String> {
+    if !settings_path(&app)?.exists() {
+        return Ok(None);
+    }
+    read_settings(&app).map(|settings| Some(StorageSettings { provider: settings.provider.redacted(), ..settings }))
+}
+
+// Command to presign a request against the configured provider, for uploads
+// or downloads done by the frontend. Send the returned method, URL and headers
+// as is; B2 uploads, for example, are a POST with an authorization header.
+#[tauri::command]
+pub async fn storage_presign(
+    app: AppHandle,
+    method: String,
+    object: String,
+    content_type: Option<String>,
+    expires_in_seconds: Option<i64>,
+) -> Result<PresignedRequest, String> {
+    let method = method.to_uppercase();
+    if !["GET", "PUT", "POST", "DELETE", "HEAD"].contains(&method.as_str()) {
+        return Err(format!("Unsupported method: {}", method));
+    }
+    let expires_in_seconds = parse_expiry(expires_in_seconds, 900)?;
+    if let Some(content_type) = &content_type {
+        validate_content_type(content_type)?;
+    }
+
+    configured_provider(&app)?
+        .presign(&method, &object, content_type.as_deref(), expires_in_seconds)
+        .await
+}
+
+// Command to upload a file to the configured provider straight from disk
+// Progress is reported with `storage://upload-progress` events. Returns the object's URL.
+#[tauri::command]
+pub async fn storage_upload(window: Window, object: String, file_path: String) -> Result<String, String> {
+    let progress = window_progress(&window, PROGRESS_EVENT);
+    upload_file(&window.app_handle(), &network::client(&window.app_handle()), &object, &file_path, progress).await
+}
+
+// Upload a file to the configured provider, returning the object's URL
+pub async fn upload_file<F>(
+    app: &AppHandle,
+    client: &reqwest::Client,
+    object: &str,
+    file_path: &str,
+    on_progress: F,
+) -> Result<String, String>
+where
+    F: Fn(UploadProgress) + Clone + Send + Sync + 'static,
+{
+    let provider = configured_provider(app)?;
+    let presign = || provider.presign_upload(object, file_path, UPLOAD_URL_SECONDS);
+    upload_with_presign(client, presign, file_path, object, on_progress).await?;
+    Ok(provider.object_url(object))
+}
+
+// Upload a listing photo to the configured provider under a unique name,
+// returning its URL
+pub async fn upload_photo(app: &AppHandle, file_path: &str) -> Result<String, String> {
+    let file_name = Path::new(file_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(|| format!("Invalid photo path: {}", file_path))?;
+    let object = format!("listing-photos/{}-{}", chrono::Utc::now().format("%Y%m%d-%H%M%S%3f"), file_name);
+    upload_file(app, &network::client(app), &object, file_path, |_| {}).await
+}
+
+// Command to upload a local photo wherever the marketplace's photos are
+// hosted, for listings that need photo URLs (e.g. a Facebook catalog).
+// Returns the photo's URL; https URLs are returned as they are.
+#[tauri::command]
+pub async fn host_listing_photo(app: AppHandle, marketplace: Marketplace, image_path: String) -> Result<String, String> {
+    if image_path.starts_with("https://") {
+        return Ok(image_path);
+    }
+    match photo_host(&app, marketplace)? {
+        PhotoHost::Storage => upload_photo(&app, &image_path).await,
+        PhotoHost::Eps => {
+            let environment = ebay_auth::load_settings(&app)?.environment;
+            let token = ebay_auth::access_token(&app).await?;
+            ebay::upload_photo(&app, environment, &token, &image_path).await
+        }
+    }
+}
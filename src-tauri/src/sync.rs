@@ -0,0 +1,609 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+use tokio::sync::Notify;
+use crate::crosslist::Marketplace;
+use crate::db::Database;
+use crate::etsy::{self, EtsyAuth};
+use crate::ebay;
+use crate::ebay_auth;
+
+// Emitted with a `SyncLogEntry` for every action sync takes
+const ACTION_EVENT: &str = "sync://action";
+
+// How far back the first poll of a marketplace looks for orders
+const FIRST_POLL_HOURS: i64 = 24;
+
+// Each poll looks back this far past the last one, in case an order showed
+// up late; orders already handled are skipped
+const POLL_OVERLAP_MINUTES: i64 = 15;
+
+const DEFAULT_INTERVAL_MINUTES: u64 = 5;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncSettings {
+    // Poll for orders in the background
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_interval_minutes")]
+    pub interval_minutes: u64,
+    // Set eBay listings to zero quantity rather than ending them, so they
+    // keep their watchers and history (needs out-of-stock control on)
+    #[serde(default)]
+    pub zero_quantity_on_ebay: bool,
+}
+
+fn default_interval_minutes() -> u64 {
+    DEFAULT_INTERVAL_MINUTES
+}
+
+impl Default for SyncSettings {
+    fn default() -> SyncSettings {
+        SyncSettings { enabled: false, interval_minutes: DEFAULT_INTERVAL_MINUTES, zero_quantity_on_ebay: false }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncAction {
+    // An order came in on the marketplace
+    Sold,
+    Ended,
+    Zeroed,
+    // Quantity lowered to what's left after a sale of one of several
+    Quantity,
+    // No API to act through; the seller has to end it by hand
+    Manual,
+    // Nothing to do, e.g. an Etsy draft
+    Skipped,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncLogEntry {
+    pub id: i64,
+    // RFC 3339
+    pub created_at: String,
+    pub sku: Option<String>,
+    pub marketplace: Marketplace,
+    pub action: SyncAction,
+    pub listing_id: Option<String>,
+    pub detail: String,
+}
+
+// Where an SKU is listed
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncedListing {
+    pub marketplace: Marketplace,
+    pub sku: String,
+    // The marketplace's listing ID; `None` for export packages
+    pub listing_id: Option<String>,
+    pub quantity: u32,
+    // active, sold, ended or manual
+    pub status: String,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SyncReport {
+    pub sales: usize,
+    pub actions: Vec<SyncLogEntry>,
+}
+
+struct Sale {
+    marketplace: Marketplace,
+    order_id: String,
+    sku: String,
+    quantity: u32,
+}
+
+fn name(marketplace: Marketplace) -> String {
+    serde_json::to_value(marketplace)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn text_enum<T: serde::de::DeserializeOwned>(row: &Row, index: usize) -> rusqlite::Result<T> {
+    let text: String = row.get(index)?;
+    serde_json::from_value(serde_json::Value::String(text))
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(index, rusqlite::types::Type::Text, Box::new(e)))
+}
+
+fn listing_from_row(row: &Row) -> rusqlite::Result<SyncedListing> {
+    Ok(SyncedListing {
+        marketplace: text_enum(row, 0)?,
+        sku: row.get(1)?,
+        listing_id: row.get(2)?,
+        quantity: row.get(3)?,
+        status: row.get(4)?,
+        updated_at: row.get(5)?,
+    })
+}
+
+fn entry_from_row(row: &Row) -> rusqlite::Result<SyncLogEntry> {
+    Ok(SyncLogEntry {
+        id: row.get(0)?,
+        created_at: row.get(1)?,
+        sku: row.get(2)?,
+        marketplace: text_enum(row, 3)?,
+        action: text_enum(row, 4)?,
+        listing_id: row.get(5)?,
+        detail: row.get(6)?,
+    })
+}
+
+// Where each SKU is listed, the orders already handled and a log of every
+// action, stored in SQLite
+pub struct SyncStore {
+    conn: Arc<Mutex<Connection>>,
+    wake: Notify,
+    // Held while a sync runs, so background and manual runs don't overlap
+    running: tokio::sync::Mutex<()>,
+}
+
+impl SyncStore {
+    pub fn new(db: &Database) -> SyncStore {
+        SyncStore { conn: db.connection(), wake: Notify::new(), running: tokio::sync::Mutex::new(()) }
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Connection>, String> {
+        self.conn.lock().map_err(|_| "Sync store lock poisoned".to_string())
+    }
+
+    // Record where an SKU is listed, replacing an earlier listing of it on
+    // the same marketplace
+    fn put_listing(&self, marketplace: Marketplace, sku: &str, listing_id: Option<&str>, quantity: u32) -> Result<(), String> {
+        let conn = self.lock()?;
+        conn.execute(
+            "INSERT OR REPLACE INTO synced_listings (marketplace, sku, listing_id, quantity, status, updated_at)
+             VALUES (?1, ?2, ?3, ?4, 'active', ?5)",
+            params![name(marketplace), sku, listing_id, quantity, Utc::now().to_rfc3339()],
+        )
+        .map_err(|e| format!("Failed to record listing {}: {}", sku, e))?;
+        Ok(())
+    }
+
+    fn listings(&self, sku: Option<&str>) -> Result<Vec<SyncedListing>, String> {
+        let conn = self.lock()?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT marketplace, sku, listing_id, quantity, status, updated_at FROM synced_listings
+                 WHERE ?1 IS NULL OR sku = ?1 ORDER BY sku, marketplace",
+            )
+            .map_err(|e| format!("Failed to read synced listings: {}", e))?;
+        let rows = stmt
+            .query_map(params![sku], listing_from_row)
+            .map_err(|e| format!("Failed to read synced listings: {}", e))?;
+        rows.collect::<Result<_, _>>()
+            .map_err(|e| format!("Failed to read synced listings: {}", e))
+    }
+
+    // SKU of a marketplace's listing
+    fn sku_for(&self, marketplace: Marketplace, listing_id: &str) -> Result<Option<String>, String> {
+        let conn = self.lock()?;
+        conn.query_row(
+            "SELECT sku FROM synced_listings WHERE marketplace = ?1 AND listing_id = ?2",
+            params![name(marketplace), listing_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Failed to read synced listings: {}", e))
+    }
+
+    fn set_status(&self, marketplace: Marketplace, sku: &str, status: &str, quantity: Option<u32>) -> Result<(), String> {
+        let conn = self.lock()?;
+        conn.execute(
+            "UPDATE synced_listings SET status = ?3, quantity = COALESCE(?4, quantity), updated_at = ?5
+             WHERE marketplace = ?1 AND sku = ?2",
+            params![name(marketplace), sku, status, quantity, Utc::now().to_rfc3339()],
+        )
+        .map_err(|e| format!("Failed to update synced listing {}: {}", sku, e))?;
+        Ok(())
+    }
+
+    fn is_handled(&self, sale: &Sale) -> Result<bool, String> {
+        let conn = self.lock()?;
+        conn.query_row(
+            "SELECT 1 FROM handled_sales WHERE marketplace = ?1 AND order_id = ?2 AND sku = ?3",
+            params![name(sale.marketplace), sale.order_id, sale.sku],
+            |_| Ok(()),
+        )
+        .optional()
+        .map(|handled| handled.is_some())
+        .map_err(|e| format!("Failed to read handled sales: {}", e))
+    }
+
+    fn mark_handled(&self, sale: &Sale) -> Result<(), String> {
+        let mut conn = self.lock()?;
+        let tx = conn.transaction().map_err(|e| format!("Failed to record handled sale: {}", e))?;
+        tx.execute(
+            "INSERT OR IGNORE INTO handled_sales (marketplace, order_id, sku, handled_at) VALUES (?1, ?2, ?3, ?4)",
+            params![name(sale.marketplace), sale.order_id, sale.sku, Utc::now().to_rfc3339()],
+        )
+        .map_err(|e| format!("Failed to record handled sale: {}", e))?;
+        tx.execute(
+            "DELETE FROM pending_sales WHERE marketplace = ?1 AND order_id = ?2 AND sku = ?3",
+            params![name(sale.marketplace), sale.order_id, sale.sku],
+        )
+        .map_err(|e| format!("Failed to record handled sale: {}", e))?;
+        tx.commit().map_err(|e| format!("Failed to record handled sale: {}", e))
+    }
+
+    // Keep a sale whose other listings couldn't all be updated, so every
+    // poll tries it again until it's handled
+    fn hold_sale(&self, sale: &Sale) -> Result<(), String> {
+        let conn = self.lock()?;
+        conn.execute(
+            "INSERT OR IGNORE INTO pending_sales (marketplace, order_id, sku, quantity, first_seen_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![name(sale.marketplace), sale.order_id, sale.sku, sale.quantity, Utc::now().to_rfc3339()],
+        )
+        .map_err(|e| format!("Failed to save pending sale: {}", e))?;
+        Ok(())
+    }
+
+    fn pending_sales(&self) -> Result<Vec<Sale>, String> {
+        let conn = self.lock()?;
+        let mut stmt = conn
+            .prepare("SELECT marketplace, order_id, sku, quantity FROM pending_sales ORDER BY first_seen_at")
+            .map_err(|e| format!("Failed to read pending sales: {}", e))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(Sale { marketplace: text_enum(row, 0)?, order_id: row.get(1)?, sku: row.get(2)?, quantity: row.get(3)? })
+            })
+            .map_err(|e| format!("Failed to read pending sales: {}", e))?;
+        rows.collect::<Result<_, _>>()
+            .map_err(|e| format!("Failed to read pending sales: {}", e))
+    }
+
+    fn log(
+        &self,
+        sku: Option<&str>,
+        marketplace: Marketplace,
+        action: SyncAction,
+        listing_id: Option<&str>,
+        detail: &str,
+    ) -> Result<SyncLogEntry, String> {
+        let action_name = serde_json::to_value(action)
+            .ok()
+            .and_then(|value| value.as_str().map(str::to_string))
+            .unwrap_or_default();
+        let conn = self.lock()?;
+        conn.execute(
+            "INSERT INTO sync_log (created_at, sku, marketplace, action, listing_id, detail)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![Utc::now().to_rfc3339(), sku, name(marketplace), action_name, listing_id, detail],
+        )
+        .map_err(|e| format!("Failed to write sync log: {}", e))?;
+        conn.query_row(
+            "SELECT id, created_at, sku, marketplace, action, listing_id, detail FROM sync_log WHERE id = ?1",
+            params![conn.last_insert_rowid()],
+            entry_from_row,
+        )
+        .map_err(|e| format!("Failed to read sync log: {}", e))
+    }
+
+    fn entries(&self, sku: Option<&str>, limit: usize) -> Result<Vec<SyncLogEntry>, String> {
+        let conn = self.lock()?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, created_at, sku, marketplace, action, listing_id, detail FROM sync_log
+                 WHERE ?1 IS NULL OR sku = ?1 ORDER BY id DESC LIMIT ?2",
+            )
+            .map_err(|e| format!("Failed to read sync log: {}", e))?;
+        let rows = stmt
+            .query_map(params![sku, limit as i64], entry_from_row)
+            .map_err(|e| format!("Failed to read sync log: {}", e))?;
+        rows.collect::<Result<_, _>>()
+            .map_err(|e| format!("Failed to read sync log: {}", e))
+    }
+
+    // When a marketplace was last polled
+    fn polled_at(&self, marketplace: Marketplace) -> Result<Option<DateTime<Utc>>, String> {
+        let conn = self.lock()?;
+        let value: Option<String> = conn
+            .query_row("SELECT value FROM sync_state WHERE key = ?1", params![name(marketplace)], |row| row.get(0))
+            .optional()
+            .map_err(|e| format!("Failed to read sync state: {}", e))?;
+        Ok(value
+            .and_then(|value| DateTime::parse_from_rfc3339(&value).ok())
+            .map(|time| time.with_timezone(&Utc)))
+    }
+
+    fn set_polled_at(&self, marketplace: Marketplace, time: DateTime<Utc>) -> Result<(), String> {
+        let conn = self.lock()?;
+        conn.execute(
+            "INSERT OR REPLACE INTO sync_state (key, value) VALUES (?1, ?2)",
+            params![name(marketplace), time.to_rfc3339()],
+        )
+        .map_err(|e| format!("Failed to save sync state: {}", e))?;
+        Ok(())
+    }
+}
+
+// Record where an SKU has been listed so a sale elsewhere ends it; called
+// after publishing to a marketplace
+pub fn record_listing(app: &AppHandle, marketplace: Marketplace, sku: &str, listing_id: Option<&str>, quantity: u32) -> Result<(), String> {
+    app.state::<SyncStore>().put_listing(marketplace, sku, listing_id, quantity)
+}
+
+fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app.path_resolver()
+        .app_data_dir()
+        .ok_or("Failed to resolve app data directory")?;
+    Ok(data_dir.join("sync.json"))
+}
+
+fn load_settings(app: &AppHandle) -> Result<SyncSettings, String> {
+    let path = settings_path(app)?;
+    if !path.exists() {
+        return Ok(SyncSettings::default());
+    }
+    let json = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read sync settings: {}", e))?;
+    serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse sync settings: {}", e))
+}
+
+// Time to poll a marketplace's orders from
+fn poll_since(store: &SyncStore, marketplace: Marketplace) -> Result<DateTime<Utc>, String> {
+    Ok(match store.polled_at(marketplace)? {
+        Some(polled_at) => polled_at - chrono::Duration::minutes(POLL_OVERLAP_MINUTES),
+        None => Utc::now() - chrono::Duration::hours(FIRST_POLL_HOURS),
+    })
+}
+
+// New orders from each connected marketplace with an API. A marketplace
+// that can't be polled is logged and skipped.
+async fn poll_sales(app: &AppHandle, store: &SyncStore, actions: &mut Vec<SyncLogEntry>) -> Result<Vec<Sale>, String> {
+    let mut sales = Vec::new();
+
+    if ebay_auth::is_connected(app) {
+        let started = Utc::now();
+        match ebay::sales_since(app, poll_since(store, Marketplace::Ebay)?).await {
+            Ok(ebay_sales) => {
+                sales.extend(ebay_sales.into_iter().map(|sale| Sale {
+                    marketplace: Marketplace::Ebay,
+                    order_id: sale.order_id,
+                    sku: sale.sku,
+                    quantity: sale.quantity,
+                }));
+                store.set_polled_at(Marketplace::Ebay, started)?;
+            }
+            Err(e) => actions.push(store.log(None, Marketplace::Ebay, SyncAction::Failed, None, &e)?),
+        }
+    }
+
+    if etsy::is_connected() {
+        let started = Utc::now();
+        let etsy_auth = app.state::<EtsyAuth>();
+        match etsy::sales_since(app, &etsy_auth, poll_since(store, Marketplace::Etsy)?.timestamp()).await {
+            Ok(etsy_sales) => {
+                for sale in etsy_sales {
+                    // Etsy listings made by the app don't carry the SKU, so
+                    // it's looked up from the listing
+                    let sku = match store.sku_for(Marketplace::Etsy, &sale.listing_id.to_string())? {
+                        Some(sku) => Some(sku),
+                        None => sale.sku,
+                    };
+                    if let Some(sku) = sku {
+                        sales.push(Sale {
+                            marketplace: Marketplace::Etsy,
+                            order_id: sale.receipt_id.to_string(),
+                            sku,
+                            quantity: sale.quantity,
+                        });
+                    }
+                }
+                store.set_polled_at(Marketplace::Etsy, started)?;
+            }
+            Err(e) => actions.push(store.log(None, Marketplace::Etsy, SyncAction::Failed, None, &e)?),
+        }
+    }
+    Ok(sales)
+}
+
+// End, zero or lower one other listing of a sold SKU; returns the action
+// taken and the listing's new status
+async fn update_listing(
+    app: &AppHandle,
+    settings: &SyncSettings,
+    listing: &SyncedListing,
+    remaining: u32,
+) -> Result<(SyncAction, String, &'static str), String> {
+    let listing_id = listing.listing_id.as_deref().unwrap_or_default();
+    match listing.marketplace {
+        Marketplace::Ebay if remaining > 0 => {
+            ebay::set_quantity(app, &listing.sku, remaining).await?;
+            Ok((SyncAction::Quantity, format!("Quantity lowered to {}", remaining), "active"))
+        }
+        Marketplace::Ebay if settings.zero_quantity_on_ebay => {
+            ebay::set_quantity(app, &listing.sku, 0).await?;
+            Ok((SyncAction::Zeroed, "Quantity set to 0".to_string(), "ended"))
+        }
+        Marketplace::Ebay => {
+            let withdrawn = ebay::end_listing(app, &listing.sku).await?;
+            Ok((SyncAction::Ended, format!("Ended {} eBay offer(s)", withdrawn), "ended"))
+        }
+        // Etsy quantities are set through listing inventory, which sync
+        // leaves alone; only sold-out items are ended
+        Marketplace::Etsy if remaining > 0 => {
+            Ok((SyncAction::Manual, format!("Lower the Etsy quantity to {}", remaining), "active"))
+        }
+        Marketplace::Etsy => {
+            let etsy_auth = app.state::<EtsyAuth>();
+            if etsy::end_listing(app, &etsy_auth, listing_id).await? {
+                Ok((SyncAction::Ended, "Deactivated the Etsy listing".to_string(), "ended"))
+            } else {
+                Ok((SyncAction::Skipped, "The Etsy listing isn't active; delete it if it's a draft".to_string(), "ended"))
+            }
+        }
+        marketplace if remaining > 0 => {
+            Ok((SyncAction::Manual, format!("Lower the quantity on {} to {} by hand", name(marketplace), remaining), "manual"))
+        }
+        marketplace => Ok((SyncAction::Manual, format!("End the listing on {} by hand", name(marketplace)), "manual")),
+    }
+}
+
+// Act on one new sale across the SKU's other listings. The sale is only
+// marked handled when every action succeeded; until then it's held and
+// retried on every run. The actions themselves are safe to repeat.
+async fn handle_sale(
+    app: &AppHandle,
+    store: &SyncStore,
+    settings: &SyncSettings,
+    sale: &Sale,
+    actions: &mut Vec<SyncLogEntry>,
+) -> Result<(), String> {
+    let listings = store.listings(Some(&sale.sku))?;
+    let sold = listings.iter().find(|listing| listing.marketplace == sale.marketplace);
+    // Stock is whatever was listed where it sold; an SKU listed outside the
+    // app is taken to be sold out
+    let remaining = sold.map(|listing| listing.quantity.saturating_sub(sale.quantity)).unwrap_or(0);
+    actions.push(store.log(
+        Some(&sale.sku),
+        sale.marketplace,
+        SyncAction::Sold,
+        sold.and_then(|listing| listing.listing_id.as_deref()),
+        &format!("Order {}: {} sold, {} left", sale.order_id, sale.quantity, remaining),
+    )?);
+
+    let mut failed = false;
+    for listing in listings.iter().filter(|listing| listing.marketplace != sale.marketplace && listing.status == "active") {
+        let entry = match update_listing(app, settings, listing, remaining).await {
+            Ok((action, detail, status)) => {
+                store.set_status(listing.marketplace, &listing.sku, status, Some(remaining))?;
+                store.log(Some(&listing.sku), listing.marketplace, action, listing.listing_id.as_deref(), &detail)?
+            }
+            Err(e) => {
+                failed = true;
+                store.log(Some(&listing.sku), listing.marketplace, SyncAction::Failed, listing.listing_id.as_deref(), &e)?
+            }
+        };
+        actions.push(entry);
+    }
+    if failed {
+        return store.hold_sale(sale);
+    }
+    if sold.is_some() {
+        let status = if remaining == 0 { "sold" } else { "active" };
+        store.set_status(sale.marketplace, &sale.sku, status, Some(remaining))?;
+    }
+    store.mark_handled(sale)
+}
+
+// Poll for new orders and update the sold SKUs' other listings
+async fn run(app: &AppHandle) -> Result<SyncReport, String> {
+    let store = app.state::<SyncStore>();
+    let _running = store.running.lock().await;
+    let settings = load_settings(app)?;
+
+    let mut actions = Vec::new();
+    let mut sales = 0;
+    // Sales that failed before are tried again first; polling moves on past
+    // them, as they're kept until handled
+    let mut pending = store.pending_sales()?;
+    for sale in poll_sales(app, &store, &mut actions).await? {
+        if !pending.iter().any(|held| held.marketplace == sale.marketplace && held.order_id == sale.order_id && held.sku == sale.sku) {
+            pending.push(sale);
+        }
+    }
+    for sale in pending {
+        if store.is_handled(&sale)? {
+            continue;
+        }
+        sales += 1;
+        handle_sale(app, &store, &settings, &sale, &mut actions).await?;
+    }
+    for entry in &actions {
+        let _ = app.emit_all(ACTION_EVENT, entry);
+    }
+    Ok(SyncReport { sales, actions })
+}
+
+// Start the background worker that syncs on the configured interval while
+// sync is enabled. Call once the sync store is managed.
+pub fn start(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let settings = load_settings(&app).unwrap_or_default();
+            if settings.enabled {
+                let _ = run(&app).await;
+            }
+            let wait = Duration::from_secs(settings.interval_minutes.max(1) * 60);
+            let _ = tokio::time::timeout(wait, app.state::<SyncStore>().wake.notified()).await;
+        }
+    });
+}
+
+// Command to configure quantity sync
+#[tauri::command]
+pub fn set_sync_settings(app: AppHandle, store: State<SyncStore>, settings: SyncSettings) -> Result<(), String> {
+    if settings.interval_minutes == 0 {
+        return Err("Sync interval must be at least a minute".to_string());
+    }
+    let path = settings_path(&app)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create settings directory: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(&settings)
+        .map_err(|e| format!("Failed to serialize sync settings: {}", e))?;
+    fs::write(&path, json)
+        .map_err(|e| format!("Failed to save sync settings: {}", e))?;
+    store.wake.notify_one();
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_sync_settings(app: AppHandle) -> Result<SyncSettings, String> {
+    load_settings(&app)
+}
+
+// Command to poll eBay and Etsy for orders now and, for each SKU sold, end
+// its listings on the other marketplaces (or zero or lower their quantity).
+// Marketplaces without an API get a `manual` log entry instead. Every
+// action is logged and emitted as a `sync://action` event.
+#[tauri::command]
+pub async fn sync_now(app: AppHandle) -> Result<SyncReport, String> {
+    run(&app).await
+}
+
+// Command to record a listing made outside the app, so sync knows the SKU
+// is listed there
+#[tauri::command]
+pub fn register_synced_listing(
+    store: State<SyncStore>,
+    marketplace: Marketplace,
+    sku: String,
+    listing_id: Option<String>,
+    quantity: Option<u32>,
+) -> Result<(), String> {
+    let sku = sku.trim();
+    if sku.is_empty() {
+        return Err("SKU is required".to_string());
+    }
+    if matches!(marketplace, Marketplace::Ebay | Marketplace::Etsy) && listing_id.as_deref().is_none_or(|id| id.trim().is_empty()) {
+        return Err("eBay and Etsy listings need their listing ID".to_string());
+    }
+    store.put_listing(marketplace, sku, listing_id.as_deref().map(str::trim), quantity.unwrap_or(1))
+}
+
+// Command to list where SKUs are listed, for one SKU or all
+#[tauri::command]
+pub fn list_synced_listings(store: State<SyncStore>, sku: Option<String>) -> Result<Vec<SyncedListing>, String> {
+    store.listings(sku.as_deref())
+}
+
+// Command to get the latest sync actions, newest first; `limit` defaults
+// to 100
+#[tauri::command]
+pub fn list_sync_log(store: State<SyncStore>, sku: Option<String>, limit: Option<usize>) -> Result<Vec<SyncLogEntry>, String> {
+    store.entries(sku.as_deref(), limit.unwrap_or(100))
+}
@@ -0,0 +1,363 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use crate::db::Database;
+use crate::listing::ListingCondition;
+use crate::measurements::Measurement;
+
+// A reusable set of listing text, e.g. the seller's usual sign-off and
+// shipping terms, with the item specifics most listings in a category share.
+// Text can use {{variables}} filled in by `render_template`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListingTemplate {
+    // Assigned when the template is created
+    #[serde(default)]
+    pub id: i64,
+    pub name: String,
+    // One of `classifier::CATEGORIES`; `None` for templates used anywhere
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub shipping: String,
+    #[serde(default)]
+    pub returns: String,
+    // Aspect name to values, e.g. "Department": ["Women"]
+    #[serde(default)]
+    pub item_specifics: BTreeMap<String, Vec<String>>,
+    // RFC 3339
+    #[serde(default)]
+    pub updated_at: String,
+}
+
+// What a template is rendered with. Everything is optional, so a listing
+// can be rendered while it's still being filled in.
+#[derive(Debug, Default, Deserialize)]
+pub struct TemplateListing {
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub brand: Option<String>,
+    #[serde(default)]
+    pub size: Option<String>,
+    #[serde(default)]
+    pub colors: Vec<String>,
+    #[serde(default)]
+    pub materials: Vec<String>,
+    #[serde(default)]
+    pub condition: Option<ListingCondition>,
+    #[serde(default)]
+    pub condition_description: Option<String>,
+    #[serde(default)]
+    pub price: Option<f64>,
+    #[serde(default)]
+    pub currency: Option<String>,
+    #[serde(default)]
+    pub category: Option<String>,
+    // Measurement name to value, e.g. "pit_to_pit"; {{measurements}} lists
+    // them by name
+    #[serde(default)]
+    pub measurements: BTreeMap<String, Measurement>,
+    // Any other {{variable}} a template uses
+    #[serde(default)]
+    pub variables: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RenderedTemplate {
+    pub description: String,
+    pub shipping: String,
+    pub returns: String,
+    // The description, shipping and returns text joined, skipping empty ones
+    pub text: String,
+    pub item_specifics: BTreeMap<String, Vec<String>>,
+    // Variables the template uses that the listing has no value for; they
+    // are left in the text as written
+    pub unresolved: Vec<String>,
+}
+
+// Listing templates, stored in SQLite alongside the other user data
+pub struct TemplateStore {
+    conn: Arc<Mutex<Connection>>,
+}
+
+fn template_from_row(row: &Row) -> rusqlite::Result<ListingTemplate> {
+    let item_specifics: String = row.get(6)?;
+    Ok(ListingTemplate {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        category: row.get(2)?,
+        description: row.get(3)?,
+        shipping: row.get(4)?,
+        returns: row.get(5)?,
+        // Only ever written by `save`, so a parse failure means corruption;
+        // the template still loads, without its item specifics
+        item_specifics: serde_json::from_str(&item_specifics).unwrap_or_default(),
+        updated_at: row.get(7)?,
+    })
+}
+
+const TEMPLATE_COLUMNS: &str = "id, name, category, description, shipping, returns, item_specifics, updated_at";
+
+impl TemplateStore {
+    pub fn new(db: &Database) -> TemplateStore {
+        TemplateStore { conn: db.connection() }
+    }
+
+    // Insert a template, or replace the one with its ID when it has one
+    fn save(&self, template: &ListingTemplate) -> Result<ListingTemplate, String> {
+        let item_specifics = serde_json::to_string(&template.item_specifics)
+            .map_err(|e| format!("Failed to serialize item specifics: {}", e))?;
+        let updated_at = Utc::now().to_rfc3339();
+        let category = template.category.as_deref().map(str::trim).filter(|category| !category.is_empty());
+        let conn = self.conn.lock().map_err(|_| "Template store lock poisoned".to_string())?;
+        let id = if template.id > 0 {
+            let updated = conn
+                .execute(
+                    "UPDATE listing_templates
+                     SET name = ?2, category = ?3, description = ?4, shipping = ?5, returns = ?6,
+                         item_specifics = ?7, updated_at = ?8
+                     WHERE id = ?1",
+                    params![
+                        template.id,
+                        template.name.trim(),
+                        category,
+                        template.description,
+                        template.shipping,
+                        template.returns,
+                        item_specifics,
+                        updated_at,
+                    ],
+                )
+                .map_err(|e| format!("Failed to save template: {}", e))?;
+            if updated == 0 {
+                return Err(format!("No template with ID {}", template.id));
+            }
+            template.id
+        } else {
+            conn.execute(
+                "INSERT INTO listing_templates
+                    (name, category, description, shipping, returns, item_specifics, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    template.name.trim(),
+                    category,
+                    template.description,
+                    template.shipping,
+                    template.returns,
+                    item_specifics,
+                    updated_at,
+                ],
+            )
+            .map_err(|e| format!("Failed to save template: {}", e))?;
+            conn.last_insert_rowid()
+        };
+        Ok(ListingTemplate {
+            id,
+            name: template.name.trim().to_string(),
+            category: category.map(str::to_string),
+            updated_at,
+            ..template.clone()
+        })
+    }
+
+    fn get(&self, id: i64) -> Result<Option<ListingTemplate>, String> {
+        let conn = self.conn.lock().map_err(|_| "Template store lock poisoned".to_string())?;
+        conn.query_row(
+            &format!("SELECT {} FROM listing_templates WHERE id = ?1", TEMPLATE_COLUMNS),
+            params![id],
+            template_from_row,
+        )
+        .optional()
+        .map_err(|e| format!("Failed to read template: {}", e))
+    }
+
+    // Templates by name; with a category, those for it and those for any
+    fn list(&self, category: Option<&str>) -> Result<Vec<ListingTemplate>, String> {
+        let conn = self.conn.lock().map_err(|_| "Template store lock poisoned".to_string())?;
+        let mut statement = conn
+            .prepare(&format!(
+                "SELECT {} FROM listing_templates
+                 WHERE ?1 IS NULL OR category IS NULL OR category = ?1
+                 ORDER BY name COLLATE NOCASE",
+                TEMPLATE_COLUMNS
+            ))
+            .map_err(|e| format!("Failed to read templates: {}", e))?;
+        let rows = statement
+            .query_map(params![category], template_from_row)
+            .map_err(|e| format!("Failed to read templates: {}", e))?;
+        rows.collect::<Result<_, _>>()
+            .map_err(|e| format!("Failed to read templates: {}", e))
+    }
+
+    fn delete(&self, id: i64) -> Result<bool, String> {
+        let conn = self.conn.lock().map_err(|_| "Template store lock poisoned".to_string())?;
+        let removed = conn
+            .execute("DELETE FROM listing_templates WHERE id = ?1", params![id])
+            .map_err(|e| format!("Failed to delete template: {}", e))?;
+        Ok(removed > 0)
+    }
+}
+
+fn condition_name(condition: ListingCondition) -> &'static str {
+    match condition {
+        ListingCondition::NewWithTags => "New with tags",
+        ListingCondition::NewWithoutTags => "New without tags",
+        ListingCondition::VeryGood => "Very good",
+        ListingCondition::Good => "Good",
+        ListingCondition::Satisfactory => "Satisfactory",
+    }
+}
+
+// "pit_to_pit" as "Pit to pit"
+fn measurement_label(name: &str) -> String {
+    let label = name.replace(['_', '-'], " ");
+    let mut chars = label.trim().chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn format_measurement(measurement: &Measurement) -> String {
+    format!("{} in ({} cm)", measurement.inches, measurement.cm)
+}
+
+fn non_empty(value: Option<&str>) -> Option<String> {
+    value.map(str::trim).filter(|value| !value.is_empty()).map(str::to_string)
+}
+
+// Value of a variable for the listing; `None` when it has none. Also takes
+// "measurements.<name>" for a single measurement.
+fn variable(listing: &TemplateListing, name: &str) -> Option<String> {
+    if let Some(value) = listing.variables.get(name) {
+        return Some(value.clone());
+    }
+    if let Some(measurement) = name.strip_prefix("measurements.") {
+        return listing.measurements.get(measurement).map(format_measurement);
+    }
+    let join = |values: &[String]| (!values.is_empty()).then(|| values.join(", "));
+    match name {
+        "title" => non_empty(listing.title.as_deref()),
+        "brand" => non_empty(listing.brand.as_deref()),
+        "size" => non_empty(listing.size.as_deref()),
+        "color" | "colors" => join(&listing.colors),
+        "material" | "materials" => join(&listing.materials),
+        "condition" => listing.condition.map(|condition| condition_name(condition).to_string()),
+        "condition_description" => non_empty(listing.condition_description.as_deref()),
+        "price" => listing.price.map(|price| match non_empty(listing.currency.as_deref()) {
+            Some(currency) => format!("{:.2} {}", price, currency.to_uppercase()),
+            None => format!("{:.2}", price),
+        }),
+        "category" => non_empty(listing.category.as_deref()),
+        // One line per measurement, e.g. "Pit to pit: 20.5 in (52 cm)"
+        "measurements" => (!listing.measurements.is_empty()).then(|| {
+            listing
+                .measurements
+                .iter()
+                .map(|(name, measurement)| format!("{}: {}", measurement_label(name), format_measurement(measurement)))
+                .collect::<Vec<_>>()
+                .join("\n")
+        }),
+        _ => None,
+    }
+}
+
+// Replace each {{variable}} in the text, with or without spaces inside the
+// braces. Unknown variables are kept as written and added to `unresolved`.
+fn substitute(text: &str, listing: &TemplateListing, unresolved: &mut Vec<String>) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        output.push_str(&rest[..start]);
+        let placeholder = &rest[start..start + 2 + length + 2];
+        let name = rest[start + 2..start + 2 + length].trim();
+        match variable(listing, name) {
+            Some(value) => output.push_str(&value),
+            None => {
+                if !unresolved.iter().any(|known| known == name) {
+                    unresolved.push(name.to_string());
+                }
+                output.push_str(placeholder);
+            }
+        }
+        rest = &rest[start + placeholder.len()..];
+    }
+    output.push_str(rest);
+    output
+}
+
+fn render(template: &ListingTemplate, listing: &TemplateListing) -> RenderedTemplate {
+    let mut unresolved = Vec::new();
+    let description = substitute(&template.description, listing, &mut unresolved);
+    let shipping = substitute(&template.shipping, listing, &mut unresolved);
+    let returns = substitute(&template.returns, listing, &mut unresolved);
+    let text = [&description, &shipping, &returns]
+        .iter()
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let item_specifics = template
+        .item_specifics
+        .iter()
+        .map(|(name, values)| {
+            let values = values
+                .iter()
+                .map(|value| substitute(value, listing, &mut unresolved))
+                .filter(|value| !value.trim().is_empty())
+                .collect();
+            (name.clone(), values)
+        })
+        .collect();
+    RenderedTemplate { description, shipping, returns, text, item_specifics, unresolved }
+}
+
+// Command to create a listing template, or update one when `id` is set;
+// returns the saved template with its ID
+#[tauri::command]
+pub fn save_template(store: State<TemplateStore>, template: ListingTemplate) -> Result<ListingTemplate, String> {
+    if template.name.trim().is_empty() {
+        return Err("Template name is required".to_string());
+    }
+    store.save(&template)
+}
+
+#[tauri::command]
+pub fn get_template(store: State<TemplateStore>, template_id: i64) -> Result<ListingTemplate, String> {
+    store.get(template_id)?.ok_or_else(|| format!("No template with ID {}", template_id))
+}
+
+// Command to list templates by name; with a category, only those for it or
+// for any category
+#[tauri::command]
+pub fn list_templates(store: State<TemplateStore>, category: Option<String>) -> Result<Vec<ListingTemplate>, String> {
+    store.list(category.as_deref().map(str::trim).filter(|category| !category.is_empty()))
+}
+
+// Command to delete a template; returns whether it existed
+#[tauri::command]
+pub fn delete_template(store: State<TemplateStore>, template_id: i64) -> Result<bool, String> {
+    store.delete(template_id)
+}
+
+// Command to fill in a template's {{variables}} from a listing: {{title}},
+// {{brand}}, {{size}}, {{colors}}, {{materials}}, {{condition}},
+// {{condition_description}}, {{price}}, {{category}}, {{measurements}} (one
+// line each) or {{measurements.<name>}}, and any in `listing.variables`
+#[tauri::command]
+pub fn render_template(
+    store: State<TemplateStore>,
+    template_id: i64,
+    listing: TemplateListing,
+) -> Result<RenderedTemplate, String> {
+    let template = store.get(template_id)?.ok_or_else(|| format!("No template with ID {}", template_id))?;
+    Ok(render(&template, &listing))
+}
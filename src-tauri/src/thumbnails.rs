@@ -0,0 +1,140 @@
+use std::fs;
+use std::path::PathBuf;
+use base64::{Engine as _, engine::general_purpose};
+use image::{DynamicImage, imageops::FilterType};
+use rsa::sha2::{Digest, Sha256};
+use serde::Serialize;
+use tauri::State;
+use crate::{image_io, photo_editing};
+
+// JPEG quality for grid thumbnails
+const THUMBNAIL_JPEG_QUALITY: u8 = 80;
+
+// Largest edge accepted for a thumbnail request
+const MAX_THUMBNAIL_EDGE: u32 = 2048;
+
+// Disk cache of resized JPEG thumbnails keyed by source content hash + size
+pub struct ThumbnailCache {
+    dir: PathBuf,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Thumbnail {
+    path: String,
+    // Only filled in when the caller asks for base64
+    data_uri: Option<String>,
+}
+
+impl ThumbnailCache {
+    pub fn new(dir: PathBuf) -> ThumbnailCache {
+        ThumbnailCache { dir }
+    }
+
+    // Return the cached thumbnail for a file, generating it on first request
+    pub fn get_or_create(&self, source_path: &str, max_edge: u32) -> Result<PathBuf, String> {
+        self.cached(source_path, &max_edge.to_string(), || image_io::open_image_scaled(source_path, max_edge))
+    }
+
+    // Same as `get_or_create`, but a `size`x`size` square cropped around the item
+    pub fn get_or_create_square(&self, source_path: &str, size: u32) -> Result<PathBuf, String> {
+        self.cached(source_path, &format!("sq{}", size), || smart_crop(source_path, size))
+    }
+
+    // Look up a thumbnail by source content hash and `variant`, rendering it on a miss
+    fn cached<F>(&self, source_path: &str, variant: &str, render: F) -> Result<PathBuf, String>
+    where
+        F: FnOnce() -> Result<DynamicImage, String>,
+    {
+        let source_bytes = fs::read(source_path)
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+        let content_hash = hex::encode(Sha256::digest(&source_bytes));
+
+        let thumbnail_path = self.dir.join(format!("{}-{}.jpg", content_hash, variant));
+        if thumbnail_path.exists() {
+            return Ok(thumbnail_path);
+        }
+
+        let img = render()?;
+        let jpeg_data = image_io::encode_jpeg(&img, THUMBNAIL_JPEG_QUALITY)?;
+
+        fs::create_dir_all(&self.dir)
+            .map_err(|e| format!("Failed to create thumbnail directory: {}", e))?;
+
+        // Write to a temp file first so a crash never leaves a truncated thumbnail
+        let temp_path = thumbnail_path.with_extension("jpg.tmp");
+        fs::write(&temp_path, &jpeg_data)
+            .map_err(|e| format!("Failed to write thumbnail: {}", e))?;
+        fs::rename(&temp_path, &thumbnail_path)
+            .map_err(|e| format!("Failed to write thumbnail: {}", e))?;
+
+        Ok(thumbnail_path)
+    }
+}
+
+// Crop the square around the item (found from edge density, as in auto-crop)
+// instead of the image center, then resize it to `size`
+fn smart_crop(source_path: &str, size: u32) -> Result<DynamicImage, String> {
+    // Decode just large enough for the short edge to cover `size`
+    let decode_edge = match image_io::read_dimensions(source_path) {
+        Some((width, height)) if width.min(height) > 0 => {
+            (size as u64 * width.max(height) as u64 / width.min(height) as u64).min(u32::MAX as u64) as u32
+        }
+        _ => size * 2,
+    };
+    let img = image_io::open_image_scaled(source_path, decode_edge.max(size))?;
+
+    let (width, height) = (img.width(), img.height());
+    let side = width.min(height);
+
+    let detection = img.thumbnail(photo_editing::DETECTION_EDGE, photo_editing::DETECTION_EDGE).to_luma8();
+    let scale = width as f64 / detection.width() as f64;
+    let (center_x, center_y) = match photo_editing::detect_subject_bounds(&detection) {
+        Some((x, y, w, h)) => ((x as f64 + w as f64 / 2.0) * scale, (y as f64 + h as f64 / 2.0) * scale),
+        None => (width as f64 / 2.0, height as f64 / 2.0),
+    };
+
+    // Keep the window inside the image
+    let left = (center_x - side as f64 / 2.0).clamp(0.0, (width - side) as f64).round() as u32;
+    let top = (center_y - side as f64 / 2.0).clamp(0.0, (height - side) as f64).round() as u32;
+
+    Ok(img.crop_imm(left, top, side, side).resize_exact(size, size, FilterType::Lanczos3))
+}
+
+// Command to get a resized JPEG thumbnail for grid views
+// Returns the cached file path, plus a base64 data URI when `as_base64` is set
+#[tauri::command(async)]
+pub fn get_thumbnail(
+    cache: State<ThumbnailCache>,
+    path: String,
+    max_edge: u32,
+    as_base64: Option<bool>,
+) -> Result<Thumbnail, String> {
+    if max_edge == 0 || max_edge > MAX_THUMBNAIL_EDGE {
+        return Err(format!("max_edge must be between 1 and {}", MAX_THUMBNAIL_EDGE));
+    }
+
+    let thumbnail_path = cache.get_or_create(&path, max_edge)?;
+
+    let data_uri = if as_base64.unwrap_or(false) {
+        let jpeg_data = fs::read(&thumbnail_path)
+            .map_err(|e| format!("Failed to read thumbnail: {}", e))?;
+        Some(format!("data:image/jpeg;base64,{}", general_purpose::STANDARD.encode(&jpeg_data)))
+    } else {
+        None
+    };
+
+    Ok(Thumbnail {
+        path: image_io::path_to_string(&thumbnail_path)?,
+        data_uri,
+    })
+}
+
+// Command to get a square JPEG thumbnail that keeps the item in frame, for grids
+// where a plain center crop would cut the item off. Cached like `get_thumbnail`.
+#[tauri::command(async)]
+pub fn smart_crop_thumbnail(cache: State<ThumbnailCache>, path: String, size: u32) -> Result<String, String> {
+    if size == 0 || size > MAX_THUMBNAIL_EDGE {
+        return Err(format!("size must be between 1 and {}", MAX_THUMBNAIL_EDGE));
+    }
+    image_io::path_to_string(&cache.get_or_create_square(&path, size)?)
+}
@@ -0,0 +1,267 @@
+use std::fs;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::AppHandle;
+use crate::{gcs, keychain, network};
+
+const DEEPL_URL: &str = "https://api.deepl.com/v2/translate";
+// DeepL API Free keys end in ":fx" and use their own host
+const DEEPL_FREE_URL: &str = "https://api-free.deepl.com/v2/translate";
+const GOOGLE_TRANSLATE_URL: &str = "https://translation.googleapis.com/language/translate/v2";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TranslationProvider {
+    #[serde(rename = "deepl")]
+    DeepL,
+    // Cloud Translation, using the registered Google credentials
+    Google,
+    // Self-hosted or public LibreTranslate server
+    Libre,
+}
+
+impl TranslationProvider {
+    // Keychain account holding the provider's API key; Google uses the
+    // registered credentials instead
+    fn key_account(self) -> Option<&'static str> {
+        match self {
+            TranslationProvider::DeepL => Some("translation-deepl"),
+            TranslationProvider::Google => None,
+            TranslationProvider::Libre => Some("translation-libre"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranslationSettings {
+    pub provider: TranslationProvider,
+    // Base URL of the LibreTranslate server, e.g. "http://localhost:5000"
+    #[serde(default)]
+    pub endpoint: Option<String>,
+}
+
+impl Default for TranslationSettings {
+    fn default() -> TranslationSettings {
+        TranslationSettings { provider: TranslationProvider::DeepL, endpoint: None }
+    }
+}
+
+impl TranslationSettings {
+    fn validate(&self) -> Result<(), String> {
+        if self.provider == TranslationProvider::Libre && self.libre_url().is_none() {
+            return Err("Endpoint is required for LibreTranslate".to_string());
+        }
+        Ok(())
+    }
+
+    fn libre_url(&self) -> Option<String> {
+        self.endpoint
+            .as_deref()
+            .map(str::trim)
+            .filter(|endpoint| !endpoint.is_empty())
+            .map(|endpoint| format!("{}/translate", endpoint.trim_end_matches('/')))
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TranslationSettingsInfo {
+    #[serde(flatten)]
+    pub settings: TranslationSettings,
+    // Whether an API key is stored for the provider; the key itself stays in the keychain
+    pub has_api_key: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Translation {
+    // Target language as requested, e.g. "de"
+    pub lang: String,
+    pub text: Option<String>,
+    // Source language the provider detected, lower case
+    pub detected_source_lang: Option<String>,
+    pub error: Option<String>,
+}
+
+fn settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let data_dir = app.path_resolver()
+        .app_data_dir()
+        .ok_or("Failed to resolve app data directory")?;
+    Ok(data_dir.join("translation.json"))
+}
+
+fn load_settings(app: &AppHandle) -> Result<TranslationSettings, String> {
+    let path = settings_path(app)?;
+    if !path.exists() {
+        return Ok(TranslationSettings::default());
+    }
+    let json = fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read translation settings: {}", e))?;
+    serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse translation settings: {}", e))
+}
+
+// Language codes are ISO 639-1, optionally with a region: "de", "pt-BR"
+fn is_language_code(code: &str) -> bool {
+    let (language, region) = code.split_once('-').unwrap_or((code, ""));
+    (2..=3).contains(&language.len())
+        && language.chars().all(|c| c.is_ascii_alphabetic())
+        && (region.is_empty() || (region.len() == 2 && region.chars().all(|c| c.is_ascii_alphabetic())))
+}
+
+// DeepL needs a variant for English and Portuguese targets; British and
+// European ones suit the EU marketplaces
+fn deepl_target(lang: &str) -> String {
+    match lang.to_uppercase().as_str() {
+        "EN" => "EN-GB".to_string(),
+        "PT" => "PT-PT".to_string(),
+        other => other.to_string(),
+    }
+}
+
+// Source languages are given to DeepL without a region
+fn deepl_source(lang: &str) -> String {
+    lang.split('-').next().unwrap_or(lang).to_uppercase()
+}
+
+async fn post_json(request: reqwest::RequestBuilder, provider: &str, body: Value) -> Result<Value, String> {
+    let response = request
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("{} request failed: {}", provider, e))?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("{} request failed ({}): {}", provider, status, body.trim()));
+    }
+    response
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse {} response: {}", provider, e))
+}
+
+// (translated text, detected source language)
+async fn translate_one(
+    app: &AppHandle,
+    settings: &TranslationSettings,
+    api_key: Option<&str>,
+    text: &str,
+    source_lang: Option<&str>,
+    target_lang: &str,
+) -> Result<(String, Option<String>), String> {
+    let client = network::client(app);
+    match settings.provider {
+        TranslationProvider::DeepL => {
+            let key = api_key.ok_or("No DeepL API key is stored")?;
+            let url = if key.ends_with(":fx") { DEEPL_FREE_URL } else { DEEPL_URL };
+            let mut body = json!({ "text": [text], "target_lang": deepl_target(target_lang) });
+            if let Some(source) = source_lang {
+                body["source_lang"] = json!(deepl_source(source));
+            }
+            let request = client.post(url).header(reqwest::header::AUTHORIZATION, format!("DeepL-Auth-Key {}", key));
+            let result = post_json(request, "DeepL", body).await?;
+            let translation = &result["translations"][0];
+            let text = translation["text"].as_str().ok_or("DeepL returned no translation")?;
+            Ok((text.to_string(), translation["detected_source_language"].as_str().map(str::to_lowercase)))
+        }
+        TranslationProvider::Google => {
+            let mut body = json!({ "q": [text], "target": target_lang, "format": "text" });
+            if let Some(source) = source_lang {
+                body["source"] = json!(source);
+            }
+            let request = client.post(GOOGLE_TRANSLATE_URL).bearer_auth(gcs::access_token(app).await?);
+            let result = post_json(request, "Google Translate", body).await?;
+            let translation = &result["data"]["translations"][0];
+            let text = translation["translatedText"].as_str().ok_or("Google Translate returned no translation")?;
+            Ok((text.to_string(), translation["detectedSourceLanguage"].as_str().map(str::to_lowercase)))
+        }
+        TranslationProvider::Libre => {
+            let url = settings.libre_url().ok_or("Endpoint is required for LibreTranslate")?;
+            let mut body = json!({
+                "q": text,
+                "source": source_lang.unwrap_or("auto"),
+                "target": target_lang,
+                "format": "text",
+            });
+            if let Some(key) = api_key {
+                body["api_key"] = json!(key);
+            }
+            let result = post_json(client.post(url), "LibreTranslate", body).await?;
+            let text = result["translatedText"].as_str().ok_or("LibreTranslate returned no translation")?;
+            Ok((text.to_string(), result["detectedLanguage"]["language"].as_str().map(str::to_lowercase)))
+        }
+    }
+}
+
+// Command to configure the translation service
+// `api_key` is stored in the OS keychain: pass a key to set it, an empty
+// string to remove it, or leave it out to keep the current one. Google
+// uses the registered Google credentials and takes no key.
+#[tauri::command]
+pub fn set_translation_settings(app: AppHandle, settings: TranslationSettings, api_key: Option<String>) -> Result<(), String> {
+    settings.validate()?;
+
+    let path = settings_path(&app)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create settings directory: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(&settings)
+        .map_err(|e| format!("Failed to serialize translation settings: {}", e))?;
+    fs::write(&path, json)
+        .map_err(|e| format!("Failed to save translation settings: {}", e))?;
+
+    let Some(account) = settings.provider.key_account() else {
+        return Ok(());
+    };
+    match api_key.as_deref().map(str::trim) {
+        Some("") => keychain::delete_secret(account),
+        Some(key) => keychain::set_secret(account, key),
+        None => Ok(()),
+    }
+}
+
+// Command to get the translation settings and whether an API key is stored
+#[tauri::command]
+pub fn get_translation_settings(app: AppHandle) -> Result<TranslationSettingsInfo, String> {
+    let settings = load_settings(&app)?;
+    let has_api_key = match settings.provider.key_account() {
+        Some(account) => keychain::get_secret(account)?.is_some(),
+        None => false,
+    };
+    Ok(TranslationSettingsInfo { settings, has_api_key })
+}
+
+// Command to translate listing text into each of `target_langs` (e.g.
+// ["de", "fr", "it"]) for cross-listing to European marketplaces. The
+// source language is detected unless `source_lang` is given. A language
+// that fails gets an `error` rather than failing the rest.
+#[tauri::command]
+pub async fn translate_text(
+    app: AppHandle,
+    text: String,
+    target_langs: Vec<String>,
+    source_lang: Option<String>,
+) -> Result<Vec<Translation>, String> {
+    if text.trim().is_empty() {
+        return Err("Nothing to translate".to_string());
+    }
+    if target_langs.is_empty() {
+        return Err("No target languages given".to_string());
+    }
+    if let Some(code) = target_langs.iter().chain(source_lang.iter()).find(|code| !is_language_code(code)) {
+        return Err(format!("Invalid language code: {}", code));
+    }
+
+    let settings = load_settings(&app)?;
+    settings.validate()?;
+    let api_key = settings.provider.key_account().map(keychain::get_secret).transpose()?.flatten();
+
+    let translations = target_langs.iter().map(|lang| async {
+        match translate_one(&app, &settings, api_key.as_deref(), &text, source_lang.as_deref(), lang).await {
+            Ok((text, detected_source_lang)) => Translation { lang: lang.clone(), text: Some(text), detected_source_lang, error: None },
+            Err(e) => Translation { lang: lang.clone(), text: None, detected_source_lang: None, error: Some(e) },
+        }
+    });
+    Ok(futures_util::future::join_all(translations).await)
+}
@@ -0,0 +1,382 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+use tokio::sync::{Notify, Semaphore};
+use crate::db::Database;
+use crate::network;
+use crate::storage::{self, UploadProgress};
+
+// Uploads running at once
+const MAX_CONCURRENT_UPLOADS: usize = 3;
+
+// Times an item is attempted before it's marked failed. Each attempt already
+// retries transient errors, so this covers longer outages.
+const MAX_QUEUE_ATTEMPTS: u32 = 3;
+
+// Wait before a failed item is attempted again, multiplied by its attempts so far
+const RETRY_DELAY_SECONDS: i64 = 30;
+
+// Longest the worker sleeps without checking for due retries
+const IDLE_POLL: Duration = Duration::from_secs(60);
+
+// Emitted with a `QueueItem` whenever an item changes status
+const STATUS_EVENT: &str = "upload-queue://status";
+
+// Emitted with a `QueueProgress` per chunk sent
+const PROGRESS_EVENT: &str = "upload-queue://progress";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UploadStatus {
+    Queued,
+    Uploading,
+    Paused,
+    Done,
+    Failed,
+}
+
+impl UploadStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            UploadStatus::Queued => "queued",
+            UploadStatus::Uploading => "uploading",
+            UploadStatus::Paused => "paused",
+            UploadStatus::Done => "done",
+            UploadStatus::Failed => "failed",
+        }
+    }
+
+    fn parse(status: &str) -> UploadStatus {
+        match status {
+            "uploading" => UploadStatus::Uploading,
+            "paused" => UploadStatus::Paused,
+            "done" => UploadStatus::Done,
+            "failed" => UploadStatus::Failed,
+            _ => UploadStatus::Queued,
+        }
+    }
+}
+
+// A file to upload, as sent by the frontend
+#[derive(Debug, Deserialize)]
+pub struct QueuedUpload {
+    file_path: String,
+    object: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QueueItem {
+    id: i64,
+    file_path: String,
+    object: String,
+    status: UploadStatus,
+    attempts: u32,
+    // Last failure, kept while the item waits for a retry
+    error: Option<String>,
+    // Object URL once uploaded
+    url: Option<String>,
+}
+
+// Payload for queue progress events
+#[derive(Debug, Clone, Serialize)]
+struct QueueProgress {
+    id: i64,
+    #[serde(flatten)]
+    progress: UploadProgress,
+}
+
+const ITEM_COLUMNS: &str = "id, file_path, object, status, attempts, error, url";
+
+fn item_from_row(row: &Row) -> rusqlite::Result<QueueItem> {
+    Ok(QueueItem {
+        id: row.get(0)?,
+        file_path: row.get(1)?,
+        object: row.get(2)?,
+        status: UploadStatus::parse(&row.get::<_, String>(3)?),
+        attempts: row.get(4)?,
+        error: row.get(5)?,
+        url: row.get(6)?,
+    })
+}
+
+// Uploads waiting for or in progress to the configured storage provider,
+// stored in SQLite so a crash or quit mid-upload doesn't lose work
+pub struct UploadQueue {
+    conn: Arc<Mutex<Connection>>,
+    // Signalled when items become ready so an idle worker picks them up
+    wake: Notify,
+}
+
+impl UploadQueue {
+    // Items that were uploading when the app last stopped are queued again.
+    pub fn new(db: &Database) -> Result<UploadQueue, String> {
+        db.lock()?
+            .execute("UPDATE upload_queue SET status = 'queued' WHERE status = 'uploading'", [])
+            .map_err(|e| format!("Failed to initialize upload queue: {}", e))?;
+        Ok(UploadQueue { conn: db.connection(), wake: Notify::new() })
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Connection>, String> {
+        self.conn.lock().map_err(|_| "Upload queue lock poisoned".to_string())
+    }
+
+    fn item(conn: &Connection, id: i64) -> Result<QueueItem, String> {
+        conn.query_row(
+            &format!("SELECT {} FROM upload_queue WHERE id = ?1", ITEM_COLUMNS),
+            params![id],
+            item_from_row,
+        ).map_err(|e| format!("Failed to read upload {}: {}", id, e))
+    }
+
+    fn items(&self) -> Result<Vec<QueueItem>, String> {
+        let conn = self.lock()?;
+        let mut stmt = conn
+            .prepare(&format!("SELECT {} FROM upload_queue ORDER BY id", ITEM_COLUMNS))
+            .map_err(|e| format!("Failed to read upload queue: {}", e))?;
+        let rows = stmt
+            .query_map([], item_from_row)
+            .map_err(|e| format!("Failed to read upload queue: {}", e))?;
+        rows.collect::<Result<_, _>>()
+            .map_err(|e| format!("Failed to read upload queue: {}", e))
+    }
+
+    fn enqueue(&self, uploads: &[QueuedUpload]) -> Result<Vec<QueueItem>, String> {
+        let mut conn = self.lock()?;
+        let tx = conn.transaction()
+            .map_err(|e| format!("Failed to start upload queue transaction: {}", e))?;
+
+        let mut ids = Vec::with_capacity(uploads.len());
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO upload_queue (file_path, object, status, created_at) VALUES (?1, ?2, 'queued', ?3)"
+            ).map_err(|e| format!("Failed to prepare upload insert: {}", e))?;
+
+            let created_at = Utc::now().to_rfc3339();
+            for upload in uploads {
+                stmt.execute(params![upload.file_path, upload.object, created_at])
+                    .map_err(|e| format!("Failed to queue {}: {}", upload.file_path, e))?;
+                ids.push(tx.last_insert_rowid());
+            }
+        }
+
+        tx.commit()
+            .map_err(|e| format!("Failed to commit upload queue: {}", e))?;
+        ids.into_iter().map(|id| UploadQueue::item(&conn, id)).collect()
+    }
+
+    // Mark the oldest due item as uploading and return it. The pick and the
+    // status change are one statement, so an item is never claimed twice.
+    fn claim_next(&self) -> Result<Option<QueueItem>, String> {
+        let conn = self.lock()?;
+        let id: Option<i64> = conn
+            .query_row(
+                "UPDATE upload_queue SET status = 'uploading', attempts = attempts + 1
+                 WHERE status = 'queued' AND id = (
+                    SELECT id FROM upload_queue WHERE status = 'queued' AND next_attempt_at <= ?1 ORDER BY id LIMIT 1
+                 )
+                 RETURNING id",
+                params![Utc::now().timestamp()],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| format!("Failed to claim upload: {}", e))?;
+        id.map(|id| UploadQueue::item(&conn, id)).transpose()
+    }
+
+    // Record an attempt's outcome; failures are queued again until attempts
+    // run out. Only an item still uploading is changed.
+    fn finish(&self, item: &QueueItem, result: Result<String, String>) -> Result<QueueItem, String> {
+        let conn = self.lock()?;
+        match result {
+            Ok(url) => conn.execute(
+                "UPDATE upload_queue SET status = 'done', error = NULL, url = ?2 WHERE id = ?1 AND status = 'uploading'",
+                params![item.id, url],
+            ),
+            Err(error) if item.attempts < MAX_QUEUE_ATTEMPTS => conn.execute(
+                "UPDATE upload_queue SET status = 'queued', error = ?2, next_attempt_at = ?3 WHERE id = ?1 AND status = 'uploading'",
+                params![item.id, error, Utc::now().timestamp() + RETRY_DELAY_SECONDS * item.attempts as i64],
+            ),
+            Err(error) => conn.execute(
+                "UPDATE upload_queue SET status = 'failed', error = ?2 WHERE id = ?1 AND status = 'uploading'",
+                params![item.id, error],
+            ),
+        }.map_err(|e| format!("Failed to update upload {}: {}", item.id, e))?;
+        UploadQueue::item(&conn, item.id)
+    }
+
+    // Move items (all of them when `ids` is None) from one status to another,
+    // returning the items that changed. Each update checks the status it
+    // moves from, so a named item that has moved on since is a conflict and
+    // nothing changes.
+    fn transition(&self, ids: Option<&[i64]>, from: UploadStatus, to: UploadStatus, reset_attempts: bool) -> Result<Vec<QueueItem>, String> {
+        let mut conn = self.lock()?;
+        let tx = conn.transaction()
+            .map_err(|e| format!("Failed to start upload queue transaction: {}", e))?;
+
+        let candidates: Vec<i64> = match ids {
+            Some(ids) => ids.to_vec(),
+            None => {
+                let mut stmt = tx.prepare("SELECT id FROM upload_queue WHERE status = ?1 ORDER BY id")
+                    .map_err(|e| format!("Failed to read upload queue: {}", e))?;
+                let rows = stmt.query_map(params![from.as_str()], |row| row.get(0))
+                    .map_err(|e| format!("Failed to read upload queue: {}", e))?;
+                rows.collect::<Result<_, _>>()
+                    .map_err(|e| format!("Failed to read upload queue: {}", e))?
+            }
+        };
+
+        for id in &candidates {
+            let updated = tx.execute(
+                "UPDATE upload_queue SET status = ?3, next_attempt_at = 0,
+                    attempts = CASE WHEN ?4 THEN 0 ELSE attempts END
+                 WHERE id = ?1 AND status = ?2",
+                params![id, from.as_str(), to.as_str(), reset_attempts],
+            ).map_err(|e| format!("Failed to update upload {}: {}", id, e))?;
+            if updated == 0 {
+                let status: Option<String> = tx
+                    .query_row("SELECT status FROM upload_queue WHERE id = ?1", params![id], |row| row.get(0))
+                    .optional()
+                    .map_err(|e| format!("Failed to read upload {}: {}", id, e))?;
+                return Err(match status {
+                    Some(status) => format!("Upload {} is {}, not {}", id, status, from.as_str()),
+                    None => format!("No upload with ID {}", id),
+                });
+            }
+        }
+
+        tx.commit()
+            .map_err(|e| format!("Failed to commit upload queue: {}", e))?;
+        candidates.into_iter().map(|id| UploadQueue::item(&conn, id)).collect()
+    }
+
+    // Seconds until the next queued item is due, if any are waiting
+    fn next_due_in(&self) -> Result<Option<i64>, String> {
+        let conn = self.lock()?;
+        let next: Option<i64> = conn
+            .query_row(
+                "SELECT MIN(next_attempt_at) FROM upload_queue WHERE status = 'queued'",
+                [],
+                |row| row.get(0),
+            )
+            .map_err(|e| format!("Failed to read upload queue: {}", e))?;
+        Ok(next.map(|at| (at - Utc::now().timestamp()).max(0)))
+    }
+}
+
+fn emit_status(app: &AppHandle, item: &QueueItem) {
+    let _ = app.emit_all(STATUS_EVENT, item);
+}
+
+async fn upload_item(app: &AppHandle, client: &reqwest::Client, item: &QueueItem) -> Result<String, String> {
+    let progress_app = app.clone();
+    let id = item.id;
+    let on_progress = move |progress| {
+        let _ = progress_app.emit_all(PROGRESS_EVENT, QueueProgress { id, progress });
+    };
+    storage::upload_file(app, client, &item.object, &item.file_path, on_progress).await
+}
+
+// Start the background worker that uploads queued items, up to
+// `MAX_CONCURRENT_UPLOADS` at a time. Call once the queue is managed.
+pub fn start(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let slots = Arc::new(Semaphore::new(MAX_CONCURRENT_UPLOADS));
+        loop {
+            let Ok(slot) = slots.clone().acquire_owned().await else {
+                return;
+            };
+            let queue = app.state::<UploadQueue>();
+
+            let item = match queue.claim_next() {
+                Ok(Some(item)) => item,
+                Ok(None) | Err(_) => {
+                    drop(slot);
+                    let wait = match queue.next_due_in() {
+                        Ok(Some(seconds)) => Duration::from_secs(seconds as u64).min(IDLE_POLL),
+                        _ => IDLE_POLL,
+                    };
+                    let _ = tokio::time::timeout(wait, queue.wake.notified()).await;
+                    continue;
+                }
+            };
+            emit_status(&app, &item);
+
+            let app = app.clone();
+            let client = network::client(&app);
+            tauri::async_runtime::spawn(async move {
+                let result = upload_item(&app, &client, &item).await;
+                drop(slot);
+
+                let queue = app.state::<UploadQueue>();
+                if let Ok(item) = queue.finish(&item, result) {
+                    emit_status(&app, &item);
+                }
+                queue.wake.notify_one();
+            });
+        }
+    });
+}
+
+// Command to add files to the upload queue; each goes to `object` on the
+// configured storage provider. Returns the new items.
+// Status changes are reported with `upload-queue://status` events and
+// progress with `upload-queue://progress`.
+#[tauri::command]
+pub fn enqueue_uploads(queue: State<UploadQueue>, uploads: Vec<QueuedUpload>) -> Result<Vec<QueueItem>, String> {
+    let items = queue.enqueue(&uploads)?;
+    queue.wake.notify_one();
+    Ok(items)
+}
+
+// Command to list every item in the upload queue, oldest first
+#[tauri::command]
+pub fn list_upload_queue(queue: State<UploadQueue>) -> Result<Vec<QueueItem>, String> {
+    queue.items()
+}
+
+// Command to pause queued items (all of them when `ids` is omitted). Uploads
+// already running finish; paused items stay paused across restarts. Naming
+// an item that isn't queued is an error and pauses none.
+#[tauri::command]
+pub fn pause_uploads(app: AppHandle, queue: State<UploadQueue>, ids: Option<Vec<i64>>) -> Result<Vec<QueueItem>, String> {
+    let items = queue.transition(ids.as_deref(), UploadStatus::Queued, UploadStatus::Paused, false)?;
+    for item in &items {
+        emit_status(&app, item);
+    }
+    Ok(items)
+}
+
+// Command to resume paused items (all of them when `ids` is omitted); as with
+// pausing, naming one that isn't paused resumes none
+#[tauri::command]
+pub fn resume_uploads(app: AppHandle, queue: State<UploadQueue>, ids: Option<Vec<i64>>) -> Result<Vec<QueueItem>, String> {
+    let items = queue.transition(ids.as_deref(), UploadStatus::Paused, UploadStatus::Queued, false)?;
+    for item in &items {
+        emit_status(&app, item);
+    }
+    queue.wake.notify_one();
+    Ok(items)
+}
+
+// Command to retry failed items (all of them when `ids` is omitted) with a
+// fresh set of attempts
+#[tauri::command]
+pub fn retry_uploads(app: AppHandle, queue: State<UploadQueue>, ids: Option<Vec<i64>>) -> Result<Vec<QueueItem>, String> {
+    let items = queue.transition(ids.as_deref(), UploadStatus::Failed, UploadStatus::Queued, true)?;
+    for item in &items {
+        emit_status(&app, item);
+    }
+    queue.wake.notify_one();
+    Ok(items)
+}
+
+// Command to drop finished items from the queue, returning how many were removed
+#[tauri::command]
+pub fn clear_finished_uploads(queue: State<UploadQueue>) -> Result<usize, String> {
+    let conn = queue.lock()?;
+    conn.execute("DELETE FROM upload_queue WHERE status = 'done'", [])
+        .map_err(|e| format!("Failed to clear upload queue: {}", e))
+}
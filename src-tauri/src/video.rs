@@ -0,0 +1,108 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use rayon::prelude::*;
+use serde::Serialize;
+use tauri::AppHandle;
+use crate::image_io;
+use crate::photo_editing;
+
+// ffmpeg is run as an external tool so the app doesn't link against libav*
+const FFMPEG: &str = "ffmpeg";
+
+// Longest edge of the video's preview thumbnail
+const PREVIEW_EDGE: u32 = 512;
+
+#[derive(Debug, Serialize)]
+pub struct FrameResult {
+    timestamp: f64,
+    output_path: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VideoFrames {
+    frames: Vec<FrameResult>,
+    preview_path: Option<String>,
+    preview_error: Option<String>,
+}
+
+// Run ffmpeg, turning a missing binary or a failed run into an error message
+fn run_ffmpeg(args: &[&str]) -> Result<(), String> {
+    let output = Command::new(FFMPEG)
+        .args(["-v", "error", "-y"])
+        .args(args)
+        .output()
+        .map_err(|e| format!("Failed to run ffmpeg (is it installed and on PATH?): {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("ffmpeg failed: {}", stderr.trim()));
+    }
+    Ok(())
+}
+
+// Save the frame at `seconds` as a full-resolution JPEG
+fn extract_frame(path: &str, seconds: f64, output_dir: &Path) -> Result<String, String> {
+    if !seconds.is_finite() || seconds < 0.0 {
+        return Err(format!("Invalid timestamp: {}", seconds));
+    }
+
+    let suffix = format!("frame-{}ms", (seconds * 1000.0).round() as u64);
+    let output_path = output_dir.join(photo_editing::output_file_name(path, &suffix, "jpg"));
+    let output = image_io::path_to_string(&output_path)?;
+    let timestamp = format!("{:.3}", seconds);
+
+    // ffmpeg writes nothing for timestamps past the end, so clear any earlier copy
+    let _ = fs::remove_file(&output_path);
+
+    // Seeking before the input is fast and frame-accurate in modern ffmpeg
+    run_ffmpeg(&["-ss", &timestamp, "-i", path, "-frames:v", "1", "-q:v", "2", &output])?;
+
+    if !output_path.exists() {
+        return Err(format!("No frame at {}s (past the end of the video?)", timestamp));
+    }
+    Ok(output)
+}
+
+// Save a small representative frame for showing the video in the photo grid
+fn extract_preview(path: &str, output_dir: &Path) -> Result<String, String> {
+    let output_path = output_dir.join(photo_editing::output_file_name(path, "preview", "jpg"));
+    let output = image_io::path_to_string(&output_path)?;
+
+    // `thumbnail` picks the most representative of the first frames, skipping
+    // black or blurry opening frames
+    let filter = format!(
+        "thumbnail,scale='min({edge},iw)':'min({edge},ih)':force_original_aspect_ratio=decrease",
+        edge = PREVIEW_EDGE
+    );
+    run_ffmpeg(&["-i", path, "-vf", &filter, "-frames:v", "1", "-q:v", "4", &output])?;
+    Ok(output)
+}
+
+// Command to pull still frames (at `timestamps`, in seconds) out of a video for
+// use as listing photos, plus a preview thumbnail of the video itself
+// Requires the `ffmpeg` command-line tool
+#[tauri::command(async)]
+pub fn extract_video_frames(app: AppHandle, path: String, timestamps: Vec<f64>) -> Result<VideoFrames, String> {
+    if !Path::new(&path).is_file() {
+        return Err(format!("Video not found: {}", path));
+    }
+
+    let output_dir = photo_editing::output_dir(&app, "frames")?;
+
+    let frames = timestamps
+        .into_par_iter()
+        .map(|timestamp| match extract_frame(&path, timestamp, &output_dir) {
+            Ok(output_path) => FrameResult { timestamp, output_path: Some(output_path), error: None },
+            Err(error) => FrameResult { timestamp, output_path: None, error: Some(error) },
+        })
+        .collect();
+
+    let (preview_path, preview_error) = match extract_preview(&path, &output_dir) {
+        Ok(preview) => (Some(preview), None),
+        Err(error) => (None, Some(error)),
+    };
+
+    Ok(VideoFrames { frames, preview_path, preview_error })
+}
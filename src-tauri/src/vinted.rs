@@ -0,0 +1,208 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::AppHandle;
+use crate::crosslist::Marketplace;
+use crate::image_io;
+use crate::listing::{self, Listing, ListingCondition, MappedListing, PackageContents};
+use crate::sizes::{self, SizeCategory};
+
+// Vinted takes up to 20 photos and asks for at least 800px on the long
+// edge; 1600px is plenty for its zoomed view
+const MAX_PHOTOS: usize = 20;
+const MIN_PHOTO_EDGE: u32 = 800;
+const PHOTO_EDGE: u32 = 1600;
+
+// Parcel size picked at listing time; it sets which shipping options the
+// buyer sees
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParcelSize {
+    // Fits a large envelope, e.g. a T-shirt or jewellery
+    Small,
+    // Fits a shoebox, e.g. shoes or a jumper
+    Medium,
+    // Fits a moving box, e.g. a coat or boots
+    Large,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VintedListing {
+    #[serde(flatten)]
+    pub item: Listing,
+    // Vinted catalogue path, e.g. ["Women", "Clothing", "Dresses"]
+    #[serde(default)]
+    pub category: Vec<String>,
+    // How `size` is mapped to Vinted's size chart
+    #[serde(default)]
+    pub size_category: Option<SizeCategory>,
+    // Suggested from `size_category` when left out
+    #[serde(default)]
+    pub parcel_size: Option<ParcelSize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VintedExport {
+    pub folder: String,
+    pub photos: Vec<String>,
+    // Vinted's fields as JSON, for tools that fill in the form
+    pub data_file: String,
+    // The same fields as text, ready to paste
+    pub text_file: String,
+    // Size as Vinted's chart shows it, e.g. "M / 12 / 40"
+    pub size: Option<String>,
+    pub parcel_size: ParcelSize,
+    // Photos below Vinted's recommended size and the like
+    pub warnings: Vec<String>,
+}
+
+// Vinted's condition names, which the app's follow
+fn vinted_condition(condition: ListingCondition) -> &'static str {
+    match condition {
+        ListingCondition::NewWithTags => "New with tags",
+        ListingCondition::NewWithoutTags => "New without tags",
+        ListingCondition::VeryGood => "Very good",
+        ListingCondition::Good => "Good",
+        ListingCondition::Satisfactory => "Satisfactory",
+    }
+}
+
+// Size as it appears in Vinted's size chart for the category: letter, UK and
+// EU sizes for women's clothing, letters or waist for men's, EU sizes for
+// shoes and ages for kids. Falls back to the size as given.
+fn vinted_size(size: &str, category: Option<SizeCategory>) -> Option<String> {
+    let size = size.trim();
+    if size.is_empty() {
+        return None;
+    }
+    let Some(category) = category else {
+        return Some(size.to_string());
+    };
+    let parsed = sizes::parse_size(size, category);
+    let mapped = match category {
+        SizeCategory::WomensClothing => {
+            let parts: Vec<String> = [parsed.letter, parsed.uk, parsed.eu].into_iter().flatten().collect();
+            (!parts.is_empty()).then(|| parts.join(" / "))
+        }
+        SizeCategory::MensClothing | SizeCategory::Clothing => {
+            parsed.letter.or_else(|| parsed.waist.map(|waist| format!("W{}", waist))).or(parsed.display)
+        }
+        SizeCategory::WomensShoes | SizeCategory::MensShoes | SizeCategory::Shoes => parsed.eu.or(parsed.display),
+        SizeCategory::KidsClothing => parsed.age.or(parsed.display),
+    };
+    Some(mapped.unwrap_or_else(|| size.to_string()))
+}
+
+// Parcel size most items of the kind fit
+fn suggested_parcel_size(category: Option<SizeCategory>) -> ParcelSize {
+    match category {
+        Some(SizeCategory::WomensShoes | SizeCategory::MensShoes | SizeCategory::Shoes) => ParcelSize::Medium,
+        _ => ParcelSize::Small,
+    }
+}
+
+// How Vinted will see the listing, with what stops it being exported
+pub fn map_listing(listing: &VintedListing) -> MappedListing {
+    let mut problems = listing.item.problems("Vinted", MAX_PHOTOS);
+    if listing.category.is_empty() {
+        problems.push("Vinted category is required".to_string());
+    }
+    MappedListing {
+        category: listing.category.clone(),
+        condition: Some(vinted_condition(listing.item.condition).to_string()),
+        size: listing.item.size.as_deref().and_then(|size| vinted_size(size, listing.size_category)),
+        max_photos: MAX_PHOTOS,
+        problems,
+    }
+}
+
+fn listing_text(listing: &VintedListing, size: Option<&str>, parcel_size: &str) -> String {
+    let item = &listing.item;
+    let mut lines = vec![
+        format!("Title: {}", item.title.trim()),
+        String::new(),
+        "Description:".to_string(),
+        item.description_with_condition(),
+        String::new(),
+    ];
+    if !listing.category.is_empty() {
+        lines.push(format!("Category: {}", listing.category.join(" > ")));
+    }
+    if let Some(brand) = item.brand.as_deref().filter(|brand| !brand.trim().is_empty()) {
+        lines.push(format!("Brand: {}", brand.trim()));
+    }
+    if let Some(size) = size {
+        lines.push(format!("Size: {}", size));
+    }
+    lines.push(format!("Condition: {}", vinted_condition(item.condition)));
+    if !item.colors.is_empty() {
+        // Vinted takes up to two colours
+        lines.push(format!("Colour: {}", item.colors.iter().take(2).cloned().collect::<Vec<_>>().join(", ")));
+    }
+    if !item.materials.is_empty() {
+        lines.push(format!("Material: {}", item.materials.join(", ")));
+    }
+    lines.push(format!("Parcel size: {}", parcel_size));
+    lines.push(format!("Price: {:.2} {}", item.price, item.currency.trim().to_uppercase()));
+    lines.join("\n") + "\n"
+}
+
+// Write a listing's Vinted export package; used by the command and by
+// cross-listing
+pub async fn export(app: &AppHandle, listing: &VintedListing, output_dir: Option<String>) -> Result<VintedExport, String> {
+    let mapped = map_listing(listing);
+    let size = mapped.size.clone();
+    let item = &listing.item;
+    let parcel_size = listing.parcel_size.unwrap_or_else(|| suggested_parcel_size(listing.size_category));
+    let parcel_name = match parcel_size {
+        ParcelSize::Small => "Small",
+        ParcelSize::Medium => "Medium",
+        ParcelSize::Large => "Large",
+    };
+
+    let warnings: Vec<String> = item
+        .photos
+        .iter()
+        .filter(|photo| image_io::read_dimensions(photo).is_some_and(|(width, height)| width.max(height) < MIN_PHOTO_EDGE))
+        .map(|photo| format!("{} is smaller than the {}px Vinted recommends", photo, MIN_PHOTO_EDGE))
+        .collect();
+
+    let data = json!({
+        "title": item.title.trim(),
+        "description": item.description_with_condition(),
+        "category": listing.category,
+        "brand": item.brand,
+        "size": size,
+        "condition": vinted_condition(item.condition),
+        "colours": item.colors.iter().take(2).collect::<Vec<_>>(),
+        "materials": item.materials,
+        "parcel_size": parcel_size,
+        "price": item.price,
+        "currency": item.currency.trim().to_uppercase(),
+    });
+    let text = listing_text(listing, size.as_deref(), parcel_name);
+    let contents = PackageContents::resized(PHOTO_EDGE, Some(data), text);
+    let package = listing::write_package(app, Marketplace::Vinted, item, &mapped, output_dir, contents).await?;
+
+    Ok(VintedExport {
+        folder: package.folder,
+        photos: package.photos,
+        data_file: package.data_file.unwrap_or_default(),
+        text_file: package.text_file,
+        size,
+        parcel_size,
+        warnings,
+    })
+}
+
+// Command to export a listing for Vinted, which has no public listing API:
+// writes a folder with the photos (at most 20), the fields as JSON and as
+// text to paste, with the size mapped to Vinted's size chart and a parcel
+// size suggested when none is given
+#[tauri::command]
+pub async fn export_to_vinted(
+    app: AppHandle,
+    listing: VintedListing,
+    output_dir: Option<String>,
+) -> Result<VintedExport, String> {
+    export(&app, &listing, output_dir).await
+}
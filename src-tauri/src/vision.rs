@@ -0,0 +1,301 @@
+use base64::{Engine as _, engine::general_purpose};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::AppHandle;
+use crate::{gcs, image_io, network};
+
+const ANNOTATE_URL: &str = "https://vision.googleapis.com/v1/images:annotate";
+
+// Local photos are sent downscaled to this edge, well under Vision's request
+// size limit and still plenty for recognition
+const VISION_EDGE: u32 = 1600;
+const VISION_JPEG_QUALITY: u8 = 85;
+
+// Results requested per feature
+const MAX_RESULTS: u32 = 10;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AnnotateResponse {
+    #[serde(default)]
+    responses: Vec<ImageAnnotations>,
+}
+
+// The parts of Vision's AnnotateImageResponse the app uses
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageAnnotations {
+    #[serde(default)]
+    pub label_annotations: Vec<EntityAnnotation>,
+    #[serde(default)]
+    pub logo_annotations: Vec<EntityAnnotation>,
+    #[serde(default)]
+    pub web_detection: Option<WebDetection>,
+    // Full text first, then one entry per word
+    #[serde(default)]
+    pub text_annotations: Vec<TextAnnotation>,
+    #[serde(default)]
+    pub face_annotations: Vec<FaceAnnotation>,
+    #[serde(default)]
+    pub error: Option<Status>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TextAnnotation {
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub bounding_poly: BoundingPoly,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FaceAnnotation {
+    #[serde(default)]
+    pub bounding_poly: BoundingPoly,
+    #[serde(default)]
+    pub detection_confidence: f64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct BoundingPoly {
+    #[serde(default)]
+    pub vertices: Vec<Vertex>,
+}
+
+impl BoundingPoly {
+    // (left, top, right, bottom) in pixels
+    pub fn extent(&self) -> (f64, f64, f64, f64) {
+        let xs = self.vertices.iter().map(|v| v.x as f64);
+        let ys = self.vertices.iter().map(|v| v.y as f64);
+        let (left, right) = (xs.clone().fold(f64::MAX, f64::min), xs.fold(0.0, f64::max));
+        let (top, bottom) = (ys.clone().fold(f64::MAX, f64::min), ys.fold(0.0, f64::max));
+        (left, top, right, bottom)
+    }
+}
+
+// Pixel position in the image sent; Vision omits coordinates that are 0
+#[derive(Debug, Default, Deserialize)]
+pub struct Vertex {
+    #[serde(default)]
+    pub x: i64,
+    #[serde(default)]
+    pub y: i64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EntityAnnotation {
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub score: f64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebDetection {
+    #[serde(default)]
+    pub best_guess_labels: Vec<WebLabel>,
+    #[serde(default)]
+    pub web_entities: Vec<WebEntity>,
+    #[serde(default)]
+    pub pages_with_matching_images: Vec<WebPage>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct WebLabel {
+    #[serde(default)]
+    pub label: String,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebEntity {
+    #[serde(default, rename(serialize = "id"))]
+    pub entity_id: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub score: f64,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebPage {
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub page_title: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Status {
+    #[serde(default)]
+    pub message: String,
+}
+
+// Vision image source for a local path, a gs:// or GCS URL, or a public URL
+fn image_source(image: &str) -> Result<Value, String> {
+    if let Some((bucket, object)) = gcs::parse_object_url(image) {
+        return Ok(json!({ "source": { "gcsImageUri": format!("gs://{}/{}", bucket, object) } }));
+    }
+    if image.starts_with("https://") || image.starts_with("http://") {
+        return Ok(json!({ "source": { "imageUri": image } }));
+    }
+
+    let img = image_io::open_image_scaled(image, VISION_EDGE)?;
+    let jpeg = image_io::encode_jpeg(&img, VISION_JPEG_QUALITY)?;
+    Ok(json!({ "content": general_purpose::STANDARD.encode(jpeg) }))
+}
+
+// Run Vision features (e.g. "WEB_DETECTION") on one image: a local path, a
+// gs:// or storage.googleapis.com URL (read with the registered credentials)
+// or a public URL. Authenticates with the registered GCS credentials.
+pub async fn annotate(app: &AppHandle, image: &str, features: &[&str]) -> Result<ImageAnnotations, String> {
+    let image_path = image.to_string();
+    let source = tauri::async_runtime::spawn_blocking(move || image_source(&image_path))
+        .await
+        .map_err(|e| format!("Failed to read image: {}", e))??;
+    request(app, source, image, features).await
+}
+
+// Run Vision features on an already encoded JPEG, e.g. a crop
+pub async fn annotate_jpeg(app: &AppHandle, jpeg: &[u8], features: &[&str]) -> Result<ImageAnnotations, String> {
+    let source = json!({ "content": general_purpose::STANDARD.encode(jpeg) });
+    request(app, source, "the image", features).await
+}
+
+async fn request(app: &AppHandle, source: Value, image: &str, features: &[&str]) -> Result<ImageAnnotations, String> {
+    let features: Vec<Value> = features
+        .iter()
+        .map(|feature| json!({ "type": feature, "maxResults": MAX_RESULTS }))
+        .collect();
+    let body = json!({ "requests": [{ "image": source, "features": features }] });
+
+    let response = network::client(app)
+        .post(ANNOTATE_URL)
+        .bearer_auth(gcs::access_token(app).await?)
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("Vision request failed: {}", e))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("Vision request failed ({}): {}", status, body.trim()));
+    }
+    let annotations = response
+        .json::<AnnotateResponse>()
+        .await
+        .map_err(|e| format!("Failed to parse Vision response: {}", e))?
+        .responses
+        .into_iter()
+        .next()
+        .unwrap_or_default();
+
+    match &annotations.error {
+        Some(error) => Err(format!("Vision could not process {}: {}", image, error.message)),
+        None => Ok(annotations),
+    }
+}
+
+// Vision page titles mark the matched words with <b> tags
+pub fn strip_tags(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => in_tag = false,
+            _ if !in_tag => plain.push(c),
+            _ => {}
+        }
+    }
+    plain.trim().to_string()
+}
+
+#[derive(Debug, Serialize)]
+pub struct Label {
+    pub description: String,
+    pub score: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MatchingPage {
+    pub url: String,
+    pub title: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ItemIdentification {
+    // Vision's best guesses at what the item is, e.g. "nike air max 90"
+    pub best_guesses: Vec<String>,
+    pub labels: Vec<Label>,
+    pub entities: Vec<WebEntity>,
+    // Pages showing the same or a very similar image, useful for pricing
+    pub pages: Vec<MatchingPage>,
+}
+
+// Command to identify the item in a photo with Vision web and label
+// detection. `image` is a local path or a gs://, storage.googleapis.com or
+// public URL.
+#[tauri::command]
+pub async fn identify_item(app: AppHandle, image: String) -> Result<ItemIdentification, String> {
+    let annotations = annotate(&app, &image, &["WEB_DETECTION", "LABEL_DETECTION"]).await?;
+    let web = annotations.web_detection.unwrap_or_default();
+
+    Ok(ItemIdentification {
+        best_guesses: web
+            .best_guess_labels
+            .into_iter()
+            .map(|guess| guess.label)
+            .filter(|label| !label.is_empty())
+            .collect(),
+        labels: annotations
+            .label_annotations
+            .into_iter()
+            .map(|label| Label { description: label.description, score: label.score })
+            .collect(),
+        entities: web
+            .web_entities
+            .into_iter()
+            .filter(|entity| !entity.description.is_empty())
+            .collect(),
+        pages: web
+            .pages_with_matching_images
+            .into_iter()
+            .map(|page| MatchingPage { title: strip_tags(&page.page_title), url: page.url })
+            .collect(),
+    })
+}
+
+#[derive(Debug, Serialize)]
+pub struct BrandMatch {
+    pub brand: String,
+    pub confidence: f64,
+}
+
+// Command to detect brand logos in a photo of an item or its tag with Vision
+// LOGO_DETECTION, best match first. A brand seen more than once keeps its
+// highest score.
+#[tauri::command]
+pub async fn detect_brand(app: AppHandle, path: String) -> Result<Vec<BrandMatch>, String> {
+    let annotations = annotate(&app, &path, &["LOGO_DETECTION"]).await?;
+
+    let mut brands: Vec<BrandMatch> = Vec::new();
+    for logo in annotations.logo_annotations {
+        let brand = logo.description.trim();
+        if brand.is_empty() {
+            continue;
+        }
+        match brands.iter_mut().find(|known| known.brand.eq_ignore_ascii_case(brand)) {
+            Some(known) => known.confidence = known.confidence.max(logo.score),
+            None => brands.push(BrandMatch { brand: brand.to_string(), confidence: logo.score }),
+        }
+    }
+    brands.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    Ok(brands)
+}
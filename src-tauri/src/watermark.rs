@@ -0,0 +1,168 @@
+use std::fs;
+use std::path::Path;
+use image::{DynamicImage, Rgba, RgbaImage, imageops::FilterType};
+use imageproc::drawing::{draw_text_mut, text_size};
+use rayon::prelude::*;
+use rusttype::{Font, Scale};
+use serde::Deserialize;
+use tauri::AppHandle;
+use crate::image_io;
+use crate::photo_editing::{self, FileResult};
+
+// Logo width as a fraction of the photo width
+const LOGO_WIDTH_FRACTION: f64 = 0.2;
+
+// Text height as a fraction of the photo height
+const TEXT_HEIGHT_FRACTION: f64 = 0.05;
+
+// Gap between the watermark and the photo edge, as a fraction of the shorter side
+const MARGIN_FRACTION: f64 = 0.03;
+
+// Fonts tried in order when no font file is given
+const SYSTEM_FONTS: [&str; 6] = [
+    "/System/Library/Fonts/Helvetica.ttc",
+    "/Library/Fonts/Arial.ttf",
+    "C:\\Windows\\Fonts\\arial.ttf",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/dejavu/DejaVuSans.ttf",
+];
+
+// Either a logo image file or a line of text, e.g. {"text": "© My Shop"}
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Watermark {
+    Image(String),
+    Text(String),
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WatermarkPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Center,
+}
+
+fn load_font(font_path: Option<&str>) -> Result<Font<'static>, String> {
+    let candidates: Vec<&str> = match font_path {
+        Some(path) => vec![path],
+        None => SYSTEM_FONTS.to_vec(),
+    };
+
+    for path in candidates {
+        if let Ok(bytes) = fs::read(path) {
+            if let Some(font) = Font::try_from_vec(bytes) {
+                return Ok(font);
+            }
+        }
+    }
+
+    Err("No usable font found; pass font_path to a .ttf file".to_string())
+}
+
+// Watermark with its logo or font loaded, ready to render
+enum PreparedMark {
+    Logo(DynamicImage),
+    Text(String, Font<'static>),
+}
+
+// Render the watermark for a photo of the given size onto a transparent layer
+fn render_mark(mark: &PreparedMark, width: u32, height: u32) -> RgbaImage {
+    match mark {
+        PreparedMark::Logo(logo) => {
+            let target_width = ((width as f64 * LOGO_WIDTH_FRACTION) as u32).max(1);
+            let target_height = ((logo.height() as f64 * target_width as f64 / logo.width() as f64) as u32).max(1);
+            logo.resize_exact(target_width, target_height, FilterType::Lanczos3).to_rgba8()
+        }
+        PreparedMark::Text(text, font) => {
+            let scale = Scale::uniform((height as f64 * TEXT_HEIGHT_FRACTION).max(8.0) as f32);
+            let (text_width, text_height) = text_size(scale, font, text);
+
+            // Dark shadow offset behind white text keeps it legible on any background
+            let shadow = (scale.y / 20.0).ceil().max(1.0) as i32;
+            let mut layer = RgbaImage::new(
+                (text_width + shadow).max(1) as u32,
+                (text_height + shadow).max(1) as u32,
+            );
+            draw_text_mut(&mut layer, Rgba([0, 0, 0, 160]), shadow, shadow, scale, font, text);
+            draw_text_mut(&mut layer, Rgba([255, 255, 255, 255]), 0, 0, scale, font, text);
+            layer
+        }
+    }
+}
+
+// Blend a watermark layer onto a photo at the given position and opacity
+fn composite(photo: &mut RgbaImage, mark: &RgbaImage, position: WatermarkPosition, opacity: f64) {
+    let (width, height) = photo.dimensions();
+    let margin = (width.min(height) as f64 * MARGIN_FRACTION) as i64;
+    let free_x = width as i64 - mark.width() as i64;
+    let free_y = height as i64 - mark.height() as i64;
+
+    let (x, y) = match position {
+        WatermarkPosition::TopLeft => (margin, margin),
+        WatermarkPosition::TopRight => (free_x - margin, margin),
+        WatermarkPosition::BottomLeft => (margin, free_y - margin),
+        WatermarkPosition::BottomRight => (free_x - margin, free_y - margin),
+        WatermarkPosition::Center => (free_x / 2, free_y / 2),
+    };
+
+    for (mx, my, pixel) in mark.enumerate_pixels() {
+        let px = x + mx as i64;
+        let py = y + my as i64;
+        if px < 0 || py < 0 || px >= width as i64 || py >= height as i64 {
+            continue;
+        }
+
+        let alpha = pixel[3] as f64 / 255.0 * opacity;
+        let target = photo.get_pixel_mut(px as u32, py as u32);
+        for c in 0..3 {
+            target[c] = (pixel[c] as f64 * alpha + target[c] as f64 * (1.0 - alpha)).round() as u8;
+        }
+    }
+}
+
+// Command to brand copies of photos with a logo or text watermark
+// `opacity` is 0.0-1.0; originals are left untouched
+#[tauri::command(async)]
+pub fn apply_watermark(
+    app: AppHandle,
+    paths: Vec<String>,
+    watermark: Watermark,
+    position: WatermarkPosition,
+    opacity: f64,
+    font_path: Option<String>,
+) -> Result<Vec<FileResult>, String> {
+    if !(0.0..=1.0).contains(&opacity) {
+        return Err("opacity must be between 0 and 1".to_string());
+    }
+
+    let mark = match watermark {
+        Watermark::Image(logo_path) => PreparedMark::Logo(image_io::open_image(&logo_path)?),
+        Watermark::Text(text) => PreparedMark::Text(text, load_font(font_path.as_deref())?),
+    };
+
+    let output_dir = photo_editing::output_dir(&app, "edited")?;
+
+    Ok(paths
+        .into_par_iter()
+        .map(|path| {
+            let result = watermark_file(&path, &mark, position, opacity, &output_dir);
+            FileResult::new(path, result)
+        })
+        .collect())
+}
+
+fn watermark_file(path: &str, mark: &PreparedMark, position: WatermarkPosition, opacity: f64, output_dir: &Path) -> Result<String, String> {
+    let mut photo = image_io::open_image(path)?.to_rgba8();
+    let layer = render_mark(mark, photo.width(), photo.height());
+    composite(&mut photo, &layer, position, opacity);
+
+    let output_path = output_dir.join(photo_editing::output_file_name(path, "watermark", "jpg"));
+    let jpeg_data = image_io::encode_jpeg(&DynamicImage::ImageRgba8(photo), photo_editing::EDIT_JPEG_QUALITY)?;
+    fs::write(&output_path, jpeg_data)
+        .map_err(|e| format!("Failed to write watermarked image: {}", e))?;
+    image_io::path_to_string(&output_path)
+}
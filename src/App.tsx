@@ -477,7 +477,7 @@ function App() {
       // Load ALL images as base64
       const base64Images: string[] = [];
       for (const photoPath of item.group.photos) {
-        const imageData = await invoke<string>('read_image_as_base64', { filePath: photoPath });
+        const imageData = await invoke<string>('read_image_as_base64', { filePath: photoPath, upright: true });
         const base64Image = imageData.split(',')[1]; // Remove data:image/jpeg;base64, prefix
         base64Images.push(base64Image);
       }